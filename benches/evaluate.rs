@@ -0,0 +1,54 @@
+//! Compares the tree walker with the bytecode VM on formulas evaluated many
+//! times over, as when plotting or solving. Run with `cargo bench`.
+
+use rust_calc::interpreter::{evaluate, vm, Environment};
+use rust_calc::parser::parse;
+use rust_calc::settings::Floats;
+use rust_calc::tokenizer::tokenize;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: usize = 200_000;
+
+const FORMULAS: [&str; 4] = [
+  "3*x^2 - 2*x + 1",
+  "sin(x)/(1 + x^2) + exp(-x/10)",
+  "(x + 1)*(x - 1)*(x + 2)*(x - 2)/(x^2 + 1) + 15%",
+  "series(i*x, i, 1, 10)",
+];
+
+fn main() {
+  println!("{:<52} {:>12} {:>12} {:>8}", "formula", "tree walker", "vm", "speedup");
+
+  for formula in FORMULAS.iter() {
+    let ast = parse(tokenize(formula.to_string())).unwrap();
+    let program = vm::compile(&ast, &["x"], Floats::Strict);
+
+    let mut environment = Environment::new();
+    let walker = time(|x| {
+      environment.bind("x", x);
+      evaluate(&ast, &environment).unwrap()
+    });
+    let compiled = time(|x| program.run(&[x]).unwrap());
+
+    println!(
+      "{:<52} {:>9.1} ns {:>9.1} ns {:>7.1}x",
+      formula,
+      per_iteration(walker),
+      per_iteration(compiled),
+      walker.as_secs_f64() / compiled.as_secs_f64()
+    );
+  }
+}
+
+fn time(mut function: impl FnMut(f64) -> f64) -> Duration {
+  let start = Instant::now();
+  for i in 0..ITERATIONS {
+    black_box(function(black_box(i as f64 / ITERATIONS as f64)));
+  }
+  start.elapsed()
+}
+
+fn per_iteration(duration: Duration) -> f64 {
+  duration.as_nanos() as f64 / ITERATIONS as f64
+}
@@ -0,0 +1,12 @@
+use rust_calc::lsp;
+use rust_calc::settings::Settings;
+use std::io;
+use std::process;
+
+fn main() {
+  let stdin = io::stdin();
+  if let Err(error) = lsp::run(stdin.lock(), io::stdout(), Settings::default()) {
+    eprintln!("Error: {}", error);
+    process::exit(1);
+  }
+}
@@ -0,0 +1,342 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn args(values: &[&str]) -> Vec<String> {
+  values.iter().map(|value| value.to_string()).collect()
+}
+
+#[test]
+fn testing_parse_args_formula() {
+  let expected = Arguments {
+    command: Command::Calculate("1+2".to_string()),
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+
+  assert_eq!(parse_args(args(&["1+2"])), Ok(expected));
+  assert_eq!(parse_args(args(&[])), Err("Formula is required".to_string()));
+  assert_eq!(
+    parse_args(args(&["1+2", "3"])),
+    Err("Unexpected argument 3".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_implicit_multiplication() {
+  let strict = parse_args(args(&["--strict", "2pi"])).unwrap();
+  let tighter = parse_args(args(&["--implicit-multiplication", "tighter", "1/2pi"])).unwrap();
+
+  assert_eq!(
+    strict.settings.implicit_multiplication,
+    ImplicitMultiplication::Disabled
+  );
+  assert_eq!(
+    tighter.settings.implicit_multiplication,
+    ImplicitMultiplication::Tighter
+  );
+  assert_eq!(tighter.command, Command::Calculate("1/2pi".to_string()));
+  assert_eq!(
+    parse_args(args(&["--angle", "deg", "--max-depth", "16", "--strict", "2pi"]))
+      .map(|arguments| (arguments.settings.angle, arguments.settings.limits.depth)),
+    Ok((Angle::Degrees, 16)),
+    "should keep the options before --strict"
+  );
+  assert_eq!(
+    parse_args(args(&["--implicit-multiplication", "loose", "2pi"])),
+    Err("Unknown implicit multiplication mode loose".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["--implicit-multiplication"])),
+    Err("--implicit-multiplication expects a mode".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_angle() {
+  let degrees = parse_args(args(&["--angle", "deg", "sin(30)"])).unwrap();
+
+  assert_eq!(degrees.settings.angle, Angle::Degrees);
+  assert_eq!(
+    parse_args(args(&["--angle", "turn", "sin(1)"])),
+    Err("Unknown angle unit turn".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["--angle"])),
+    Err("--angle expects a unit".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_floats() {
+  let ieee = parse_args(args(&["--floats", "ieee", "1/inf"])).unwrap();
+
+  assert_eq!(ieee.settings.floats, Floats::Ieee);
+  assert_eq!(
+    parse_args(args(&["--floats", "loose", "1"])),
+    Err("Unknown floats mode loose".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_limits() {
+  let limited = parse_args(args(&[
+    "--max-depth",
+    "16",
+    "--max-steps",
+    "5000",
+    "--timeout",
+    "250",
+    "1",
+  ]))
+  .unwrap();
+
+  assert_eq!(limited.settings.limits.depth, 16);
+  assert_eq!(limited.settings.limits.steps, 5000);
+  assert_eq!(limited.settings.limits.time, Some(Duration::from_millis(250)));
+  assert_eq!(
+    parse_args(args(&["--max-tokens", "0", "1"])),
+    Err("--max-tokens expects a positive integer, got 0".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["--max-length"])),
+    Err("--max-length expects a positive integer".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_programmer() {
+  let checked = parse_args(args(&["--programmer", "i32", "~0"])).unwrap();
+  let wrapped = parse_args(args(&["--overflow", "wrap", "--programmer", "u8", "run", "flags.calc"])).unwrap();
+
+  assert_eq!(
+    checked.settings.programmer,
+    Some(Programmer {
+      integer: IntegerType { bits: 32, signed: true },
+      overflow: Overflow::Error,
+    })
+  );
+  assert_eq!(
+    wrapped.settings.programmer.map(|programmer| programmer.overflow),
+    Some(Overflow::Wrap)
+  );
+  assert_eq!(
+    parse_args(args(&["--programmer", "u7", "1"])),
+    Err("Unknown integer type u7".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["--overflow", "wrap", "1"])),
+    Err("--overflow is only accepted with --programmer".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["--programmer", "u8", "diff", "x", "x"])),
+    Err("--programmer is only accepted by calculate, run and serve".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_unknown_option() {
+  assert_eq!(
+    parse_args(args(&["--fast", "1"])),
+    Err("Unknown option --fast".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_diff() {
+  let expected = Arguments {
+    command: Command::Diff {
+      formula: "x^2".to_string(),
+      variable: "x".to_string(),
+      at: None,
+    },
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+  let expected_at = Arguments {
+    command: Command::Diff {
+      formula: "x^2".to_string(),
+      variable: "x".to_string(),
+      at: Some(-1.5),
+    },
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+
+  assert_eq!(parse_args(args(&["diff", "x^2", "x"])), Ok(expected));
+  assert_eq!(parse_args(args(&["diff", "x^2", "x", "--at", "-1.5"])), Ok(expected_at));
+  assert_eq!(
+    parse_args(args(&["diff", "x^2"])),
+    Err("Variable is required".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["1+2", "--at", "3"])),
+    Err("--at is only accepted by diff".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["diff", "x^2", "x", "--at", "a"])),
+    Err("--at expects a number, got a".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_simplify() {
+  let expected = Arguments {
+    command: Command::Simplify("x*1+0".to_string()),
+    settings: Settings::strict(),
+    output: Output::Text,
+  };
+
+  assert_eq!(parse_args(args(&["--strict", "simplify", "x*1+0"])), Ok(expected));
+  assert_eq!(parse_args(args(&["simplify"])), Err("Formula is required".to_string()));
+}
+
+#[test]
+fn testing_parse_args_run() {
+  let expected = Arguments {
+    command: Command::Run("budget.calc".to_string()),
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+
+  assert_eq!(parse_args(args(&["run", "budget.calc"])), Ok(expected));
+  assert_eq!(parse_args(args(&["run"])), Err("Script file is required".to_string()));
+}
+
+#[test]
+fn testing_parse_args_stats() {
+  let expected = Arguments {
+    command: Command::Stats,
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+
+  assert_eq!(parse_args(args(&["stats"])), Ok(expected));
+  assert_eq!(
+    parse_args(args(&["stats", "numbers.txt"])),
+    Err("Unexpected argument numbers.txt".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_solve() {
+  let expected = Arguments {
+    command: Command::Solve {
+      formula: "x^2 = 4".to_string(),
+      variable: "x".to_string(),
+      options: SolveOptions::default(),
+    },
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+  let expected_options = Arguments {
+    command: Command::Solve {
+      formula: "x^2 = 4".to_string(),
+      variable: "x".to_string(),
+      options: SolveOptions {
+        interval: (-10.0, 0.5),
+        tolerance: 1e-9,
+        ..SolveOptions::default()
+      },
+    },
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+
+  assert_eq!(parse_args(args(&["solve", "x^2 = 4", "x"])), Ok(expected));
+  assert_eq!(
+    parse_args(args(&[
+      "solve",
+      "x^2 = 4",
+      "x",
+      "--in",
+      "-10..0.5",
+      "--tolerance",
+      "1e-9"
+    ])),
+    Ok(expected_options)
+  );
+  assert_eq!(
+    parse_args(args(&["solve", "x^2 = 4", "x", "--in", "10..-10"])),
+    Err("--in expects an interval such as -10..10, got 10..-10".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["solve", "x^2 = 4", "x", "--tolerance", "0"])),
+    Err("--tolerance expects a positive number, got 0".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["x^2", "--in", "0..1"])),
+    Err("--in and --tolerance are only accepted by solve".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_plot() {
+  let expected = Arguments {
+    command: Command::Plot {
+      formulas: vec!["sin(x)".to_string(), "cos(x)".to_string()],
+      options: PlotOptions {
+        x: (-5.0, 5.0),
+        y: Some((-1.0, 1.0)),
+        width: 40,
+        height: 10,
+        style: Style::Ascii,
+      },
+    },
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+
+  assert_eq!(
+    parse_args(args(&[
+      "plot", "sin(x)", "cos(x)", "--x", "-5..5", "--y", "-1..1", "--size", "40x10", "--ascii"
+    ])),
+    Ok(expected)
+  );
+  assert_eq!(parse_args(args(&["plot"])), Err("Formula is required".to_string()));
+  assert_eq!(
+    parse_args(args(&["plot", "x", "--size", "big"])),
+    Err("--size expects a size such as 60x15, got big".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["x", "--ascii"])),
+    Err("--x, --y, --size and --ascii are only accepted by plot".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_output() {
+  let expected = Arguments {
+    command: Command::Calculate("1+2".to_string()),
+    settings: Settings::default(),
+    output: Output::Json,
+  };
+
+  assert_eq!(parse_args(args(&["--output", "json", "1+2"])), Ok(expected));
+  assert_eq!(
+    parse_args(args(&["--output", "xml", "1+2"])),
+    Err("Unknown output format xml".to_string())
+  );
+  assert_eq!(
+    parse_args(args(&["--output", "json", "run", "budget.calc"])),
+    Err("--output json is only accepted by calculate, diff and simplify".to_string())
+  );
+}
+
+#[test]
+fn testing_parse_args_serve() {
+  let serve = |bind: &str| Arguments {
+    command: Command::Serve(bind.to_string()),
+    settings: Settings::default(),
+    output: Output::Text,
+  };
+
+  assert_eq!(parse_args(args(&["serve"])), Ok(serve("127.0.0.1:8080")));
+  assert_eq!(
+    parse_args(args(&["serve", "--bind", "0.0.0.0:9000"])),
+    Ok(serve("0.0.0.0:9000"))
+  );
+  assert_eq!(
+    parse_args(args(&["1+2", "--bind", "0.0.0.0:9000"])),
+    Err("--bind is only accepted by serve".to_string())
+  );
+}
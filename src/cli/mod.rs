@@ -0,0 +1,292 @@
+use rust_calc::interpreter::solver::SolveOptions;
+use rust_calc::plot::{PlotOptions, Style};
+use rust_calc::settings::{Angle, Floats, ImplicitMultiplication, IntegerType, Overflow, Programmer, Settings};
+use std::time::Duration;
+
+pub const USAGE: &str = "Use: calc [options] '1+2-3*4/5'
+     calc [options] diff 'x^2*sin(x)' x [--at 2]
+     calc [options] simplify 'x*1+0'
+     calc [options] run budget.calc
+     calc stats < numbers.txt
+     calc [options] serve [--bind 127.0.0.1:8080]
+     calc [options] solve 'x^3 - 2*x = 5' x [--in -10..10] [--tolerance 1e-9]
+     calc [options] plot 'sin(x)/x' ['cos(x)' ...] [--x -10..10] [--y -1..1] [--size 60x15] [--ascii]
+
+Options:
+  --output <format>                  text, or json for calculate, diff and simplify
+  --strict                           reject implicit multiplication such as 2(3+4)
+  --implicit-multiplication <mode>   same (as *), tighter (than * and /) or off
+  --angle <unit>                     rad, deg or grad for trigonometric functions, rad by default
+  --floats <mode>                    strict (overflow, underflow and NaN are errors) or ieee
+                                     (inf and nan are values), strict by default
+  --programmer <type>                integers of type u8, u16, u32, u64, i8, i16, i32 or i64,
+                                     with & | ^ (xor) ~ << >>, shown in hex and binary too
+  --overflow <mode>                  error or wrap when a result does not fit, error by default
+  --max-length <characters>          longest formula accepted, 65536 by default
+  --max-tokens <count>               most tokens in a formula, 16384 by default
+  --max-depth <levels>               most brackets, calls and operators nested, 128 by default
+  --max-steps <count>                most steps of an evaluation, 100000000 by default
+  --timeout <milliseconds>           longest an evaluation may run, unbounded by default
+  --at <number>                      evaluate the derivative at this point
+  --in <from>..<to>                  interval searched for roots, -100..100 by default
+  --tolerance <number>               relative precision of the roots, 1e-12 by default
+  --x <from>..<to>                   range of x in a plot, -10..10 by default
+  --y <from>..<to>                   range of the values in a plot, fitted by default
+  --size <width>x<height>            size of a plot in characters, 60x15 by default
+  --ascii                            plot with ASCII markers instead of braille dots
+  --bind <address>                   address the server listens on, 127.0.0.1:8080 by default";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+  Calculate(String),
+  Diff {
+    formula: String,
+    variable: String,
+    at: Option<f64>,
+  },
+  Simplify(String),
+  /// Path of a script to run
+  Run(String),
+  /// Address to serve the HTTP API on
+  Serve(String),
+  /// Summary of the numbers read from stdin
+  Stats,
+  Solve {
+    formula: String,
+    variable: String,
+    options: SolveOptions,
+  },
+  Plot {
+    formulas: Vec<String>,
+    options: PlotOptions,
+  },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Output {
+  Text,
+  /// One JSON object per result, as written by `rust_calc::json`
+  Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Arguments {
+  pub command: Command,
+  pub settings: Settings,
+  pub output: Output,
+}
+
+pub fn parse_args(args: Vec<String>) -> Result<Arguments, String> {
+  let mut settings = Settings::default();
+  let mut output = Output::Text;
+  let mut integer: Option<IntegerType> = None;
+  let mut overflow: Option<Overflow> = None;
+  let mut at: Option<f64> = None;
+  let mut bind: Option<String> = None;
+  let mut solve_options: Option<SolveOptions> = None;
+  let mut plot_options: Option<PlotOptions> = None;
+  let mut positional: Vec<String> = Vec::new();
+  let mut args = args.into_iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--strict" => settings.implicit_multiplication = ImplicitMultiplication::Disabled,
+      "--implicit-multiplication" => {
+        let mode = args.next().ok_or("--implicit-multiplication expects a mode")?;
+        settings.implicit_multiplication = implicit_multiplication(&mode)?;
+      }
+      "--angle" => {
+        let unit = args.next().ok_or("--angle expects a unit")?;
+        settings.angle = Angle::parse(&unit).ok_or(format!("Unknown angle unit {}", unit))?;
+      }
+      "--floats" => {
+        let mode = args.next().ok_or("--floats expects a mode")?;
+        settings.floats = match mode.as_str() {
+          "strict" => Floats::Strict,
+          "ieee" => Floats::Ieee,
+          _ => return Err(format!("Unknown floats mode {}", mode)),
+        };
+      }
+      "--programmer" => {
+        let name = args.next().ok_or("--programmer expects an integer type such as u8")?;
+        integer = Some(IntegerType::parse(&name).ok_or(format!("Unknown integer type {}", name))?);
+      }
+      "--overflow" => {
+        let mode = args.next().ok_or("--overflow expects a mode")?;
+        overflow = Some(match mode.as_str() {
+          "error" => Overflow::Error,
+          "wrap" => Overflow::Wrap,
+          _ => return Err(format!("Unknown overflow mode {}", mode)),
+        });
+      }
+      "--max-length" => settings.limits.length = limit(&arg, args.next())? as usize,
+      "--max-tokens" => settings.limits.tokens = limit(&arg, args.next())? as usize,
+      "--max-depth" => settings.limits.depth = limit(&arg, args.next())? as usize,
+      "--max-steps" => settings.limits.steps = limit(&arg, args.next())?,
+      "--timeout" => settings.limits.time = Some(Duration::from_millis(limit(&arg, args.next())?)),
+      "--output" => {
+        let format = args.next().ok_or("--output expects a format")?;
+        output = match format.as_str() {
+          "text" => Output::Text,
+          "json" => Output::Json,
+          _ => return Err(format!("Unknown output format {}", format)),
+        };
+      }
+      "--at" => {
+        let value = args.next().ok_or("--at expects a number")?;
+        at = Some(
+          value
+            .parse()
+            .map_err(|_| format!("--at expects a number, got {}", value))?,
+        );
+      }
+      "--in" => {
+        let value = args.next().ok_or("--in expects an interval such as -10..10")?;
+        solve_options.get_or_insert_with(SolveOptions::default).interval = interval("--in", &value)?;
+      }
+      "--tolerance" => {
+        let value = args.next().ok_or("--tolerance expects a number")?;
+        let tolerance = value
+          .parse()
+          .ok()
+          .filter(|tolerance: &f64| *tolerance > 0.0)
+          .ok_or(format!("--tolerance expects a positive number, got {}", value))?;
+        solve_options.get_or_insert_with(SolveOptions::default).tolerance = tolerance;
+      }
+      "--x" => {
+        let value = args.next().ok_or("--x expects an interval such as -10..10")?;
+        plot_options.get_or_insert_with(PlotOptions::default).x = interval("--x", &value)?;
+      }
+      "--y" => {
+        let value = args.next().ok_or("--y expects an interval such as -10..10")?;
+        plot_options.get_or_insert_with(PlotOptions::default).y = Some(interval("--y", &value)?);
+      }
+      "--size" => {
+        let value = args.next().ok_or("--size expects a size such as 60x15")?;
+        let (width, height) = size(&value)?;
+        let options = plot_options.get_or_insert_with(PlotOptions::default);
+        options.width = width;
+        options.height = height;
+      }
+      "--bind" => bind = Some(args.next().ok_or("--bind expects an address such as 127.0.0.1:8080")?),
+      "--ascii" => plot_options.get_or_insert_with(PlotOptions::default).style = Style::Ascii,
+      flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+      _ => positional.push(arg),
+    }
+  }
+
+  let mut positional = positional.into_iter();
+  let command = match positional.next().as_deref() {
+    Some("diff") => Command::Diff {
+      formula: positional.next().ok_or("Formula is required")?,
+      variable: positional.next().ok_or("Variable is required")?,
+      at,
+    },
+    Some("solve") => Command::Solve {
+      formula: positional.next().ok_or("Formula is required")?,
+      variable: positional.next().ok_or("Variable is required")?,
+      options: solve_options.take().unwrap_or_default(),
+    },
+    Some("plot") => {
+      let formulas: Vec<String> = positional.by_ref().collect();
+      if formulas.is_empty() {
+        return Err("Formula is required".to_string());
+      }
+      Command::Plot {
+        formulas,
+        options: plot_options.take().unwrap_or_default(),
+      }
+    }
+    Some("simplify") => Command::Simplify(positional.next().ok_or("Formula is required")?),
+    Some("run") => Command::Run(positional.next().ok_or("Script file is required")?),
+    Some("stats") => Command::Stats,
+    Some("serve") => Command::Serve(bind.take().unwrap_or_else(|| "127.0.0.1:8080".to_string())),
+    Some(formula) => Command::Calculate(formula.to_string()),
+    None => return Err("Formula is required".to_string()),
+  };
+  if let Some(extra) = positional.next() {
+    return Err(format!("Unexpected argument {}", extra));
+  }
+  if at.is_some() && !matches!(command, Command::Diff { .. }) {
+    return Err("--at is only accepted by diff".to_string());
+  }
+  if bind.is_some() {
+    return Err("--bind is only accepted by serve".to_string());
+  }
+  if solve_options.is_some() {
+    return Err("--in and --tolerance are only accepted by solve".to_string());
+  }
+  if plot_options.is_some() {
+    return Err("--x, --y, --size and --ascii are only accepted by plot".to_string());
+  }
+
+  match integer {
+    Some(_) if !matches!(command, Command::Calculate(_) | Command::Run(_) | Command::Serve(_)) => {
+      return Err("--programmer is only accepted by calculate, run and serve".to_string())
+    }
+    Some(integer) => {
+      settings.programmer = Some(Programmer {
+        integer,
+        overflow: overflow.unwrap_or(Overflow::Error),
+      })
+    }
+    None if overflow.is_some() => return Err("--overflow is only accepted with --programmer".to_string()),
+    None => {}
+  }
+
+  let is_json_command = matches!(
+    command,
+    Command::Calculate(_) | Command::Diff { .. } | Command::Simplify(_)
+  );
+  if output == Output::Json && !is_json_command {
+    return Err("--output json is only accepted by calculate, diff and simplify".to_string());
+  }
+
+  Ok(Arguments {
+    command,
+    settings,
+    output,
+  })
+}
+
+fn interval(flag: &str, value: &str) -> Result<(f64, f64), String> {
+  let error = || format!("{} expects an interval such as -10..10, got {}", flag, value);
+  let (from, to) = value.split_once("..").ok_or_else(error)?;
+
+  match (from.parse::<f64>(), to.parse::<f64>()) {
+    (Ok(from), Ok(to)) if from < to => Ok((from, to)),
+    _ => Err(error()),
+  }
+}
+
+fn size(value: &str) -> Result<(usize, usize), String> {
+  let error = || format!("--size expects a size such as 60x15, got {}", value);
+  let (width, height) = value.split_once('x').ok_or_else(error)?;
+
+  match (width.parse::<usize>(), height.parse::<usize>()) {
+    (Ok(width), Ok(height)) if width >= 10 && height >= 2 => Ok((width, height)),
+    _ => Err(error()),
+  }
+}
+
+/// The positive integer given to a limit `flag`.
+fn limit(flag: &str, value: Option<String>) -> Result<u64, String> {
+  let value = value.ok_or(format!("{} expects a positive integer", flag))?;
+  value
+    .parse()
+    .ok()
+    .filter(|limit| *limit > 0)
+    .ok_or(format!("{} expects a positive integer, got {}", flag, value))
+}
+
+fn implicit_multiplication(mode: &str) -> Result<ImplicitMultiplication, String> {
+  match mode {
+    "same" => Ok(ImplicitMultiplication::SameAsExplicit),
+    "tighter" => Ok(ImplicitMultiplication::Tighter),
+    "off" => Ok(ImplicitMultiplication::Disabled),
+    _ => Err(format!("Unknown implicit multiplication mode {}", mode)),
+  }
+}
+
+#[cfg(test)]
+mod cli_spec;
@@ -0,0 +1,84 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn compile(formula: &str) -> CompiledExpr {
+  CompiledExpr::new(formula, &Settings::default()).unwrap()
+}
+
+#[test]
+fn testing_eval() {
+  let expr = compile("a*x+b");
+
+  assert_eq!(expr.variables().to_vec(), vec!["a", "x", "b"]);
+  assert_eq!(expr.eval(&[("a", 2.0), ("x", 3.0), ("b", 1.0)]), Ok(7.0));
+  assert_eq!(
+    expr.eval(&[("b", 0.5), ("x", -1.0), ("a", 4.0)]),
+    Ok(-3.5),
+    "should accept the bindings in any order"
+  );
+  assert_eq!(compile("2^10").eval(&[]), Ok(1024.0));
+  assert_eq!(compile("series(i*x, i, 1, 4)").eval(&[("x", 0.5)]), Ok(5.0));
+}
+
+#[test]
+fn testing_eval_validates_bindings() {
+  let expr = compile("a*x+b");
+
+  assert_eq!(
+    expr.eval(&[("a", 2.0), ("x", 3.0)]),
+    Err(Error::UndefinedIdentifier("b".to_string()))
+  );
+  assert_eq!(
+    expr.eval(&[("a", 2.0), ("x", 3.0), ("b", 1.0), ("y", 0.0)]),
+    Err(Error::InvalidArgument(
+      "y is not a variable of the expression".to_string()
+    ))
+  );
+  assert_eq!(
+    expr.eval(&[("a", 2.0), ("x", 3.0), ("a", 1.0)]),
+    Err(Error::InvalidArgument("a is bound more than once".to_string()))
+  );
+  assert_eq!(compile("1/x").eval(&[("x", 0.0)]), Err(Error::DivisionByZero));
+}
+
+#[test]
+fn testing_eval_within_limits() {
+  let limited = Settings {
+    limits: Limits {
+      steps: 1000,
+      ..Limits::default()
+    },
+    ..Settings::default()
+  };
+  let expr = CompiledExpr::new("series(i*x, i, 1, n)", &limited).unwrap();
+
+  assert_eq!(expr.eval(&[("x", 1.0), ("n", 100.0)]), Ok(5050.0));
+  assert_eq!(
+    expr.eval(&[("x", 1.0), ("n", 100.0)]),
+    Ok(5050.0),
+    "should give each evaluation steps of its own"
+  );
+  assert_eq!(expr.eval(&[("x", 1.0), ("n", 1e6)]), Err(Error::TooManySteps(1000)));
+}
+
+#[test]
+fn testing_new_rejects_invalid_formulas() {
+  assert!(CompiledExpr::new("1+*2", &Settings::default()).is_err());
+}
+
+#[test]
+fn testing_shared_across_threads() {
+  fn assert_send_sync<T: Send + Sync>() {}
+  assert_send_sync::<CompiledExpr>();
+
+  let expr = compile("x^2");
+  let handles: Vec<_> = (0..4)
+    .map(|i| {
+      let expr = expr.clone();
+      std::thread::spawn(move || expr.eval(&[("x", i as f64)]))
+    })
+    .collect();
+  let results: Vec<_> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+
+  assert_eq!(results, vec![Ok(0.0), Ok(1.0), Ok(4.0), Ok(9.0)]);
+}
@@ -0,0 +1,68 @@
+use crate::interpreter::vm::{self, Program};
+use crate::parser::parse_with;
+use crate::settings::{Limits, Settings};
+use crate::tokenizer::tokenize_with;
+use crate::Error;
+use std::sync::Arc;
+
+/// A formula parsed and compiled once, to be evaluated any number of times
+/// with different values of its variables. Clones share the compiled program.
+#[derive(Clone, Debug)]
+pub struct CompiledExpr {
+  program: Arc<Program>,
+  /// Bounds each evaluation on its own
+  limits: Limits,
+}
+impl CompiledExpr {
+  pub fn new(formula: &str, settings: &Settings) -> Result<CompiledExpr, Error> {
+    let ast = parse_with(tokenize_with(formula.to_string(), &settings.limits), settings)?;
+    let variables = vm::free_variables(&ast);
+    let variables: Vec<&str> = variables.iter().map(String::as_str).collect();
+
+    Ok(CompiledExpr {
+      program: Arc::new(vm::compile(&ast, &variables, settings.floats)),
+      limits: settings.limits.clone(),
+    })
+  }
+
+  /// Names of the free variables, in the order they first appear.
+  pub fn variables(&self) -> &[String] {
+    self.program.variables()
+  }
+
+  /// Evaluates the expression with a value bound to each of its variables.
+  pub fn eval(&self, bindings: &[(&str, f64)]) -> Result<f64, Error> {
+    let variables = self.variables();
+
+    for (index, (name, _)) in bindings.iter().enumerate() {
+      if !variables.iter().any(|variable| variable == name) {
+        return Err(Error::InvalidArgument(format!(
+          "{} is not a variable of the expression",
+          name
+        )));
+      }
+      if bindings[..index].iter().any(|(other, _)| other == name) {
+        return Err(Error::InvalidArgument(format!("{} is bound more than once", name)));
+      }
+    }
+    if let Some(missing) = variables
+      .iter()
+      .find(|variable| !bindings.iter().any(|(name, _)| name == variable))
+    {
+      return Err(Error::UndefinedIdentifier(missing.clone()));
+    }
+
+    self.program.run_with(
+      |registers| {
+        for (name, value) in bindings {
+          let index = variables.iter().position(|variable| variable == name).unwrap();
+          registers[index] = *value;
+        }
+      },
+      &self.limits,
+    )
+  }
+}
+
+#[cfg(test)]
+mod compiled_spec;
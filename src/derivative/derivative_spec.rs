@@ -0,0 +1,162 @@
+use super::*;
+use crate::interpreter::evaluate;
+use crate::parser::parse_with;
+use crate::tokenizer::tokenize;
+use pretty_assertions::assert_eq;
+
+fn d(formula: &str) -> String {
+  run_with(formula.to_string(), "x", &Settings::default()).unwrap()
+}
+
+fn parse(formula: &str) -> Ast {
+  parse_with(tokenize(formula.to_string()), &Settings::default()).unwrap()
+}
+
+fn value(ast: &Ast, x: f64) -> f64 {
+  evaluate(ast, &Environment::new().with("x", x)).unwrap()
+}
+
+#[test]
+fn testing_derive_basic_operations() {
+  assert_eq!(d("5"), "0");
+  assert_eq!(d("x"), "1");
+  assert_eq!(d("y"), "0", "should treat other names as constants");
+  assert_eq!(d("x + 3"), "1");
+  assert_eq!(d("3 - x"), "-1");
+  assert_eq!(d("y*x"), "y");
+  assert_eq!(d("x^3"), "3*x^2");
+  assert_eq!(d("1/x"), "-(1/x^2)");
+  assert_eq!(d("2^x"), "0.6931471805599453*2^x");
+  assert_eq!(d("e^x"), "e^x");
+  assert_eq!(d("e^(2x)"), "2*e^(2*x)");
+  assert_eq!(d("x^x"), "(ln(x) + 1)*x^x");
+}
+
+#[test]
+fn testing_derive_long_chains() {
+  assert_eq!(d(&vec!["x"; 5000].join(" + ")), "5000");
+  assert_eq!(d(&vec!["x^2"; 2000].join(" - ")), "-(3996*x)");
+}
+
+#[test]
+fn testing_derive_product_quotient_and_chain_rules() {
+  assert_eq!(d("x^2*sin(x)"), "x^2*cos(x) + 2*x*sin(x)");
+  assert_eq!(d("sin(x)/x"), "(x*cos(x) - sin(x))/x^2");
+  assert_eq!(d("cos(2x)"), "-(2*sin(2*x))");
+  assert_eq!(d("(x+1)^2"), "2*x + 2");
+}
+
+#[test]
+fn testing_derive_postfix_operators() {
+  assert_eq!(d("x%"), "1/100");
+  assert_eq!(d("x!"), "digamma(x + 1)*x!");
+  assert_eq!(d("x + 10%"), "11/10", "should differentiate x + x*10/100");
+}
+
+#[test]
+fn testing_derive_units_of_durations_as_variables() {
+  let d = |formula: &str, variable: &str| run_with(formula.to_string(), variable, &Settings::default()).unwrap();
+
+  assert_eq!(d("3s^2", "s"), "6*s");
+  assert_eq!(d("2d", "d"), "2");
+  assert_eq!(d("h*3h", "h"), "6*h");
+  assert_eq!(d("5w + 1", "w"), "5");
+  assert_eq!(
+    run_at("3h^2".to_string(), "h", 2.0, &Settings::default()),
+    Ok("12".to_string())
+  );
+}
+
+#[test]
+fn testing_derive_matrices() {
+  assert_eq!(
+    d("[x, x^2; 3, y*x]"),
+    "[1, 2*x; 0, y]",
+    "should differentiate each element"
+  );
+  assert_eq!(
+    run_at("[x^2, 1]".to_string(), "x", 3.0, &Settings::default()),
+    Ok("[6, 0]".to_string())
+  );
+}
+
+#[test]
+fn testing_derive_functions() {
+  assert_eq!(d("sin(x)"), "cos(x)");
+  assert_eq!(d("cos(x)"), "-sin(x)");
+  assert_eq!(d("tan(x)"), "1/cos(x)^2");
+  assert_eq!(d("asin(x)"), "1/sqrt(-x^2 + 1)");
+  assert_eq!(d("acos(x)"), "-(1/sqrt(-x^2 + 1))");
+  assert_eq!(d("atan(x)"), "1/(x^2 + 1)");
+  assert_eq!(d("sinh(x)"), "cosh(x)");
+  assert_eq!(d("cosh(x)"), "sinh(x)");
+  assert_eq!(d("tanh(x)"), "1/cosh(x)^2");
+  assert_eq!(d("exp(x)"), "exp(x)");
+  assert_eq!(d("ln(x)"), "1/x");
+  assert_eq!(d("log(x)"), "0.43429448190325176/x");
+  assert_eq!(d("sqrt(x)"), "1/(2*sqrt(x))");
+  assert_eq!(d("abs(x)"), "x/abs(x)");
+  assert_eq!(
+    run_with("digamma(x)".to_string(), "x", &Settings::default()),
+    Err(Error::NotDifferentiable("digamma".to_string()))
+  );
+}
+
+#[test]
+fn testing_derive_matches_finite_differences() {
+  let formulas = [
+    "x^2*sin(x)",
+    "sin(x)/x",
+    "x^x",
+    "exp(-x^2)",
+    "ln(x^2 + 1)*cos(x)",
+    "sqrt(x)*atan(x)",
+    "x!",
+    "2^x - 3x + 5%",
+  ];
+  let h = 1e-6;
+
+  for formula in formulas.iter() {
+    let ast = parse(formula);
+    let derivative = derive(&ast, "x").unwrap();
+
+    for &x in [0.5, 1.3, 2.0].iter() {
+      let expected = (value(&ast, x + h) - value(&ast, x - h)) / (2.0 * h);
+      let result = value(&derivative, x);
+      assert!(
+        (result - expected).abs() < 1e-5 * expected.abs().max(1.0),
+        "d/dx {} at {} should be {} but was {}",
+        formula,
+        x,
+        expected,
+        result
+      );
+    }
+  }
+}
+
+#[test]
+fn testing_derive_prints_a_formula_that_parses_back() {
+  let derivative = derive(&parse("x^2*sin(x) - 1/x"), "x").unwrap();
+  let reparsed = parse(&derivative.to_string());
+
+  assert_eq!(value(&reparsed, 1.7), value(&derivative, 1.7));
+}
+
+#[test]
+fn testing_run_at() {
+  assert_eq!(
+    run_at("x^3".to_string(), "x", 2.0, &Settings::default()),
+    Ok("12".to_string())
+  );
+}
+
+#[test]
+fn testing_derive_rejects_invalid_variables() {
+  assert_eq!(
+    run_with("x^2".to_string(), "sin", &Settings::default()),
+    Err(Error::InvalidExpression(
+      "Bad format: sin is not a variable name".to_string()
+    ))
+  );
+}
@@ -0,0 +1,325 @@
+use crate::error::Located;
+use crate::interpreter::{self, Environment};
+use crate::parser::{parse_within, validate_variable, Ast, Node};
+use crate::settings::Settings;
+use crate::simplifier;
+use crate::tokenizer::{tokenize_within, Key, Kind, Token};
+use crate::Error;
+
+/// Differentiates `formula` with respect to `variable`, printing the result
+/// once simplified.
+pub fn run_with(formula: String, variable: &str, settings: &Settings) -> Result<String, Error> {
+  run_located(formula, variable, None, settings).map_err(Error::from)
+}
+
+/// Value of the derivative of `formula` where `variable` is `at`.
+pub fn run_at(formula: String, variable: &str, at: f64, settings: &Settings) -> Result<String, Error> {
+  run_located(formula, variable, Some(at), settings).map_err(Error::from)
+}
+
+/// Runs `run_with`, or `run_at` when given a value `at`, failing with the
+/// part of the formula the error is about where it is known.
+pub fn run_located(formula: String, variable: &str, at: Option<f64>, settings: &Settings) -> Result<String, Located> {
+  let mut environment = Environment::new()
+    .with_floats(settings.floats)
+    .with_limits(&settings.limits);
+  let variables = [variable.to_string()];
+  let tokens = tokenize_within(formula, &settings.limits, &variables, environment.budget())?;
+  let ast = parse_within(Ok(tokens.clone()), settings, environment.budget())?;
+  let located = |error| Located::named(error, &tokens);
+  let derivative = derive(&ast, variable).map_err(located)?;
+
+  let at = match at {
+    Some(at) => at,
+    None => return Ok(simplifier::simplify_in(&derivative, &environment).to_string()),
+  };
+  environment.bind(variable, at);
+  interpreter::run_ast(derivative, &environment).map_err(located)
+}
+
+pub fn derive(ast: &Ast, variable: &str) -> Result<Ast, Error> {
+  validate_variable(variable)?;
+
+  match derive_ast(ast, variable)? {
+    Node::Ast(ast) => Ok(*ast),
+    node => Ok(Ast::new(
+      Ast::new_operator(Key::Addition),
+      Ast::new_number(vec![Key::Zero]),
+      Some(node),
+    )),
+  }
+}
+
+fn derive_ast(ast: &Ast, variable: &str) -> Result<Node, Error> {
+  // along the left operands of a chain in a loop, as 1+2+3 is (1+2)+3, with
+  // the derivative of each left operand failing only if its rule takes it
+  let spine = ast.left_spine();
+  let innermost = spine[0]
+    .operand_a
+    .as_ref()
+    .map_or(Err(Error::InvalidOperand), |a| derive_node(a, variable));
+  spine
+    .into_iter()
+    .fold(innermost, |da, ast| derive_operation(ast, da, variable))
+}
+
+/// The derivative of `ast`, given `da`, the derivative of its left operand.
+fn derive_operation(ast: &Ast, da: Result<Node, Error>, variable: &str) -> Result<Node, Error> {
+  let (operator, a, b) = match (&ast.operator, &ast.operand_a, &ast.operand_b) {
+    (Some(operator), Some(a), Some(b)) => (&operator.keys[0], a, b),
+    _ => return Err(Error::InvalidOperand),
+  };
+
+  match (operator, b) {
+    // a ± b% is a ± a*b/100, where (a*b/100)' = a'*b/100 + a*(b/100)'
+    (Key::Addition, Node::Percentage(rate)) | (Key::Subtraction, Node::Percentage(rate)) => {
+      let da = da?;
+      let rate = percentage(rate);
+      let relative = add(
+        mul(da.clone(), rate.clone()),
+        mul(a.clone(), derive_node(&rate, variable)?),
+      );
+      match operator {
+        Key::Addition => Ok(add(da, relative)),
+        _ => Ok(sub(da, relative)),
+      }
+    }
+    (Key::Addition, _) => Ok(add(da?, derive_node(b, variable)?)),
+    (Key::Subtraction, _) => Ok(sub(da?, derive_node(b, variable)?)),
+    // (ab)' = a'b + ab'
+    (Key::Multiplication, _) | (Key::Of, _) => Ok(add(mul(da?, b.clone()), mul(a.clone(), derive_node(b, variable)?))),
+    // (a/b)' = (a'b - ab')/b^2
+    (Key::Division, _) => Ok(div(
+      sub(mul(da?, b.clone()), mul(a.clone(), derive_node(b, variable)?)),
+      pow(b.clone(), number(2.0)),
+    )),
+    (Key::Exponentiation, _) => derive_power(a, da, b, variable),
+    (Key::BitNot, _)
+    | (Key::BitAnd, _)
+    | (Key::BitOr, _)
+    | (Key::Xor, _)
+    | (Key::ShiftLeft, _)
+    | (Key::ShiftRight, _)
+    | (Key::PlusMinus, _) => Err(Error::NotDifferentiable(operator.to_string())),
+    _ => Err(Error::TokenNaO),
+  }
+}
+
+fn derive_power(a: &Node, da: Result<Node, Error>, b: &Node, variable: &str) -> Result<Node, Error> {
+  match (depends_on(a, variable), depends_on(b, variable)) {
+    (_, false) => {
+      // (a^n)' = n*a^(n-1)*a'
+      let exponent = sub(b.clone(), number(1.0));
+      Ok(mul(mul(b.clone(), pow(a.clone(), exponent)), da?))
+    }
+    (false, true) => {
+      // (c^b)' = c^b*ln(c)*b'
+      let power = pow(a.clone(), b.clone());
+      Ok(mul(mul(power, call("ln", a.clone())), derive_node(b, variable)?))
+    }
+    (true, true) => {
+      // (a^b)' = a^b*(b'*ln(a) + b*a'/a)
+      let power = pow(a.clone(), b.clone());
+      let rate = add(
+        mul(derive_node(b, variable)?, call("ln", a.clone())),
+        div(mul(b.clone(), da?), a.clone()),
+      );
+      Ok(mul(power, rate))
+    }
+  }
+}
+
+fn derive_node(node: &Node, variable: &str) -> Result<Node, Error> {
+  match node {
+    Node::Ast(ast) => derive_ast(ast, variable),
+    Node::Call(function, arguments) => match arguments.as_slice() {
+      [argument] => Ok(mul(
+        derive_function(&function.to_string(), argument)?,
+        derive_node(argument, variable)?,
+      )),
+      _ => Err(Error::NotDifferentiable(function.to_string())),
+    },
+    // (u!)' = u!*ψ(u + 1)*u'
+    Node::Factorial(operand) => Ok(mul(
+      mul(
+        node.clone(),
+        call("digamma", add(operand.as_ref().clone(), number(1.0))),
+      ),
+      derive_node(operand, variable)?,
+    )),
+    Node::Percentage(operand) => Ok(div(derive_node(operand, variable)?, number(100.0))),
+    Node::Interval(_, _) if depends_on(node, variable) => Err(Error::NotDifferentiable(node.to_string())),
+    Node::Interval(_, _) => Ok(number(0.0)),
+    Node::Matrix(rows) => rows
+      .iter()
+      .map(|row| row.iter().map(|element| derive_node(element, variable)).collect())
+      .collect::<Result<_, _>>()
+      .map(Node::Matrix),
+    Node::Token(token) if token.kind == Kind::Identifier && token.to_string() == variable => Ok(number(1.0)),
+    Node::Token(_) => Ok(number(0.0)),
+  }
+}
+
+/// Derivative of the built-in function at `u`, to be chained with u'.
+fn derive_function(name: &str, u: &Node) -> Result<Node, Error> {
+  let u = u.clone();
+  let one = || number(1.0);
+  let square = |node: Node| pow(node, number(2.0));
+
+  match name {
+    "sin" => Ok(call("cos", u)),
+    "cos" => Ok(neg(call("sin", u))),
+    "tan" => Ok(div(one(), square(call("cos", u)))),
+    "asin" => Ok(div(one(), call("sqrt", sub(one(), square(u))))),
+    "acos" => Ok(neg(div(one(), call("sqrt", sub(one(), square(u)))))),
+    "atan" => Ok(div(one(), add(one(), square(u)))),
+    "sinh" => Ok(call("cosh", u)),
+    "cosh" => Ok(call("sinh", u)),
+    "tanh" => Ok(div(one(), square(call("cosh", u)))),
+    "exp" => Ok(call("exp", u)),
+    "ln" => Ok(div(one(), u)),
+    "log" => Ok(div(one(), mul(u, call("ln", number(10.0))))),
+    "sqrt" => Ok(div(one(), mul(number(2.0), call("sqrt", u)))),
+    "abs" => Ok(div(u.clone(), call("abs", u))),
+    _ => Err(Error::NotDifferentiable(name.to_string())),
+  }
+}
+
+fn depends_on(node: &Node, variable: &str) -> bool {
+  match node {
+    Node::Ast(ast) => ast.spine_operands().into_iter().any(|node| depends_on(node, variable)),
+    Node::Call(_, arguments) => arguments.iter().any(|node| depends_on(node, variable)),
+    Node::Factorial(node) | Node::Percentage(node) => depends_on(node, variable),
+    Node::Interval(lower, upper) => depends_on(lower, variable) || depends_on(upper, variable),
+    Node::Matrix(rows) => rows.iter().flatten().any(|node| depends_on(node, variable)),
+    Node::Token(token) => token.kind == Kind::Identifier && token.to_string() == variable,
+  }
+}
+
+// The constructors below fold numbers and drop neutral elements on the way,
+// so the derivative does not carry the 0s and 1s the rules produce.
+
+fn value_of(node: &Node) -> Option<f64> {
+  match node {
+    Node::Token(token) if token.kind == Kind::Number => token.value,
+    // down the left operands of a chain of + and - in a loop, stopping at
+    // any other operation
+    Node::Ast(_) => {
+      let mut operations = Vec::new();
+      let mut node = node;
+      while let Node::Ast(ast) = node {
+        match (&ast.operator, &ast.operand_a, &ast.operand_b) {
+          (Some(operator), Some(a), Some(b)) if matches!(operator.keys[0], Key::Addition | Key::Subtraction) => {
+            operations.push((&operator.keys[0], b));
+            node = a;
+          }
+          _ => return None,
+        }
+      }
+      let value = value_of(node)?;
+      operations
+        .into_iter()
+        .rev()
+        .try_fold(value, |value, (key, b)| match key {
+          Key::Subtraction => Some(value - value_of(b)?),
+          _ => Some(value + value_of(b)?),
+        })
+    }
+    _ => None,
+  }
+}
+
+fn fold(value: Option<f64>, otherwise: impl FnOnce() -> Node) -> Node {
+  match value {
+    Some(value) if value.is_finite() => number(value),
+    _ => otherwise(),
+  }
+}
+
+fn number(value: f64) -> Node {
+  if value < 0.0 {
+    return binary(Key::Subtraction, number(0.0), number(-value));
+  }
+  Node::Token(Token::new_float(value.abs()))
+}
+
+fn binary(key: Key, a: Node, b: Node) -> Node {
+  Node::Ast(Box::new(Ast::new(Ast::new_operator(key), Some(a), Some(b))))
+}
+
+fn call(name: &str, argument: Node) -> Node {
+  Node::Call(Token::new_function(name), vec![argument])
+}
+
+fn percentage(rate: &Node) -> Node {
+  div(rate.clone(), number(100.0))
+}
+
+fn add(a: Node, b: Node) -> Node {
+  match (value_of(&a), value_of(&b)) {
+    (Some(x), Some(y)) => fold(Some(x + y), || binary(Key::Addition, a, b)),
+    (Some(0.0), _) => b,
+    (_, Some(0.0)) => a,
+    _ => binary(Key::Addition, a, b),
+  }
+}
+
+fn sub(a: Node, b: Node) -> Node {
+  match (value_of(&a), value_of(&b)) {
+    (Some(x), Some(y)) => fold(Some(x - y), || binary(Key::Subtraction, a, b)),
+    (_, Some(0.0)) => a,
+    (Some(0.0), _) => neg(b),
+    _ => binary(Key::Subtraction, a, b),
+  }
+}
+
+fn neg(node: Node) -> Node {
+  if let Some(value) = value_of(&node) {
+    return number(-value);
+  }
+  if let Node::Ast(ast) = &node {
+    if let (Some(operator), true, Some(operand)) = (&ast.operator, is_zero_node(&ast.operand_a), &ast.operand_b) {
+      if operator.keys[0] == Key::Subtraction {
+        return operand.clone();
+      }
+    }
+  }
+  binary(Key::Subtraction, number(0.0), node)
+}
+
+fn is_zero_node(node: &Option<Node>) -> bool {
+  node.as_ref().and_then(value_of) == Some(0.0)
+}
+
+fn mul(a: Node, b: Node) -> Node {
+  match (value_of(&a), value_of(&b)) {
+    (Some(x), Some(y)) => fold(Some(x * y), || binary(Key::Multiplication, a, b)),
+    (Some(0.0), _) | (_, Some(0.0)) => number(0.0),
+    (Some(1.0), _) => b,
+    (_, Some(1.0)) => a,
+    (Some(-1.0), _) => neg(b),
+    (_, Some(-1.0)) => neg(a),
+    _ => binary(Key::Multiplication, a, b),
+  }
+}
+
+fn div(a: Node, b: Node) -> Node {
+  match (value_of(&a), value_of(&b)) {
+    (Some(x), Some(y)) if y != 0.0 && (x / y).fract() == 0.0 => number(x / y),
+    (Some(0.0), _) => number(0.0),
+    (_, Some(1.0)) => a,
+    _ => binary(Key::Division, a, b),
+  }
+}
+
+fn pow(a: Node, b: Node) -> Node {
+  match (value_of(&a), value_of(&b)) {
+    (Some(x), Some(y)) if y.fract() == 0.0 => fold(Some(x.powf(y)), || binary(Key::Exponentiation, a, b)),
+    (_, Some(0.0)) => number(1.0),
+    (_, Some(1.0)) => a,
+    _ => binary(Key::Exponentiation, a, b),
+  }
+}
+
+#[cfg(test)]
+mod derivative_spec;
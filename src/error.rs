@@ -1,10 +1,148 @@
-#[derive(Debug, PartialEq)]
+use crate::tokenizer::{Kind, Token};
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Error {
+  DimensionMismatch(String),
   DivisionByZero,
+  FactorialOfNegativeInteger,
+  /// A formula longer than the characters the limits allow
+  InputTooLong(usize),
+  InvalidArgument(String),
   InvalidExpression(String),
   InvalidOperand,
   InvalidTokenSequence,
+  /// An equation that holds for every value over an interval, as `x = x`
+  InfiniteSolutions(String),
+  NoConvergence(String),
+  NotDifferentiable(String),
+  /// A result outside the range of the integer type of programmer mode, or
+  /// of f64 with strict floats
+  Overflow(String),
+  /// A float result of strict floats too small to keep its precision
+  Underflow(String),
+  /// A NaN result of strict floats, as the square root of a negative number
+  NotANumber(String),
+  /// An evaluation that ran for longer than the limits allow
+  Timeout(Duration),
   TokenNaN,
   TokenNaO,
+  /// An expression nested deeper than the levels the limits allow
+  TooDeep(usize),
+  /// A formula of more tokens than the limits allow
+  TooManyTokens(usize),
+  /// An evaluation of more steps than the limits allow
+  TooManySteps(u64),
+  UndefinedIdentifier(String),
   UndefinedSymbol(String),
 }
+impl Error {
+  /// Errors that only mean the expression is undefined for these values, as
+  /// a division by zero, rather than that it cannot be evaluated at all.
+  pub fn is_domain_error(&self) -> bool {
+    matches!(
+      self,
+      Error::DivisionByZero | Error::FactorialOfNegativeInteger | Error::InvalidArgument(_) | Error::NotANumber(_)
+    )
+  }
+
+  /// Stable name of the variant, as reported in JSON output.
+  pub fn kind(&self) -> &'static str {
+    match self {
+      Error::DimensionMismatch(_) => "dimension_mismatch",
+      Error::DivisionByZero => "division_by_zero",
+      Error::FactorialOfNegativeInteger => "factorial_of_negative_integer",
+      Error::InputTooLong(_) => "input_too_long",
+      Error::InvalidArgument(_) => "invalid_argument",
+      Error::InvalidExpression(_) => "invalid_expression",
+      Error::InvalidOperand => "invalid_operand",
+      Error::InvalidTokenSequence => "invalid_token_sequence",
+      Error::InfiniteSolutions(_) => "infinite_solutions",
+      Error::NoConvergence(_) => "no_convergence",
+      Error::NotANumber(_) => "not_a_number",
+      Error::NotDifferentiable(_) => "not_differentiable",
+      Error::Overflow(_) => "overflow",
+      Error::Timeout(_) => "timeout",
+      Error::TokenNaN => "token_nan",
+      Error::TokenNaO => "token_nao",
+      Error::TooDeep(_) => "too_deep",
+      Error::TooManySteps(_) => "too_many_steps",
+      Error::TooManyTokens(_) => "too_many_tokens",
+      Error::Underflow(_) => "underflow",
+      Error::UndefinedIdentifier(_) => "undefined_identifier",
+      Error::UndefinedSymbol(_) => "undefined_symbol",
+    }
+  }
+}
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::DivisionByZero => f.write_str("Division by zero"),
+      Error::FactorialOfNegativeInteger => f.write_str("Factorial of a negative integer"),
+      Error::DimensionMismatch(message)
+      | Error::InvalidArgument(message)
+      | Error::InvalidExpression(message)
+      | Error::InfiniteSolutions(message)
+      | Error::NoConvergence(message)
+      | Error::NotANumber(message)
+      | Error::Overflow(message)
+      | Error::Underflow(message)
+      | Error::UndefinedSymbol(message) => f.write_str(message),
+      Error::InputTooLong(length) => write!(f, "Input is longer than {} characters", length),
+      Error::InvalidOperand => f.write_str("Invalid operand"),
+      Error::InvalidTokenSequence => f.write_str("Invalid token sequence"),
+      Error::NotDifferentiable(name) => write!(f, "{} is not differentiable", name),
+      Error::TokenNaN => f.write_str("Token is not a number"),
+      Error::TokenNaO => f.write_str("Token is not an operator"),
+      Error::Timeout(time) => write!(f, "Evaluation took longer than {:?}", time),
+      Error::TooDeep(depth) => write!(f, "Expression is nested deeper than {} levels", depth),
+      Error::TooManySteps(steps) => write!(f, "Evaluation took more than {} steps", steps),
+      Error::TooManyTokens(tokens) => write!(f, "Input has more than {} tokens", tokens),
+      Error::UndefinedIdentifier(name) => write!(f, "Undefined identifier {}", name),
+    }
+  }
+}
+
+/// An error with the byte offsets of the part of the formula it is about,
+/// where they are known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Located {
+  pub error: Error,
+  pub span: Option<(usize, usize)>,
+}
+impl Located {
+  pub fn at(error: Error, span: Option<(usize, usize)>) -> Located {
+    Located { error, span }
+  }
+
+  /// An evaluation `error` at the first of `tokens` it names, as the
+  /// identifier that is undefined, or nowhere when it names none.
+  pub fn named(error: Error, tokens: &[Token]) -> Located {
+    let (kind, name) = match &error {
+      Error::UndefinedIdentifier(name) => (Kind::Identifier, name),
+      Error::NotDifferentiable(name) => (Kind::Function, name),
+      _ => return error.into(),
+    };
+    let span = tokens
+      .iter()
+      .find(|token| token.kind == kind && token.to_string() == *name)
+      .and_then(|token| token.span);
+    Located::at(error, span)
+  }
+}
+impl From<Error> for Located {
+  fn from(error: Error) -> Located {
+    Located::at(error, None)
+  }
+}
+impl From<Located> for Error {
+  fn from(located: Located) -> Error {
+    located.error
+  }
+}
+impl fmt::Display for Located {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.error)
+  }
+}
@@ -0,0 +1,72 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn s(value: &str) -> String {
+  value.to_string()
+}
+
+fn big(digits: &str) -> BigInt {
+  BigInt::parse(digits).unwrap()
+}
+
+#[test]
+fn testing_parse_and_display() {
+  assert_eq!(big("0").to_string(), "0");
+  assert_eq!(big("000123").to_string(), "123");
+  assert_eq!(big("1000000000").to_string(), "1000000000");
+  assert_eq!(
+    big("123456789012345678901234567890").to_string(),
+    "123456789012345678901234567890"
+  );
+  assert_eq!(BigInt::parse("1.5"), None, "should only parse integer digits");
+  assert_eq!(BigInt::parse(""), None);
+}
+
+#[test]
+fn testing_add_and_sub() {
+  assert_eq!(big("999999999").add(&big("1")).to_string(), "1000000000");
+  assert_eq!(big("1000000000").sub(&big("1")).to_string(), "999999999");
+  assert_eq!(big("2").sub(&big("5")).to_string(), "-3");
+  assert_eq!(big("5").sub(&big("5")).to_string(), "0");
+  assert_eq!(big("2").sub(&big("5")).add(&big("10")).to_string(), "7");
+}
+
+#[test]
+fn testing_mul() {
+  assert_eq!(
+    big("123456789123").mul(&big("987654321987")).to_string(),
+    "121932631355968601347401"
+  );
+  assert_eq!(big("2").sub(&big("5")).mul(&big("3")).to_string(), "-9");
+}
+
+#[test]
+fn testing_div_rem() {
+  let div_rem = |a: BigInt, b: BigInt| {
+    let (quotient, remainder) = a.div_rem(&b).unwrap();
+    (quotient.to_string(), remainder.to_string())
+  };
+
+  assert_eq!(div_rem(big("7"), big("2")), (s("3"), s("1")));
+  assert_eq!(
+    div_rem(
+      big("265252859812191058636308480000000"),
+      big("304888344611713860501504000000")
+    ),
+    (s("870"), s("0"))
+  );
+  assert_eq!(
+    div_rem(big("1267650600228229401496703205376"), big("1000000007")),
+    (s("1267650591354675262013"), s("976371285"))
+  );
+  assert_eq!(div_rem(big("0").sub(&big("7")), big("2")), (s("-3"), s("-1")));
+  assert_eq!(big("7").div_rem(&big("0")), None, "should not divide by zero");
+}
+
+#[test]
+fn testing_is_zero_and_to_u64() {
+  assert!(big("5").sub(&big("5")).is_zero());
+  assert!(!big("1000000000").is_zero());
+  assert_eq!(big("120").to_u64(), Some(120));
+  assert_eq!(big("2").sub(&big("3")).to_u64(), None);
+}
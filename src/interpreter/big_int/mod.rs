@@ -0,0 +1,184 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+const BASE: u64 = 1_000_000_000;
+
+/// Arbitrary precision integer used where f64 loses integer precision,
+/// stored as base 10^9 limbs from the least significant one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigInt {
+  negative: bool,
+  limbs: Vec<u32>,
+}
+impl BigInt {
+  pub fn from_u64(mut value: u64) -> BigInt {
+    let mut limbs = Vec::new();
+    while value > 0 {
+      limbs.push((value % BASE) as u32);
+      value /= BASE;
+    }
+    BigInt { negative: false, limbs }
+  }
+
+  pub fn parse(digits: &str) -> Option<BigInt> {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+      return None;
+    }
+    let mut limbs = Vec::new();
+    let mut end = digits.len();
+    while end > 0 {
+      let start = end.saturating_sub(9);
+      limbs.push(digits[start..end].parse().ok()?);
+      end = start;
+    }
+    Some(BigInt::normalize(false, limbs))
+  }
+
+  pub fn to_u64(&self) -> Option<u64> {
+    if self.negative || self.limbs.len() > 2 {
+      return None;
+    }
+    let value = self
+      .limbs
+      .iter()
+      .rev()
+      .fold(0u64, |acc, &limb| acc * BASE + limb as u64);
+    Some(value)
+  }
+
+  /// Limbs of the integer, which the cost of operations on it grows with.
+  pub fn size(&self) -> usize {
+    self.limbs.len()
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.limbs.is_empty()
+  }
+
+  pub fn add(&self, other: &BigInt) -> BigInt {
+    if self.negative == other.negative {
+      return BigInt::normalize(self.negative, BigInt::add_magnitude(&self.limbs, &other.limbs));
+    }
+    match BigInt::compare_magnitude(&self.limbs, &other.limbs) {
+      Ordering::Less => BigInt::normalize(other.negative, BigInt::sub_magnitude(&other.limbs, &self.limbs)),
+      _ => BigInt::normalize(self.negative, BigInt::sub_magnitude(&self.limbs, &other.limbs)),
+    }
+  }
+
+  pub fn sub(&self, other: &BigInt) -> BigInt {
+    self.add(&other.neg())
+  }
+
+  pub fn mul(&self, other: &BigInt) -> BigInt {
+    let mut limbs = vec![0u64; self.limbs.len() + other.limbs.len()];
+    for (i, &a) in self.limbs.iter().enumerate() {
+      let mut carry = 0u64;
+      for (j, &b) in other.limbs.iter().enumerate() {
+        let current = limbs[i + j] + a as u64 * b as u64 + carry;
+        limbs[i + j] = current % BASE;
+        carry = current / BASE;
+      }
+      limbs[i + other.limbs.len()] += carry;
+    }
+    let limbs = limbs.into_iter().map(|limb| limb as u32).collect();
+    BigInt::normalize(self.negative != other.negative, limbs)
+  }
+
+  /// The quotient truncated toward zero and the remainder, which has the
+  /// sign of `self`, or None dividing by zero.
+  pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+    if other.limbs.is_empty() {
+      return None;
+    }
+    let divisor = BigInt::normalize(false, other.limbs.clone());
+    let mut quotient = vec![0u32; self.limbs.len()];
+    let mut remainder = BigInt::from_u64(0);
+
+    for (i, &limb) in self.limbs.iter().enumerate().rev() {
+      let limbs = std::iter::once(limb).chain(remainder.limbs.iter().copied()).collect();
+      remainder = BigInt::normalize(false, limbs);
+      // the largest limb of the quotient that leaves a remainder, searched
+      // for as the divisor has any number of limbs
+      let (mut low, mut high) = (0, BASE - 1);
+      while low < high {
+        let middle = (low + high).div_ceil(2);
+        let product = divisor.mul(&BigInt::from_u64(middle));
+        match BigInt::compare_magnitude(&product.limbs, &remainder.limbs) {
+          Ordering::Greater => high = middle - 1,
+          _ => low = middle,
+        }
+      }
+      remainder = remainder.sub(&divisor.mul(&BigInt::from_u64(low)));
+      quotient[i] = low as u32;
+    }
+
+    Some((
+      BigInt::normalize(self.negative != other.negative, quotient),
+      BigInt::normalize(self.negative, remainder.limbs),
+    ))
+  }
+
+  pub fn is_negative(&self) -> bool {
+    self.negative
+  }
+
+  pub fn neg(&self) -> BigInt {
+    BigInt::normalize(!self.negative, self.limbs.clone())
+  }
+
+  fn normalize(negative: bool, mut limbs: Vec<u32>) -> BigInt {
+    while limbs.last() == Some(&0) {
+      limbs.pop();
+    }
+    let negative = negative && !limbs.is_empty();
+    BigInt { negative, limbs }
+  }
+
+  fn compare_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+  }
+
+  fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut limbs = Vec::new();
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+      let current = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+      limbs.push((current % BASE) as u32);
+      carry = current / BASE;
+    }
+    if carry > 0 {
+      limbs.push(carry as u32);
+    }
+    limbs
+  }
+
+  // expects |a| >= |b|
+  fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut limbs = Vec::new();
+    let mut borrow = 0i64;
+    for (i, &limb) in a.iter().enumerate() {
+      let mut current = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+      borrow = 0;
+      if current < 0 {
+        current += BASE as i64;
+        borrow = 1;
+      }
+      limbs.push(current as u32);
+    }
+    limbs
+  }
+}
+impl fmt::Display for BigInt {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut limbs = self.limbs.iter().rev();
+    match limbs.next() {
+      None => return write!(f, "0"),
+      Some(limb) if self.negative => write!(f, "-{}", limb)?,
+      Some(limb) => write!(f, "{}", limb)?,
+    }
+    limbs.try_for_each(|limb| write!(f, "{:09}", limb))
+  }
+}
+
+#[cfg(test)]
+mod big_int_spec;
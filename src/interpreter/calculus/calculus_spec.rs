@@ -0,0 +1,95 @@
+use super::super::run;
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn value(formula: &str) -> Result<f64, Error> {
+  run(formula.to_string()).map(|value| value.parse().unwrap())
+}
+
+fn close(formula: &str, expected: f64) -> bool {
+  (value(formula).unwrap() - expected).abs() < 1e-9
+}
+
+#[test]
+fn testing_series_and_product() {
+  assert_eq!(value("series(i, i, 1, 100)"), Ok(5050.0));
+  assert_eq!(value("series(i^2, i, 1, 3)"), Ok(14.0));
+  assert_eq!(value("product(i, i, 1, 5)"), Ok(120.0));
+  assert_eq!(value("series(i, i, 1, 0)"), Ok(0.0), "should be 0 over an empty range");
+  assert_eq!(value("product(i, i, 1, 0)"), Ok(1.0), "should be 1 over an empty range");
+  assert_eq!(value("series(series(i*j, j, 1, i), i, 1, 3)"), Ok(25.0));
+}
+
+#[test]
+fn testing_sum_and_prod_add_up_their_arguments() {
+  let environment = Environment::new()
+    .with("a", 1.0)
+    .with("b", 2.0)
+    .with("c", 3.0)
+    .with("d", 4.0);
+  let body = |formula: &str| {
+    let ast = crate::parser::parse(crate::tokenizer::tokenize(formula.to_string())).unwrap();
+    super::super::evaluate(&ast, &environment)
+  };
+
+  assert_eq!(
+    body("sum(a, b, c, d)"),
+    Ok(10.0),
+    "should not read b as the variable of a series"
+  );
+  assert_eq!(body("prod(a, b, c, d)"), Ok(24.0));
+  assert_eq!(body("series(a, b, c, d)"), Ok(2.0));
+  assert_eq!(body("product(a, b, c, d)"), Ok(1.0));
+}
+
+#[test]
+fn testing_integrate() {
+  assert!(close("integrate(x^2, x, 0, 3)", 9.0));
+  assert!(close("integrate(sin(x), x, 0, pi)", 2.0));
+  assert!(close("integrate(exp(x), x, 1, 0)", 1.0 - std::f64::consts::E));
+  assert!(
+    close("integrate(1/sqrt(x), x, 0, 1)", 2.0),
+    "should handle a singular end"
+  );
+  assert!(close("integrate(integrate(x*y, y, 0, x), x, 0, 1)", 0.125));
+  assert_eq!(value("integrate(x, x, 2, 2)"), Ok(0.0));
+}
+
+#[test]
+fn testing_bound_variable_scope() {
+  let environment = Environment::new().with("i", 10.0);
+  let body = |formula: &str| {
+    let ast = crate::parser::parse(crate::tokenizer::tokenize(formula.to_string())).unwrap();
+    super::super::evaluate(&ast, &environment)
+  };
+
+  assert_eq!(body("series(i, i, 1, 3) + i"), Ok(16.0), "should restore the outer i");
+  assert_eq!(
+    body("series(k, k, 1, i)"),
+    Ok(55.0),
+    "should see outer variables in the bounds"
+  );
+}
+
+#[test]
+fn testing_calculus_errors() {
+  assert_eq!(
+    value("product(i, 2, 1, 3)"),
+    Err(Error::InvalidExpression(
+      "Bad format: Function product expects a variable as its 2nd argument".to_string()
+    ))
+  );
+  assert_eq!(
+    value("product(i, i, 1)"),
+    Err(Error::InvalidExpression(
+      "Bad format: Function product expects 4 arguments".to_string()
+    ))
+  );
+  assert_eq!(
+    value("series(i, i, 1, 2.5)"),
+    Err(Error::InvalidArgument(
+      "series expects integer bounds, got 1 and 2.5".to_string()
+    ))
+  );
+  assert_eq!(value("series(1/i, i, 0, 3)"), Err(Error::DivisionByZero));
+}
@@ -0,0 +1,196 @@
+use super::{checked, describe, evaluate_node, Environment};
+use crate::parser::Node;
+use crate::settings::Floats;
+use crate::tokenizer::Kind;
+use crate::Error;
+
+/// Functions called as `f(expression, variable, from, to)`, whose expression
+/// is evaluated once per value bound to the variable.
+pub const FUNCTIONS: [&str; 3] = ["integrate", "product", "series"];
+
+/// Subintervals the integration may split into before giving up.
+const MAX_INTERVALS: usize = 1000;
+
+const TOLERANCE: f64 = 1e-10;
+
+// Gauss-Kronrod 7-15 abscissae and weights, from the outermost node inwards.
+const KRONROD_NODES: [f64; 8] = [
+  0.991_455_371_120_812_6,
+  0.949_107_912_342_758_5,
+  0.864_864_423_359_769_1,
+  0.741_531_185_599_394_4,
+  0.586_087_235_467_691_1,
+  0.405_845_151_377_397_2,
+  0.207_784_955_007_898_5,
+  0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+  0.022_935_322_010_529_225,
+  0.063_092_092_629_978_55,
+  0.104_790_010_322_250_18,
+  0.140_653_259_715_525_92,
+  0.169_004_726_639_267_9,
+  0.190_350_578_064_785_4,
+  0.204_432_940_075_298_9,
+  0.209_482_141_084_727_83,
+];
+// Weights of the Gauss nodes, the odd Kronrod nodes and the centre.
+const GAUSS_WEIGHTS: [f64; 4] = [
+  0.129_484_966_168_869_7,
+  0.279_705_391_489_276_7,
+  0.381_830_050_505_118_9,
+  0.417_959_183_673_469_4,
+];
+
+pub fn call(name: &str, arguments: &[Node], environment: &Environment) -> Result<f64, Error> {
+  let (body, variable, from, to) = split_arguments(name, arguments)?;
+  let from = evaluate_node(Some(from), environment)?;
+  let to = evaluate_node(Some(to), environment)?;
+
+  // the variable shadows any outer one only while the body is evaluated
+  let mut scope = environment.clone();
+  let mut body = |value: f64| {
+    scope.bind(&variable, value);
+    evaluate_node(Some(body), &scope)
+  };
+
+  let value = match name {
+    "integrate" => integrate(&mut body, from, to)?,
+    "series" => series(name, &mut body, from, to, 0.0, |total, value| total + value)?,
+    "product" => series(name, &mut body, from, to, 1.0, |total, value| total * value)?,
+    _ => return Err(Error::UndefinedIdentifier(name.to_string())),
+  };
+  checked_total(environment.floats, name, from, to, value)
+}
+
+/// The value of the series or integral `name` from `from` to `to`, whose
+/// terms are within range but may add up past it.
+pub(super) fn checked_total(floats: Floats, name: &str, from: f64, to: f64, value: f64) -> Result<f64, Error> {
+  checked(floats, value, false, || {
+    format!("{} from {} to {}", name, describe(from), describe(to))
+  })
+}
+
+/// Whether a call is a series or an integral. These have names of their own,
+/// so `sum(a, b, c, d)` always adds up its arguments whatever they are.
+pub(super) fn is_calculus(name: &str) -> bool {
+  FUNCTIONS.contains(&name)
+}
+
+/// The expression, variable and bounds of a call, checking their shape.
+pub(super) fn split_arguments<'a>(
+  name: &str,
+  arguments: &'a [Node],
+) -> Result<(&'a Node, String, &'a Node, &'a Node), Error> {
+  match arguments {
+    [body, Node::Token(variable), from, to] if variable.kind == Kind::Identifier => {
+      Ok((body, variable.to_string(), from, to))
+    }
+    [_, _, _, _] => Err(Error::InvalidExpression(format!(
+      "Bad format: Function {} expects a variable as its 2nd argument",
+      name
+    ))),
+    _ => Err(Error::InvalidExpression(format!(
+      "Bad format: Function {} expects 4 arguments",
+      name
+    ))),
+  }
+}
+
+/// Folds the body over the integers from `from` to `to`, giving `initial`
+/// when the range is empty.
+pub(super) fn series(
+  name: &str,
+  body: &mut impl FnMut(f64) -> Result<f64, Error>,
+  from: f64,
+  to: f64,
+  initial: f64,
+  operation: fn(f64, f64) -> f64,
+) -> Result<f64, Error> {
+  if from.fract() != 0.0 || to.fract() != 0.0 {
+    return Err(Error::InvalidArgument(format!(
+      "{} expects integer bounds, got {} and {}",
+      name, from, to
+    )));
+  }
+
+  let mut total = initial;
+  let mut index = from;
+  while index <= to {
+    total = operation(total, body(index)?);
+    index += 1.0;
+  }
+  Ok(total)
+}
+
+/// Adaptive Gauss-Kronrod quadrature, splitting the subinterval with the
+/// largest error estimate until the total error is within the tolerance.
+pub(super) fn integrate(body: &mut impl FnMut(f64) -> Result<f64, Error>, from: f64, to: f64) -> Result<f64, Error> {
+  if from == to {
+    return Ok(0.0);
+  }
+  if !from.is_finite() || !to.is_finite() {
+    return Err(Error::InvalidArgument(format!(
+      "integrate expects finite bounds, got {} and {}",
+      from, to
+    )));
+  }
+
+  let mut intervals = vec![kronrod(body, from, to)?];
+  loop {
+    let value: f64 = intervals.iter().map(|interval| interval.value).sum();
+    let error: f64 = intervals.iter().map(|interval| interval.error).sum();
+
+    if error <= TOLERANCE * value.abs().max(1.0) {
+      return Ok(value);
+    }
+    if intervals.len() >= MAX_INTERVALS || !value.is_finite() {
+      return Err(Error::NoConvergence(format!(
+        "integrate did not converge between {} and {}",
+        from, to
+      )));
+    }
+
+    let worst = (0..intervals.len())
+      .max_by(|a, b| intervals[*a].error.total_cmp(&intervals[*b].error))
+      .unwrap();
+    let Interval { from, to, .. } = intervals.swap_remove(worst);
+    let middle = (from + to) / 2.0;
+    intervals.push(kronrod(body, from, middle)?);
+    intervals.push(kronrod(body, middle, to)?);
+  }
+}
+
+struct Interval {
+  from: f64,
+  to: f64,
+  value: f64,
+  error: f64,
+}
+
+fn kronrod(body: &mut impl FnMut(f64) -> Result<f64, Error>, from: f64, to: f64) -> Result<Interval, Error> {
+  let centre = (from + to) / 2.0;
+  let half = (to - from) / 2.0;
+
+  let value = body(centre)?;
+  let mut kronrod = value * KRONROD_WEIGHTS[7];
+  let mut gauss = value * GAUSS_WEIGHTS[3];
+  for i in 0..7 {
+    let offset = half * KRONROD_NODES[i];
+    let pair = body(centre - offset)? + body(centre + offset)?;
+    kronrod += KRONROD_WEIGHTS[i] * pair;
+    if i % 2 == 1 {
+      gauss += GAUSS_WEIGHTS[i / 2] * pair;
+    }
+  }
+
+  Ok(Interval {
+    from,
+    to,
+    value: kronrod * half,
+    error: ((kronrod - gauss) * half).abs(),
+  })
+}
+
+#[cfg(test)]
+mod calculus_spec;
@@ -0,0 +1,86 @@
+use super::super::spec_helpers::{calculate, invalid, ok};
+use super::*;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn testing_civil_days() {
+  assert_eq!(days_from_civil(1970, 1, 1), 0);
+  assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+  assert_eq!(days_from_civil(1900, 3, 1), -25_508);
+  assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+  assert_eq!(civil_from_days(-1), (1969, 12, 31));
+}
+
+#[test]
+fn testing_literals() {
+  assert_eq!(calculate("2026-10-18"), ok("2026-10-18"));
+  assert_eq!(calculate("2026-10-18T09:05"), ok("2026-10-18T09:05"));
+  assert_eq!(calculate("2026-10-18T09:05:07"), ok("2026-10-18T09:05:07"));
+  assert_eq!(calculate("90min"), ok("1h 30min"));
+  assert_eq!(calculate("3d 4h"), ok("3d 4h"));
+  assert_eq!(calculate("1w 0.5s"), ok("7d 0.5s"));
+  assert_eq!(calculate("2026-02-29"), invalid("Date 2026-02-29 does not exist"));
+  assert_eq!(calculate("2024-02-29"), ok("2024-02-29"));
+  assert_eq!(
+    calculate("2026-10-18T24:00"),
+    invalid("Date 2026-10-18T24:00 does not exist")
+  );
+}
+
+#[test]
+fn testing_arithmetic() {
+  assert_eq!(calculate("2026-10-18 + 3d 4h"), ok("2026-10-21T04:00"));
+  assert_eq!(calculate("2h + 2026-10-18T23:00"), ok("2026-10-19T01:00"));
+  assert_eq!(calculate("2026-03-01 - 1d"), ok("2026-02-28"));
+  assert_eq!(calculate("2026-12-25 - 2026-10-18"), ok("68d"));
+  assert_eq!(calculate("2026-10-18 - 2026-10-19T06:00"), ok("-1d 6h"));
+  assert_eq!(calculate("-(1h 30min) * 2"), ok("-3h"));
+  assert_eq!(calculate("3 * 20min + 1h"), ok("2h"));
+  assert_eq!(calculate("1d / 4"), ok("6h"));
+  assert_eq!(calculate("(2026-10-19T09:30 - 2026-10-18) / 1h"), ok("33.5"));
+  assert_eq!(calculate("1h + 50%"), ok("1h 30min"));
+  assert_eq!(calculate("1d / 0"), Err(Error::DivisionByZero));
+  assert_eq!(
+    calculate("2026-10-18 + 2026-10-19"),
+    invalid("Operator + does not apply to a date and a date")
+  );
+  assert_eq!(
+    calculate("1h + 1"),
+    invalid("Operator + does not apply to a duration and a number")
+  );
+  assert_eq!(calculate("(1h)!"), invalid("Operator ! does not apply to a duration"));
+}
+
+#[test]
+fn testing_functions() {
+  assert_eq!(calculate("weekday(2026-10-18)"), ok("7"));
+  assert_eq!(calculate("weekday(2026-10-19T23:59)"), ok("1"));
+  assert_eq!(calculate("weekday(1970-01-01)"), ok("4"));
+  assert_eq!(calculate("add_business_days(2026-10-18, 45)"), ok("2026-12-18"));
+  assert_eq!(calculate("add_business_days(2026-10-17, -1)"), ok("2026-10-16"));
+  assert_eq!(calculate("add_business_days(2026-10-21, -7)"), ok("2026-10-12"));
+  assert_eq!(
+    calculate("add_business_days(1999-12-31T08:00, 3)"),
+    ok("2000-01-05T08:00")
+  );
+  assert_eq!(calculate("add_business_days(2026-10-18, 0)"), ok("2026-10-18"));
+  assert_eq!(calculate("sqrt((2026-10-05 - 2026-10-01) / 1d)"), ok("2"));
+  assert_eq!(
+    calculate("weekday(3)"),
+    invalid("Function weekday expects a date, got a number")
+  );
+  assert_eq!(
+    calculate("add_business_days(2026-10-18, 1.5)"),
+    invalid("Function add_business_days expects a whole number of days, got 1.5")
+  );
+  assert_eq!(
+    calculate("weekday(2026-10-18, 1)"),
+    Err(Error::InvalidExpression(
+      "Bad format: Function weekday expects 1 argument".to_string()
+    ))
+  );
+  assert_eq!(
+    calculate("sqrt(1h)"),
+    invalid("Function sqrt does not apply to a duration")
+  );
+}
@@ -0,0 +1,371 @@
+use super::{
+  calculus, checked, checked_call, checked_variadic, evaluate_chain, evaluate_node as evaluate_number, format,
+  function_named, interval, operate as operate_number, start_using, variadic, Environment, Value as Held,
+};
+use crate::parser::{Ast, Node};
+use crate::settings::Floats;
+use crate::tokenizer::{Key, Kind, Token};
+use crate::Error;
+use std::fmt;
+
+/// Functions taking dates, which the other evaluators reject.
+pub const FUNCTIONS: [&str; 2] = ["add_business_days", "weekday"];
+
+const MINUTE: f64 = 60.0;
+const HOUR: f64 = 60.0 * MINUTE;
+const DAY: f64 = 24.0 * HOUR;
+
+/// A value of an expression with dates, which are all in UTC so that the
+/// results never depend on the host.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+  Number(f64),
+  /// Seconds since 1970-01-01T00:00
+  Date(f64),
+  /// Seconds, negative for a duration back in time
+  Duration(f64),
+}
+impl Value {
+  fn describe(&self) -> &'static str {
+    match self {
+      Value::Number(_) => "a number",
+      Value::Date(_) => "a date",
+      Value::Duration(_) => "a duration",
+    }
+  }
+}
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Value::Number(value) => f.write_str(&format(Ok(value))),
+      Value::Date(seconds) => write_date(f, seconds),
+      Value::Duration(seconds) => write_duration(f, seconds),
+    }
+  }
+}
+
+/// `2026-10-18`, with the time as `T09:30` or `T09:30:15` unless it is
+/// midnight.
+fn write_date(f: &mut fmt::Formatter, seconds: f64) -> fmt::Result {
+  let days = (seconds / DAY).floor();
+  let (year, month, day) = civil_from_days(days as i64);
+  write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+
+  let time = seconds - days * DAY;
+  if time == 0.0 {
+    return Ok(());
+  }
+  let (hours, minutes, seconds) = ((time / HOUR).floor(), (time % HOUR / MINUTE).floor(), time % MINUTE);
+  write!(f, "T{:02}:{:02}", hours, minutes)?;
+  match seconds {
+    _ if seconds == 0.0 => Ok(()),
+    _ if seconds < 10.0 => write!(f, ":0{}", seconds),
+    _ => write!(f, ":{}", seconds),
+  }
+}
+
+/// `3d 4h 5min 6s`, leaving out the units with nothing in them.
+fn write_duration(f: &mut fmt::Formatter, seconds: f64) -> fmt::Result {
+  if seconds < 0.0 {
+    f.write_str("-")?;
+  }
+  let mut rest = seconds.abs();
+  let mut parts = Vec::new();
+  for (unit, size) in [("d", DAY), ("h", HOUR), ("min", MINUTE)].iter() {
+    let count = (rest / size).floor();
+    if count > 0.0 {
+      parts.push(format!("{}{}", count, unit));
+      rest -= count * size;
+    }
+  }
+  if rest > 0.0 || parts.is_empty() {
+    parts.push(format!("{}s", format(Ok(rest))));
+  }
+  f.write_str(&parts.join(" "))
+}
+
+/// Days since 1970-01-01 of a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  // years start in March, so the leap day is the last of its year
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day of the date `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month + 2) / 5 + 1;
+  let month = if month < 10 { month + 3 } else { month - 9 };
+  let year = year_of_era + era * 400;
+  (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Seconds since 1970-01-01T00:00 of a date token, as the lexer reads it.
+fn parse_date(text: &str) -> Result<f64, Error> {
+  let field = |from: usize| text.get(from..from + 2).and_then(|field| field.parse::<i64>().ok());
+  let year = text.get(..4).and_then(|year| year.parse::<i64>().ok());
+  let (year, month, day) = match (year, field(5), field(8)) {
+    (Some(year), Some(month), Some(day)) => (year, month, day),
+    _ => return Err(Error::TokenNaN),
+  };
+  let (hours, minutes, seconds) = (field(11).unwrap_or(0), field(14).unwrap_or(0), field(17).unwrap_or(0));
+
+  let days = days_from_civil(year, month, day);
+  let exists = (1..=12).contains(&month) && day >= 1 && civil_from_days(days) == (year, month, day);
+  if !exists || hours >= 24 || minutes >= 60 || seconds >= 60 {
+    return Err(Error::InvalidArgument(format!("Date {} does not exist", text)));
+  }
+  Ok(days as f64 * DAY + (hours * 3600 + minutes * 60 + seconds) as f64)
+}
+
+/// Seconds of a duration token, as `3d 4h`.
+fn parse_duration(text: &str) -> Result<f64, Error> {
+  text
+    .split(' ')
+    .filter(|part| !part.is_empty())
+    .try_fold(0.0, |total, part| {
+      let unit = part
+        .find(|letter: char| letter.is_ascii_alphabetic())
+        .unwrap_or(part.len());
+      let size = match &part[unit..] {
+        "w" => 7.0 * DAY,
+        "d" => DAY,
+        "h" => HOUR,
+        "min" => MINUTE,
+        "s" => 1.0,
+        _ => return Err(Error::TokenNaN),
+      };
+      let count: f64 = part[..unit].parse().map_err(|_| Error::TokenNaN)?;
+      Ok(total + count * size)
+    })
+}
+
+/// ISO 8601 day of the week, from 1 for Monday to 7 for Sunday.
+fn weekday(date: f64) -> f64 {
+  // 1970-01-01 was a Thursday
+  ((date / DAY).floor() + 3.0).rem_euclid(7.0) + 1.0
+}
+
+/// The date `days` working days after `date`, skipping Saturdays and Sundays
+/// as spreadsheets do, or before it for a negative count.
+fn add_business_days(date: f64, days: f64) -> Result<f64, Error> {
+  if days.fract() != 0.0 || !days.is_finite() {
+    return Err(Error::InvalidArgument(format!(
+      "Function add_business_days expects a whole number of days, got {}",
+      days
+    )));
+  }
+  if days == 0.0 {
+    return Ok(date);
+  }
+
+  let step = days.signum() * DAY;
+  let mut date = date;
+  // counting from a weekend is counting from the working day on its other
+  // side, which makes every 5 working days a week
+  while weekday(date) > 5.0 {
+    date -= step;
+  }
+  date += (days.abs() / 5.0).floor() * 7.0 * step;
+  let mut remaining = days.abs() % 5.0;
+  while remaining > 0.0 {
+    date += step;
+    if weekday(date) <= 5.0 {
+      remaining -= 1.0;
+    }
+  }
+  Ok(date)
+}
+
+/// Whether `ast` has a date or a duration, written or in a variable, or
+/// calls a function taking dates.
+pub fn uses_dates(ast: &Ast, environment: &Environment) -> bool {
+  ast
+    .spine_operands()
+    .into_iter()
+    .any(|node| node_uses_dates(node, environment))
+}
+
+fn node_uses_dates(node: &Node, environment: &Environment) -> bool {
+  let uses = |node: &Node| node_uses_dates(node, environment);
+  match node {
+    Node::Ast(ast) => uses_dates(ast, environment),
+    Node::Call(function, arguments) => FUNCTIONS.contains(&function.to_string().as_str()) || arguments.iter().any(uses),
+    Node::Factorial(node) | Node::Percentage(node) => uses(node),
+    Node::Interval(lower, upper) => uses(lower) || uses(upper),
+    Node::Matrix(rows) => rows.iter().flatten().any(uses),
+    Node::Token(token) => {
+      matches!(token.kind, Kind::Date | Kind::Duration)
+        || environment.holds(node, |value| matches!(value, Held::Date(_) | Held::Duration(_)))
+    }
+  }
+}
+
+/// The error of the evaluators that only produce numbers when they meet a
+/// date or a duration.
+pub fn unsupported(node: &Node) -> Error {
+  Error::InvalidArgument(format!("Date expression {} cannot be evaluated as a number", node))
+}
+
+pub fn evaluate(ast: &Ast, environment: &Environment) -> Result<Value, Error> {
+  let floats = environment.floats;
+  let spine = ast.left_spine();
+  let start = start_using(&spine, |node| node_uses_dates(node, environment));
+  let check = |ast: &Ast| ast.operator.as_ref().map(|_| ()).ok_or(Error::TokenNaO);
+  let operand = |node: Option<&Node>| evaluate_node(node, environment);
+
+  evaluate_chain(&spine[start..], environment, check, operand, |value_a, ast| {
+    let operator = ast.operator.as_ref().ok_or(Error::TokenNaO)?;
+    let value = match (&operator.keys[0], ast.operand_b.as_ref()) {
+      // a ± b% is relative to a, so 3d + 50% is 4d 12h
+      (Key::Addition, Some(Node::Percentage(node))) | (Key::Subtraction, Some(Node::Percentage(node))) => {
+        let rate = evaluate_number(Some(node), environment)? / 100.0;
+        let change = operate(
+          floats,
+          &Token::new_operator(Key::Multiplication),
+          value_a,
+          Value::Number(rate),
+        )?;
+        operate(floats, operator, value_a, change)?
+      }
+      (_, operand_b) => operate(floats, operator, value_a, evaluate_node(operand_b, environment)?)?,
+    };
+    // the seconds of dates and durations, as well as numbers
+    let (Value::Number(number) | Value::Date(number) | Value::Duration(number)) = value;
+    checked(floats, number, false, || ast.to_string())?;
+    Ok(value)
+  })
+}
+
+pub fn evaluate_node(node: Option<&Node>, environment: &Environment) -> Result<Value, Error> {
+  let node = match node {
+    Some(node) if node_uses_dates(node, environment) => node,
+    node => return evaluate_number(node, environment).map(Value::Number),
+  };
+  environment.budget.spend(1)?;
+
+  match node {
+    Node::Ast(ast) => evaluate(ast, environment),
+    Node::Call(function, arguments) => call(function, arguments, environment),
+    Node::Token(token) if token.kind == Kind::Identifier => match environment.get(&token.to_string()) {
+      Some(Held::Date(seconds)) => Ok(Value::Date(*seconds)),
+      Some(Held::Duration(seconds)) => Ok(Value::Duration(*seconds)),
+      _ => evaluate_number(Some(node), environment).map(Value::Number),
+    },
+    Node::Token(token) if token.kind == Kind::Date => parse_date(&token.to_string()).map(Value::Date),
+    Node::Token(token) => parse_duration(&token.to_string()).map(Value::Duration),
+    Node::Factorial(operand) | Node::Percentage(operand) => {
+      let postfix = if matches!(node, Node::Factorial(_)) { "!" } else { "%" };
+      let value = evaluate_node(Some(operand), environment)?;
+      Err(Error::InvalidArgument(format!(
+        "Operator {} does not apply to {}",
+        postfix,
+        value.describe()
+      )))
+    }
+    Node::Interval(_, _) => Err(interval::unsupported(node)),
+    Node::Matrix(_) => Err(unsupported(node)),
+  }
+}
+
+fn operate(floats: Floats, operator: &Token, value_a: Value, value_b: Value) -> Result<Value, Error> {
+  use Value::*;
+
+  match (&operator.keys[0], value_a, value_b) {
+    (_, Number(a), Number(b)) => operate_number(floats, operator, a, b).map(Number),
+    // the 0 that parse pads a single value with, and the sign of a duration
+    (Key::Addition, Number(zero), value) | (Key::Addition, value, Number(zero)) if zero == 0.0 => Ok(value),
+    (Key::Subtraction, Number(0.0), Duration(b)) => Ok(Duration(-b)),
+    (Key::Addition, Date(a), Duration(b)) | (Key::Addition, Duration(b), Date(a)) => Ok(Date(a + b)),
+    (Key::Subtraction, Date(a), Duration(b)) => Ok(Date(a - b)),
+    (Key::Subtraction, Date(a), Date(b)) => Ok(Duration(a - b)),
+    (Key::Addition, Duration(a), Duration(b)) => Ok(Duration(a + b)),
+    (Key::Subtraction, Duration(a), Duration(b)) => Ok(Duration(a - b)),
+    (Key::Multiplication, Duration(a), Number(b))
+    | (Key::Multiplication, Number(b), Duration(a))
+    | (Key::Of, Number(b), Duration(a)) => Ok(Duration(a * b)),
+    (Key::Division, Duration(_), Number(b)) | (Key::Division, Duration(_), Duration(b)) if b == 0.0 => {
+      Err(Error::DivisionByZero)
+    }
+    (Key::Division, Duration(a), Number(b)) => Ok(Duration(a / b)),
+    // how many times a duration fits in another, as in (d2 - d1) / 1h
+    (Key::Division, Duration(a), Duration(b)) => Ok(Number(a / b)),
+    (key, a, b) => Err(Error::InvalidArgument(format!(
+      "Operator {} does not apply to {} and {}",
+      key,
+      a.describe(),
+      b.describe()
+    ))),
+  }
+}
+
+fn call(function: &Token, arguments: &[Node], environment: &Environment) -> Result<Value, Error> {
+  let name = function.to_string();
+  if calculus::is_calculus(&name) {
+    return Err(unsupported(&Node::Call(function.clone(), arguments.to_vec())));
+  }
+  let values = arguments
+    .iter()
+    .map(|argument| evaluate_node(Some(argument), environment))
+    .collect::<Result<Vec<Value>, Error>>()?;
+  let expects = |count: usize| {
+    let plural = if count == 1 { "" } else { "s" };
+    Err(Error::InvalidExpression(format!(
+      "Bad format: Function {} expects {} argument{}",
+      name, count, plural
+    )))
+  };
+
+  match (name.as_str(), values.as_slice()) {
+    ("weekday", [Value::Date(date)]) => Ok(Value::Number(weekday(*date))),
+    ("weekday", [value]) => Err(Error::InvalidArgument(format!(
+      "Function weekday expects a date, got {}",
+      value.describe()
+    ))),
+    ("weekday", _) => expects(1),
+    ("add_business_days", [Value::Date(date), Value::Number(days)]) => add_business_days(*date, *days).map(Value::Date),
+    ("add_business_days", [_, _]) => Err(Error::InvalidArgument(
+      "Function add_business_days expects a date and a number of days".to_string(),
+    )),
+    ("add_business_days", _) => expects(2),
+    _ => {
+      let numbers = values
+        .iter()
+        .map(|value| match value {
+          Value::Number(number) => Ok(*number),
+          value => Err(Error::InvalidArgument(format!(
+            "Function {} does not apply to {}",
+            name,
+            value.describe()
+          ))),
+        })
+        .collect::<Result<Vec<f64>, Error>>()?;
+      match (
+        variadic::function_named(&name),
+        function_named(&name),
+        numbers.as_slice(),
+      ) {
+        (Some(function), _, numbers) => {
+          checked_variadic(environment.floats, &name, numbers, function(numbers)?).map(Value::Number)
+        }
+        (None, Some(function), [number]) => {
+          checked_call(environment.floats, &name, *number, function(*number)).map(Value::Number)
+        }
+        (None, Some(_), _) => expects(1),
+        (None, None, _) => Err(unsupported(&Node::Call(function.clone(), arguments.to_vec()))),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod calendar_spec;
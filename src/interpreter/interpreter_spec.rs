@@ -1,6 +1,9 @@
 use super::*;
-use crate::tokenizer::Key;
+use crate::parser::parse;
+use crate::settings::{Angle, Floats, IntegerType, Limits, Overflow, Programmer};
+use crate::tokenizer::{tokenize, Key};
 use pretty_assertions::assert_eq;
+use std::time::Duration;
 
 #[test]
 fn testing_run() {
@@ -11,17 +14,122 @@ fn testing_run() {
 }
 
 #[test]
-fn testing_interpret() {
+fn testing_evaluate() {
   let ast = Ast::new(
     Ast::new_operator(Key::Addition),
     Ast::new_number(vec![Key::One]),
     Ast::new_number(vec![Key::Two]),
   );
-  let result = interpret(Ok(ast));
+  let result = evaluate(&ast, &Environment::new());
 
   assert_eq!(result, Ok(3.0), "should calculate formula 1+2 and return 3");
 }
 
+#[test]
+fn testing_evaluate_percentage_relative_to_operand_a() {
+  let percentage = |keys| Some(Node::Percentage(Box::new(Ast::new_number(keys).unwrap())));
+
+  let addition = Ast::new(
+    Ast::new_operator(Key::Addition),
+    Ast::new_number(vec![Key::Two, Key::Zero, Key::Zero]),
+    percentage(vec![Key::One, Key::Five]),
+  );
+  let subtraction = Ast::new(
+    Ast::new_operator(Key::Subtraction),
+    Ast::new_number(vec![Key::Two, Key::Zero, Key::Zero]),
+    percentage(vec![Key::One, Key::Five]),
+  );
+  let multiplication = Ast::new(
+    Ast::new_operator(Key::Multiplication),
+    Ast::new_number(vec![Key::Two, Key::Zero, Key::Zero]),
+    percentage(vec![Key::One, Key::Five]),
+  );
+
+  assert_eq!(
+    evaluate(&addition, &Environment::new()),
+    Ok(230.0),
+    "should add 15% of 200 to 200"
+  );
+  assert_eq!(
+    evaluate(&subtraction, &Environment::new()),
+    Ok(170.0),
+    "should subtract 15% of 200 from 200"
+  );
+  assert_eq!(
+    evaluate(&multiplication, &Environment::new()),
+    Ok(30.0),
+    "should scale 200 by 15/100"
+  );
+}
+
+#[test]
+fn testing_evaluate_node_percentage() {
+  let node = Node::Percentage(Box::new(Ast::new_number(vec![Key::Five, Key::Zero]).unwrap()));
+
+  assert_eq!(
+    evaluate_node(Some(&node), &Environment::new()),
+    Ok(0.5),
+    "should divide the percentage by 100"
+  );
+}
+
+#[test]
+fn testing_basic_operation_calling_of() {
+  let operator = Token::new_operator(Key::Of);
+  let result = basic_operation(&operator, 0.15, 200.0);
+
+  assert_eq!(result, Ok(30.0), "should take the rate of the right operand");
+}
+
+#[test]
+fn testing_fold_exact() {
+  let fold = |formula: &str| {
+    let ast = parse(tokenize(formula.to_string())).unwrap();
+    fold_exact(Node::Ast(Box::new(ast)), &Budget::default()).unwrap()
+  };
+
+  assert_eq!(
+    fold("25!+1"),
+    Folded::Exact(BigInt::parse("15511210043330985984000001").unwrap()),
+    "should keep every digit of 25!+1"
+  );
+  assert_eq!(
+    fold("1.5 + 2*3").into_ast(),
+    parse(tokenize("1.5 + 6".to_string())).unwrap(),
+    "should only fold the integer subtrees"
+  );
+  assert_eq!(
+    fold("x + (2-5)").into_ast(),
+    parse(tokenize("x + (0-3)".to_string())).unwrap()
+  );
+  assert_eq!(
+    fold("7/2").into_ast(),
+    parse(tokenize("7/2".to_string())).unwrap(),
+    "should leave divisions with a remainder to floats"
+  );
+}
+
+#[test]
+fn testing_exact_pow_and_factorial() {
+  let budget = Budget::default();
+  let pow =
+    |base: u64, exponent| exact_pow(&BigInt::from_u64(base), exponent, &budget).map(|integer| integer.to_string());
+  let factorial = |n| exact_factorial(n, &budget).map(|integer| integer.to_string());
+
+  assert_eq!(pow(2, 100), Ok("1267650600228229401496703205376".to_string()));
+  assert_eq!(pow(7, 0), Ok("1".to_string()));
+  assert_eq!(factorial(0), Ok("1".to_string()));
+  assert_eq!(factorial(5), Ok("120".to_string()));
+  assert_eq!(factorial(25), Ok("15511210043330985984000000".to_string()));
+}
+
+#[test]
+fn testing_constant() {
+  assert_eq!(constant("pi"), Ok(std::f64::consts::PI));
+  assert_eq!(constant("e"), Ok(std::f64::consts::E));
+  assert_eq!(constant("x"), Err(Error::UndefinedIdentifier("x".to_string())));
+}
+
 #[test]
 fn testing_format() {
   assert_eq!(
@@ -34,6 +142,203 @@ fn testing_format() {
     "0".to_string(),
     "should convert to integer numbers without fraction"
   );
+  assert_eq!(format(Ok(f64::NEG_INFINITY)), "-inf".to_string());
+  assert_eq!(format(Ok(f64::NAN)), "nan".to_string());
+}
+
+#[test]
+fn testing_float_policies() {
+  let ieee = Settings {
+    floats: Floats::Ieee,
+    ..Settings::default()
+  };
+
+  assert_eq!(
+    run("1.5^2000".to_string()),
+    Err(Error::Overflow("1.5^2000 is too large for a float".to_string()))
+  );
+  assert_eq!(
+    run("2^-1100".to_string()),
+    Err(Error::Underflow("2^-1100 is too small for a float".to_string()))
+  );
+  assert_eq!(
+    run("0.5^1060*3".to_string()),
+    Err(Error::Underflow("0.5^1060 is too small for a float".to_string())),
+    "should reject subnormal results"
+  );
+  assert_eq!(
+    run("1 + asin(2)".to_string()),
+    Err(Error::NotANumber("asin(2) is not a number".to_string()))
+  );
+  assert_eq!(
+    run("inf".to_string()),
+    Err(Error::Overflow("inf is too large for a float".to_string()))
+  );
+  assert_eq!(
+    run("product(10^i, i, 1, 30)".to_string()),
+    Err(Error::Overflow(
+      "product from 1 to 30 is too large for a float".to_string()
+    ))
+  );
+  assert_eq!(
+    run("[1, 2]*10^300*10^8".to_string()),
+    Err(Error::Overflow(
+      "An element of [1, 2]*10^300*10^8 is too large for a float".to_string()
+    ))
+  );
+  assert_eq!(
+    run("300!".to_string()).map(|digits| digits.len()),
+    Ok(615),
+    "should stay exact"
+  );
+  assert_eq!(run("ln(1) + sin(0)".to_string()), Ok("0".to_string()));
+  assert_eq!(
+    run("10^308*10.0".to_string()),
+    Err(Error::Overflow("1e308*10 is too large for a float".to_string())),
+    "should not print the 309 digits of the operand"
+  );
+  assert_eq!(
+    run("2^1024/3".to_string()),
+    Err(Error::Overflow(
+      "1.7976931348623159e308 is too large for a float".to_string()
+    ))
+  );
+
+  assert_eq!(
+    run("1e308*10".to_string()),
+    Err(Error::Overflow("1e308*10 is too large for a float".to_string()))
+  );
+  assert_eq!(
+    run("1e-400".to_string()),
+    Err(Error::Underflow("1e-400 is too small for a float".to_string()))
+  );
+  assert_eq!(run("0e5 + 2.5e-3".to_string()), Ok("0.0025".to_string()));
+
+  assert_eq!(run_with("1e308*10".to_string(), &ieee), Ok("inf".to_string()));
+  assert_eq!(run_with("1.5^2000".to_string(), &ieee), Ok("inf".to_string()));
+  assert_eq!(run_with("2^-1100".to_string(), &ieee), Ok("0".to_string()));
+  assert_eq!(run_with("-1/0.5^-2000".to_string(), &ieee), Ok("0".to_string()));
+  assert_eq!(run_with("inf - inf".to_string(), &ieee), Ok("nan".to_string()));
+  assert_eq!(run_with("1/inf + -inf".to_string(), &ieee), Ok("-inf".to_string()));
+  assert_eq!(run_script("x = sqrt(-1); x + 1", &ieee), Ok("nan".to_string()));
+  assert_eq!(run_with("1/0".to_string(), &ieee), Ok("inf".to_string()));
+  assert_eq!(run_with("-1/0".to_string(), &ieee), Ok("-inf".to_string()));
+  assert_eq!(run_with("0/0".to_string(), &ieee), Ok("nan".to_string()));
+  assert_eq!(run_script("x = 2 - 2; 1/x", &ieee), Ok("inf".to_string()));
+  assert_eq!(run_with("[1, -1]/0".to_string(), &ieee), Ok("[inf, -inf]".to_string()));
+  assert_eq!(
+    evaluate(
+      &parse(tokenize("1/(2 - 2)".to_string())).unwrap(),
+      &Environment::new().with_floats(Floats::Ieee)
+    ),
+    Ok(f64::INFINITY),
+    "should divide by zero in the tree walker too"
+  );
+  assert_eq!(run("0/0".to_string()), Err(Error::DivisionByZero));
+}
+
+#[test]
+fn testing_abbreviate() {
+  assert_eq!(abbreviate("12345"), "12345");
+  assert_eq!(abbreviate(&format!("1{}", "0".repeat(308))), "1e308");
+  assert_eq!(abbreviate(&format!("25{}", "0".repeat(30))), "2.5e31");
+  assert_eq!(abbreviate(&format!("0.{}3", "0".repeat(400))), "3e-401");
+  assert_eq!(describe(1e308), "1e308");
+  assert_eq!(describe(-1.5e-9), "-1.5e-9");
+  assert_eq!(describe(0.25), "0.25");
+}
+
+#[test]
+fn testing_limits() {
+  let limited = |limits: Limits| Settings {
+    limits,
+    ..Settings::default()
+  };
+  let steps = limited(Limits {
+    steps: 1000,
+    ..Limits::default()
+  });
+  let time = limited(Limits {
+    steps: u64::MAX,
+    time: Some(Duration::from_millis(10)),
+    ..Limits::default()
+  });
+
+  assert_eq!(
+    run_with(
+      format!("{}1{}", "sin(".repeat(128), ")".repeat(128)),
+      &Settings::default()
+    )
+    .map(|value| value.len() > 1),
+    Ok(true),
+    "should evaluate what is within the depth"
+  );
+  assert_eq!(
+    run_with(
+      format!("{}1{}", "(".repeat(1000), ")".repeat(1000)),
+      &Settings::default()
+    ),
+    Err(Error::TooDeep(128))
+  );
+  assert_eq!(
+    run_with("series(i, i, 1, 100)".to_string(), &steps),
+    Ok("5050".to_string())
+  );
+  assert_eq!(
+    run_with("series(i, i, 1, 10^6)".to_string(), &steps),
+    Err(Error::TooManySteps(1000))
+  );
+  assert_eq!(
+    run_with("[1, 2] * series(i, i, 1, 10^6)".to_string(), &steps),
+    Err(Error::TooManySteps(1000)),
+    "should count the steps of the tree walker too"
+  );
+  assert_eq!(
+    run_script("a = series(i, i, 1, 600); series(i, i, 1, 600)", &steps),
+    Err(Error::TooManySteps(1000)),
+    "should share the steps between the statements of a script"
+  );
+  assert_eq!(
+    run_with("series(i, i, 1, 10^12)".to_string(), &time),
+    Err(Error::Timeout(Duration::from_millis(10)))
+  );
+  assert_eq!(
+    run_with("(1000!)^1000".to_string(), &steps),
+    Err(Error::TooManySteps(1000)),
+    "should count the limbs of exact integers"
+  );
+  assert_eq!(
+    run_with("2^10^10".to_string(), &time),
+    Err(Error::Timeout(Duration::from_millis(10)))
+  );
+  assert_eq!(
+    run_with(
+      format!("{}+", vec!["(1)"; 4096].join("+")),
+      &limited(Limits {
+        time: Some(Duration::from_millis(0)),
+        ..Limits::default()
+      })
+    ),
+    Err(Error::Timeout(Duration::from_millis(0))),
+    "should take the time of tokenizing and parsing from the limits too"
+  );
+}
+
+#[test]
+fn testing_run_long_chains() {
+  let sum = (1..=5000).map(|i| i.to_string()).collect::<Vec<String>>().join("+");
+
+  assert_eq!(
+    run(sum.clone()),
+    Ok("12502500".to_string()),
+    "should not count the operations of a chain as levels"
+  );
+  assert_eq!(run(format!("{}+0.5", sum)), Ok("12502500.5".to_string()));
+  assert_eq!(run(format!("{}+[0, 1]", sum)), Ok("[12502500, 12502501]".to_string()));
+  assert_eq!(
+    run_script(&format!("n = {}\nn/2", sum), &Settings::default()),
+    Ok("6251250".to_string())
+  );
 }
 
 #[test]
@@ -42,10 +347,10 @@ fn testing_to_float() {
   let token_02 = Token::new_number(vec![Key::One, Key::Dot, Key::Five]);
   let token_03 = Token::new_operator(Key::Multiplication);
 
-  assert_eq!(to_float(token_01), Ok(1.0), "should convert token to float 1.0");
-  assert_eq!(to_float(token_02), Ok(1.5), "should convert token to float 1.5");
+  assert_eq!(to_float(&token_01), Ok(1.0), "should convert token to float 1.0");
+  assert_eq!(to_float(&token_02), Ok(1.5), "should convert token to float 1.5");
   assert_eq!(
-    to_float(token_03),
+    to_float(&token_03),
     Err(Error::TokenNaN),
     "should return an error when token is not a number"
   );
@@ -56,7 +361,7 @@ fn testing_basic_operation_calling_multiplication() {
   let operator = Token::new_operator(Key::Multiplication);
   let operand_a = 6.0;
   let operand_b = 7.0;
-  let result = basic_operation(operator, operand_a, operand_b);
+  let result = basic_operation(&operator, operand_a, operand_b);
 
   assert_eq!(
     result,
@@ -70,7 +375,7 @@ fn testing_basic_operation_calling_division() {
   let operator = Token::new_operator(Key::Division);
   let operand_a = 210.0;
   let operand_b = 5.0;
-  let result = basic_operation(operator, operand_a, operand_b);
+  let result = basic_operation(&operator, operand_a, operand_b);
 
   assert_eq!(result, Ok(42.0), "should calculate tokens using the division function");
 }
@@ -80,7 +385,7 @@ fn testing_basic_operation_calling_addiction() {
   let operator = Token::new_operator(Key::Addition);
   let operand_a = 17.0;
   let operand_b = 25.0;
-  let result = basic_operation(operator, operand_a, operand_b);
+  let result = basic_operation(&operator, operand_a, operand_b);
 
   assert_eq!(result, Ok(42.0), "should calculate tokens using the addition function");
 }
@@ -90,7 +395,7 @@ fn testing_basic_operation_calling_subtraction() {
   let operator = Token::new_operator(Key::Subtraction);
   let operand_a = 78.0;
   let operand_b = 36.0;
-  let result = basic_operation(operator, operand_a, operand_b);
+  let result = basic_operation(&operator, operand_a, operand_b);
 
   assert_eq!(
     result,
@@ -104,7 +409,7 @@ fn testing_basic_operation_returning_error() {
   let operator = Token::new_number(vec![Key::Zero]);
   let operand_a = 78.0;
   let operand_b = 36.0;
-  let result = basic_operation(operator, operand_a, operand_b);
+  let result = basic_operation(&operator, operand_a, operand_b);
 
   assert_eq!(
     result,
@@ -113,6 +418,45 @@ fn testing_basic_operation_returning_error() {
   );
 }
 
+#[test]
+fn testing_basic_operation_calling_bitwise_operators() {
+  let operate = |key, a, b| basic_operation(&Token::new_operator(key), a, b);
+
+  assert_eq!(operate(Key::BitAnd, 12.0, 10.0), Ok(8.0));
+  assert_eq!(operate(Key::BitOr, 12.0, 10.0), Ok(14.0));
+  assert_eq!(operate(Key::Xor, 12.0, 10.0), Ok(6.0));
+  assert_eq!(operate(Key::BitNot, 0.0, 5.0), Ok(-6.0), "should ignore operand a");
+  assert_eq!(operate(Key::ShiftLeft, 3.0, 4.0), Ok(48.0));
+  assert_eq!(operate(Key::ShiftRight, -16.0, 2.0), Ok(-4.0));
+  assert_eq!(
+    operate(Key::BitAnd, 1.5, 1.0),
+    Err(Error::InvalidArgument(
+      "Operator & expects integers, got 1.5".to_string()
+    ))
+  );
+  assert_eq!(
+    operate(Key::ShiftLeft, 1.0, 64.0),
+    Err(Error::InvalidArgument(
+      "Operator << expects a shift from 0 to 63, got 64".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_run_bitwise_operators() {
+  assert_eq!(run("0xff & 0b1010 | 1 << 4".to_string()), Ok("26".to_string()));
+  assert_eq!(
+    run("~x - 1".to_string()),
+    Err(Error::UndefinedIdentifier("x".to_string()))
+  );
+  assert_eq!(
+    run("2^3 & 12".to_string()),
+    Ok("8".to_string()),
+    "should keep ^ as a power"
+  );
+  assert_eq!(run("-~2 * 3".to_string()), Ok("9".to_string()));
+}
+
 #[test]
 fn testing_multiplication() {
   let token_a = 2.0;
@@ -140,6 +484,33 @@ fn testing_division_by_zero() {
   assert_eq!(result, Err(Error::DivisionByZero));
 }
 
+#[test]
+fn testing_exponentiation() {
+  assert_eq!(exponentiation(2.0, 10.0), Ok(1024.0));
+  assert_eq!(exponentiation(4.0, 0.5), Ok(2.0));
+}
+
+#[test]
+fn testing_factorial() {
+  assert_eq!(factorial(0.0), Ok(1.0));
+  assert_eq!(factorial(10.0), Ok(3628800.0));
+  assert_eq!(factorial(171.0), Ok(f64::INFINITY), "should overflow past 170!");
+  assert_eq!(factorial(-1.0), Err(Error::FactorialOfNegativeInteger));
+}
+
+#[test]
+fn testing_gamma() {
+  let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+
+  assert!(close(gamma(1.0), 1.0), "Γ(1) should be 1");
+  assert!(close(gamma(5.0), 24.0), "Γ(5) should be 4!");
+  assert!(close(gamma(0.5), std::f64::consts::PI.sqrt()), "Γ(1/2) should be √π");
+  assert!(
+    close(gamma(-0.5), -2.0 * std::f64::consts::PI.sqrt()),
+    "Γ(-1/2) should be -2√π"
+  );
+}
+
 #[test]
 fn testing_addition() {
   let token_a = 40.0;
@@ -157,3 +528,117 @@ fn testing_subtraction() {
   let result = subtraction(token_a, token_b);
   assert_eq!(result, Ok(38.0));
 }
+
+#[test]
+fn testing_angle_modes() {
+  let run_in = |script: &str, angle: Angle| {
+    let settings = Settings {
+      angle,
+      ..Settings::default()
+    };
+    run_script(script, &settings)
+  };
+
+  assert_eq!(run_in("cos(180)", Angle::Degrees), Ok("-1".to_string()));
+  assert_eq!(run_in("sin(100)", Angle::Gradians), Ok("1".to_string()));
+  assert_eq!(run_in("atan(1)", Angle::Degrees), Ok("45".to_string()));
+  assert_eq!(run_in("acos(0)", Angle::Gradians), Ok("100".to_string()));
+  assert_eq!(
+    run_in("sin(90°)", Angle::Radians),
+    Ok("1".to_string()),
+    "should read ° as degrees in radians"
+  );
+  assert_eq!(run_in("sin(90°)", Angle::Gradians), Ok("1".to_string()));
+  assert_eq!(run_in("deg(pi)", Angle::Radians), Ok("180".to_string()));
+  assert_eq!(run_in("rad(180)", Angle::Degrees), run_in("pi", Angle::Degrees));
+  assert_eq!(run_in("x = 90°; sin(x)", Angle::Radians), Ok("1".to_string()));
+}
+
+#[test]
+fn testing_run_script() {
+  let script = "rate = 8\nprice = 250\nprice + rate%; price - rate%\n# done";
+
+  assert_eq!(
+    run_script(script, &Settings::default()),
+    Ok("270\n230".to_string()),
+    "should print each expression with the variables assigned before it"
+  );
+  assert_eq!(
+    run_script("x = 2; x = x^10; x", &Settings::default()),
+    Ok("1024".to_string())
+  );
+  assert_eq!(run_script("# nothing", &Settings::default()), Ok("".to_string()));
+  assert_eq!(
+    run_script(
+      "flags = 0b1010; flags ^ 0xf",
+      &Settings {
+        programmer: Some(Programmer {
+          integer: IntegerType { bits: 8, signed: false },
+          overflow: Overflow::Error,
+        }),
+        ..Settings::default()
+      }
+    ),
+    Ok("5 (0x5, 0b101)".to_string()),
+    "should run in programmer mode"
+  );
+  assert_eq!(
+    run_script("y + 1; y = 2", &Settings::default()),
+    Err(Error::UndefinedIdentifier("y".to_string()))
+  );
+}
+
+#[test]
+fn testing_run_script_units_of_durations_as_variables() {
+  let run = |script: &str| run_script(script, &Settings::default());
+
+  assert_eq!(run("h = 2; 3h"), Ok("6".to_string()));
+  assert_eq!(run("d = 1.5\n2d + 1d"), Ok("4.5".to_string()));
+  assert_eq!(run("s = 4; w = 3; 2w*s"), Ok("24".to_string()));
+  assert_eq!(
+    run("x = 2; 2026-10-18 + 3d 4h"),
+    Ok("2026-10-21T04:00".to_string()),
+    "should keep the units that are not variables"
+  );
+}
+
+#[test]
+fn testing_run_script_binds_values_of_any_kind() {
+  let run = |script: &str| run_script(script, &Settings::default());
+
+  assert_eq!(run("m = [1, 2; 3, 4]\ndet(m)"), Ok("-2".to_string()));
+  assert_eq!(run("m = [2, 0; 0, 4]; n = inv(m); m*n"), Ok("[1, 0; 0, 1]".to_string()));
+  assert_eq!(
+    run("start = 2026-10-18; length = 3d; start + length*2"),
+    Ok("2026-10-24".to_string())
+  );
+  assert_eq!(run("x = [1 .. 2]; x*2"), Ok("[2 .. 4]".to_string()));
+  assert_eq!(
+    run("m = [1, 2]; d = 2026-10-18; m + d"),
+    Err(Error::InvalidArgument(
+      "Date expression d cannot be evaluated as a number".to_string()
+    ))
+  );
+  assert_eq!(
+    run("x = [1 .. 2]; sqrt(x) + series(x, i, 1, 2)").map_err(|error| error.kind()),
+    Err("invalid_argument")
+  );
+}
+
+#[test]
+fn testing_run_script_located() {
+  assert_eq!(
+    run_script_located("x = 2\nx + 1; x/0\nx", &Settings::default()),
+    Err(ScriptError {
+      line: 2,
+      statement: "x/0".to_string(),
+      error: Error::DivisionByZero,
+    })
+  );
+  assert_eq!(
+    run_script_located("x = 2\nx = (1", &Settings::default())
+      .unwrap_err()
+      .to_string(),
+    "line 2: x = (1: Bad format: Bracket ( is not closed"
+  );
+}
@@ -0,0 +1,98 @@
+use super::super::spec_helpers::{calculate, invalid, ok};
+use super::*;
+use crate::tokenizer::tokenize;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn testing_outward_rounding() {
+  assert_eq!(sum(1.0, 2.0), (3.0, 3.0));
+  assert_eq!(
+    sum(0.1, 0.2),
+    (0.30000000000000004_f64.next_down(), 0.30000000000000004)
+  );
+  assert_eq!(product(3.0, 0.1).0.next_up(), product(3.0, 0.1).1);
+  assert_eq!(quotient(1.0, 3.0).0.next_up(), quotient(1.0, 3.0).1);
+  assert_eq!(quotient(1.0, 4.0), (0.25, 0.25));
+  assert_eq!(product(f64::MAX, 2.0), (f64::MAX, f64::INFINITY));
+}
+
+#[test]
+fn testing_literals() {
+  let literal = |text: &str| literal(&tokenize(text.to_string()).unwrap()[0]);
+  assert_eq!(literal("2.5"), Ok(Interval::point(2.5)));
+  assert_eq!(literal("1024"), Ok(Interval::point(1024.0)));
+  assert_eq!(literal("0.1"), Ok(Interval::around(0.1)));
+  assert_eq!(calculate("[1 .. 2.5]"), ok("[1 .. 2.5]"));
+  assert_eq!(
+    calculate("[1.9 .. 2.1]"),
+    ok("[1.8999999999999997 .. 2.1000000000000005]")
+  );
+  assert_eq!(calculate("[2 .. 3] + 0.1 - 0.1").map(|_| ()), Ok(()));
+  assert_eq!(
+    calculate("[2 .. 1]"),
+    invalid("Interval [2 .. 1] has its lower bound above its upper bound")
+  );
+}
+
+#[test]
+fn testing_arithmetic() {
+  assert_eq!(calculate("[1 .. 2] + [10 .. 20]"), ok("[11 .. 22]"));
+  assert_eq!(calculate("[1 .. 2] - [10 .. 20]"), ok("[-19 .. -8]"));
+  assert_eq!(calculate("-[1 .. 2]*[-3 .. 4]"), ok("[-8 .. 6]"));
+  assert_eq!(calculate("[1 .. 2]/[4 .. 8]"), ok("[0.125 .. 0.5]"));
+  assert_eq!(calculate("[-2 .. 3]^2"), ok("[0 .. 9]"));
+  assert_eq!(calculate("[-2 .. 3]^3"), ok("[-8 .. 27]"));
+  assert_eq!(calculate("[2 .. 4]^-1"), ok("[0.25 .. 0.5]"));
+  assert_eq!(calculate("[3 .. 5]!"), ok("[6 .. 120]"));
+  assert_eq!(calculate("[100 .. 200] + 50%"), ok("[150 .. 300]"));
+  assert_eq!(
+    calculate("[1 .. 3] << 2"),
+    invalid("Operator << expects single integers, got [1 .. 3]")
+  );
+  assert_eq!(calculate("[6 .. 6] & 3"), ok("[2 .. 2]"));
+  assert_eq!(
+    calculate("[-1 .. 4]^0.5"),
+    invalid("Operator ^ expects a non-negative base for the exponent [0.5 .. 0.5], got [-1 .. 4]")
+  );
+  // the result holds 1/3 although no float is 1/3
+  let ast = crate::parser::parse(crate::tokenizer::tokenize("[1 .. 1]/3".to_string())).unwrap();
+  let third = evaluate(&ast, &Environment::new()).unwrap();
+  assert_eq!(third.lower.next_up(), third.upper);
+}
+
+#[test]
+fn testing_division_by_intervals_with_zero() {
+  assert_eq!(calculate("[1 .. 2]/[0 .. 4]"), ok("[0.25 .. inf]"));
+  assert_eq!(calculate("[1 .. 2]/[-4 .. 0]"), ok("[-inf .. -0.25]"));
+  assert_eq!(calculate("[-2 .. -1]/[0 .. 4]"), ok("[-inf .. -0.25]"));
+  assert_eq!(calculate("[0 .. 2]/[0 .. 4]"), ok("[0 .. inf]"));
+  assert_eq!(calculate("[1 .. 2]/[-1 .. 1]"), ok("[-inf .. inf]"));
+  assert_eq!(calculate("[0 .. 0]/[-1 .. 1]"), ok("[0 .. 0]"));
+  assert_eq!(calculate("[1 .. 2]/[0 .. 0]"), Err(Error::DivisionByZero));
+}
+
+#[test]
+fn testing_functions() {
+  assert_eq!(calculate("sqrt([4 .. 9])"), ok("[2 .. 3]"));
+  assert_eq!(calculate("abs([-3 .. 2])"), ok("[0 .. 3]"));
+  assert_eq!(
+    calculate("sin([0 .. 4])").map(|value| value.ends_with(" .. 1]")),
+    Ok(true)
+  );
+  assert_eq!(
+    calculate("cos([-1 .. 1])").map(|value| value.ends_with(" .. 1]")),
+    Ok(true)
+  );
+  assert_eq!(calculate("tan([1 .. 2])"), ok("[-inf .. inf]"));
+  assert_eq!(
+    calculate("ln([0 .. 1])"),
+    invalid("Function ln is not defined on all of [0 .. 1]")
+  );
+  assert_eq!(
+    calculate("mean([1 .. 2], 3)"),
+    invalid("Function mean does not apply to intervals")
+  );
+
+  let exp = apply("exp", Interval { lower: 0.0, upper: 1.0 }).unwrap();
+  assert!(exp.lower <= 1.0 && exp.upper >= std::f64::consts::E);
+}
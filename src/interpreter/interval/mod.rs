@@ -0,0 +1,568 @@
+use super::{
+  basic_operation, bitwise_operation, calculus, calendar, checked, constant, evaluate_chain, format, matrix, variadic,
+  Environment, Value as Held,
+};
+use crate::parser::{Ast, Node};
+use crate::tokenizer::{Key, Kind, Token};
+use crate::Error;
+use std::convert::TryFrom;
+use std::f64::consts::{PI, TAU};
+use std::fmt;
+
+// below this the error terms of products and quotients lose bits to
+// underflow, so their results are widened both ways
+const TINY: f64 = f64::MIN_POSITIVE * 9_007_199_254_740_992.0;
+
+// 2^53, beyond which integers converted to floats may be rounded
+const EXACT_INTEGERS: f64 = 9_007_199_254_740_992.0;
+
+// the smallest value of x! over the reals from 0 to 1, near x = 0.46
+const FACTORIAL_MINIMUM: f64 = 0.885;
+
+/// The closed interval of every value an expression could take, with bounds
+/// rounded outward so that the exact result is never left out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+  pub lower: f64,
+  pub upper: f64,
+}
+impl Interval {
+  fn point(value: f64) -> Interval {
+    Interval {
+      lower: value,
+      upper: value,
+    }
+  }
+
+  /// The floats on either side of `value`, a rounded result whose error is
+  /// unknown but below an ulp.
+  fn around(value: f64) -> Interval {
+    Interval {
+      lower: value.next_down(),
+      upper: value.next_up(),
+    }
+  }
+
+  fn entire() -> Interval {
+    Interval {
+      lower: f64::NEG_INFINITY,
+      upper: f64::INFINITY,
+    }
+  }
+
+  fn is_point(&self) -> bool {
+    self.lower == self.upper
+  }
+
+  fn contains_zero(&self) -> bool {
+    self.lower <= 0.0 && 0.0 <= self.upper
+  }
+
+  fn negate(self) -> Interval {
+    Interval {
+      lower: -self.upper,
+      upper: -self.lower,
+    }
+  }
+}
+impl fmt::Display for Interval {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "[{} .. {}]", format(Ok(self.lower)), format(Ok(self.upper)))
+  }
+}
+
+/// `value` rounded toward -∞, where `error` is the exact result less
+/// `value`, which is NaN once the operation overflowed.
+fn down(value: f64, error: f64) -> f64 {
+  match value {
+    _ if value == f64::INFINITY => f64::MAX,
+    _ if error < 0.0 || (value != 0.0 && value.abs() < TINY) => value.next_down(),
+    _ => value,
+  }
+}
+
+/// `value` rounded toward +∞, as `down` does toward -∞.
+fn up(value: f64, error: f64) -> f64 {
+  match value {
+    _ if value == f64::NEG_INFINITY => f64::MIN,
+    _ if error > 0.0 || (value != 0.0 && value.abs() < TINY) => value.next_up(),
+    _ => value,
+  }
+}
+
+/// `a + b` rounded down and up, finding the error of the sum exactly as
+/// Knuth's two-sum does.
+fn sum(a: f64, b: f64) -> (f64, f64) {
+  let value = a + b;
+  let b_part = value - a;
+  let error = (a - (value - b_part)) + (b - b_part);
+  (down(value, error), up(value, error))
+}
+
+/// `a * b` rounded down and up, with the error of the product from a fused
+/// multiply-add.
+fn product(a: f64, b: f64) -> (f64, f64) {
+  // 0 times an unbounded side still is 0
+  if a == 0.0 || b == 0.0 {
+    return (0.0, 0.0);
+  }
+  let value = a * b;
+  let error = match value {
+    // underflowed all the way, toward the sign of the product
+    _ if value == 0.0 => a.signum() * b.signum(),
+    _ => a.mul_add(b, -value),
+  };
+  (down(value, error), up(value, error))
+}
+
+/// `a / b` rounded down and up, with the error of the quotient from its
+/// remainder `a - q*b`, which a fused multiply-add gives exactly.
+fn quotient(a: f64, b: f64) -> (f64, f64) {
+  let value = a / b;
+  let remainder = (-value).mul_add(b, a);
+  let error = remainder.signum() * b.signum() * (remainder != 0.0) as i32 as f64;
+  (down(value, error), up(value, error))
+}
+
+/// The interval of `bound` applied to every pair of bounds of `a` and `b`,
+/// for operations whose extremes are at the corners. NaN corners, as ∞/∞,
+/// are left out.
+fn corners(a: Interval, b: Interval, bound: fn(f64, f64) -> (f64, f64)) -> Interval {
+  let results = [
+    bound(a.lower, b.lower),
+    bound(a.lower, b.upper),
+    bound(a.upper, b.lower),
+    bound(a.upper, b.upper),
+  ];
+  Interval {
+    lower: results.iter().map(|result| result.0).fold(f64::INFINITY, f64::min),
+    upper: results.iter().map(|result| result.1).fold(f64::NEG_INFINITY, f64::max),
+  }
+}
+
+fn addition(a: Interval, b: Interval) -> Interval {
+  Interval {
+    lower: sum(a.lower, b.lower).0,
+    upper: sum(a.upper, b.upper).1,
+  }
+}
+
+fn multiplication(a: Interval, b: Interval) -> Interval {
+  corners(a, b, product)
+}
+
+/// `a / b`, which is unbounded on the sides where b reaches 0. The result
+/// covers both sides of 0 when b has values of both signs.
+fn division(a: Interval, b: Interval) -> Result<Interval, Error> {
+  if b.lower == 0.0 && b.upper == 0.0 {
+    return Err(Error::DivisionByZero);
+  }
+  if !b.contains_zero() {
+    return Ok(corners(a, b, quotient));
+  }
+  if a.lower == 0.0 && a.upper == 0.0 {
+    return Ok(Interval::point(0.0));
+  }
+
+  if b.lower == 0.0 {
+    // b from 0+ to its upper bound
+    return Ok(Interval {
+      lower: if a.lower >= 0.0 {
+        quotient(a.lower, b.upper).0
+      } else {
+        f64::NEG_INFINITY
+      },
+      upper: if a.upper <= 0.0 {
+        quotient(a.upper, b.upper).1
+      } else {
+        f64::INFINITY
+      },
+    });
+  }
+  if b.upper == 0.0 {
+    // b from its lower bound to 0-
+    return Ok(Interval {
+      lower: if a.upper <= 0.0 {
+        quotient(a.upper, b.lower).0
+      } else {
+        f64::NEG_INFINITY
+      },
+      upper: if a.lower >= 0.0 {
+        quotient(a.lower, b.lower).1
+      } else {
+        f64::INFINITY
+      },
+    });
+  }
+  Ok(Interval::entire())
+}
+
+/// Bounds of x^n for x from `lower` to `upper`, both non-negative, squaring
+/// the bounds rounded outward.
+fn power_bounds(lower: f64, upper: f64, mut n: u64) -> (f64, f64) {
+  let (mut result, mut base) = ((1.0, 1.0), (lower, upper));
+  while n > 0 {
+    if n & 1 == 1 {
+      result = (product(result.0, base.0).0, product(result.1, base.1).1);
+    }
+    base = (product(base.0, base.0).0, product(base.1, base.1).1);
+    n >>= 1;
+  }
+  result
+}
+
+/// Bounds of x^n for a single x of any sign and an odd n.
+fn odd_power(x: f64, n: u64) -> (f64, f64) {
+  if x >= 0.0 {
+    return power_bounds(x, x, n);
+  }
+  let (lower, upper) = power_bounds(-x, -x, n);
+  (-upper, -lower)
+}
+
+fn exponentiation(a: Interval, b: Interval) -> Result<Interval, Error> {
+  if b.is_point() && b.lower.fract() == 0.0 {
+    let n = b.lower.abs() as u64;
+    let power = if n.is_multiple_of(2) {
+      let (lower, upper) = (a.lower.abs(), a.upper.abs());
+      let (lower, upper) = match a.contains_zero() {
+        true => power_bounds(0.0, lower.max(upper), n),
+        false => power_bounds(lower.min(upper), lower.max(upper), n),
+      };
+      Interval { lower, upper }
+    } else {
+      Interval {
+        lower: odd_power(a.lower, n).0,
+        upper: odd_power(a.upper, n).1,
+      }
+    };
+    return match b.lower < 0.0 {
+      true => division(Interval::point(1.0), power),
+      false => Ok(power),
+    };
+  }
+
+  if a.lower < 0.0 {
+    return Err(Error::InvalidArgument(format!(
+      "Operator ^ expects a non-negative base for the exponent {}, got {}",
+      b, a
+    )));
+  }
+  // x^y grows or shrinks along each of x ≥ 0 and y, so its extremes are
+  // at the corners
+  let power = corners(a, b, |x, y| {
+    let Interval { lower, upper } = Interval::around(x.powf(y));
+    (lower.max(0.0), upper)
+  });
+  Ok(power)
+}
+
+/// A bitwise operator, applied to single integers as the other evaluators
+/// do.
+fn bitwise(key: &Key, a: Interval, b: Interval) -> Result<Interval, Error> {
+  let operation = bitwise_operation(key).ok_or(Error::TokenNaO)?;
+  if let Some(interval) = [a, b].iter().find(|interval| !interval.is_point()) {
+    return Err(Error::InvalidArgument(format!(
+      "Operator {} expects single integers, got {}",
+      key, interval
+    )));
+  }
+  let value = operation(a.lower, b.lower)?;
+  match value.abs() > EXACT_INTEGERS {
+    true => Ok(Interval::around(value)),
+    false => Ok(Interval::point(value)),
+  }
+}
+
+fn operate(operator: &Token, a: Interval, b: Interval) -> Result<Interval, Error> {
+  match &operator.keys[0] {
+    Key::Addition => Ok(addition(a, b)),
+    Key::Subtraction => Ok(addition(a, b.negate())),
+    Key::Multiplication | Key::Of => Ok(multiplication(a, b)),
+    Key::Division => division(a, b),
+    Key::Exponentiation => exponentiation(a, b),
+    Key::PlusMinus => basic_operation(operator, a.lower, b.lower).map(Interval::point),
+    key => bitwise(key, a, b),
+  }
+}
+
+/// n! for whole bounds, multiplying up the factors of each bound.
+fn factorial(x: Interval) -> Result<Interval, Error> {
+  if x.lower < 0.0 || x.lower.fract() != 0.0 || x.upper.fract() != 0.0 {
+    return Err(Error::InvalidArgument(format!(
+      "Operator ! expects an interval with non-negative whole bounds, got {}",
+      x
+    )));
+  }
+  let factors = |n: f64| (2..=n.min(171.0) as u64).map(|k| Interval::point(k as f64));
+  let lower = factors(x.lower).fold(Interval::point(1.0), multiplication).lower;
+  let upper = factors(x.upper).fold(Interval::point(1.0), multiplication).upper;
+  // x! dips below 1 between 0 and 1
+  let lower = if x.lower == 0.0 && x.upper > 0.0 {
+    FACTORIAL_MINIMUM
+  } else {
+    lower
+  };
+  Ok(Interval { lower, upper })
+}
+
+fn percentage(x: Interval) -> Interval {
+  corners(x, Interval::point(100.0), quotient)
+}
+
+/// Whether `x` may reach a point `offset + k*period`, erring on the side of
+/// yes as the multiples of π are rounded.
+fn may_reach(x: Interval, offset: f64, period: f64) -> bool {
+  const SLACK: f64 = 1e-6;
+  if x.upper - x.lower >= period || x.lower.abs().max(x.upper.abs()) > 1e9 {
+    return true;
+  }
+  ((x.lower - offset) / period - SLACK).ceil() <= (x.upper - offset) / period + SLACK
+}
+
+/// `function` over `x`, where it is increasing, each bound widened by the
+/// ulp the math library may be off by.
+fn increasing(function: fn(f64) -> f64, x: Interval) -> Interval {
+  Interval {
+    lower: Interval::around(function(x.lower)).lower,
+    upper: Interval::around(function(x.upper)).upper,
+  }
+}
+
+/// sin or cos over `x`, reaching 1 at `peak` plus whole turns and -1 half a
+/// turn away.
+fn periodic(function: fn(f64) -> f64, x: Interval, peak: f64) -> Interval {
+  let ends = Interval {
+    lower: function(x.lower).min(function(x.upper)),
+    upper: function(x.lower).max(function(x.upper)),
+  };
+  Interval {
+    lower: match may_reach(x, peak + PI, TAU) {
+      true => -1.0,
+      false => Interval::around(ends.lower).lower.max(-1.0),
+    },
+    upper: match may_reach(x, peak, TAU) {
+      true => 1.0,
+      false => Interval::around(ends.upper).upper.min(1.0),
+    },
+  }
+}
+
+/// The single-argument function `name` over `x`.
+fn apply(name: &str, x: Interval) -> Result<Interval, Error> {
+  let within = |from: f64, to: f64| from <= x.lower && x.upper <= to;
+  let domain_error = || {
+    Err(Error::InvalidArgument(format!(
+      "Function {} is not defined on all of {}",
+      name, x
+    )))
+  };
+  // the bound of x nearest to 0 and the one farthest from it
+  let (nearest, farthest) = match x.contains_zero() {
+    true => (0.0, x.lower.abs().max(x.upper.abs())),
+    false => (x.lower.abs().min(x.upper.abs()), x.lower.abs().max(x.upper.abs())),
+  };
+
+  match name {
+    "sqrt" if !within(0.0, f64::INFINITY) => domain_error(),
+    "ln" | "log" if !within(0.0, f64::INFINITY) || x.lower == 0.0 => domain_error(),
+    "asin" | "acos" if !within(-1.0, 1.0) => domain_error(),
+    "sqrt" => {
+      // the square root is rounded correctly, and its error found exactly
+      let root = |value: f64| {
+        let root = value.sqrt();
+        let error = (-root).mul_add(root, value);
+        (down(root, error), up(root, error))
+      };
+      Ok(Interval {
+        lower: root(x.lower).0.max(0.0),
+        upper: root(x.upper).1,
+      })
+    }
+    "abs" => Ok(Interval {
+      lower: nearest,
+      upper: farthest,
+    }),
+    "cosh" => Ok(Interval {
+      lower: Interval::around(nearest.cosh()).lower.max(1.0),
+      upper: Interval::around(farthest.cosh()).upper,
+    }),
+    "acos" => Ok(Interval {
+      lower: Interval::around(x.upper.acos()).lower.max(0.0),
+      upper: Interval::around(x.lower.acos()).upper,
+    }),
+    "exp" => {
+      let result = increasing(f64::exp, x);
+      Ok(Interval {
+        lower: result.lower.max(0.0),
+        ..result
+      })
+    }
+    "ln" => Ok(increasing(f64::ln, x)),
+    "log" => Ok(increasing(f64::log10, x)),
+    "asin" => Ok(increasing(f64::asin, x)),
+    "atan" => Ok(increasing(f64::atan, x)),
+    "sinh" => Ok(increasing(f64::sinh, x)),
+    "tanh" => Ok(increasing(f64::tanh, x)),
+    "sin" => Ok(periodic(f64::sin, x, PI / 2.0)),
+    "cos" => Ok(periodic(f64::cos, x, 0.0)),
+    // unbounded around each pole of tan
+    "tan" if may_reach(x, PI / 2.0, PI) => Ok(Interval::entire()),
+    "tan" => Ok(increasing(f64::tan, x)),
+    _ => Err(Error::InvalidArgument(format!(
+      "Function {} does not apply to intervals",
+      name
+    ))),
+  }
+}
+
+/// The number written as `token`, or the floats on either side of it when
+/// it falls between them.
+fn literal(token: &Token) -> Result<Interval, Error> {
+  let value = token.value.ok_or(Error::TokenNaN)?;
+  let text = token.to_string();
+  let (text, exponent) = match text.split_once('e') {
+    Some((text, exponent)) => (text, exponent.parse::<i64>().ok()),
+    None => (text.as_str(), Some(0)),
+  };
+  // the digits n over 10^d, which is a float when 5^d divides n and leaves
+  // no more than the 53 bits of a float, or times 10^-d when d is negative
+  let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+  let digits = format!("{}{}", integer, fraction).parse::<u128>().ok();
+  let places = exponent.map(|exponent| fraction.len() as i64 - exponent);
+  let fits = |n: u128| n as f64 as u128 == n;
+  let power = |base: u128, places: i64| {
+    u32::try_from(places.abs())
+      .ok()
+      .and_then(|places| base.checked_pow(places))
+  };
+  let is_exact = match (digits, places) {
+    (Some(digits), Some(places)) if places < 0 => power(10, places)
+      .and_then(|ten| digits.checked_mul(ten))
+      .is_some_and(fits),
+    (Some(digits), Some(places)) => match power(5, places) {
+      Some(five) => digits % five == 0 && fits(digits / five),
+      None => false,
+    },
+    _ => false,
+  };
+  match is_exact {
+    true => Ok(Interval::point(value)),
+    false => Ok(Interval::around(value)),
+  }
+}
+
+/// Whether `ast` has an interval literal or variable, and so has to be
+/// evaluated here.
+pub fn uses_intervals(ast: &Ast, environment: &Environment) -> bool {
+  ast
+    .spine_operands()
+    .into_iter()
+    .any(|node| node_uses_intervals(node, environment))
+}
+
+fn node_uses_intervals(node: &Node, environment: &Environment) -> bool {
+  let uses = |node: &Node| node_uses_intervals(node, environment);
+  match node {
+    Node::Ast(ast) => uses_intervals(ast, environment),
+    Node::Call(_, arguments) => arguments.iter().any(uses),
+    Node::Factorial(node) | Node::Percentage(node) => uses(node),
+    Node::Interval(_, _) => true,
+    Node::Matrix(rows) => rows.iter().flatten().any(uses),
+    Node::Token(_) => environment.holds(node, |value| matches!(value, Held::Interval(_))),
+  }
+}
+
+/// The error of the evaluators that only produce numbers when they meet an
+/// interval.
+pub fn unsupported(node: &Node) -> Error {
+  Error::InvalidArgument(format!("Interval expression {} cannot be evaluated as a number", node))
+}
+
+/// Evaluates `ast` with every value an interval, numbers included, so that
+/// the result bounds the rounding errors of the whole expression.
+pub fn evaluate(ast: &Ast, environment: &Environment) -> Result<Interval, Error> {
+  let check = |ast: &Ast| ast.operator.as_ref().map(|_| ()).ok_or(Error::TokenNaO);
+  let operand = |node: Option<&Node>| evaluate_node(node, environment);
+
+  evaluate_chain(&ast.left_spine(), environment, check, operand, |value_a, ast| {
+    let operator = ast.operator.as_ref().ok_or(Error::TokenNaO)?;
+    let value_b = match (&operator.keys[0], ast.operand_b.as_ref()) {
+      // a ± b% is relative to a
+      (Key::Addition, Some(Node::Percentage(node))) | (Key::Subtraction, Some(Node::Percentage(node))) => {
+        multiplication(value_a, percentage(evaluate_node(Some(node), environment)?))
+      }
+      (_, operand_b) => evaluate_node(operand_b, environment)?,
+    };
+
+    operate(operator, value_a, value_b)
+  })
+}
+
+pub fn evaluate_node(node: Option<&Node>, environment: &Environment) -> Result<Interval, Error> {
+  environment.budget.spend(1)?;
+  match node {
+    Some(Node::Ast(ast)) => evaluate(ast, environment),
+    Some(Node::Call(function, arguments)) => call(function, arguments, environment),
+    Some(Node::Factorial(node)) => evaluate_node(Some(node), environment).and_then(factorial),
+    Some(Node::Percentage(node)) => evaluate_node(Some(node), environment).map(percentage),
+    Some(node @ Node::Interval(lower, upper)) => {
+      let lower = evaluate_node(Some(lower), environment)?.lower;
+      let upper = evaluate_node(Some(upper), environment)?.upper;
+      if lower > upper {
+        return Err(Error::InvalidArgument(format!(
+          "Interval {} has its lower bound above its upper bound",
+          node
+        )));
+      }
+      Ok(Interval { lower, upper })
+    }
+    Some(node @ Node::Matrix(_)) => Err(matrix::unsupported(node)),
+    Some(Node::Token(token)) if token.kind == Kind::Identifier => {
+      let name = token.to_string();
+      if let Some(Held::Interval(interval)) = environment.get(&name) {
+        return Ok(*interval);
+      }
+      match environment.number(&name)? {
+        Some(value) => Ok(Interval::point(value)),
+        None => match constant(&name)? {
+          // inf and nan are exact, where IEEE floats allow them
+          value if !value.is_finite() => checked(environment.floats, value, false, || name).map(Interval::point),
+          // and the other constants rounded to the nearest float
+          value => Ok(Interval::around(value)),
+        },
+      }
+    }
+    Some(Node::Token(token)) if token.kind == Kind::Number => literal(token),
+    Some(node @ Node::Token(_)) => Err(calendar::unsupported(node)),
+    None => Err(Error::InvalidOperand),
+  }
+}
+
+fn call(function: &Token, arguments: &[Node], environment: &Environment) -> Result<Interval, Error> {
+  let name = function.to_string();
+  let node = || Node::Call(function.clone(), arguments.to_vec());
+  if matrix::FUNCTIONS.contains(&name.as_str()) {
+    return Err(matrix::unsupported(&node()));
+  }
+  if calendar::FUNCTIONS.contains(&name.as_str()) {
+    return Err(calendar::unsupported(&node()));
+  }
+  if calculus::is_calculus(&name) || variadic::FUNCTIONS.contains(&name.as_str()) {
+    return Err(Error::InvalidArgument(format!(
+      "Function {} does not apply to intervals",
+      name
+    )));
+  }
+
+  match arguments {
+    [argument] => apply(&name, evaluate_node(Some(argument), environment)?),
+    _ => Err(Error::InvalidExpression(format!(
+      "Bad format: Function {} expects 1 argument",
+      name
+    ))),
+  }
+}
+
+#[cfg(test)]
+mod interval_spec;
@@ -0,0 +1,93 @@
+use super::super::spec_helpers::{calculate, mismatch, ok};
+use crate::Error;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn testing_literals() {
+  assert_eq!(calculate("[1, 2; 3, 4]"), ok("[1, 2; 3, 4]"));
+  assert_eq!(calculate("[1+1, 2^3, -1]"), ok("[2, 8, -1]"));
+  assert_eq!(calculate("[1; 2.5]"), ok("[1; 2.5]"));
+  assert_eq!(calculate("[2]"), ok("2"), "should group a single element");
+  assert_eq!(
+    calculate("[[1, 2], 3]"),
+    mismatch("Elements of a matrix should be numbers")
+  );
+}
+
+#[test]
+fn testing_element_wise_operations() {
+  assert_eq!(calculate("[1, 2; 3, 4] + [10, 20; 30, 40]"), ok("[11, 22; 33, 44]"));
+  assert_eq!(calculate("[1, 2] - 1"), ok("[0, 1]"));
+  assert_eq!(calculate("2[1, 2]"), ok("[2, 4]"));
+  assert_eq!(calculate("-[1, 2]"), ok("[-1, -2]"));
+  assert_eq!(calculate("[2, 4] / 2"), ok("[1, 2]"));
+  assert_eq!(calculate("[100, 200] + 10%"), ok("[110, 220]"));
+  assert_eq!(calculate("[3, 4]!"), ok("[6, 24]"));
+  assert_eq!(calculate("sqrt([4, 9])"), ok("[2, 3]"));
+  assert_eq!(calculate("[1, 2] / 0"), Err(Error::DivisionByZero));
+  assert_eq!(
+    calculate("[1, 2] + [1, 2, 3]"),
+    mismatch("Cannot add a 1x2 matrix and a 1x3 matrix")
+  );
+  assert_eq!(
+    calculate("1 / [1, 2]"),
+    Err(Error::InvalidArgument(
+      "Cannot divide by a matrix, multiply by its inv instead".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_matrix_product() {
+  assert_eq!(calculate("[1, 2; 3, 4] * [5, 6; 7, 8]"), ok("[19, 22; 43, 50]"));
+  assert_eq!(calculate("[1, 2; 3, 4] * [1; 1]"), ok("[3; 7]"));
+  assert_eq!(
+    calculate("[1, 2, 3] * [4; 5; 6]"),
+    ok("32"),
+    "should collapse a 1x1 result"
+  );
+  assert_eq!(calculate("[1, 1; 0, 1]^3"), ok("[1, 3; 0, 1]"));
+  assert_eq!(calculate("[2, 0; 0, 4]^-1"), ok("[0.5, 0; 0, 0.25]"));
+  assert_eq!(
+    calculate("[1, 2, 3] * [1, 2, 3]"),
+    mismatch("Cannot multiply a 1x3 matrix by a 1x3 matrix")
+  );
+  assert_eq!(
+    calculate("[1, 2]^2"),
+    mismatch("Function ^ expects a square matrix, got 1x2")
+  );
+}
+
+#[test]
+fn testing_functions() {
+  assert_eq!(calculate("transpose([1, 2, 3; 4, 5, 6])"), ok("[1, 4; 2, 5; 3, 6]"));
+  assert_eq!(calculate("det([1, 2; 3, 4])"), ok("-2"));
+  assert_eq!(calculate("det([2, 0, 1; 1, 3, 2; 1, 1, 2])"), ok("6"));
+  assert_eq!(calculate("inv([2, 1; 1, 1])"), ok("[1, -1; -1, 2]"));
+  assert_eq!(calculate("[4, 8; 2, 6] * inv([4, 8; 2, 6])"), ok("[1, 0; 0, 1]"));
+  assert_eq!(calculate("dot([1, 2, 3], [4; 5; 6])"), ok("32"));
+  assert_eq!(calculate("cross([1, 0, 0], [0, 1, 0])"), ok("[0, 0, 1]"));
+  assert_eq!(calculate("det(5) + inv(4)"), ok("5.25"));
+  assert_eq!(
+    calculate("inv([1, 2; 2, 4])"),
+    Err(Error::InvalidArgument("Matrix is singular".to_string()))
+  );
+  assert_eq!(
+    calculate("det([1, 2, 3])"),
+    mismatch("Function det expects a square matrix, got 1x3")
+  );
+  assert_eq!(
+    calculate("dot([1, 2], [1, 2, 3])"),
+    mismatch("Function dot expects two vectors of the same length, got 1x2 and 1x3")
+  );
+  assert_eq!(
+    calculate("cross([1, 2], [3, 4])"),
+    mismatch("Function cross expects vectors of 3 elements, got 2")
+  );
+  assert_eq!(
+    calculate("dot([1, 2])"),
+    Err(Error::InvalidExpression(
+      "Bad format: Function dot expects 2 arguments".to_string()
+    ))
+  );
+}
@@ -0,0 +1,497 @@
+use super::{
+  addition, bitwise_operation, calculus, checked, checked_call, checked_factorial, checked_percentage,
+  checked_variadic, division_in, evaluate_chain, evaluate_node as evaluate_number, factorial, format, function_named,
+  interval, multiplication, operate as operate_number, percentage, start_using, subtraction, variadic, Environment,
+  Value as Held,
+};
+use crate::parser::{Ast, Node};
+use crate::settings::Floats;
+use crate::tokenizer::{Key, Token};
+use crate::Error;
+use std::fmt;
+
+/// Functions taking matrices, which the other evaluators reject.
+pub const FUNCTIONS: [&str; 5] = ["cross", "det", "dot", "inv", "transpose"];
+
+/// A value of an expression with matrices, where a 1x1 matrix is a number.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  Number(f64),
+  Matrix(Matrix),
+}
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Number(value) => f.write_str(&format(Ok(*value))),
+      Value::Matrix(matrix) => write!(f, "{}", matrix),
+    }
+  }
+}
+
+/// Rows by columns of numbers, a vector having a single row or column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+  pub rows: usize,
+  pub columns: usize,
+  /// Elements row after row
+  pub elements: Vec<f64>,
+}
+impl Matrix {
+  fn get(&self, row: usize, column: usize) -> f64 {
+    self.elements[row * self.columns + column]
+  }
+
+  fn shape(&self) -> String {
+    format!("{}x{}", self.rows, self.columns)
+  }
+
+  fn is_vector(&self) -> bool {
+    self.rows == 1 || self.columns == 1
+  }
+
+  fn identity(size: usize) -> Matrix {
+    let elements = (0..size * size)
+      .map(|index| if index / size == index % size { 1.0 } else { 0.0 })
+      .collect();
+    Matrix {
+      rows: size,
+      columns: size,
+      elements,
+    }
+  }
+
+  fn map(&self, function: impl Fn(f64) -> Result<f64, Error>) -> Result<Matrix, Error> {
+    Ok(Matrix {
+      elements: self
+        .elements
+        .iter()
+        .map(|&value| function(value))
+        .collect::<Result<_, _>>()?,
+      ..*self
+    })
+  }
+
+  fn transpose(&self) -> Matrix {
+    let elements = (0..self.rows * self.columns)
+      .map(|index| self.get(index % self.rows, index / self.rows))
+      .collect();
+    Matrix {
+      rows: self.columns,
+      columns: self.rows,
+      elements,
+    }
+  }
+
+  fn product(&self, other: &Matrix) -> Result<Matrix, Error> {
+    if self.columns != other.rows {
+      return Err(Error::DimensionMismatch(format!(
+        "Cannot multiply a {} matrix by a {} matrix",
+        self.shape(),
+        other.shape()
+      )));
+    }
+
+    let elements = (0..self.rows * other.columns)
+      .map(|index| {
+        let (row, column) = (index / other.columns, index % other.columns);
+        (0..self.columns).map(|k| self.get(row, k) * other.get(k, column)).sum()
+      })
+      .collect();
+    Ok(Matrix {
+      rows: self.rows,
+      columns: other.columns,
+      elements,
+    })
+  }
+
+  fn square(&self, function: &str) -> Result<usize, Error> {
+    if self.rows != self.columns {
+      return Err(Error::DimensionMismatch(format!(
+        "Function {} expects a square matrix, got {}",
+        function,
+        self.shape()
+      )));
+    }
+    Ok(self.rows)
+  }
+
+  /// Gaussian elimination with partial pivoting.
+  fn determinant(&self) -> Result<f64, Error> {
+    let size = self.square("det")?;
+    let mut rows: Vec<Vec<f64>> = self.elements.chunks(size).map(<[f64]>::to_vec).collect();
+    let mut determinant = 1.0;
+
+    for column in 0..size {
+      let pivot = (column..size)
+        .max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))
+        .unwrap_or(column);
+      if rows[pivot][column] == 0.0 {
+        return Ok(0.0);
+      }
+      if pivot != column {
+        rows.swap(pivot, column);
+        determinant = -determinant;
+      }
+      determinant *= rows[column][column];
+      let pivot_row = rows[column].clone();
+      for row in rows.iter_mut().skip(column + 1) {
+        let factor = row[column] / pivot_row[column];
+        for (value, pivot) in row.iter_mut().zip(pivot_row.iter()).skip(column) {
+          *value -= factor * pivot;
+        }
+      }
+    }
+
+    Ok(determinant)
+  }
+
+  /// Gauss-Jordan elimination with partial pivoting, failing on a pivot too
+  /// small against the largest element to tell apart from 0.
+  fn inverse(&self) -> Result<Matrix, Error> {
+    let size = self.square("inv")?;
+    let largest = self
+      .elements
+      .iter()
+      .fold(0.0_f64, |largest, value| largest.max(value.abs()));
+    let tolerance = largest * size as f64 * f64::EPSILON;
+    let mut rows: Vec<Vec<f64>> = self.elements.chunks(size).map(<[f64]>::to_vec).collect();
+    let mut inverse: Vec<Vec<f64>> = Matrix::identity(size)
+      .elements
+      .chunks(size)
+      .map(<[f64]>::to_vec)
+      .collect();
+
+    for column in 0..size {
+      let pivot = (column..size)
+        .max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))
+        .unwrap_or(column);
+      if rows[pivot][column].abs() <= tolerance {
+        return Err(Error::InvalidArgument("Matrix is singular".to_string()));
+      }
+      rows.swap(pivot, column);
+      inverse.swap(pivot, column);
+
+      let scale = rows[column][column];
+      for k in 0..size {
+        rows[column][k] /= scale;
+        inverse[column][k] /= scale;
+      }
+      for row in (0..size).filter(|&row| row != column) {
+        let factor = rows[row][column];
+        for k in 0..size {
+          rows[row][k] -= factor * rows[column][k];
+          inverse[row][k] -= factor * inverse[column][k];
+        }
+      }
+    }
+
+    Ok(Matrix {
+      rows: size,
+      columns: size,
+      elements: inverse.concat(),
+    })
+  }
+}
+impl fmt::Display for Matrix {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let rows: Vec<String> = self
+      .elements
+      .chunks(self.columns)
+      .map(|row| {
+        row
+          .iter()
+          .map(|&value| format(Ok(value)))
+          .collect::<Vec<String>>()
+          .join(", ")
+      })
+      .collect();
+    write!(f, "[{}]", rows.join("; "))
+  }
+}
+
+/// A matrix as a value, collapsing to a number when it is 1x1.
+fn value(matrix: Matrix) -> Value {
+  match matrix.elements.as_slice() {
+    [value] => Value::Number(*value),
+    _ => Value::Matrix(matrix),
+  }
+}
+
+/// A number as a 1x1 matrix.
+fn matrix(value: Value) -> Matrix {
+  match value {
+    Value::Number(number) => Matrix {
+      rows: 1,
+      columns: 1,
+      elements: vec![number],
+    },
+    Value::Matrix(matrix) => matrix,
+  }
+}
+
+/// Whether `ast` has a matrix literal or variable, or calls a function
+/// taking matrices, and so has to be evaluated here rather than as a number.
+pub fn uses_matrices(ast: &Ast, environment: &Environment) -> bool {
+  ast
+    .spine_operands()
+    .into_iter()
+    .any(|node| node_uses_matrices(node, environment))
+}
+
+fn node_uses_matrices(node: &Node, environment: &Environment) -> bool {
+  let uses = |node: &Node| node_uses_matrices(node, environment);
+  match node {
+    Node::Ast(ast) => uses_matrices(ast, environment),
+    Node::Call(function, arguments) => FUNCTIONS.contains(&function.to_string().as_str()) || arguments.iter().any(uses),
+    Node::Factorial(node) | Node::Percentage(node) => uses(node),
+    Node::Interval(lower, upper) => uses(lower) || uses(upper),
+    Node::Matrix(_) => true,
+    Node::Token(_) => environment.holds(node, |value| matches!(value, Held::Matrix(_))),
+  }
+}
+
+/// The error of the evaluators that only produce numbers when they meet a
+/// matrix.
+pub fn unsupported(node: &Node) -> Error {
+  Error::InvalidArgument(format!("Matrix expression {} cannot be evaluated as a number", node))
+}
+
+pub fn evaluate(ast: &Ast, environment: &Environment) -> Result<Value, Error> {
+  let floats = environment.floats;
+  let spine = ast.left_spine();
+  let start = start_using(&spine, |node| node_uses_matrices(node, environment));
+  let check = |ast: &Ast| ast.operator.as_ref().map(|_| ()).ok_or(Error::TokenNaO);
+  let operand = |node: Option<&Node>| evaluate_node(node, environment);
+
+  evaluate_chain(&spine[start..], environment, check, operand, |value_a, ast| {
+    let operator = ast.operator.as_ref().ok_or(Error::TokenNaO)?;
+    let value = match (&operator.keys[0], ast.operand_b.as_ref()) {
+      // a ± b% is relative to a, element by element
+      (Key::Addition, Some(Node::Percentage(node))) | (Key::Subtraction, Some(Node::Percentage(node))) => {
+        let rate = evaluate_node(Some(node), environment)?;
+        let rate = element_wise(&rate, &Value::Number(100.0), division_in(floats), "divide")?;
+        let change = element_wise(&value_a, &rate, multiplication, "multiply")?;
+        operate(floats, operator, value_a, change)?
+      }
+      (_, operand_b) => operate(floats, operator, value_a, evaluate_node(operand_b, environment)?)?,
+    };
+    checked_value(floats, value, || Node::Ast(Box::new(ast.clone())))
+  })
+}
+
+/// `value`, the result of `node`, once `floats` let each of its elements
+/// through.
+fn checked_value(floats: Floats, value: Value, node: impl Fn() -> Node) -> Result<Value, Error> {
+  match &value {
+    Value::Number(number) => {
+      checked(floats, *number, false, || node().to_string())?;
+    }
+    Value::Matrix(matrix) => {
+      for &element in matrix.elements.iter() {
+        checked(floats, element, false, || format!("An element of {}", node()))?;
+      }
+    }
+  }
+  Ok(value)
+}
+
+pub fn evaluate_node(node: Option<&Node>, environment: &Environment) -> Result<Value, Error> {
+  let node = match node {
+    Some(node) if node_uses_matrices(node, environment) => node,
+    node => return evaluate_number(node, environment).map(Value::Number),
+  };
+  environment.budget.spend(1)?;
+
+  match node {
+    Node::Ast(ast) => evaluate(ast, environment),
+    Node::Call(function, arguments) => {
+      let value = call(function, arguments, environment)?;
+      checked_value(environment.floats, value, || node.clone())
+    }
+    Node::Factorial(node) => map(evaluate_node(Some(node), environment)?, |x| {
+      checked_factorial(environment.floats, x, factorial(x)?)
+    }),
+    Node::Percentage(node) => map(evaluate_node(Some(node), environment)?, |x| {
+      checked_percentage(environment.floats, x, percentage(x))
+    }),
+    Node::Interval(_, _) => Err(interval::unsupported(node)),
+    Node::Matrix(rows) => {
+      let mut elements = Vec::new();
+      for element in rows.iter().flatten() {
+        match evaluate_node(Some(element), environment)? {
+          Value::Number(value) => elements.push(value),
+          Value::Matrix(_) => {
+            return Err(Error::DimensionMismatch(
+              "Elements of a matrix should be numbers".to_string(),
+            ))
+          }
+        }
+      }
+      Ok(value(Matrix {
+        rows: rows.len(),
+        columns: rows[0].len(),
+        elements,
+      }))
+    }
+    Node::Token(token) => match environment.get(&token.to_string()) {
+      Some(Held::Matrix(matrix)) => Ok(Value::Matrix(matrix.clone())),
+      _ => evaluate_number(Some(node), environment).map(Value::Number),
+    },
+  }
+}
+
+fn map(value: Value, function: impl Fn(f64) -> Result<f64, Error>) -> Result<Value, Error> {
+  match value {
+    Value::Number(number) => function(number).map(Value::Number),
+    Value::Matrix(matrix) => matrix.map(function).map(Value::Matrix),
+  }
+}
+
+/// Applies `function` to the elements at the same place in matrices of the
+/// same shape, or to every element of a matrix with a number.
+fn element_wise(
+  value_a: &Value,
+  value_b: &Value,
+  function: fn(f64, f64) -> Result<f64, Error>,
+  verb: &str,
+) -> Result<Value, Error> {
+  match (value_a, value_b) {
+    (Value::Number(a), Value::Number(b)) => function(*a, *b).map(Value::Number),
+    (Value::Matrix(a), Value::Number(b)) => a.map(|a| function(a, *b)).map(Value::Matrix),
+    (Value::Number(a), Value::Matrix(b)) => b.map(|b| function(*a, b)).map(Value::Matrix),
+    (Value::Matrix(a), Value::Matrix(b)) if a.rows == b.rows && a.columns == b.columns => {
+      let elements = a
+        .elements
+        .iter()
+        .zip(b.elements.iter())
+        .map(|(&a, &b)| function(a, b))
+        .collect::<Result<_, _>>()?;
+      Ok(Value::Matrix(Matrix { elements, ..*a }))
+    }
+    (Value::Matrix(a), Value::Matrix(b)) => Err(Error::DimensionMismatch(format!(
+      "Cannot {} a {} matrix and a {} matrix",
+      verb,
+      a.shape(),
+      b.shape()
+    ))),
+  }
+}
+
+fn operate(floats: Floats, operator: &Token, value_a: Value, value_b: Value) -> Result<Value, Error> {
+  match (&operator.keys[0], value_a, value_b) {
+    (_, Value::Number(a), Value::Number(b)) => operate_number(floats, operator, a, b).map(Value::Number),
+    (Key::Addition, a, b) => element_wise(&a, &b, addition, "add"),
+    (Key::Subtraction, a, b) => element_wise(&a, &b, subtraction, "subtract"),
+    (Key::Multiplication, Value::Matrix(a), Value::Matrix(b)) => a.product(&b).map(value),
+    (Key::Multiplication, a, b) | (Key::Of, a, b) => element_wise(&a, &b, multiplication, "multiply"),
+    (Key::Division, a, Value::Number(b)) => element_wise(&a, &Value::Number(b), division_in(floats), "divide"),
+    (Key::Division, _, Value::Matrix(_)) => Err(Error::InvalidArgument(
+      "Cannot divide by a matrix, multiply by its inv instead".to_string(),
+    )),
+    (Key::Exponentiation, Value::Matrix(a), Value::Number(b)) => power(&a, b).map(value),
+    (Key::Exponentiation, _, Value::Matrix(_)) => Err(Error::InvalidArgument(
+      "Cannot raise to the power of a matrix".to_string(),
+    )),
+    (key, a, b) => match bitwise_operation(key) {
+      Some(operation) => element_wise(&a, &b, operation, "combine"),
+      None => Err(Error::TokenNaO),
+    },
+  }
+}
+
+/// A square matrix multiplied by itself, or by its inverse for a negative
+/// exponent.
+fn power(base: &Matrix, exponent: f64) -> Result<Matrix, Error> {
+  let size = base.square("^")?;
+  if exponent.fract() != 0.0 {
+    return Err(Error::InvalidArgument(
+      "A matrix can only be raised to an integer power".to_string(),
+    ));
+  }
+
+  let base = if exponent < 0.0 { base.inverse()? } else { base.clone() };
+  (0..exponent.abs() as u64).try_fold(Matrix::identity(size), |result, _| result.product(&base))
+}
+
+fn call(function: &Token, arguments: &[Node], environment: &Environment) -> Result<Value, Error> {
+  let name = function.to_string();
+  if calculus::is_calculus(&name) {
+    return Err(unsupported(&Node::Call(function.clone(), arguments.to_vec())));
+  }
+  if let Some(function) = variadic::function_named(&name) {
+    let mut values = Vec::new();
+    for argument in arguments {
+      match evaluate_node(Some(argument), environment)? {
+        Value::Number(value) => values.push(value),
+        Value::Matrix(matrix) => values.extend(matrix.elements),
+      }
+    }
+    return checked_variadic(environment.floats, &name, &values, function(&values)?).map(Value::Number);
+  }
+
+  let expected = match name.as_str() {
+    "dot" | "cross" => 2,
+    _ => 1,
+  };
+  if arguments.len() != expected {
+    let plural = if expected == 1 { "" } else { "s" };
+    return Err(Error::InvalidExpression(format!(
+      "Bad format: Function {} expects {} argument{}",
+      name, expected, plural
+    )));
+  }
+  let mut values = arguments
+    .iter()
+    .map(|argument| evaluate_node(Some(argument), environment))
+    .collect::<Result<Vec<Value>, Error>>()?;
+  let argument = values.remove(0);
+
+  match name.as_str() {
+    "transpose" => Ok(value(matrix(argument).transpose())),
+    "det" => matrix(argument).determinant().map(Value::Number),
+    "inv" => matrix(argument).inverse().map(value),
+    "dot" => {
+      let (a, b) = vectors(&name, argument, values.remove(0))?;
+      Ok(Value::Number(
+        a.elements.iter().zip(b.elements.iter()).map(|(a, b)| a * b).sum(),
+      ))
+    }
+    "cross" => {
+      let (a, b) = vectors(&name, argument, values.remove(0))?;
+      let (u, v) = (&a.elements, &b.elements);
+      if u.len() != 3 {
+        return Err(Error::DimensionMismatch(format!(
+          "Function cross expects vectors of 3 elements, got {}",
+          u.len()
+        )));
+      }
+      let elements = vec![
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+      ];
+      Ok(value(Matrix { elements, ..a }))
+    }
+    _ => match function_named(&name) {
+      Some(function) => map(argument, |x| checked_call(environment.floats, &name, x, function(x))),
+      None => Err(Error::UndefinedIdentifier(name)),
+    },
+  }
+}
+
+/// The arguments of `function` as two vectors of the same length.
+fn vectors(function: &str, a: Value, b: Value) -> Result<(Matrix, Matrix), Error> {
+  let (a, b) = (matrix(a), matrix(b));
+  if !a.is_vector() || !b.is_vector() || a.elements.len() != b.elements.len() {
+    return Err(Error::DimensionMismatch(format!(
+      "Function {} expects two vectors of the same length, got {} and {}",
+      function,
+      a.shape(),
+      b.shape()
+    )));
+  }
+  Ok((a, b))
+}
+
+#[cfg(test)]
+mod matrix_spec;
@@ -1,64 +1,829 @@
-use crate::parser::{parse, Ast, Node};
-use crate::tokenizer::{tokenize, Key, Kind, Token};
+use crate::error::Located;
+use crate::parser::{parse_statement, parse_within, split_statements, Ast, Node, Statement};
+use crate::settings::{Budget, Floats, Limits, Settings};
+use crate::tokenizer::{tokenize_within, Key, Kind, Token};
 use crate::Error;
+use big_int::BigInt;
+use interval::Interval;
+use matrix::Matrix;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use uncertainty::Uncertain;
+
+mod big_int;
+mod calculus;
+pub mod calendar;
+pub mod interval;
+pub mod matrix;
+mod programmer;
+pub mod solver;
+pub mod uncertainty;
+pub mod variadic;
+pub mod vm;
 
-#[rustfmt::skip]
 pub fn run(formula: String) -> Result<String, Error> {
-  Ok(formula)
-    .map(tokenize)
-    .map(parse)
-    .map(interpret)
-    .map(format)
+  run_with(formula, &Settings::default())
 }
 
-fn interpret(ast: Result<Ast, Error>) -> Result<f64, Error> {
-  let Ast {
-    operator,
-    operand_a,
-    operand_b,
-  } = ast?;
-
-  let value_a = match operand_a {
-    Some(Node::Ast(boxed_ast)) => interpret(Ok(*boxed_ast))?,
-    Some(Node::Token(token)) => to_float(token)?,
-    None => return Err(Error::InvalidOperand),
+pub fn run_with(formula: String, settings: &Settings) -> Result<String, Error> {
+  run_located(formula, settings).map_err(Error::from)
+}
+
+/// Runs `formula` as `run_with` does, failing with the part of the formula
+/// the error is about where it is known.
+pub fn run_located(formula: String, settings: &Settings) -> Result<String, Located> {
+  let environment = Environment::new()
+    .with_floats(settings.floats)
+    .with_limits(&settings.limits);
+  let tokens = tokenize_within(formula, &settings.limits, &[], environment.budget())?;
+  let ast = parse_within(Ok(tokens.clone()), settings, environment.budget())?;
+  run_ast_with(ast, &environment, settings).map_err(|error| Located::named(error, &tokens))
+}
+
+/// Runs `ast` as `run_ast` does, or over the integers of programmer mode when
+/// the settings ask for it.
+pub fn run_ast_with(ast: Ast, environment: &Environment, settings: &Settings) -> Result<String, Error> {
+  match &settings.programmer {
+    Some(programmer) => {
+      let value = programmer::evaluate(&ast, environment, programmer)?;
+      Ok(programmer::format(value, &programmer.integer))
+    }
+    None => run_ast(ast, environment),
+  }
+}
+
+pub fn run_ast(ast: Ast, environment: &Environment) -> Result<String, Error> {
+  if let Some(value) = evaluate_other(&ast, environment)? {
+    return Ok(value.to_string());
+  }
+
+  match fold_exact(Node::Ast(Box::new(ast)), &environment.budget)? {
+    Folded::Exact(integer) => Ok(integer.to_string()),
+    folded => compile_and_run(&folded.into_ast(), environment).map(|value| format(Ok(value))),
+  }
+}
+
+/// Evaluates `ast` to a value of whichever kind it has, as an assignment
+/// binds it.
+pub fn evaluate_value(ast: Ast, environment: &Environment) -> Result<Value, Error> {
+  if let Some(value) = evaluate_other(&ast, environment)? {
+    return Ok(value);
+  }
+
+  let ast = fold_exact(Node::Ast(Box::new(ast)), &environment.budget)?.into_ast();
+  compile_and_run(&ast, environment).map(Value::Number)
+}
+
+/// The value of `ast` when its literals, functions or variables make it
+/// something else than a number, by the evaluator of that kind of values.
+fn evaluate_other(ast: &Ast, environment: &Environment) -> Result<Option<Value>, Error> {
+  let value = if matrix::uses_matrices(ast, environment) {
+    matrix::evaluate(ast, environment)?.into()
+  } else if calendar::uses_dates(ast, environment) {
+    calendar::evaluate(ast, environment)?.into()
+  } else if interval::uses_intervals(ast, environment) {
+    Value::Interval(interval::evaluate(ast, environment)?)
+  } else if uncertainty::uses_uncertainty(ast, environment) {
+    Value::Uncertain(uncertainty::evaluate(ast, environment)?)
+  } else {
+    return Ok(None);
   };
-  let value_b = match operand_b {
-    Some(Node::Ast(boxed_ast)) => interpret(Ok(*boxed_ast))?,
-    Some(Node::Token(token)) => to_float(token)?,
-    None => return Err(Error::InvalidOperand),
+  Ok(Some(value))
+}
+
+/// Runs the statements of a script in order with a shared environment,
+/// printing the value of each expression statement on a line.
+pub fn run_script(script: &str, settings: &Settings) -> Result<String, Error> {
+  run_script_located(script, settings).map_err(|failure| failure.error)
+}
+
+/// A statement of a script that failed, and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+  /// Line of the statement, from 1
+  pub line: usize,
+  pub statement: String,
+  pub error: Error,
+}
+impl fmt::Display for ScriptError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}: {}", self.line, self.statement, self.error)
+  }
+}
+
+/// Runs a script as `run_script` does, telling on failure which statement
+/// failed.
+pub fn run_script_located(script: &str, settings: &Settings) -> Result<String, ScriptError> {
+  let mut environment = Environment::new()
+    .with_floats(settings.floats)
+    .with_limits(&settings.limits);
+  let mut lines: Vec<String> = Vec::new();
+
+  for (line, content) in script.lines().enumerate() {
+    for (_, statement) in split_statements(content) {
+      let statement = statement.trim();
+      if statement.is_empty() {
+        continue;
+      }
+      let fail = |error| ScriptError {
+        line: line + 1,
+        statement: statement.to_string(),
+        error,
+      };
+
+      let tokens = tokenize_within(
+        statement.to_string(),
+        &settings.limits,
+        &environment.names(),
+        environment.budget(),
+      );
+      let parsed = parse_statement(tokens, settings, environment.budget()).map_err(|located| fail(located.error))?;
+      let is_expression = matches!(parsed, Statement::Expression(_));
+      let value = run_statement(parsed, &mut environment, settings).map_err(fail)?;
+      if is_expression {
+        lines.push(value);
+      }
+    }
+  }
+
+  Ok(lines.join("\n"))
+}
+
+/// Runs a single statement of a script, binding the variable of an assignment
+/// in `environment`, and returns the value of the statement.
+pub fn run_statement(
+  statement: Statement,
+  environment: &mut Environment,
+  settings: &Settings,
+) -> Result<String, Error> {
+  match (statement, &settings.programmer) {
+    (Statement::Assignment(name, ast), Some(programmer)) => {
+      let value = programmer::evaluate(&ast, environment, programmer)?;
+      environment.bind(&name, value as f64);
+      Ok(programmer::format(value, &programmer.integer))
+    }
+    (Statement::Assignment(name, ast), None) => {
+      let value = evaluate_value(ast, environment)?;
+      let printed = value.to_string();
+      environment.bind_value(&name, value);
+      Ok(printed)
+    }
+    (Statement::Expression(ast), _) => run_ast_with(ast, environment, settings),
+  }
+}
+
+fn compile_and_run(ast: &Ast, environment: &Environment) -> Result<f64, Error> {
+  let (names, values): (Vec<&str>, Vec<f64>) = environment
+    .variables
+    .iter()
+    .filter_map(|(name, value)| match value {
+      Value::Number(value) => Some((name.as_str(), *value)),
+      _ => None,
+    })
+    .unzip();
+  vm::compile(ast, &names, environment.floats).run_within(&values, &environment.budget)
+}
+
+/// A value of any of the kinds the evaluators produce, as a variable holds
+/// it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  Number(f64),
+  Matrix(Matrix),
+  /// Seconds since 1970-01-01T00:00 UTC
+  Date(f64),
+  /// Seconds, negative for a duration back in time
+  Duration(f64),
+  Interval(Interval),
+  Uncertain(Uncertain),
+}
+impl From<matrix::Value> for Value {
+  fn from(value: matrix::Value) -> Value {
+    match value {
+      matrix::Value::Number(number) => Value::Number(number),
+      matrix::Value::Matrix(matrix) => Value::Matrix(matrix),
+    }
+  }
+}
+impl From<calendar::Value> for Value {
+  fn from(value: calendar::Value) -> Value {
+    match value {
+      calendar::Value::Number(number) => Value::Number(number),
+      calendar::Value::Date(seconds) => Value::Date(seconds),
+      calendar::Value::Duration(seconds) => Value::Duration(seconds),
+    }
+  }
+}
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Number(value) => f.write_str(&format(Ok(*value))),
+      Value::Matrix(matrix) => write!(f, "{}", matrix),
+      Value::Date(seconds) => write!(f, "{}", calendar::Value::Date(*seconds)),
+      Value::Duration(seconds) => write!(f, "{}", calendar::Value::Duration(*seconds)),
+      Value::Interval(interval) => write!(f, "{}", interval),
+      Value::Uncertain(uncertain) => write!(f, "{}", uncertain),
+    }
+  }
+}
+
+/// Values bound to variable names while evaluating an expression.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Environment {
+  variables: HashMap<String, Value>,
+  /// What the operations do with results an f64 cannot hold
+  floats: Floats,
+  /// Steps and time left to the evaluation, shared with the scopes of sum,
+  /// prod and integrate cloned from this environment
+  budget: Rc<Budget>,
+}
+impl Environment {
+  pub fn new() -> Environment {
+    Environment::default()
+  }
+
+  pub fn with(mut self, name: &str, value: f64) -> Environment {
+    self.bind(name, value);
+    self
+  }
+
+  pub fn with_floats(mut self, floats: Floats) -> Environment {
+    self.floats = floats;
+    self
+  }
+
+  /// Counts the steps and the time of evaluations in this environment
+  /// against `limits`, from now on.
+  pub fn with_limits(mut self, limits: &Limits) -> Environment {
+    self.budget = Rc::new(Budget::new(limits));
+    self
+  }
+
+  pub fn bind(&mut self, name: &str, value: f64) {
+    self.bind_value(name, Value::Number(value));
+  }
+
+  pub fn bind_value(&mut self, name: &str, value: Value) {
+    self.variables.insert(name.to_string(), value);
+  }
+
+  pub fn get(&self, name: &str) -> Option<&Value> {
+    self.variables.get(name)
+  }
+
+  /// The number bound to `name`, failing when it holds a value of another
+  /// kind, which the evaluators of numbers cannot take.
+  pub fn number(&self, name: &str) -> Result<Option<f64>, Error> {
+    let node = || Node::Token(Token::new_identifier(name));
+    match self.get(name) {
+      None => Ok(None),
+      Some(Value::Number(value)) => Ok(Some(*value)),
+      Some(Value::Matrix(_)) => Err(matrix::unsupported(&node())),
+      Some(Value::Date(_)) | Some(Value::Duration(_)) => Err(calendar::unsupported(&node())),
+      Some(Value::Interval(_)) => Err(interval::unsupported(&node())),
+      Some(Value::Uncertain(_)) => Err(uncertainty::unsupported(&node())),
+    }
+  }
+
+  /// Whether `node` is a variable holding a value `is_kind` accepts.
+  fn holds(&self, node: &Node, is_kind: fn(&Value) -> bool) -> bool {
+    match node {
+      Node::Token(token) if token.kind == Kind::Identifier => self.get(&token.to_string()).is_some_and(is_kind),
+      _ => false,
+    }
+  }
+
+  /// Steps and time left, which tokenizing and parsing the formula take
+  /// from as well.
+  pub fn budget(&self) -> &Budget {
+    &self.budget
+  }
+
+  pub fn floats(&self) -> Floats {
+    self.floats
+  }
+
+  /// Names of the variables bound so far.
+  pub fn names(&self) -> Vec<String> {
+    self.variables.keys().cloned().collect()
+  }
+}
+
+pub fn evaluate(ast: &Ast, environment: &Environment) -> Result<f64, Error> {
+  let check = |ast: &Ast| operator_of(ast).map(|_| ());
+  let operand = |node: Option<&Node>| evaluate_node(node, environment);
+  evaluate_chain(&ast.left_spine(), environment, check, operand, |value_a, ast| {
+    let operator = operator_of(ast)?;
+    let value_b = match (&operator.keys[0], ast.operand_b.as_ref()) {
+      // a ± b% is relative to a: 200 + 15% is 200 + 200 * 0.15
+      (Key::Addition, Some(Node::Percentage(node))) | (Key::Subtraction, Some(Node::Percentage(node))) => {
+        let rate = evaluate_node(Some(node), environment)?;
+        value_a * checked_percentage(environment.floats, rate, percentage(rate))?
+      }
+      (_, operand_b) => evaluate_node(operand_b, environment)?,
+    };
+
+    operate(environment.floats, operator, value_a, value_b)
+  })
+}
+
+/// The operator of `ast`, which the evaluators of numbers take unless it is
+/// a ±.
+fn operator_of(ast: &Ast) -> Result<&Token, Error> {
+  let operator = ast.operator.as_ref().ok_or(Error::TokenNaO)?;
+  if operator.keys[0] == Key::PlusMinus {
+    return Err(uncertainty::unsupported(&Node::Ast(Box::new(ast.clone()))));
+  }
+  Ok(operator)
+}
+
+/// Where the operations of a `spine` start to have an operand that `uses`
+/// accepts, as the evaluators of other values than numbers leave the
+/// operations before to the evaluator of numbers. The outermost operation is
+/// always theirs.
+fn start_using(spine: &[&Ast], uses: impl Fn(&Node) -> bool) -> usize {
+  let mut used = spine[0].operand_a.iter().any(&uses);
+  let start = spine.iter().position(|ast| {
+    used = used || ast.operand_b.iter().any(&uses);
+    used
+  });
+  start.unwrap_or(spine.len() - 1)
+}
+
+/// Evaluates the operations of a `spine`, innermost first, in a loop rather
+/// than recursing into their left operands as `1+2+3` nests `1+2`. As that
+/// walk would, `check` takes every operation from the outermost in, with a
+/// step for each inner one, before `operand` evaluates the left operand of
+/// the innermost and `apply` each operation to the value on its left.
+fn evaluate_chain<T>(
+  spine: &[&Ast],
+  environment: &Environment,
+  check: impl Fn(&Ast) -> Result<(), Error>,
+  operand: impl FnOnce(Option<&Node>) -> Result<T, Error>,
+  apply: impl FnMut(T, &Ast) -> Result<T, Error>,
+) -> Result<T, Error> {
+  for (i, ast) in spine.iter().enumerate().rev() {
+    if i + 1 < spine.len() {
+      environment.budget.spend(1)?;
+    }
+    check(ast)?;
+  }
+  let value = operand(spine[0].operand_a.as_ref())?;
+  spine.iter().copied().try_fold(value, apply)
+}
+
+pub fn evaluate_node(node: Option<&Node>, environment: &Environment) -> Result<f64, Error> {
+  environment.budget.spend(1)?;
+  match node {
+    Some(Node::Ast(boxed_ast)) => evaluate(boxed_ast, environment),
+    Some(Node::Call(function, arguments)) => call(function, arguments, environment),
+    Some(Node::Factorial(node)) => {
+      let x = evaluate_node(Some(node), environment)?;
+      checked_factorial(environment.floats, x, factorial(x)?)
+    }
+    Some(Node::Percentage(node)) => {
+      let x = evaluate_node(Some(node), environment)?;
+      checked_percentage(environment.floats, x, percentage(x))
+    }
+    Some(node @ Node::Interval(_, _)) => Err(interval::unsupported(node)),
+    Some(node @ Node::Matrix(_)) => Err(matrix::unsupported(node)),
+    Some(Node::Token(token)) if token.kind == Kind::Identifier => variable(&token.to_string(), environment),
+    Some(node @ Node::Token(token)) if matches!(token.kind, Kind::Date | Kind::Duration) => {
+      Err(calendar::unsupported(node))
+    }
+    Some(Node::Token(token)) => literal(environment.floats, token),
+    None => Err(Error::InvalidOperand),
+  }
+}
+
+fn variable(name: &str, environment: &Environment) -> Result<f64, Error> {
+  match environment.number(name)? {
+    Some(value) => Ok(value),
+    None => checked(environment.floats, constant(name)?, false, || name.to_string()),
+  }
+}
+
+/// The value of a named constant, where `inf` and `nan` are only let through
+/// by IEEE floats.
+fn constant(name: &str) -> Result<f64, Error> {
+  match name {
+    "pi" => Ok(std::f64::consts::PI),
+    "e" => Ok(std::f64::consts::E),
+    "inf" => Ok(f64::INFINITY),
+    "nan" => Ok(f64::NAN),
+    _ => Err(Error::UndefinedIdentifier(name.to_string())),
+  }
+}
+
+fn call(function: &Token, arguments: &[Node], environment: &Environment) -> Result<f64, Error> {
+  let name = function.to_string();
+  if calculus::is_calculus(&name) {
+    return calculus::call(&name, arguments, environment);
+  }
+  if matrix::FUNCTIONS.contains(&name.as_str()) {
+    return Err(matrix::unsupported(&Node::Call(function.clone(), arguments.to_vec())));
+  }
+  if calendar::FUNCTIONS.contains(&name.as_str()) {
+    return Err(calendar::unsupported(&Node::Call(function.clone(), arguments.to_vec())));
+  }
+  if let Some(function) = variadic::function_named(&name) {
+    let values = arguments
+      .iter()
+      .map(|argument| evaluate_node(Some(argument), environment))
+      .collect::<Result<Vec<f64>, Error>>()?;
+    return checked_variadic(environment.floats, &name, &values, function(&values)?);
+  }
+
+  let value = match arguments {
+    [argument] => evaluate_node(Some(argument), environment)?,
+    _ => {
+      return Err(Error::InvalidExpression(format!(
+        "Bad format: Function {} expects 1 argument",
+        name
+      )))
+    }
+  };
+
+  match function_named(&name) {
+    Some(function) => checked_call(environment.floats, &name, value, function(value)),
+    None => Err(Error::UndefinedIdentifier(name)),
+  }
+}
+
+/// The single-argument function called `name`.
+fn function_named(name: &str) -> Option<fn(f64) -> f64> {
+  match name {
+    "sin" => Some(f64::sin),
+    "cos" => Some(f64::cos),
+    "tan" => Some(f64::tan),
+    "asin" => Some(f64::asin),
+    "acos" => Some(f64::acos),
+    "atan" => Some(f64::atan),
+    "sinh" => Some(f64::sinh),
+    "cosh" => Some(f64::cosh),
+    "tanh" => Some(f64::tanh),
+    "exp" => Some(f64::exp),
+    "ln" => Some(f64::ln),
+    "log" => Some(f64::log10),
+    "sqrt" => Some(f64::sqrt),
+    "abs" => Some(f64::abs),
+    "digamma" => Some(digamma),
+    _ => None,
+  }
+}
+
+fn percentage(value: f64) -> f64 {
+  value / 100.0
+}
+
+/// A node after `fold_exact`.
+#[derive(Debug, PartialEq)]
+enum Folded {
+  /// The value of an integer-only node
+  Exact(BigInt),
+  /// Any other node, with its integer-only subtrees replaced by their values
+  Partial(Node),
+}
+impl Folded {
+  fn into_node(self) -> Node {
+    match self {
+      Folded::Exact(integer) if integer.is_negative() => {
+        let ast = Ast::new(
+          Ast::new_operator(Key::Subtraction),
+          Ast::new_number(vec![Key::Zero]),
+          Some(Node::Token(Token::new_integer(&integer.neg().to_string()))),
+        );
+        Node::Ast(Box::new(ast))
+      }
+      Folded::Exact(integer) => Node::Token(Token::new_integer(&integer.to_string())),
+      Folded::Partial(node) => node,
+    }
+  }
+
+  fn into_ast(self) -> Ast {
+    match self.into_node() {
+      Node::Ast(ast) => *ast,
+      node => Ast::new(
+        Ast::new_operator(Key::Addition),
+        Ast::new_number(vec![Key::Zero]),
+        Some(node),
+      ),
+    }
+  }
+}
+
+/// Works out every subtree made only of integers and of `+`, `-`, `*`, `/`
+/// that divides, `^` and `!` exactly, so that `171!/170!` is 171 although
+/// 171! is past the range of f64. Only the values of these subtrees are then
+/// rounded to floats, where the rest of the expression needs them.
+fn fold_exact(node: Node, budget: &Budget) -> Result<Folded, Error> {
+  budget.spend(1)?;
+  let folded = match node {
+    Node::Ast(ast) => {
+      // along the left operands of a chain in a loop, a step for each
+      let (operand_a, operations) = ast.into_left_spine();
+      budget.spend(operations.len() as u64 - 1)?;
+      let mut folded = operand_a.map(|node| fold_exact(node, budget)).transpose()?;
+      for (operator, operand_b) in operations {
+        let operand_b = operand_b.map(|node| fold_exact(node, budget)).transpose()?;
+        folded = Some(fold_operation(operator, folded, operand_b, budget)?);
+      }
+      // the spine has at least the operation of the node
+      folded.unwrap()
+    }
+    Node::Factorial(node) => match fold_exact(*node, budget)? {
+      Folded::Exact(n) => match n.to_u64() {
+        Some(n) => Folded::Exact(exact_factorial(n, budget)?),
+        None => Folded::Partial(Node::Factorial(Box::new(Folded::Exact(n).into_node()))),
+      },
+      folded => Folded::Partial(Node::Factorial(Box::new(folded.into_node()))),
+    },
+    Node::Percentage(node) => Folded::Partial(Node::Percentage(Box::new(fold_exact(*node, budget)?.into_node()))),
+    Node::Call(function, arguments) => {
+      let arguments = arguments
+        .into_iter()
+        .map(|argument| Ok(fold_exact(argument, budget)?.into_node()))
+        .collect::<Result<Vec<Node>, Error>>()?;
+      Folded::Partial(Node::Call(function, arguments))
+    }
+    Node::Token(token) if token.kind == Kind::Number => match BigInt::parse(&token.to_string()) {
+      Some(integer) => Folded::Exact(integer),
+      None => Folded::Partial(Node::Token(token)),
+    },
+    node => Folded::Partial(node),
+  };
+  Ok(folded)
+}
+
+/// `operator` applied to the folded operands, exactly when both are
+/// integers and so is the result.
+fn fold_operation(
+  operator: Option<Token>,
+  operand_a: Option<Folded>,
+  operand_b: Option<Folded>,
+  budget: &Budget,
+) -> Result<Folded, Error> {
+  if let (Some(operator), Some(Folded::Exact(a)), Some(Folded::Exact(b))) = (&operator, &operand_a, &operand_b) {
+    if let Some(integer) = exact_operation(&operator.keys[0], a, b, budget)? {
+      return Ok(Folded::Exact(integer));
+    }
+  }
+  let ast = Ast::new(
+    operator,
+    operand_a.map(Folded::into_node),
+    operand_b.map(Folded::into_node),
+  );
+  Ok(Folded::Partial(Node::Ast(Box::new(ast))))
+}
+
+/// The exact result of `key` applied to `a` and `b`, paying a step for each
+/// limb operation, or None when it is not an integer.
+fn exact_operation(key: &Key, a: &BigInt, b: &BigInt, budget: &Budget) -> Result<Option<BigInt>, Error> {
+  let integer = match key {
+    Key::Addition | Key::Subtraction => {
+      budget.spend(a.size().max(b.size()) as u64)?;
+      if *key == Key::Addition {
+        a.add(b)
+      } else {
+        a.sub(b)
+      }
+    }
+    Key::Multiplication => exact_mul(a, b, budget)?,
+    Key::Division => {
+      // a search over the values of each limb of the quotient
+      budget.spend((a.size() as u64).saturating_mul(b.size() as u64).saturating_mul(30))?;
+      match a.div_rem(b) {
+        Some((quotient, remainder)) if remainder.is_zero() => quotient,
+        _ => return Ok(None),
+      }
+    }
+    Key::Exponentiation => match b.to_u64() {
+      Some(exponent) => exact_pow(a, exponent, budget)?,
+      None => return Ok(None),
+    },
+    _ => return Ok(None),
   };
+  Ok(Some(integer))
+}
+
+fn exact_mul(a: &BigInt, b: &BigInt, budget: &Budget) -> Result<BigInt, Error> {
+  budget.spend((a.size() as u64).saturating_mul(b.size() as u64))?;
+  Ok(a.mul(b))
+}
+
+/// `base` to the `exponent` by squaring, failing before a product that would
+/// take more steps than are left.
+fn exact_pow(base: &BigInt, exponent: u64, budget: &Budget) -> Result<BigInt, Error> {
+  let mut result = BigInt::from_u64(1);
+  let mut square = base.clone();
+  let mut exponent = exponent;
+  while exponent > 0 {
+    if exponent & 1 == 1 {
+      result = exact_mul(&result, &square, budget)?;
+    }
+    exponent >>= 1;
+    if exponent > 0 {
+      square = exact_mul(&square, &square, budget)?;
+    }
+  }
+  Ok(result)
+}
 
-  basic_operation(operator.unwrap(), value_a, value_b)
+fn exact_factorial(n: u64, budget: &Budget) -> Result<BigInt, Error> {
+  (2..=n).try_fold(BigInt::from_u64(1), |product, i| {
+    exact_mul(&product, &BigInt::from_u64(i), budget)
+  })
 }
 
 fn format(num: Result<f64, Error>) -> String {
   let value = num.unwrap();
-  if value.fract() == 0.0 {
+  if value.is_nan() {
+    return "nan".to_string();
+  }
+  if value.is_finite() && value.fract() == 0.0 && value.abs() < isize::MAX as f64 {
     return (value as isize).to_string();
   }
   value.to_string()
 }
 
-fn to_float(token: Token) -> Result<f64, Error> {
-  if token.kind != Kind::Number {
-    return Err(Error::TokenNaN);
+fn to_float(token: &Token) -> Result<f64, Error> {
+  match token.value {
+    Some(value) if token.kind == Kind::Number => Ok(value),
+    _ => Err(Error::TokenNaN),
   }
-  let keys_str = token.keys.iter().fold("".to_string(), |mut acc, key| {
-    acc.push_str(&key.to_string());
-    acc
-  });
+}
+
+/// The value of a number token, whose digits may go past the range of f64.
+fn literal(floats: Floats, token: &Token) -> Result<f64, Error> {
+  let value = to_float(token)?;
+  // the digits before the exponent of 0e5
+  let is_zero = token
+    .keys
+    .iter()
+    .take_while(|key| **key != Key::Letter('e'))
+    .all(|key| matches!(key, Key::Zero | Key::Dot));
+  checked(floats, value, value == 0.0 && !is_zero, || {
+    abbreviate(&token.to_string())
+  })
+}
+
+/// `value` for an error message, in scientific notation where its digits
+/// would be too many to read.
+fn describe(value: f64) -> String {
+  if value != 0.0 && value.is_finite() && !(1e-7..1e21).contains(&value.abs()) {
+    return format!("{:e}", value);
+  }
+  format(Ok(value))
+}
+
+/// The digits of a number literal for an error message, in scientific
+/// notation past 21 characters, as the literals exact integers become.
+fn abbreviate(number: &str) -> String {
+  if number.len() <= 21 {
+    return number.to_string();
+  }
+  let mut parts = number.splitn(2, '.');
+  let integer = parts.next().unwrap_or_default().trim_start_matches('0');
+  let digits = format!("{}{}", integer, parts.next().unwrap_or_default());
+  let significant = digits.trim_start_matches('0');
+  let exponent = integer.len() as isize - (digits.len() - significant.len()) as isize - 1;
+
+  let significant = significant.trim_end_matches('0');
+  match significant.len() {
+    0 => "0".to_string(),
+    1 => format!("{}e{}", significant, exponent),
+    length => {
+      let fraction = significant[1..length.min(17)].trim_end_matches('0');
+      match fraction {
+        "" => format!("{}e{}", &significant[..1], exponent),
+        fraction => format!("{}.{}e{}", &significant[..1], fraction, exponent),
+      }
+    }
+  }
+}
 
-  Ok(keys_str.parse().unwrap())
+/// `value`, the result of `operation`, as `floats` let it through.
+///
+/// Strict floats keep every value finite, so a value that is not comes from
+/// the operation itself, as does an underflow: to a subnormal value, or to 0
+/// when `vanished` says the exact result is not 0.
+fn checked(floats: Floats, value: f64, vanished: bool, operation: impl FnOnce() -> String) -> Result<f64, Error> {
+  if floats == Floats::Ieee {
+    return Ok(value);
+  }
+  if value.is_nan() {
+    return Err(Error::NotANumber(format!("{} is not a number", operation())));
+  }
+  if value.is_infinite() {
+    return Err(Error::Overflow(format!("{} is too large for a float", operation())));
+  }
+  if value.is_subnormal() || vanished {
+    return Err(Error::Underflow(format!("{} is too small for a float", operation())));
+  }
+  Ok(value)
 }
 
-fn basic_operation(operator: Token, value_a: f64, value_b: f64) -> Result<f64, Error> {
+/// `operator` applied to `a` and `b`, as `floats` let the result through.
+fn operate(floats: Floats, operator: &Token, a: f64, b: f64) -> Result<f64, Error> {
+  let value = match operator.keys[0] {
+    Key::Division => division_in(floats)(a, b)?,
+    _ => basic_operation(operator, a, b)?,
+  };
+  checked_operation(floats, &operator.keys[0], a, b, value)
+}
+
+fn checked_operation(floats: Floats, key: &Key, a: f64, b: f64, value: f64) -> Result<f64, Error> {
+  // a product or a power is only 0 with a 0 factor or base
+  let vanished = match key {
+    Key::Multiplication | Key::Of => a != 0.0 && b != 0.0,
+    Key::Division | Key::Exponentiation => a != 0.0,
+    _ => false,
+  };
+  checked(floats, value, value == 0.0 && vanished, || {
+    format!("{}{}{}", describe(a), key, describe(b))
+  })
+}
+
+fn checked_call(floats: Floats, name: &str, x: f64, value: f64) -> Result<f64, Error> {
+  // the functions that reach 0 away from 0 as well
+  let vanished = x != 0.0 && !matches!(name, "cos" | "acos" | "ln" | "log" | "digamma");
+  checked(floats, value, value == 0.0 && vanished, || {
+    format!("{}({})", name, describe(x))
+  })
+}
+
+fn checked_variadic(floats: Floats, name: &str, values: &[f64], value: f64) -> Result<f64, Error> {
+  checked(floats, value, false, || {
+    let values: Vec<String> = values.iter().map(|&value| describe(value)).collect();
+    format!("{}({})", name, values.join(", "))
+  })
+}
+
+fn checked_factorial(floats: Floats, x: f64, value: f64) -> Result<f64, Error> {
+  checked(floats, value, value == 0.0, || format!("{}!", describe(x)))
+}
+
+fn checked_percentage(floats: Floats, x: f64, value: f64) -> Result<f64, Error> {
+  checked(floats, value, value == 0.0 && x != 0.0, || format!("{}%", describe(x)))
+}
+
+fn basic_operation(operator: &Token, value_a: f64, value_b: f64) -> Result<f64, Error> {
   match operator.keys[0] {
     Key::Multiplication => multiplication(value_a, value_b),
     Key::Division => division(value_a, value_b),
+    Key::Of => multiplication(value_a, value_b),
+    Key::Exponentiation => exponentiation(value_a, value_b),
     Key::Addition => addition(value_a, value_b),
     Key::Subtraction => subtraction(value_a, value_b),
-    _ => Err(Error::TokenNaO),
+    // reached from the evaluators of matrices, dates and intervals
+    Key::PlusMinus => Err(Error::InvalidArgument(
+      "Operator ± does not apply to matrices, dates or intervals".to_string(),
+    )),
+    ref key => match bitwise_operation(key) {
+      Some(operation) => operation(value_a, value_b),
+      None => Err(Error::TokenNaO),
+    },
+  }
+}
+
+/// An operator applied to its operands.
+type Operation = fn(f64, f64) -> Result<f64, Error>;
+
+/// The operation of a bitwise operator over floats holding integers, where
+/// `~` ignores its left operand.
+fn bitwise_operation(key: &Key) -> Option<Operation> {
+  match key {
+    Key::BitNot => Some(|_, b| Ok(!integer("~", b)? as f64)),
+    Key::BitAnd => Some(|a, b| Ok((integer("&", a)? & integer("&", b)?) as f64)),
+    Key::BitOr => Some(|a, b| Ok((integer("|", a)? | integer("|", b)?) as f64)),
+    Key::Xor => Some(|a, b| Ok((integer("^", a)? ^ integer("^", b)?) as f64)),
+    Key::ShiftLeft => Some(|a, b| Ok((integer("<<", a)? << shift("<<", b)?) as f64)),
+    Key::ShiftRight => Some(|a, b| Ok((integer(">>", a)? >> shift(">>", b)?) as f64)),
+    _ => None,
+  }
+}
+
+/// The value as a 64-bit integer for the bitwise `operator`.
+fn integer(operator: &str, value: f64) -> Result<i64, Error> {
+  // 2^63, the first float past the range of i64
+  const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+  if value.fract() != 0.0 || !(-LIMIT..LIMIT).contains(&value) {
+    return Err(Error::InvalidArgument(format!(
+      "Operator {} expects integers, got {}",
+      operator, value
+    )));
+  }
+  Ok(value as i64)
+}
+
+fn shift(operator: &str, value: f64) -> Result<u32, Error> {
+  match integer(operator, value)? {
+    amount @ 0..=63 => Ok(amount as u32),
+    amount => Err(Error::InvalidArgument(format!(
+      "Operator {} expects a shift from 0 to 63, got {}",
+      operator, amount
+    ))),
   }
 }
 
@@ -73,6 +838,87 @@ fn division(value_a: f64, value_b: f64) -> Result<f64, Error> {
   Ok(value_a / value_b)
 }
 
+/// Division as `floats` have it, where IEEE floats divide by zero to an
+/// infinity, or to NaN for 0/0.
+fn division_in(floats: Floats) -> Operation {
+  match floats {
+    Floats::Strict => division,
+    Floats::Ieee => |value_a, value_b| Ok(value_a / value_b),
+  }
+}
+
+fn exponentiation(value_a: f64, value_b: f64) -> Result<f64, Error> {
+  Ok(value_a.powf(value_b))
+}
+
+/// n! for non-negative integers and Γ(x + 1) for any other real x.
+fn factorial(value: f64) -> Result<f64, Error> {
+  if value.fract() != 0.0 {
+    return Ok(gamma(value + 1.0));
+  }
+  if value < 0.0 {
+    return Err(Error::FactorialOfNegativeInteger);
+  }
+  if value > 170.0 {
+    return Ok(f64::INFINITY);
+  }
+  Ok((2..=value as u64).fold(1.0, |acc, n| acc * n as f64))
+}
+
+/// Lanczos approximation (g = 7, n = 9) with the reflection formula below 0.5.
+fn gamma(value: f64) -> f64 {
+  const G: f64 = 7.0;
+  const COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+  ];
+
+  if value < 0.5 {
+    return std::f64::consts::PI / ((std::f64::consts::PI * value).sin() * gamma(1.0 - value));
+  }
+
+  let value = value - 1.0;
+  let t = value + G + 0.5;
+  let series = COEFFICIENTS[1..]
+    .iter()
+    .enumerate()
+    .fold(COEFFICIENTS[0], |acc, (i, coefficient)| {
+      acc + coefficient / (value + i as f64 + 1.0)
+    });
+
+  (2.0 * std::f64::consts::PI).sqrt() * t.powf(value + 0.5) * (-t).exp() * series
+}
+
+/// ψ(x), the derivative of ln Γ(x), shifting x up to where the asymptotic
+/// series converges and reflecting negative values.
+fn digamma(value: f64) -> f64 {
+  if value <= 0.0 && value.fract() == 0.0 {
+    return f64::NAN;
+  }
+  if value < 0.0 {
+    return digamma(1.0 - value) - std::f64::consts::PI / (std::f64::consts::PI * value).tan();
+  }
+
+  let mut value = value;
+  let mut result = 0.0;
+  while value < 10.0 {
+    result -= 1.0 / value;
+    value += 1.0;
+  }
+  let square = 1.0 / (value * value);
+  let series =
+    square * (1.0 / 12.0 - square * (1.0 / 120.0 - square * (1.0 / 252.0 - square * (1.0 / 240.0 - square / 132.0))));
+
+  result + value.ln() - 0.5 / value - series
+}
+
 fn addition(value_a: f64, value_b: f64) -> Result<f64, Error> {
   Ok(value_a + value_b)
 }
@@ -83,3 +929,7 @@ fn subtraction(value_a: f64, value_b: f64) -> Result<f64, Error> {
 
 #[cfg(test)]
 mod interpreter_spec;
+/// Shorthands shared by the specs of the evaluators, comparing what a
+/// formula prints with the expected text or error.
+#[cfg(test)]
+mod spec_helpers;
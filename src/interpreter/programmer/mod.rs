@@ -0,0 +1,180 @@
+use super::{calendar, constant, evaluate_chain, interval, matrix, Environment};
+use crate::parser::{Ast, Node};
+use crate::settings::{IntegerType, Overflow, Programmer};
+use crate::tokenizer::{Key, Kind};
+use crate::Error;
+use std::convert::TryFrom;
+use std::fmt;
+
+// n! has at least 64 factors of 2 from here on, so it wraps to 0 in any type
+const FACTORIAL_WRAPS_TO_ZERO: i128 = 130;
+
+/// Evaluates `ast` over the fixed-width integers of programmer mode, keeping
+/// every intermediate result within the integer type.
+pub fn evaluate(ast: &Ast, environment: &Environment, programmer: &Programmer) -> Result<i128, Error> {
+  Evaluator {
+    programmer,
+    environment,
+  }
+  .ast(ast)
+}
+
+/// A value of programmer mode in decimal, then in hexadecimal and binary as
+/// the bits of the integer type, so -1 in i8 is `-1 (0xff, 0b11111111)`.
+pub fn format(value: i128, integer: &IntegerType) -> String {
+  let bits = value as u128 & mask(integer);
+  format!("{} (0x{:x}, 0b{:b})", value, bits, bits)
+}
+
+fn mask(integer: &IntegerType) -> u128 {
+  (1 << integer.bits) - 1
+}
+
+/// The low bits of `value` read as the integer type.
+fn wrap(value: i128, integer: &IntegerType) -> i128 {
+  let bits = (value as u128 & mask(integer)) as i128;
+  if integer.signed && bits > integer.max() {
+    bits - (1 << integer.bits)
+  } else {
+    bits
+  }
+}
+
+fn unavailable(what: &str) -> Error {
+  Error::InvalidArgument(format!("{} not available in programmer mode", what))
+}
+
+struct Evaluator<'a> {
+  programmer: &'a Programmer,
+  environment: &'a Environment,
+}
+impl Evaluator<'_> {
+  /// Checks the result of `expression` against the integer type, wrapping it
+  /// or failing as set. The flag tells that it overflowed even the i128 it
+  /// was computed in.
+  fn fit(&self, (value, overflowed): (i128, bool), expression: &dyn fmt::Display) -> Result<i128, Error> {
+    let integer = &self.programmer.integer;
+    if !overflowed && (integer.min()..=integer.max()).contains(&value) {
+      return Ok(value);
+    }
+    match self.programmer.overflow {
+      Overflow::Wrap => Ok(wrap(value, integer)),
+      Overflow::Error => Err(Error::Overflow(format!("{} does not fit in {}", expression, integer))),
+    }
+  }
+
+  fn ast(&self, ast: &Ast) -> Result<i128, Error> {
+    let check = |ast: &Ast| ast.operator.as_ref().map(|_| ()).ok_or(Error::TokenNaO);
+    let operand = |node: Option<&Node>| self.node(node);
+    evaluate_chain(&ast.left_spine(), self.environment, check, operand, |a, ast| {
+      self.operation(a, ast)
+    })
+  }
+
+  /// The operation of `ast` on the value `a` of its left operand.
+  fn operation(&self, a: i128, ast: &Ast) -> Result<i128, Error> {
+    let key = &ast.operator.as_ref().ok_or(Error::TokenNaO)?.keys[0];
+    let b = self.node(ast.operand_b.as_ref())?;
+    let integer = &self.programmer.integer;
+
+    let exact = match key {
+      Key::Addition => a.overflowing_add(b),
+      Key::Subtraction => a.overflowing_sub(b),
+      Key::Multiplication => a.overflowing_mul(b),
+      Key::Division if b == 0 => return Err(Error::DivisionByZero),
+      // truncates toward zero, as integer division does in C
+      Key::Division => (a / b, false),
+      Key::Exponentiation => match u32::try_from(b) {
+        Ok(exponent) => a.overflowing_pow(exponent),
+        Err(_) => {
+          return Err(Error::InvalidArgument(format!(
+            "Programmer mode only raises to powers from 0 to {}, got {}",
+            u32::MAX,
+            b
+          )))
+        }
+      },
+      Key::BitNot if integer.signed => (!b, false),
+      Key::BitNot => (integer.max() - b, false),
+      Key::BitAnd => (a & b, false),
+      Key::BitOr => (a | b, false),
+      Key::Xor => (a ^ b, false),
+      // exact, as neither the value nor the shift exceed 64 bits
+      Key::ShiftLeft => (a << self.shift("<<", b)?, false),
+      // arithmetic for signed types, logical for unsigned ones as their
+      // values are never negative
+      Key::ShiftRight => (a >> self.shift(">>", b)?, false),
+      Key::Of => return Err(unavailable("Operator of is")),
+      Key::PlusMinus => return Err(unavailable("Operator ± is")),
+      _ => return Err(Error::TokenNaO),
+    };
+
+    self.fit(exact, ast)
+  }
+
+  fn shift(&self, operator: &str, amount: i128) -> Result<u32, Error> {
+    let bits = self.programmer.integer.bits;
+    match u32::try_from(amount) {
+      Ok(shift) if shift < bits => Ok(shift),
+      _ => Err(Error::InvalidArgument(format!(
+        "Operator {} expects a shift from 0 to {}, got {}",
+        operator,
+        bits - 1,
+        amount
+      ))),
+    }
+  }
+
+  fn node(&self, node: Option<&Node>) -> Result<i128, Error> {
+    self.environment.budget.spend(1)?;
+    match node {
+      Some(Node::Ast(ast)) => self.ast(ast),
+      Some(Node::Call(function, _)) => Err(unavailable(&format!("Function {} is", function))),
+      Some(node @ Node::Factorial(operand)) => {
+        let n = self.node(Some(operand))?;
+        if n < 0 {
+          return Err(Error::FactorialOfNegativeInteger);
+        }
+        (2..=n.min(FACTORIAL_WRAPS_TO_ZERO)).try_fold(1, |product: i128, k| self.fit(product.overflowing_mul(k), node))
+      }
+      Some(Node::Percentage(_)) => Err(unavailable("Percentages are")),
+      Some(node @ Node::Interval(_, _)) => Err(interval::unsupported(node)),
+      Some(node @ Node::Matrix(_)) => Err(matrix::unsupported(node)),
+      Some(Node::Token(token)) if token.kind == Kind::Identifier => {
+        let name = token.to_string();
+        let value = match self.environment.number(&name)? {
+          Some(value) => value,
+          None => constant(&name)?,
+        };
+        self.integer(value, &name)
+      }
+      Some(Node::Token(token)) if token.kind == Kind::Number => {
+        // only checked as part of the expression, so -128 is an i8 even
+        // though 128 is not
+        let number = token.to_string();
+        match number.parse::<i128>() {
+          Ok(value) => Ok(value),
+          Err(_) => self.integer(token.value.ok_or(Error::TokenNaN)?, &number),
+        }
+      }
+      Some(node @ Node::Token(_)) => Err(calendar::unsupported(node)),
+      None => Err(Error::InvalidOperand),
+    }
+  }
+
+  /// A float bound to a variable, or written with a fraction, as an integer
+  /// of the type.
+  fn integer(&self, value: f64, expression: &str) -> Result<i128, Error> {
+    if value.fract() != 0.0 || !value.is_finite() {
+      return Err(Error::InvalidArgument(format!(
+        "Programmer mode only accepts integers, got {}",
+        value
+      )));
+    }
+    // saturates beyond i128, far outside of any type
+    self.fit((value as i128, value.abs() >= 2f64.powi(127)), &expression)
+  }
+}
+
+#[cfg(test)]
+mod programmer_spec;
@@ -0,0 +1,105 @@
+use super::super::run_with;
+use super::super::spec_helpers::{ok, overflow};
+use crate::settings::{IntegerType, Overflow, Programmer, Settings};
+use crate::Error;
+use pretty_assertions::assert_eq;
+
+fn calculate(formula: &str, integer: &str, overflow: Overflow) -> Result<String, Error> {
+  let programmer = Programmer {
+    integer: IntegerType::parse(integer).unwrap(),
+    overflow,
+  };
+  let settings = Settings {
+    programmer: Some(programmer),
+    ..Settings::default()
+  };
+  run_with(formula.to_string(), &settings)
+}
+
+fn checked(formula: &str, integer: &str) -> Result<String, Error> {
+  calculate(formula, integer, Overflow::Error)
+}
+
+fn wrapped(formula: &str, integer: &str) -> Result<String, Error> {
+  calculate(formula, integer, Overflow::Wrap)
+}
+
+#[test]
+fn testing_integer_types() {
+  assert_eq!(IntegerType::parse("i32"), Some(IntegerType { bits: 32, signed: true }));
+  assert_eq!(IntegerType::parse("u12"), None);
+  assert_eq!(IntegerType::parse("f64"), None);
+  assert_eq!(IntegerType::parse("u64").unwrap().max(), u64::MAX as i128);
+  assert_eq!(IntegerType::parse("i8").unwrap().min(), -128);
+  assert_eq!(IntegerType::parse("u16").unwrap().to_string(), "u16");
+}
+
+#[test]
+fn testing_bitwise_operators() {
+  assert_eq!(checked("0xf0 | 0x0f", "u8"), ok("255 (0xff, 0b11111111)"));
+  assert_eq!(checked("0b1100 & 0b1010", "u8"), ok("8 (0x8, 0b1000)"));
+  assert_eq!(
+    checked("0b1100 ^ 0b1010", "u8"),
+    ok("6 (0x6, 0b110)"),
+    "should read ^ as xor"
+  );
+  assert_eq!(checked("~0", "u8"), ok("255 (0xff, 0b11111111)"));
+  assert_eq!(checked("~0", "i8"), ok("-1 (0xff, 0b11111111)"));
+  assert_eq!(checked("1 << 4 | 1", "u8"), ok("17 (0x11, 0b10001)"));
+  assert_eq!(
+    checked("-128 >> 1", "i8"),
+    ok("-64 (0xc0, 0b11000000)"),
+    "should keep the sign"
+  );
+  assert_eq!(checked("3² + 7/2", "u8"), ok("12 (0xc, 0b1100)"));
+  assert_eq!(
+    checked("1 << 8", "u8"),
+    Err(Error::InvalidArgument(
+      "Operator << expects a shift from 0 to 7, got 8".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_overflow() {
+  assert_eq!(checked("200 + 100", "u8"), overflow("200 + 100 does not fit in u8"));
+  assert_eq!(wrapped("200 + 100", "u8"), ok("44 (0x2c, 0b101100)"));
+  assert_eq!(checked("-1", "u32"), overflow("-1 does not fit in u32"));
+  assert_eq!(
+    wrapped("-1", "u32"),
+    ok("4294967295 (0xffffffff, 0b11111111111111111111111111111111)")
+  );
+  assert_eq!(wrapped("127 + 1", "i8"), ok("-128 (0x80, 0b10000000)"));
+  assert_eq!(checked("0x40 << 1", "i8"), overflow("64 << 1 does not fit in i8"));
+  assert_eq!(checked("-128 / -1", "i8"), overflow("-128/(-1) does not fit in i8"));
+  assert_eq!(
+    wrapped("0xffffffffffffffff * 0xffffffffffffffff", "u64"),
+    ok("1 (0x1, 0b1)")
+  );
+  assert_eq!(wrapped("200!", "u64"), ok("0 (0x0, 0b0)"));
+  assert_eq!(checked("6!", "u8"), overflow("6! does not fit in u8"));
+  assert_eq!(checked("300", "u8"), overflow("300 does not fit in u8"));
+}
+
+#[test]
+fn testing_unavailable_operations() {
+  assert_eq!(
+    checked("1.5 + 1", "i32"),
+    Err(Error::InvalidArgument(
+      "Programmer mode only accepts integers, got 1.5".to_string()
+    ))
+  );
+  assert_eq!(
+    checked("sin(1)", "i32"),
+    Err(Error::InvalidArgument(
+      "Function sin is not available in programmer mode".to_string()
+    ))
+  );
+  assert_eq!(
+    checked("pi", "i32"),
+    Err(Error::InvalidArgument(
+      "Programmer mode only accepts integers, got 3.141592653589793".to_string()
+    ))
+  );
+  assert_eq!(checked("1 / 0", "i32"), Err(Error::DivisionByZero));
+}
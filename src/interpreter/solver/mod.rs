@@ -0,0 +1,260 @@
+use super::{evaluate, Environment};
+use crate::parser::{parse_equation, validate_variable, Equation};
+use crate::settings::{Floats, Limits, Settings};
+use crate::tokenizer::tokenize_with_variables;
+use crate::Error;
+
+/// Sub-intervals sampled for sign changes in the search interval.
+const SAMPLES: usize = 1000;
+
+/// Where roots are searched for and how precisely they are located.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolveOptions {
+  pub interval: (f64, f64),
+  /// Relative width under which an iteration has converged
+  pub tolerance: f64,
+  pub max_iterations: usize,
+}
+impl Default for SolveOptions {
+  fn default() -> SolveOptions {
+    SolveOptions {
+      interval: (-100.0, 100.0),
+      tolerance: 1e-12,
+      max_iterations: 100,
+    }
+  }
+}
+
+/// Solves `formula` for `variable`, printing one root per line.
+pub fn run_with(formula: String, variable: &str, options: &SolveOptions, settings: &Settings) -> Result<String, Error> {
+  let tokens = tokenize_with_variables(formula, &settings.limits, &[variable.to_string()]).map_err(Error::from);
+  let equation = parse_equation(tokens, settings)?;
+  let roots = solve(&equation, variable, options, &settings.limits)?;
+
+  if roots.is_empty() {
+    let (lo, hi) = options.interval;
+    return Ok(format!("No real roots in [{}, {}]", lo, hi));
+  }
+  let lines: Vec<String> = roots.iter().map(|root| format!("{} = {}", variable, root)).collect();
+  Ok(lines.join("\n"))
+}
+
+/// Real roots of `equation` in the interval of `options`, in ascending order.
+///
+/// Sign changes between samples are narrowed with Newton steps kept inside the
+/// bracket by bisection, and roots touching zero without crossing it are
+/// looked for with plain Newton iteration from the smallest samples. Every
+/// evaluation of the equation counts against the same `limits`.
+pub fn solve(equation: &Equation, variable: &str, options: &SolveOptions, limits: &Limits) -> Result<Vec<f64>, Error> {
+  validate_variable(variable)?;
+
+  // values out of range are not roots, as IEEE floats leave them
+  let environment = Environment::new().with_floats(Floats::Ieee).with_limits(limits);
+  let function = Function {
+    equation,
+    variable,
+    environment,
+  };
+  let (lo, hi) = options.interval;
+  let (lo, hi) = (lo.min(hi), lo.max(hi));
+  let step = (hi - lo) / SAMPLES as f64;
+
+  let points: Vec<f64> = (0..=SAMPLES).map(|i| lo + step * i as f64).collect();
+  let values = points
+    .iter()
+    .map(|x| function.at(*x))
+    .collect::<Result<Vec<Option<f64>>, Error>>()?;
+
+  if let Some((from, to)) = zero_run(&function, &points, &values)? {
+    return Err(Error::InfiniteSolutions(format!(
+      "Every {} from {} to {} is a solution",
+      variable, from, to
+    )));
+  }
+
+  let mut roots: Vec<f64> = Vec::new();
+  for i in 0..=SAMPLES {
+    match (values[i], values.get(i + 1).copied().flatten()) {
+      (Some(0.0), _) => roots.push(points[i]),
+      (Some(a), Some(b)) if a * b < 0.0 => {
+        if let Some(root) = bisect_newton(&function, points[i], points[i + 1], options)? {
+          roots.push(root);
+        }
+      }
+      _ => {}
+    }
+  }
+  for i in 1..SAMPLES {
+    if let (Some(before), Some(value), Some(after)) = (values[i - 1], values[i], values[i + 1]) {
+      let is_touching = before * value > 0.0 && value * after > 0.0;
+      if is_touching && value.abs() < before.abs() && value.abs() <= after.abs() {
+        if let Some(root) = newton(&function, points[i], (points[i - 1], points[i + 1]), options)? {
+          roots.push(root);
+        }
+      }
+    }
+  }
+
+  let roots = roots
+    .into_iter()
+    .map(|root| snap(&function, root))
+    .collect::<Result<Vec<f64>, Error>>()?;
+  Ok(deduplicate(roots, options.tolerance))
+}
+
+/// The first run of samples where the function is 0, as all of them are for
+/// `x = x`, taken as a run when the function is 0 between two of them as
+/// well rather than at a pair of roots that happen to fall on the samples.
+fn zero_run(function: &Function, points: &[f64], values: &[Option<f64>]) -> Result<Option<(f64, f64)>, Error> {
+  let is_zero = |i: usize| values.get(i) == Some(&Some(0.0));
+  for i in 0..SAMPLES {
+    if is_zero(i) && is_zero(i + 1) && function.at((points[i] + points[i + 1]) / 2.0)? == Some(0.0) {
+      let end = (i + 1..=SAMPLES).take_while(|&j| is_zero(j)).last().unwrap_or(i + 1);
+      return Ok(Some((points[i], points[end])));
+    }
+  }
+  Ok(None)
+}
+
+/// `lhs - rhs` as a function of the variable.
+struct Function<'a> {
+  equation: &'a Equation,
+  variable: &'a str,
+  /// Shares its budget with the environment of every evaluation
+  environment: Environment,
+}
+impl Function<'_> {
+  /// The value at `x`, or `None` where it is undefined, as at a division by
+  /// zero. Other errors, such as an unknown name, are returned.
+  fn at(&self, x: f64) -> Result<Option<f64>, Error> {
+    let environment = self.environment.clone().with(self.variable, x);
+    let value =
+      evaluate(&self.equation.lhs, &environment).and_then(|lhs| Ok(lhs - evaluate(&self.equation.rhs, &environment)?));
+
+    match value {
+      Ok(value) if value.is_finite() => Ok(Some(value)),
+      Ok(_) => Ok(None),
+      Err(error) if error.is_domain_error() => Ok(None),
+      Err(error) => Err(error),
+    }
+  }
+
+  fn slope(&self, x: f64) -> Result<Option<f64>, Error> {
+    let h = 1e-6 * x.abs().max(1.0);
+    match (self.at(x + h)?, self.at(x - h)?) {
+      (Some(a), Some(b)) => Ok(Some((a - b) / (2.0 * h))),
+      _ => Ok(None),
+    }
+  }
+}
+
+/// Narrows the sign change between `a` and `b`, or returns `None` when it
+/// turns out to be a pole rather than a root.
+fn bisect_newton(function: &Function, mut a: f64, mut b: f64, options: &SolveOptions) -> Result<Option<f64>, Error> {
+  let (mut value_a, value_b) = match (function.at(a)?, function.at(b)?) {
+    (Some(value_a), Some(value_b)) => (value_a, value_b),
+    _ => return Ok(None),
+  };
+  let bound = value_a.abs().min(value_b.abs());
+  let mut x = (a + b) / 2.0;
+
+  for _ in 0..options.max_iterations {
+    let value = match function.at(x)? {
+      Some(value) => value,
+      None => return Ok(None),
+    };
+    if value == 0.0 {
+      return Ok(Some(x));
+    }
+    if value.signum() == value_a.signum() {
+      a = x;
+      value_a = value;
+    } else {
+      b = x;
+    }
+
+    let next = match function.slope(x)? {
+      Some(slope) if slope != 0.0 => x - value / slope,
+      _ => f64::NAN,
+    };
+    // a last Newton step may land on a bound of the bracket
+    let converged = (next - x).abs() <= options.tolerance * x.abs().max(1.0);
+    x = if converged || (next > a && next < b) {
+      next
+    } else {
+      (a + b) / 2.0
+    };
+
+    if converged || b - a <= options.tolerance * x.abs().max(1.0) {
+      return match function.at(x)? {
+        Some(value) if value.abs() <= bound => Ok(Some(x)),
+        _ => Ok(None),
+      };
+    }
+  }
+
+  Err(Error::NoConvergence(format!(
+    "No convergence between {} and {} after {} iterations",
+    a, b, options.max_iterations
+  )))
+}
+
+/// Newton iteration from `x` for a root that does not change sign, accepted
+/// only if it stays within `bounds` and reaches zero within the tolerance.
+fn newton(function: &Function, mut x: f64, bounds: (f64, f64), options: &SolveOptions) -> Result<Option<f64>, Error> {
+  for _ in 0..options.max_iterations {
+    let (value, slope) = match (function.at(x)?, function.slope(x)?) {
+      (Some(value), Some(slope)) => (value, slope),
+      _ => return Ok(None),
+    };
+    if value == 0.0 {
+      return Ok(Some(x));
+    }
+    if slope == 0.0 {
+      return Ok(None);
+    }
+
+    let next = x - value / slope;
+    if next < bounds.0 || next > bounds.1 {
+      return Ok(None);
+    }
+    let converged = (next - x).abs() <= options.tolerance * x.abs().max(1.0);
+    x = next;
+
+    if converged {
+      return match function.at(x)? {
+        Some(value) if value.abs() <= options.tolerance => Ok(Some(x)),
+        _ => Ok(None),
+      };
+    }
+  }
+
+  Ok(None)
+}
+
+/// Rounds a root to the nearest integer when that is an exact root.
+fn snap(function: &Function, root: f64) -> Result<f64, Error> {
+  if (root - root.round()).abs() > 1e-6 * root.abs().max(1.0) {
+    return Ok(root);
+  }
+  match function.at(root.round())? {
+    Some(0.0) => Ok(root.round()),
+    _ => Ok(root),
+  }
+}
+
+fn deduplicate(mut roots: Vec<f64>, tolerance: f64) -> Vec<f64> {
+  roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+  let mut unique: Vec<f64> = Vec::new();
+  for root in roots {
+    match unique.last() {
+      Some(last) if (root - last).abs() <= 1e3 * tolerance * root.abs().max(1.0) => {}
+      _ => unique.push(root),
+    }
+  }
+  unique
+}
+
+#[cfg(test)]
+mod solver_spec;
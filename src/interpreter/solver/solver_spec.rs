@@ -0,0 +1,142 @@
+use super::*;
+use crate::tokenizer::tokenize;
+use pretty_assertions::assert_eq;
+
+fn roots(formula: &str) -> Result<Vec<f64>, Error> {
+  roots_with(formula, &SolveOptions::default())
+}
+
+fn roots_with(formula: &str, options: &SolveOptions) -> Result<Vec<f64>, Error> {
+  let equation = parse_equation(tokenize(formula.to_string()), &Settings::default())?;
+  solve(&equation, "x", options, &Limits::default())
+}
+
+fn close(actual: &[f64], expected: &[f64]) -> bool {
+  actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-9)
+}
+
+#[test]
+fn testing_solve_sign_changes() {
+  assert_eq!(roots("x^2 = 4"), Ok(vec![-2.0, 2.0]));
+  assert_eq!(roots("2x + 1 = 0"), Ok(vec![-0.5]));
+
+  let cubic = roots("x^3 - 2*x = 5").unwrap();
+  assert!(close(&cubic, &[2.0945514815423265]), "{:?}", cubic);
+
+  let sine = roots_with(
+    "sin(x)",
+    &SolveOptions {
+      interval: (-1.0, 7.0),
+      ..SolveOptions::default()
+    },
+  )
+  .unwrap();
+  let pi = std::f64::consts::PI;
+  assert!(close(&sine, &[0.0, pi, 2.0 * pi]), "{:?}", sine);
+}
+
+#[test]
+fn testing_solve_touching_roots() {
+  let touching = roots("(x - 1.5)^2").unwrap();
+  assert!(close(&touching, &[1.5]), "{:?}", touching);
+  assert_eq!(
+    roots("x^2 + 1"),
+    Ok(vec![]),
+    "should not find roots of a positive function"
+  );
+}
+
+#[test]
+fn testing_solve_skips_poles() {
+  assert_eq!(roots("1/x"), Ok(vec![]), "should not take the pole for a root");
+  assert_eq!(roots("1/(x - 0.25) = 2"), Ok(vec![0.75]));
+}
+
+#[test]
+fn testing_solve_interval() {
+  let options = SolveOptions {
+    interval: (0.0, 10.0),
+    ..SolveOptions::default()
+  };
+
+  assert_eq!(roots_with("x^2 = 4", &options), Ok(vec![2.0]));
+}
+
+#[test]
+fn testing_solve_no_convergence() {
+  let options = SolveOptions {
+    max_iterations: 2,
+    ..SolveOptions::default()
+  };
+
+  assert_eq!(
+    roots_with("x^3 - 2*x = 5", &options).map_err(|error| matches!(error, Error::NoConvergence(_))),
+    Err(true)
+  );
+}
+
+#[test]
+fn testing_solve_identities() {
+  assert_eq!(
+    roots("x = x"),
+    Err(Error::InfiniteSolutions(
+      "Every x from -100 to 100 is a solution".to_string()
+    ))
+  );
+  assert_eq!(
+    roots("abs(x) = x"),
+    Err(Error::InfiniteSolutions(
+      "Every x from 0 to 100 is a solution".to_string()
+    )),
+    "should report a run of roots rather than every sample of it"
+  );
+}
+
+#[test]
+fn testing_solve_errors() {
+  assert_eq!(roots("x + y"), Err(Error::UndefinedIdentifier("y".to_string())));
+  assert_eq!(
+    run_with("x = 1".to_string(), "2", &SolveOptions::default(), &Settings::default()),
+    Err(Error::InvalidExpression(
+      "Bad format: 2 is not a variable name".to_string()
+    ))
+  );
+
+  let limited = Settings {
+    limits: Limits {
+      steps: 1000,
+      ..Limits::default()
+    },
+    ..Settings::default()
+  };
+  assert_eq!(
+    run_with("x^2 = 4".to_string(), "x", &SolveOptions::default(), &limited),
+    Err(Error::TooManySteps(1000)),
+    "should count the steps of every sample against the limits"
+  );
+}
+
+#[test]
+fn testing_run_with() {
+  let run = |formula: &str| run_with(formula.to_string(), "x", &SolveOptions::default(), &Settings::default());
+
+  assert_eq!(run("x^2 = 4"), Ok("x = -2\nx = 2".to_string()));
+  assert_eq!(run("x^2 = -4"), Ok("No real roots in [-100, 100]".to_string()));
+}
+
+#[test]
+fn testing_run_with_units_of_durations_as_variables() {
+  let run = |formula: &str, variable: &str| {
+    run_with(
+      formula.to_string(),
+      variable,
+      &SolveOptions::default(),
+      &Settings::default(),
+    )
+  };
+
+  assert_eq!(run("2d = 4", "d"), Ok("d = 2".to_string()));
+  assert_eq!(run("3h - 6 = 0", "h"), Ok("h = 2".to_string()));
+  assert_eq!(run("2s = 1", "s"), Ok("s = 0.5".to_string()));
+  assert_eq!(run("w^2 = 4w", "w"), Ok("w = 0\nw = 4".to_string()));
+}
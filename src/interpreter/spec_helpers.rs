@@ -0,0 +1,22 @@
+use super::run;
+use crate::Error;
+
+pub fn calculate(formula: &str) -> Result<String, Error> {
+  run(formula.to_string())
+}
+
+pub fn ok(value: &str) -> Result<String, Error> {
+  Ok(value.to_string())
+}
+
+pub fn invalid(message: &str) -> Result<String, Error> {
+  Err(Error::InvalidArgument(message.to_string()))
+}
+
+pub fn mismatch(message: &str) -> Result<String, Error> {
+  Err(Error::DimensionMismatch(message.to_string()))
+}
+
+pub fn overflow(message: &str) -> Result<String, Error> {
+  Err(Error::Overflow(message.to_string()))
+}
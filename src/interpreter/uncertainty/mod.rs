@@ -0,0 +1,314 @@
+use super::{
+  calculus, calendar, checked_call, checked_factorial, checked_percentage, checked_variadic, digamma, evaluate_chain,
+  factorial, format, function_named, interval, literal, matrix, operate as operate_number, percentage, variable,
+  variadic, Environment, Value as Held,
+};
+use crate::parser::{Ast, Node};
+use crate::settings::Floats;
+use crate::tokenizer::{Key, Kind, Token};
+use crate::Error;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// tells apart the sources of error of every ± over the run of the program
+static SOURCES: AtomicUsize = AtomicUsize::new(0);
+
+/// A value known up to independent errors, as a measurement, carried through
+/// calculations to the first order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Uncertain {
+  pub value: f64,
+  /// The error each independent source contributes to the value, which
+  /// keeps two uses of the same measurement correlated, so `x - x` is
+  /// exactly 0
+  errors: BTreeMap<usize, f64>,
+}
+impl Uncertain {
+  fn exact(value: f64) -> Uncertain {
+    Uncertain {
+      value,
+      ..Uncertain::default()
+    }
+  }
+
+  /// The standard uncertainty, adding up the independent errors in
+  /// quadrature.
+  pub fn uncertainty(&self) -> f64 {
+    self.errors.values().map(|error| error * error).sum::<f64>().sqrt()
+  }
+
+  fn is_exact(&self) -> bool {
+    self.errors.is_empty()
+  }
+}
+impl fmt::Display for Uncertain {
+  /// The uncertainty to two significant digits, and the value to the first
+  /// of them, as `10.0 ± 0.32`.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let uncertainty = self.uncertainty();
+    if uncertainty == 0.0 || !uncertainty.is_finite() || !self.value.is_finite() {
+      return write!(f, "{} ± {}", format(Ok(self.value)), format(Ok(uncertainty)));
+    }
+
+    let mut digit = uncertainty.log10().floor() as i32;
+    // 0.0996 is 0.10 to two digits, which starts a digit higher
+    if round(uncertainty, 1 - digit) >= 10f64.powi(digit + 1) {
+      digit += 1;
+    }
+    write!(f, "{} ± {}", fixed(self.value, -digit), fixed(uncertainty, 1 - digit))
+  }
+}
+
+fn round(value: f64, decimals: i32) -> f64 {
+  let scale = 10f64.powi(decimals);
+  (value * scale).round() / scale
+}
+
+/// `value` with `decimals` places, rounding to tens and beyond when they are
+/// negative.
+fn fixed(value: f64, decimals: i32) -> String {
+  match usize::try_from(decimals) {
+    Ok(decimals) => format!("{:.*}", decimals, value),
+    Err(_) => format!("{:.0}", round(value, decimals)),
+  }
+}
+
+/// The first-order result of a function of `operands`, given its value and
+/// its partial derivative along each operand.
+fn propagate(value: f64, operands: &[(&Uncertain, f64)]) -> Uncertain {
+  let mut errors = BTreeMap::new();
+  for (operand, derivative) in operands {
+    for (source, error) in operand.errors.iter() {
+      *errors.entry(*source).or_insert(0.0) += derivative * error;
+    }
+  }
+  Uncertain { value, errors }
+}
+
+/// The slope of `function` at `x` by central differences, for the functions
+/// without a derivative of their own.
+fn slope(function: impl Fn(f64) -> Result<f64, Error>, x: f64) -> Result<f64, Error> {
+  let step = 1e-6 * x.abs().max(1.0);
+  Ok((function(x + step)? - function(x - step)?) / (2.0 * step))
+}
+
+/// The derivative of the single-argument function `name` at `x`.
+fn derivative(name: &str, x: f64) -> Result<f64, Error> {
+  let derivative = match name {
+    "sin" => x.cos(),
+    "cos" => -x.sin(),
+    "tan" => 1.0 / x.cos().powi(2),
+    "asin" => 1.0 / (1.0 - x * x).sqrt(),
+    "acos" => -1.0 / (1.0 - x * x).sqrt(),
+    "atan" => 1.0 / (1.0 + x * x),
+    "sinh" => x.cosh(),
+    "cosh" => x.sinh(),
+    "tanh" => 1.0 / x.cosh().powi(2),
+    "exp" => x.exp(),
+    "ln" => 1.0 / x,
+    "log" => 1.0 / (x * std::f64::consts::LN_10),
+    "sqrt" => 0.5 / x.sqrt(),
+    "abs" => x.signum(),
+    _ => {
+      let function = function_named(name).ok_or_else(|| Error::UndefinedIdentifier(name.to_string()))?;
+      return slope(|x| Ok(function(x)), x);
+    }
+  };
+  Ok(derivative)
+}
+
+/// Whether `ast` has a ± or a variable bound to an uncertain value, and so
+/// has to be evaluated here.
+pub fn uses_uncertainty(ast: &Ast, environment: &Environment) -> bool {
+  let is_plus_minus = |ast: &&Ast| matches!(&ast.operator, Some(operator) if operator.keys[0] == Key::PlusMinus);
+  ast.left_spine().iter().any(is_plus_minus)
+    || ast
+      .spine_operands()
+      .into_iter()
+      .any(|node| node_uses_uncertainty(node, environment))
+}
+
+fn node_uses_uncertainty(node: &Node, environment: &Environment) -> bool {
+  let uses = |node: &Node| node_uses_uncertainty(node, environment);
+  match node {
+    Node::Ast(ast) => uses_uncertainty(ast, environment),
+    Node::Call(_, arguments) => arguments.iter().any(uses),
+    Node::Factorial(node) | Node::Percentage(node) => uses(node),
+    Node::Interval(lower, upper) => uses(lower) || uses(upper),
+    Node::Matrix(rows) => rows.iter().flatten().any(uses),
+    Node::Token(_) => environment.holds(node, |value| matches!(value, Held::Uncertain(_))),
+  }
+}
+
+/// The error of the evaluators that only produce numbers when they meet a ±.
+pub fn unsupported(node: &Node) -> Error {
+  Error::InvalidArgument(format!("Uncertain expression {} cannot be evaluated as a number", node))
+}
+
+pub fn evaluate(ast: &Ast, environment: &Environment) -> Result<Uncertain, Error> {
+  let check = |ast: &Ast| ast.operator.as_ref().map(|_| ()).ok_or(Error::TokenNaO);
+  let operand = |node: Option<&Node>| evaluate_node(node, environment);
+
+  evaluate_chain(&ast.left_spine(), environment, check, operand, |value_a, ast| {
+    let operator = ast.operator.as_ref().ok_or(Error::TokenNaO)?;
+    let value_b = match (&operator.keys[0], ast.operand_b.as_ref()) {
+      // a + b% is relative to a
+      (Key::Addition, Some(Node::Percentage(node))) | (Key::Subtraction, Some(Node::Percentage(node))) => {
+        let rate = evaluate_node(Some(node), environment)?;
+        let rate = propagate(rate.value / 100.0, &[(&rate, 0.01)]);
+        operate(
+          environment.floats,
+          &Token::new_operator(Key::Multiplication),
+          &value_a,
+          &rate,
+        )?
+      }
+      // and so is the uncertainty of a ± b%, to the value of a
+      (Key::PlusMinus, Some(Node::Percentage(node))) => {
+        let rate = evaluate_node(Some(node), environment)?;
+        let scale = value_a.value / 100.0;
+        propagate(rate.value * scale, &[(&rate, scale)])
+      }
+      (_, operand_b) => evaluate_node(operand_b, environment)?,
+    };
+
+    operate(environment.floats, operator, &value_a, &value_b)
+  })
+}
+
+pub fn evaluate_node(node: Option<&Node>, environment: &Environment) -> Result<Uncertain, Error> {
+  environment.budget.spend(1)?;
+  match node {
+    Some(Node::Ast(ast)) => evaluate(ast, environment),
+    Some(Node::Call(function, arguments)) => call(function, arguments, environment),
+    Some(Node::Factorial(node)) => {
+      let x = evaluate_node(Some(node), environment)?;
+      let value = checked_factorial(environment.floats, x.value, factorial(x.value)?)?;
+      // (x!)' = x!*ψ(x + 1)
+      Ok(propagate(value, &[(&x, value * digamma(x.value + 1.0))]))
+    }
+    Some(Node::Percentage(node)) => {
+      let x = evaluate_node(Some(node), environment)?;
+      let value = checked_percentage(environment.floats, x.value, percentage(x.value))?;
+      Ok(propagate(value, &[(&x, 0.01)]))
+    }
+    Some(node @ Node::Interval(_, _)) => Err(interval::unsupported(node)),
+    Some(node @ Node::Matrix(_)) => Err(matrix::unsupported(node)),
+    Some(Node::Token(token)) if token.kind == Kind::Identifier => {
+      let name = token.to_string();
+      match environment.get(&name) {
+        Some(Held::Uncertain(value)) => Ok(value.clone()),
+        _ => variable(&name, environment).map(Uncertain::exact),
+      }
+    }
+    Some(node @ Node::Token(token)) if matches!(token.kind, Kind::Date | Kind::Duration) => {
+      Err(calendar::unsupported(node))
+    }
+    Some(Node::Token(token)) => literal(environment.floats, token).map(Uncertain::exact),
+    None => Err(Error::InvalidOperand),
+  }
+}
+
+fn operate(floats: Floats, operator: &Token, a: &Uncertain, b: &Uncertain) -> Result<Uncertain, Error> {
+  let (x, y) = (a.value, b.value);
+  let key = &operator.keys[0];
+  if *key == Key::PlusMinus {
+    if !b.is_exact() {
+      return Err(Error::InvalidArgument(format!(
+        "Operator ± expects an exact uncertainty, got {}",
+        b
+      )));
+    }
+    let mut value = a.clone();
+    value.errors.insert(SOURCES.fetch_add(1, Ordering::Relaxed), y.abs());
+    return Ok(value);
+  }
+
+  let value = operate_number(floats, operator, x, y)?;
+  let derivatives = match key {
+    Key::Addition => (1.0, 1.0),
+    Key::Subtraction => (1.0, -1.0),
+    Key::Multiplication | Key::Of => (y, x),
+    Key::Division => (1.0 / y, -x / (y * y)),
+    Key::Exponentiation if !b.is_exact() && x <= 0.0 => {
+      return Err(Error::InvalidArgument(format!(
+        "Operator ^ expects a positive base for the uncertain exponent {}, got {}",
+        b, x
+      )))
+    }
+    // (x^y)' = y*x^(y - 1)*x' + x^y*ln(x)*y', leaving out the terms of an
+    // exact operand
+    Key::Exponentiation => (
+      if a.is_exact() { 0.0 } else { y * x.powf(y - 1.0) },
+      if b.is_exact() { 0.0 } else { value * x.ln() },
+    ),
+    _ if a.is_exact() && b.is_exact() => (0.0, 0.0),
+    _ => {
+      return Err(Error::InvalidArgument(format!(
+        "Operator {} does not apply to uncertain values",
+        operator
+      )))
+    }
+  };
+
+  Ok(propagate(value, &[(a, derivatives.0), (b, derivatives.1)]))
+}
+
+fn call(function: &Token, arguments: &[Node], environment: &Environment) -> Result<Uncertain, Error> {
+  let name = function.to_string();
+  let node = || Node::Call(function.clone(), arguments.to_vec());
+  if matrix::FUNCTIONS.contains(&name.as_str()) {
+    return Err(matrix::unsupported(&node()));
+  }
+  if calendar::FUNCTIONS.contains(&name.as_str()) {
+    return Err(calendar::unsupported(&node()));
+  }
+  if calculus::is_calculus(&name) {
+    return Err(Error::InvalidArgument(format!(
+      "Function {} does not apply to uncertain values",
+      name
+    )));
+  }
+  let values = arguments
+    .iter()
+    .map(|argument| evaluate_node(Some(argument), environment))
+    .collect::<Result<Vec<Uncertain>, Error>>()?;
+  let nominal: Vec<f64> = values.iter().map(|value| value.value).collect();
+
+  if let Some(function) = variadic::function_named(&name) {
+    // the slope along each uncertain argument, holding the others
+    let mut operands = Vec::new();
+    for (i, value) in values.iter().enumerate().filter(|(_, value)| !value.is_exact()) {
+      let along = |x: f64| {
+        let mut moved = nominal.clone();
+        moved[i] = x;
+        function(&moved)
+      };
+      operands.push((value, slope(along, value.value)?));
+    }
+    let value = checked_variadic(environment.floats, &name, &nominal, function(&nominal)?)?;
+    return Ok(propagate(value, &operands));
+  }
+
+  let x = match values.as_slice() {
+    [x] => x,
+    _ => {
+      return Err(Error::InvalidExpression(format!(
+        "Bad format: Function {} expects 1 argument",
+        name
+      )))
+    }
+  };
+  let function = function_named(&name).ok_or_else(|| Error::UndefinedIdentifier(name.clone()))?;
+  let derivative = match x.is_exact() {
+    true => 0.0,
+    false => derivative(&name, x.value)?,
+  };
+  let value = checked_call(environment.floats, &name, x.value, function(x.value))?;
+  Ok(propagate(value, &[(x, derivative)]))
+}
+
+#[cfg(test)]
+mod uncertainty_spec;
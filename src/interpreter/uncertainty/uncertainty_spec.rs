@@ -0,0 +1,58 @@
+use super::super::run_script;
+use super::super::spec_helpers::{calculate, invalid, ok};
+use super::*;
+use crate::settings::Settings;
+use pretty_assertions::assert_eq;
+
+fn measured(value: f64, uncertainty: f64) -> Uncertain {
+  let mut errors = BTreeMap::new();
+  errors.insert(0, uncertainty);
+  Uncertain { value, errors }
+}
+
+#[test]
+fn testing_display() {
+  assert_eq!(measured(10.0, 0.3202).to_string(), "10.0 ± 0.32");
+  assert_eq!(measured(25.0, 1.0).to_string(), "25 ± 1.0");
+  assert_eq!(measured(1.2345, 0.0996).to_string(), "1.2 ± 0.10");
+  assert_eq!(measured(1234.5, 12.0).to_string(), "1230 ± 12");
+  assert_eq!(Uncertain::exact(2.5).to_string(), "2.5 ± 0");
+}
+
+#[test]
+fn testing_propagation() {
+  assert_eq!(calculate("(5.0 ± 0.1) * (2.0 ± 0.05)"), ok("10.0 ± 0.32"));
+  assert_eq!(calculate("(5.0 +/- 0.1) * (2.0 +/- 0.05)"), ok("10.0 ± 0.32"));
+  assert_eq!(calculate("5 ± 0.3 + 4 ± 0.4"), ok("9.0 ± 0.50"));
+  assert_eq!(calculate("(6 ± 0.3) / (2 ± 0.1)"), ok("3.0 ± 0.21"));
+  assert_eq!(calculate("2 * 100 ± 5%"), ok("200 ± 10"));
+  assert_eq!(calculate("(4 ± 0.1)^2"), ok("16.0 ± 0.80"));
+  assert_eq!(
+    calculate("1 ± (1 ± 0.1)"),
+    invalid("Operator ± expects an exact uncertainty, got 1.0 ± 0.10")
+  );
+  assert_eq!(
+    calculate("(6 ± 1) & 3"),
+    invalid("Operator & does not apply to uncertain values")
+  );
+}
+
+#[test]
+fn testing_functions() {
+  assert_eq!(calculate("sqrt(16 ± 0.4)"), ok("4.00 ± 0.050"));
+  assert_eq!(calculate("ln(10 ± 1)"), ok("2.3 ± 0.10"));
+  assert_eq!(calculate("sum(1 ± 0.3, 2 ± 0.4)"), ok("3.0 ± 0.50"));
+  assert_eq!(
+    calculate("integrate(x ± 1, x, 0, 1)"),
+    invalid("Function integrate does not apply to uncertain values")
+  );
+}
+
+#[test]
+fn testing_correlated_variables() {
+  let script = "x = 5 ± 0.1\nx * x\nx - x\n(x ± 0.1) - x\nx = 2\nx";
+  assert_eq!(
+    run_script(script, &Settings::default()),
+    ok("25 ± 1.0\n0 ± 0\n0.0 ± 0.10\n2")
+  );
+}
@@ -0,0 +1,153 @@
+use super::format;
+use crate::Error;
+
+/// Functions of any number of values, where a matrix argument gives all of
+/// its elements, so `mean([1, 2, 3])` is `mean(1, 2, 3)`.
+pub const FUNCTIONS: [&str; 9] = [
+  "max",
+  "mean",
+  "median",
+  "min",
+  "percentile",
+  "prod",
+  "stdev",
+  "sum",
+  "variance",
+];
+
+pub type Function = fn(&[f64]) -> Result<f64, Error>;
+
+/// The function of many values called `name`.
+pub fn function_named(name: &str) -> Option<Function> {
+  match name {
+    "max" => Some(max),
+    "mean" => Some(mean),
+    "median" => Some(median),
+    "min" => Some(min),
+    "percentile" => Some(percentile),
+    "prod" => Some(prod),
+    "stdev" => Some(stdev),
+    "sum" => Some(sum),
+    "variance" => Some(variance),
+    _ => None,
+  }
+}
+
+pub fn sum(values: &[f64]) -> Result<f64, Error> {
+  Ok(values.iter().sum())
+}
+
+pub fn prod(values: &[f64]) -> Result<f64, Error> {
+  Ok(values.iter().product())
+}
+
+pub fn min(values: &[f64]) -> Result<f64, Error> {
+  at_least("min", values, 1)?;
+  Ok(values.iter().copied().fold(f64::INFINITY, f64::min))
+}
+
+pub fn max(values: &[f64]) -> Result<f64, Error> {
+  at_least("max", values, 1)?;
+  Ok(values.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+}
+
+pub fn mean(values: &[f64]) -> Result<f64, Error> {
+  at_least("mean", values, 1)?;
+  Ok(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+pub fn median(values: &[f64]) -> Result<f64, Error> {
+  at_least("median", values, 1)?;
+  Ok(interpolate(&sorted(values), 0.5))
+}
+
+/// The sample variance, dividing by one less than the number of values.
+pub fn variance(values: &[f64]) -> Result<f64, Error> {
+  at_least("variance", values, 2)?;
+  let mean = mean(values)?;
+  let squares: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+  Ok(squares / (values.len() - 1) as f64)
+}
+
+/// The sample standard deviation.
+pub fn stdev(values: &[f64]) -> Result<f64, Error> {
+  at_least("stdev", values, 2)?;
+  variance(values).map(f64::sqrt)
+}
+
+/// `percentile(values, p)`, interpolating between the closest ranks as
+/// spreadsheets do, so the 50th percentile is the median.
+pub fn percentile(values: &[f64]) -> Result<f64, Error> {
+  at_least("percentile", values, 2)?;
+  let (rank, values) = values.split_last().unwrap_or((&0.0, values));
+  if !(0.0..=100.0).contains(rank) {
+    return Err(Error::InvalidArgument(format!(
+      "Function percentile expects a percentile from 0 to 100, got {}",
+      rank
+    )));
+  }
+  Ok(interpolate(&sorted(values), rank / 100.0))
+}
+
+/// A table summarizing the numbers of `input`, separated by spaces, commas
+/// or newlines. The spread is left out below 2 numbers.
+pub fn stats(input: &str) -> Result<String, Error> {
+  let values = input
+    .split(|character: char| character.is_whitespace() || character == ',')
+    .filter(|number| !number.is_empty())
+    .map(|number| match number.parse::<f64>() {
+      Ok(value) if value.is_finite() => Ok(value),
+      _ => Err(Error::InvalidArgument(format!("{} is not a number", number))),
+    })
+    .collect::<Result<Vec<f64>, Error>>()?;
+  if values.is_empty() {
+    return Err(Error::InvalidArgument("There are no numbers to summarize".to_string()));
+  }
+
+  let rows: [(&str, Function); 9] = [
+    ("sum", sum),
+    ("mean", mean),
+    ("median", median),
+    ("stdev", stdev),
+    ("variance", variance),
+    ("min", min),
+    ("max", max),
+    ("p25", |values| percentile(&[values, &[25.0]].concat())),
+    ("p75", |values| percentile(&[values, &[75.0]].concat())),
+  ];
+  let mut table = format!("{:<10}{}", "count", values.len());
+  for (name, function) in rows.iter() {
+    if let Ok(value) = function(&values) {
+      table.push_str(&format!("\n{:<10}{}", name, format(Ok(value))));
+    }
+  }
+  Ok(table)
+}
+
+fn at_least(name: &str, values: &[f64], count: usize) -> Result<(), Error> {
+  if values.len() < count {
+    let plural = if count == 1 { "" } else { "s" };
+    return Err(Error::InvalidArgument(format!(
+      "Function {} expects at least {} value{}",
+      name, count, plural
+    )));
+  }
+  Ok(())
+}
+
+fn sorted(values: &[f64]) -> Vec<f64> {
+  let mut values = values.to_vec();
+  values.sort_by(f64::total_cmp);
+  values
+}
+
+/// The value at `fraction` of the way through sorted `values`.
+fn interpolate(values: &[f64], fraction: f64) -> f64 {
+  let position = fraction * (values.len() - 1) as f64;
+  let below = position.floor() as usize;
+  let above = position.ceil() as usize;
+  values[below] + (values[above] - values[below]) * (position - below as f64)
+}
+
+#[cfg(test)]
+mod variadic_spec;
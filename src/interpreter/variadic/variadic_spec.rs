@@ -0,0 +1,104 @@
+use super::super::run;
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn value(formula: &str) -> Result<f64, Error> {
+  run(formula.to_string()).map(|value| value.parse().unwrap())
+}
+
+#[test]
+fn testing_aggregates() {
+  let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+  assert_eq!(sum(&values), Ok(40.0));
+  assert_eq!(mean(&values), Ok(5.0));
+  assert_eq!(median(&values), Ok(4.5));
+  assert_eq!(median(&[3.0, 1.0, 2.0]), Ok(2.0), "should sort the values");
+  assert_eq!(min(&values), Ok(2.0));
+  assert_eq!(max(&values), Ok(9.0));
+  assert_eq!(variance(&values), Ok(32.0 / 7.0));
+  assert_eq!(stdev(&[1.0, 3.0]), Ok(2.0_f64.sqrt()));
+  assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0, 5.0, 90.0]), Ok(4.6));
+  assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0, 50.0]), Ok(2.5));
+}
+
+#[test]
+fn testing_aggregate_errors() {
+  assert_eq!(
+    mean(&[]),
+    Err(Error::InvalidArgument(
+      "Function mean expects at least 1 value".to_string()
+    ))
+  );
+  assert_eq!(
+    stdev(&[1.0]),
+    Err(Error::InvalidArgument(
+      "Function stdev expects at least 2 values".to_string()
+    ))
+  );
+  assert_eq!(
+    percentile(&[1.0, 2.0, 101.0]),
+    Err(Error::InvalidArgument(
+      "Function percentile expects a percentile from 0 to 100, got 101".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_calls() {
+  assert_eq!(value("sum(1, 2, 3)"), Ok(6.0));
+  assert_eq!(value("sum(1, 2, 1, 3)"), Ok(7.0));
+  assert_eq!(
+    value("sum(i, i, 1, 3)"),
+    Err(Error::UndefinedIdentifier("i".to_string())),
+    "should leave series to series"
+  );
+  assert_eq!(value("prod(2, 3, 4)"), Ok(24.0));
+  assert_eq!(value("mean(1, 2, 3) * 2"), Ok(4.0));
+  assert_eq!(
+    value("mean([1, 2; 3, 4], 5)"),
+    Ok(3.0),
+    "should take the elements of lists"
+  );
+  assert_eq!(value("percentile([15, 20, 35, 40, 50], 40)"), Ok(29.0));
+  assert_eq!(value("max(sin(0), 1/2)"), Ok(0.5));
+  assert_eq!(value("median(1, 2/0)"), Err(Error::DivisionByZero));
+}
+
+#[test]
+fn testing_stats() {
+  assert_eq!(
+    stats("2 4 4\n4, 5 5\n7 9\n"),
+    Ok(
+      [
+        "count     8",
+        "sum       40",
+        "mean      5",
+        "median    4.5",
+        "stdev     2.138089935299395",
+        "variance  4.571428571428571",
+        "min       2",
+        "max       9",
+        "p25       4",
+        "p75       5.5",
+      ]
+      .join("\n")
+    )
+  );
+  assert_eq!(
+    stats("3"),
+    Ok(
+      "count     1\nsum       3\nmean      3\nmedian    3\nmin       3\nmax       3\np25       3\np75       3"
+        .to_string()
+    ),
+    "should leave out the spread of a single number"
+  );
+  assert_eq!(
+    stats("1 two"),
+    Err(Error::InvalidArgument("two is not a number".to_string()))
+  );
+  assert_eq!(
+    stats(" \n"),
+    Err(Error::InvalidArgument("There are no numbers to summarize".to_string()))
+  );
+}
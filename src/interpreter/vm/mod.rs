@@ -0,0 +1,536 @@
+use super::calculus::{self, integrate, series};
+use super::{
+  addition, bitwise_operation, checked, checked_call, checked_factorial, checked_operation, checked_percentage,
+  checked_variadic, constant, division_in, exponentiation, factorial, function_named, literal, multiplication,
+  operator_of, percentage, subtraction, Budget, Operation,
+};
+use super::{calendar, interval, matrix, variadic};
+use crate::parser::{Ast, Node};
+use crate::settings::{Floats, Limits};
+use crate::tokenizer::{Key, Kind, Token, FUNCTIONS};
+use crate::Error;
+
+/// Registers and stack slots a program runs with before it needs the heap.
+const INLINE: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Instruction {
+  /// Pushes a number
+  Constant(f64),
+  /// Pushes the value of a register
+  Load(usize),
+  Add,
+  Subtract,
+  Multiply,
+  Divide,
+  Power,
+  /// Replaces a and b with a + a * b, as in a + b% relative to a
+  AddRelative,
+  /// Replaces a and b with a - a * b, as in a - b% relative to a
+  SubtractRelative,
+  /// Replaces a and b with the result of a bitwise operator
+  Bitwise(Operation),
+  /// Divides the top by 100
+  Percent,
+  Factorial,
+  /// Applies a single-argument function to the top
+  Function {
+    name: &'static str,
+    function: fn(f64) -> f64,
+  },
+  /// Replaces the `count` values on top with the result of a function of
+  /// many values
+  Variadic {
+    name: &'static str,
+    function: variadic::Function,
+    count: usize,
+  },
+  /// Replaces the bounds on top with the result of the `length` instructions
+  /// that follow, run once per value bound to `register`
+  Calculus {
+    function: Calculus,
+    register: usize,
+    length: usize,
+  },
+  /// Stops with the error at this index of the program
+  Fail(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Calculus {
+  Integrate,
+  Product,
+  Series,
+}
+
+/// An expression compiled to instructions for a stack machine.
+#[derive(Clone, Debug)]
+pub struct Program {
+  instructions: Vec<Instruction>,
+  errors: Vec<Error>,
+  variables: Vec<String>,
+  /// Registers of the variables, followed by those bound by sum, prod and
+  /// integrate
+  registers: usize,
+  /// Most values on the stack at once
+  depth: usize,
+  floats: Floats,
+}
+impl Program {
+  pub fn instructions(&self) -> &[Instruction] {
+    &self.instructions
+  }
+
+  /// Names of the variables, in the order `run` expects their values.
+  pub fn variables(&self) -> &[String] {
+    &self.variables
+  }
+
+  /// Evaluates the program with the values of its variables, on the stack of
+  /// the caller unless the expression is unusually deep.
+  pub fn run(&self, values: &[f64]) -> Result<f64, Error> {
+    self.run_within(values, &Budget::default())
+  }
+
+  /// Evaluates the program as `run` does, counting its instructions against
+  /// the steps and the time of `budget`.
+  pub(super) fn run_within(&self, values: &[f64], budget: &Budget) -> Result<f64, Error> {
+    if values.len() != self.variables.len() {
+      return Err(Error::InvalidArgument(format!(
+        "Expected {} values, got {}",
+        self.variables.len(),
+        values.len()
+      )));
+    }
+
+    self.run_bound(|registers| registers.copy_from_slice(values), budget)
+  }
+
+  /// Evaluates the program once `bind` has set the registers of the
+  /// variables, in the order of `variables`, within the steps and the time
+  /// of `limits`.
+  pub fn run_with(&self, bind: impl FnOnce(&mut [f64]), limits: &Limits) -> Result<f64, Error> {
+    self.run_bound(bind, &Budget::new(limits))
+  }
+
+  fn run_bound(&self, bind: impl FnOnce(&mut [f64]), budget: &Budget) -> Result<f64, Error> {
+    let variables = self.variables.len();
+
+    if self.registers <= INLINE && self.depth <= INLINE {
+      let mut registers = [0.0; INLINE];
+      let mut stack = [0.0; INLINE];
+      bind(&mut registers[..variables]);
+      self.execute(&self.instructions, &mut registers, &mut stack, budget)
+    } else {
+      let mut registers = vec![0.0; self.registers];
+      let mut stack = vec![0.0; self.depth];
+      bind(&mut registers[..variables]);
+      self.execute(&self.instructions, &mut registers, &mut stack, budget)
+    }
+  }
+
+  fn execute(
+    &self,
+    instructions: &[Instruction],
+    registers: &mut [f64],
+    stack: &mut [f64],
+    budget: &Budget,
+  ) -> Result<f64, Error> {
+    // the body of sum, prod and integrate counts again on every run
+    budget.spend(instructions.len() as u64)?;
+    let mut top = 0;
+    let mut next = 0;
+
+    while next < instructions.len() {
+      let instruction = instructions[next];
+      next += 1;
+
+      match instruction {
+        Instruction::Constant(value) => {
+          stack[top] = value;
+          top += 1;
+        }
+        Instruction::Load(register) => {
+          stack[top] = registers[register];
+          top += 1;
+        }
+        Instruction::Add => top = self.binary(stack, top, addition, &Key::Addition)?,
+        Instruction::Subtract => top = self.binary(stack, top, subtraction, &Key::Subtraction)?,
+        Instruction::Multiply => top = self.binary(stack, top, multiplication, &Key::Multiplication)?,
+        Instruction::Divide => top = self.binary(stack, top, division_in(self.floats), &Key::Division)?,
+        Instruction::Power => top = self.binary(stack, top, exponentiation, &Key::Exponentiation)?,
+        Instruction::AddRelative => {
+          stack[top - 1] *= stack[top - 2];
+          top = self.binary(stack, top, addition, &Key::Addition)?;
+        }
+        Instruction::SubtractRelative => {
+          stack[top - 1] *= stack[top - 2];
+          top = self.binary(stack, top, subtraction, &Key::Subtraction)?;
+        }
+        // integers of 64 bits, which always fit
+        Instruction::Bitwise(operation) => {
+          stack[top - 2] = operation(stack[top - 2], stack[top - 1])?;
+          top -= 1;
+        }
+        Instruction::Percent => {
+          let x = stack[top - 1];
+          stack[top - 1] = checked_percentage(self.floats, x, percentage(x))?;
+        }
+        Instruction::Factorial => {
+          let x = stack[top - 1];
+          stack[top - 1] = checked_factorial(self.floats, x, factorial(x)?)?;
+        }
+        Instruction::Function { name, function } => {
+          let x = stack[top - 1];
+          stack[top - 1] = checked_call(self.floats, name, x, function(x))?;
+        }
+        Instruction::Variadic { name, function, count } => {
+          top -= count;
+          let values = &stack[top..top + count];
+          stack[top] = checked_variadic(self.floats, name, values, function(values)?)?;
+          top += 1;
+        }
+        Instruction::Calculus {
+          function,
+          register,
+          length,
+        } => {
+          top -= 2;
+          let (from, to) = (stack[top], stack[top + 1]);
+          let instructions = &instructions[next..next + length];
+
+          // the body runs on the stack above the values below the bounds
+          let above = &mut stack[top..];
+          let mut body = |value: f64| {
+            registers[register] = value;
+            self.execute(instructions, registers, above, budget)
+          };
+          let (name, value) = match function {
+            Calculus::Integrate => ("integrate", integrate(&mut body, from, to)?),
+            Calculus::Series => (
+              "series",
+              series("series", &mut body, from, to, 0.0, |total, value| total + value)?,
+            ),
+            Calculus::Product => (
+              "product",
+              series("product", &mut body, from, to, 1.0, |total, value| total * value)?,
+            ),
+          };
+
+          stack[top] = calculus::checked_total(self.floats, name, from, to, value)?;
+          top += 1;
+          next += length;
+        }
+        Instruction::Fail(index) => return Err(self.errors[index].clone()),
+      }
+    }
+
+    Ok(stack[top - 1])
+  }
+
+  /// Replaces the two values on top with the result of `operation`, the
+  /// operator `key`.
+  fn binary(&self, stack: &mut [f64], top: usize, operation: Operation, key: &Key) -> Result<usize, Error> {
+    let (a, b) = (stack[top - 2], stack[top - 1]);
+    stack[top - 2] = checked_operation(self.floats, key, a, b, operation(a, b)?)?;
+    Ok(top - 1)
+  }
+}
+
+/// Compiles `ast` for the given variables, whose values are passed to `run`
+/// in the same order, and for what `floats` do with results out of range.
+///
+/// Anything the tree walker would fail on compiles to an instruction failing
+/// with the same error at the same point of the evaluation.
+pub fn compile(ast: &Ast, variables: &[&str], floats: Floats) -> Program {
+  let mut compiler = Compiler {
+    program: Program {
+      instructions: Vec::new(),
+      errors: Vec::new(),
+      variables: variables.iter().map(|name| name.to_string()).collect(),
+      registers: variables.len(),
+      depth: 0,
+      floats,
+    },
+    scopes: Vec::new(),
+    depth: 0,
+  };
+  compiler.ast(ast);
+  compiler.program
+}
+
+/// Identifiers of `ast` that are neither constants nor bound by sum, prod or
+/// integrate, in the order they first appear.
+pub fn free_variables(ast: &Ast) -> Vec<String> {
+  let mut variables = Vec::new();
+  collect_ast(ast, &mut Vec::new(), &mut variables);
+  variables
+}
+
+fn collect_ast(ast: &Ast, scopes: &mut Vec<String>, variables: &mut Vec<String>) {
+  for node in ast.spine_operands() {
+    collect_node(node, scopes, variables);
+  }
+}
+
+fn collect_node(node: &Node, scopes: &mut Vec<String>, variables: &mut Vec<String>) {
+  match node {
+    Node::Ast(ast) => collect_ast(ast, scopes, variables),
+    Node::Call(function, arguments) => {
+      let name = function.to_string();
+      match calculus::split_arguments(&name, arguments) {
+        Ok((body, variable, from, to)) if calculus::is_calculus(&name) => {
+          collect_node(from, scopes, variables);
+          collect_node(to, scopes, variables);
+          scopes.push(variable);
+          collect_node(body, scopes, variables);
+          scopes.pop();
+        }
+        _ => {
+          for argument in arguments {
+            collect_node(argument, scopes, variables);
+          }
+        }
+      }
+    }
+    Node::Factorial(node) | Node::Percentage(node) => collect_node(node, scopes, variables),
+    Node::Interval(lower, upper) => {
+      collect_node(lower, scopes, variables);
+      collect_node(upper, scopes, variables);
+    }
+    Node::Matrix(rows) => {
+      for element in rows.iter().flatten() {
+        collect_node(element, scopes, variables);
+      }
+    }
+    Node::Token(token) if token.kind == Kind::Identifier => {
+      let name = token.to_string();
+      if !scopes.contains(&name) && !variables.contains(&name) && constant(&name).is_err() {
+        variables.push(name);
+      }
+    }
+    Node::Token(_) => {}
+  }
+}
+
+struct Compiler {
+  program: Program,
+  /// Variables bound around the current instruction, innermost last
+  scopes: Vec<(String, usize)>,
+  depth: usize,
+}
+impl Compiler {
+  fn emit(&mut self, instruction: Instruction) {
+    match instruction {
+      // a failure stands for the value it never produces
+      Instruction::Constant(_) | Instruction::Load(_) | Instruction::Fail(_) => self.depth += 1,
+      // the values on top give way to the result
+      Instruction::Variadic { count, .. } => self.depth = self.depth + 1 - count,
+      Instruction::Add
+      | Instruction::Subtract
+      | Instruction::Multiply
+      | Instruction::Divide
+      | Instruction::Power
+      | Instruction::AddRelative
+      | Instruction::SubtractRelative
+      | Instruction::Bitwise(_) => self.depth -= 1,
+      _ => {}
+    }
+    self.program.depth = self.program.depth.max(self.depth);
+    self.program.instructions.push(instruction);
+  }
+
+  fn fail(&mut self, error: Error) {
+    self.program.errors.push(error);
+    self.emit(Instruction::Fail(self.program.errors.len() - 1));
+  }
+
+  fn ast(&mut self, ast: &Ast) {
+    // along the left operands of a chain in a loop, where the tree walker
+    // rejects an operator before the operations inside it
+    let spine = ast.left_spine();
+    let rejected = spine
+      .iter()
+      .enumerate()
+      .rev()
+      .find_map(|(i, ast)| Some((i, operator_of(ast).err()?)));
+    let chain = match rejected {
+      Some((i, error)) => {
+        self.fail(error);
+        &spine[i + 1..]
+      }
+      None => {
+        self.node(spine[0].operand_a.as_ref());
+        &spine[..]
+      }
+    };
+    for ast in chain {
+      self.operation(ast);
+    }
+  }
+
+  /// The operation of `ast` on the value of its left operand, on top of the
+  /// stack.
+  fn operation(&mut self, ast: &Ast) {
+    let key = match operator_of(ast) {
+      Ok(operator) => operator.keys[0].clone(),
+      Err(error) => return self.fail(error),
+    };
+    match (&key, ast.operand_b.as_ref()) {
+      (Key::Addition, Some(Node::Percentage(node))) => {
+        self.node(Some(node));
+        self.emit(Instruction::Percent);
+        self.emit(Instruction::AddRelative);
+      }
+      (Key::Subtraction, Some(Node::Percentage(node))) => {
+        self.node(Some(node));
+        self.emit(Instruction::Percent);
+        self.emit(Instruction::SubtractRelative);
+      }
+      (_, operand_b) => {
+        self.node(operand_b);
+        match key {
+          Key::Multiplication | Key::Of => self.emit(Instruction::Multiply),
+          Key::Division => self.emit(Instruction::Divide),
+          Key::Exponentiation => self.emit(Instruction::Power),
+          Key::Addition => self.emit(Instruction::Add),
+          Key::Subtraction => self.emit(Instruction::Subtract),
+          ref key => match bitwise_operation(key) {
+            Some(operation) => self.emit(Instruction::Bitwise(operation)),
+            None => self.fail(Error::TokenNaO),
+          },
+        }
+      }
+    }
+  }
+
+  fn node(&mut self, node: Option<&Node>) {
+    match node {
+      Some(Node::Ast(ast)) => self.ast(ast),
+      Some(Node::Call(function, arguments)) => self.call(function, arguments),
+      Some(Node::Factorial(node)) => {
+        self.node(Some(node));
+        self.emit(Instruction::Factorial);
+      }
+      Some(Node::Percentage(node)) => {
+        self.node(Some(node));
+        self.emit(Instruction::Percent);
+      }
+      Some(node @ Node::Interval(_, _)) => self.fail(interval::unsupported(node)),
+      Some(node @ Node::Matrix(_)) => self.fail(matrix::unsupported(node)),
+      Some(Node::Token(token)) if token.kind == Kind::Identifier => self.variable(&token.to_string()),
+      Some(node @ Node::Token(token)) if matches!(token.kind, Kind::Date | Kind::Duration) => {
+        self.fail(calendar::unsupported(node))
+      }
+      Some(Node::Token(token)) => match literal(self.program.floats, token) {
+        Ok(value) => self.emit(Instruction::Constant(value)),
+        Err(error) => self.fail(error),
+      },
+      None => self.fail(Error::InvalidOperand),
+    }
+  }
+
+  fn variable(&mut self, name: &str) {
+    let bound = self.scopes.iter().rev().find(|(bound, _)| bound == name);
+    let register = bound
+      .map(|(_, register)| *register)
+      .or_else(|| self.program.variables.iter().position(|variable| variable == name));
+
+    let constant = constant(name).and_then(|value| checked(self.program.floats, value, false, || name.to_string()));
+    match (register, constant) {
+      (Some(register), _) => self.emit(Instruction::Load(register)),
+      (None, Ok(value)) => self.emit(Instruction::Constant(value)),
+      (None, Err(error)) => self.fail(error),
+    }
+  }
+
+  fn call(&mut self, function: &Token, arguments: &[Node]) {
+    let name = function.to_string();
+    if calculus::is_calculus(&name) {
+      return self.calculus(&name, arguments);
+    }
+    if matrix::FUNCTIONS.contains(&name.as_str()) {
+      return self.fail(matrix::unsupported(&Node::Call(function.clone(), arguments.to_vec())));
+    }
+    if calendar::FUNCTIONS.contains(&name.as_str()) {
+      return self.fail(calendar::unsupported(&Node::Call(function.clone(), arguments.to_vec())));
+    }
+    if let Some(function) = variadic::function_named(&name) {
+      for argument in arguments {
+        self.node(Some(argument));
+      }
+      return self.emit(Instruction::Variadic {
+        name: static_name(&name),
+        function,
+        count: arguments.len(),
+      });
+    }
+
+    match arguments {
+      [argument] => self.node(Some(argument)),
+      _ => {
+        return self.fail(Error::InvalidExpression(format!(
+          "Bad format: Function {} expects 1 argument",
+          name
+        )))
+      }
+    }
+    match function_named(&name) {
+      Some(function) => self.emit(Instruction::Function {
+        name: static_name(&name),
+        function,
+      }),
+      None => self.fail(Error::UndefinedIdentifier(name)),
+    }
+  }
+
+  /// Compiles the bounds, then the body inline after the instruction that
+  /// runs it, on the stack slots the bounds leave free.
+  fn calculus(&mut self, name: &str, arguments: &[Node]) {
+    let (body, variable, from, to) = match calculus::split_arguments(name, arguments) {
+      Ok(arguments) => arguments,
+      Err(error) => return self.fail(error),
+    };
+    let function = match name {
+      "integrate" => Calculus::Integrate,
+      "product" => Calculus::Product,
+      _ => Calculus::Series,
+    };
+
+    self.node(Some(from));
+    self.node(Some(to));
+    let register = self.program.registers;
+    self.program.registers += 1;
+
+    let start = self.program.instructions.len();
+    self.program.instructions.push(Instruction::Calculus {
+      function,
+      register,
+      length: 0,
+    });
+    let depth = self.depth;
+    self.depth -= 2;
+    self.scopes.push((variable, register));
+    self.node(Some(body));
+    self.scopes.pop();
+
+    self.program.instructions[start] = Instruction::Calculus {
+      function,
+      register,
+      length: self.program.instructions.len() - start - 1,
+    };
+    self.depth = depth - 1;
+  }
+}
+
+/// The name of a known function, kept in instructions for their errors.
+fn static_name(name: &str) -> &'static str {
+  FUNCTIONS
+    .iter()
+    .find(|function| **function == name)
+    .copied()
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod vm_spec;
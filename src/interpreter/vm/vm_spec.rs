@@ -0,0 +1,111 @@
+use super::super::{evaluate, Environment};
+use super::*;
+use crate::parser::{parse, parse_with};
+use crate::settings::Settings;
+use crate::tokenizer::tokenize;
+use pretty_assertions::assert_eq;
+
+fn ast(formula: &str) -> Ast {
+  parse(tokenize(formula.to_string())).unwrap()
+}
+
+#[test]
+fn testing_same_results_as_the_tree_walker() {
+  let environment = Environment::new().with("x", 1.5).with("y", -2.0);
+  let formulas = [
+    "1+2-3*4/6",
+    "2^3^2",
+    "-x^2 + 3*y",
+    "200 + 15%",
+    "200 - 15%",
+    "10% of 50",
+    "(1+2)! + 3.5!",
+    "sin(x)/cos(y) + ln(x) + digamma(x)",
+    "pi * e",
+    "series(i^2, i, 1, 10) + product(i, i, 1, 5)",
+    "series(series(i*j, j, 1, i), i, 1, 3)",
+    "integrate(x*y, x, 0, 2)",
+    "series(x, x, 1, 3) + x",
+    "1/0",
+    "(-3)!",
+    "1/(x - x) + z",
+    "z + 1/0",
+    "series(i, i, 1, 2.5)",
+    "series(i, 2, 1, 3)",
+    "sin(1, 2)",
+    "mean(x, y, 3) + sum(1, x) * median(y)",
+    "stdev(x)",
+    "~x & 0xff | y << 2 >> 1",
+    "x << 64",
+    "x + 1h",
+    "weekday(x)",
+    "[x .. 2] + 1",
+    "x ± 1",
+    "x^2000 + 1",
+    "2^-1100",
+    "exp(800*y)",
+    "sqrt(y)",
+    "171! - 1",
+    "product(i, i, 1, 200)",
+    "max(x^1800, 1)",
+    "inf - nan + 1%",
+  ];
+
+  for &floats in [Floats::Strict, Floats::Ieee].iter() {
+    let environment = environment.clone().with_floats(floats);
+    for formula in formulas.iter() {
+      let ast = ast(formula);
+      let program = compile(&ast, &["x", "y"], floats);
+
+      // as text, for nan to equal itself
+      assert_eq!(
+        format!("{:?}", program.run(&[1.5, -2.0])),
+        format!("{:?}", evaluate(&ast, &environment)),
+        "should evaluate {} as the tree walker does with {:?} floats",
+        formula,
+        floats
+      );
+    }
+  }
+}
+
+#[test]
+fn testing_compile() {
+  let program = compile(&ast("2*x + 1"), &["x"], Floats::Strict);
+
+  assert_eq!(program.variables().to_vec(), vec!["x".to_string()]);
+  assert_eq!(
+    format!("{:?}", program.instructions()),
+    "[Constant(2.0), Load(0), Multiply, Constant(1.0), Add]"
+  );
+  assert_eq!(program.run(&[3.0]), Ok(7.0));
+  assert_eq!(program.run(&[-0.5]), Ok(0.0));
+}
+
+#[test]
+fn testing_run_checks_the_values() {
+  let program = compile(&ast("x + y"), &["x", "y"], Floats::Strict);
+
+  assert_eq!(
+    program.run(&[1.0]),
+    Err(Error::InvalidArgument("Expected 2 values, got 1".to_string()))
+  );
+}
+
+#[test]
+fn testing_run_deep_expressions() {
+  let formula = format!("{}1{}", "1+(".repeat(100), ")".repeat(100));
+  let mut settings = Settings::default();
+  settings.limits.depth = 200;
+  let ast = parse_with(tokenize(formula), &settings).unwrap();
+  let program = compile(&ast, &[], Floats::Strict);
+
+  assert_eq!(program.run(&[]), Ok(101.0), "should move the stack to the heap");
+}
+
+#[test]
+fn testing_free_variables() {
+  assert_eq!(free_variables(&ast("a*x + b - a")), vec!["a", "x", "b"]);
+  assert_eq!(free_variables(&ast("series(i*x, i, 1, n) + pi*e")), vec!["n", "x"]);
+  assert_eq!(free_variables(&ast("2 + 3")), Vec::<String>::new());
+}
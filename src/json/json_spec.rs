@@ -0,0 +1,142 @@
+use super::*;
+use crate::interpreter::run_located;
+use crate::settings::{IntegerType, Overflow, Programmer, Settings};
+use crate::Error;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn testing_success() {
+  assert_eq!(
+    result("1+2", &Ok("3".to_string())),
+    r#"{"input":"1+2","value":3,"formatted":"3"}"#
+  );
+  assert_eq!(
+    success("25!", "15511210043330985984000000"),
+    r#"{"input":"25!","value":15511210043330986000000000,"formatted":"15511210043330985984000000"}"#,
+    "should keep the exact digits in formatted"
+  );
+  assert_eq!(
+    success("diff x^2", "2*x"),
+    r#"{"input":"diff x^2","value":null,"formatted":"2*x"}"#
+  );
+}
+
+#[test]
+fn testing_success_programmer() {
+  let settings = Settings {
+    programmer: Some(Programmer {
+      integer: IntegerType { bits: 8, signed: false },
+      overflow: Overflow::Error,
+    }),
+    ..Settings::default()
+  };
+  let formatted = run_located("0xff".to_string(), &settings);
+  assert_eq!(
+    result("0xff", &formatted),
+    r#"{"input":"0xff","value":255,"formatted":"255 (0xff, 0b11111111)"}"#
+  );
+  assert_eq!(
+    success("~0", "-1 (0xff, 0b11111111)"),
+    r#"{"input":"~0","value":-1,"formatted":"-1 (0xff, 0b11111111)"}"#
+  );
+  assert_eq!(
+    success("f(1)", "1 (0xf, 0b1"),
+    r#"{"input":"f(1)","value":null,"formatted":"1 (0xf, 0b1"}"#,
+    "should only read a value formatted as programmer mode does"
+  );
+}
+
+#[test]
+fn testing_failure() {
+  assert_eq!(
+    failure("1/0", &Error::DivisionByZero.into()),
+    r#"{"error":{"kind":"division_by_zero","message":"Division by zero","span":null}}"#
+  );
+  assert_eq!(
+    failure(
+      "2*y + 1",
+      &Located::at(Error::UndefinedIdentifier("y".to_string()), Some((2, 3)))
+    ),
+    r#"{"error":{"kind":"undefined_identifier","message":"Undefined identifier y","span":[2,3]}}"#
+  );
+}
+
+#[test]
+fn testing_failure_spans() {
+  let span = |formula: &str| {
+    let reported = result(formula, &run_located(formula.to_string(), &Settings::default()));
+    parse(&reported)
+      .unwrap()
+      .get("error")
+      .unwrap()
+      .get("span")
+      .unwrap()
+      .to_string()
+  };
+
+  assert_eq!(span("π + € 2"), "[4,5]", "should count characters");
+  assert_eq!(span("1 + 12.3.4*2"), "[4,10]");
+  assert_eq!(span("2*y + 1"), "[2,3]");
+  assert_eq!(span("2 + (3*4"), "[4,5]", "should point at the bracket left open");
+  assert_eq!(span("1 + sin 2"), "[4,7]");
+  assert_eq!(span("3 4"), "[2,3]");
+  assert_eq!(span("2*3 +"), "[4,5]");
+  assert_eq!(span("[1, 2; 3]"), "[0,9]");
+  assert_eq!(span("1/0"), "null");
+}
+
+#[test]
+fn testing_string() {
+  assert_eq!(string("say \"hi\"\\\n\t\u{1}"), r#""say \"hi\"\\\n\t\u0001""#);
+}
+
+#[test]
+fn testing_parse() {
+  assert_eq!(
+    parse(r#" {"formula": "a*x", "variables": {"a": -2.5e1, "x": 3}, "list": [true, false, null, "é\n"]} "#),
+    Ok(Value::Object(vec![
+      ("formula".to_string(), Value::String("a*x".to_string())),
+      (
+        "variables".to_string(),
+        Value::Object(vec![
+          ("a".to_string(), Value::Number(-25.0)),
+          ("x".to_string(), Value::Number(3.0)),
+        ])
+      ),
+      (
+        "list".to_string(),
+        Value::Array(vec![
+          Value::Bool(true),
+          Value::Bool(false),
+          Value::Null,
+          Value::String("é\n".to_string()),
+        ])
+      ),
+    ]))
+  );
+  assert_eq!(parse("[]"), Ok(Value::Array(vec![])));
+  assert_eq!(parse(r#"{"a": 1"#), Err("Unexpected end of JSON".to_string()));
+  assert_eq!(parse("[1] 2"), Err("Unexpected 2 at 4".to_string()));
+  assert_eq!(parse(&"[".repeat(100)), Err("JSON nested deeper than 32".to_string()));
+}
+
+#[test]
+fn testing_display() {
+  let text = r#"{"id":7,"params":{"uri":"file:///a.calc","list":[true,null,-2.5,"\"x\""]}}"#;
+
+  assert_eq!(
+    parse(text).unwrap().to_string(),
+    text,
+    "should write back what was parsed"
+  );
+}
+
+#[test]
+fn testing_ast() {
+  let ast = crate::parser::parse(crate::tokenizer::tokenize("2*sin(x)!".to_string())).unwrap();
+
+  assert_eq!(
+    super::ast(&ast),
+    r#"{"operator":"*","operands":[{"number":2},{"factorial":{"call":"sin","arguments":[{"identifier":"x"}]}}]}"#
+  );
+}
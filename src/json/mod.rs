@@ -0,0 +1,326 @@
+use crate::error::Located;
+use crate::parser::{Ast, Node};
+use crate::tokenizer::Kind;
+use std::fmt;
+
+/// Nesting of arrays and objects `parse` accepts before giving up.
+const MAX_DEPTH: usize = 32;
+
+/// A parsed JSON value, keeping the members of objects in order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Value>),
+  Object(Vec<(String, Value)>),
+}
+impl Value {
+  /// The member `name` of an object.
+  pub fn get(&self, name: &str) -> Option<&Value> {
+    match self {
+      Value::Object(members) => members.iter().find(|(key, _)| key == name).map(|(_, value)| value),
+      _ => None,
+    }
+  }
+}
+impl fmt::Display for Value {
+  /// The value written back as compact JSON.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Null => write!(f, "null"),
+      Value::Bool(value) => write!(f, "{}", value),
+      Value::Number(value) if value.is_finite() => write!(f, "{}", value),
+      Value::Number(_) => write!(f, "null"),
+      Value::String(value) => write!(f, "{}", string(value)),
+      Value::Array(values) => {
+        let values: Vec<String> = values.iter().map(Value::to_string).collect();
+        write!(f, "[{}]", values.join(","))
+      }
+      Value::Object(members) => {
+        let members: Vec<String> = members
+          .iter()
+          .map(|(name, value)| format!("{}:{}", string(name), value))
+          .collect();
+        write!(f, "{{{}}}", members.join(","))
+      }
+    }
+  }
+}
+
+/// The JSON object reporting the result of `input`:
+///
+/// - `{"input":string,"value":number|null,"formatted":string}` on success,
+///   where `value` is null when `formatted` is not a finite number, and is
+///   the decimal integer of a value of programmer mode, formatted with its
+///   hexadecimal and binary as `255 (0xff, 0b11111111)`.
+/// - `{"error":{"kind":string,"message":string,"span":[start,end]|null}}` on
+///   failure, where `span` holds the character offsets of the part of `input`
+///   the error is about, end excluded, when it is known.
+pub fn result(input: &str, result: &Result<String, Located>) -> String {
+  match result {
+    Ok(formatted) => success(input, formatted),
+    Err(error) => failure(input, error),
+  }
+}
+
+pub fn success(input: &str, formatted: &str) -> String {
+  let value = programmer_integer(formatted)
+    .unwrap_or(formatted)
+    .parse::<f64>()
+    .ok()
+    .filter(|value| value.is_finite())
+    .map_or("null".to_string(), |value| value.to_string());
+
+  format!(
+    "{{\"input\":{},\"value\":{},\"formatted\":{}}}",
+    string(input),
+    value,
+    string(formatted)
+  )
+}
+
+/// The decimal integer of `formatted` when it is a value of programmer mode.
+fn programmer_integer(formatted: &str) -> Option<&str> {
+  let (integer, bases) = formatted.split_once(" (0x")?;
+  let is_programmer = bases.ends_with(')') && bases.contains(", 0b") && integer.parse::<i128>().is_ok();
+  Some(integer).filter(|_| is_programmer)
+}
+
+pub fn failure(input: &str, located: &Located) -> String {
+  let offset = |byte: usize| input.get(..byte).map_or(0, |text| text.chars().count());
+  let span = located.span.map_or("null".to_string(), |(start, end)| {
+    format!("[{},{}]", offset(start), offset(end))
+  });
+
+  format!(
+    "{{\"error\":{{\"kind\":{},\"message\":{},\"span\":{}}}}}",
+    string(located.error.kind()),
+    string(&located.error.to_string()),
+    span
+  )
+}
+
+/// The tree of `ast`, where every node is an object with one of the members
+/// `operator` (with `operands`), `number`, `identifier`, `call` (with
+/// `arguments`), `factorial`, `percentage` or `matrix` (an array of rows).
+pub fn ast(ast: &Ast) -> String {
+  let operand = |operand: &Option<Node>| operand.as_ref().map_or("null".to_string(), node);
+  // along the left operands of a chain in a loop, as 1+2+3 is (1+2)+3
+  let spine = ast.left_spine();
+  let innermost = operand(&spine[0].operand_a);
+
+  spine.into_iter().fold(innermost, |operand_a, ast| {
+    let operator = ast
+      .operator
+      .as_ref()
+      .map_or("null".to_string(), |operator| string(&operator.to_string()));
+    format!(
+      "{{\"operator\":{},\"operands\":[{},{}]}}",
+      operator,
+      operand_a,
+      operand(&ast.operand_b)
+    )
+  })
+}
+
+fn node(node: &Node) -> String {
+  match node {
+    Node::Ast(boxed_ast) => ast(boxed_ast),
+    Node::Call(function, arguments) => {
+      let arguments: Vec<String> = arguments.iter().map(self::node).collect();
+      format!(
+        "{{\"call\":{},\"arguments\":[{}]}}",
+        string(&function.to_string()),
+        arguments.join(",")
+      )
+    }
+    Node::Factorial(operand) => format!("{{\"factorial\":{}}}", self::node(operand)),
+    Node::Interval(lower, upper) => format!("{{\"interval\":[{},{}]}}", self::node(lower), self::node(upper)),
+    Node::Matrix(rows) => {
+      let rows: Vec<String> = rows
+        .iter()
+        .map(|row| format!("[{}]", row.iter().map(self::node).collect::<Vec<String>>().join(",")))
+        .collect();
+      format!("{{\"matrix\":[{}]}}", rows.join(","))
+    }
+    Node::Percentage(operand) => format!("{{\"percentage\":{}}}", self::node(operand)),
+    Node::Token(token) if token.kind == Kind::Number => format!("{{\"number\":{}}}", token),
+    Node::Token(token) if token.kind == Kind::Date => format!("{{\"date\":{}}}", string(&token.to_string())),
+    Node::Token(token) if token.kind == Kind::Duration => format!("{{\"duration\":{}}}", string(&token.to_string())),
+    Node::Token(token) => format!("{{\"identifier\":{}}}", string(&token.to_string())),
+  }
+}
+
+/// Parses a JSON document, rejecting anything after its value.
+pub fn parse(text: &str) -> Result<Value, String> {
+  let mut reader = Reader { text, position: 0 };
+  let value = reader.value(0)?;
+  reader.skip_space();
+  if reader.position < text.len() {
+    return Err(reader.unexpected());
+  }
+  Ok(value)
+}
+
+struct Reader<'a> {
+  text: &'a str,
+  position: usize,
+}
+impl Reader<'_> {
+  fn rest(&self) -> &str {
+    &self.text[self.position..]
+  }
+
+  fn skip_space(&mut self) {
+    let rest = self.rest();
+    self.position += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+  }
+
+  fn unexpected(&self) -> String {
+    match self.rest().chars().next() {
+      Some(character) => format!("Unexpected {} at {}", character, self.position),
+      None => "Unexpected end of JSON".to_string(),
+    }
+  }
+
+  fn expect(&mut self, literal: &str) -> Result<(), String> {
+    if !self.rest().starts_with(literal) {
+      return Err(self.unexpected());
+    }
+    self.position += literal.len();
+    Ok(())
+  }
+
+  fn value(&mut self, depth: usize) -> Result<Value, String> {
+    if depth > MAX_DEPTH {
+      return Err(format!("JSON nested deeper than {}", MAX_DEPTH));
+    }
+    self.skip_space();
+
+    match self.rest().chars().next() {
+      Some('n') => self.expect("null").map(|_| Value::Null),
+      Some('t') => self.expect("true").map(|_| Value::Bool(true)),
+      Some('f') => self.expect("false").map(|_| Value::Bool(false)),
+      Some('"') => self.string().map(Value::String),
+      Some('[') => {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.members(']', |reader| {
+          values.push(reader.value(depth + 1)?);
+          Ok(())
+        })?;
+        Ok(Value::Array(values))
+      }
+      Some('{') => {
+        self.position += 1;
+        let mut members = Vec::new();
+        self.members('}', |reader| {
+          reader.skip_space();
+          let key = reader.string()?;
+          reader.skip_space();
+          reader.expect(":")?;
+          members.push((key, reader.value(depth + 1)?));
+          Ok(())
+        })?;
+        Ok(Value::Object(members))
+      }
+      Some('-') | Some('0'..='9') => self.number(),
+      _ => Err(self.unexpected()),
+    }
+  }
+
+  /// Reads the comma-separated members of an array or object up to `close`.
+  fn members(&mut self, close: char, mut member: impl FnMut(&mut Self) -> Result<(), String>) -> Result<(), String> {
+    self.skip_space();
+    if self.rest().starts_with(close) {
+      self.position += 1;
+      return Ok(());
+    }
+    loop {
+      member(self)?;
+      self.skip_space();
+      match self.rest().chars().next() {
+        Some(',') => self.position += 1,
+        Some(character) if character == close => {
+          self.position += 1;
+          return Ok(());
+        }
+        _ => return Err(self.unexpected()),
+      }
+    }
+  }
+
+  fn number(&mut self) -> Result<Value, String> {
+    let rest = self.rest();
+    let length = rest
+      .find(|character: char| !matches!(character, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+      .unwrap_or(rest.len());
+    let value = rest[..length].parse().map_err(|_| self.unexpected())?;
+    self.position += length;
+    Ok(Value::Number(value))
+  }
+
+  fn string(&mut self) -> Result<String, String> {
+    self.expect("\"")?;
+    let mut value = String::new();
+    let mut characters = self.rest().char_indices();
+
+    while let Some((index, character)) = characters.next() {
+      match character {
+        '"' => {
+          self.position += index + 1;
+          return Ok(value);
+        }
+        '\\' => {
+          let escaped = match characters.next().map(|(_, escaped)| escaped) {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+              let hex: String = characters.by_ref().take(4).map(|(_, digit)| digit).collect();
+              u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or(format!("Invalid escape \\u{} in JSON string", hex))?
+            }
+            _ => return Err("Invalid escape in JSON string".to_string()),
+          };
+          value.push(escaped);
+        }
+        control if (control as u32) < 0x20 => return Err("Control character in JSON string".to_string()),
+        character => value.push(character),
+      }
+    }
+
+    Err("Unterminated JSON string".to_string())
+  }
+}
+
+/// `value` as a quoted JSON string.
+pub fn string(value: &str) -> String {
+  let mut quoted = String::with_capacity(value.len() + 2);
+  quoted.push('"');
+  for character in value.chars() {
+    match character {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      '\n' => quoted.push_str("\\n"),
+      '\r' => quoted.push_str("\\r"),
+      '\t' => quoted.push_str("\\t"),
+      control if (control as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", control as u32)),
+      character => quoted.push(character),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+#[cfg(test)]
+mod json_spec;
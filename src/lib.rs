@@ -0,0 +1,75 @@
+pub mod compiled;
+pub mod derivative;
+pub mod error;
+pub mod interpreter;
+pub mod json;
+pub mod lsp;
+pub mod parser;
+pub mod plot;
+pub mod server;
+pub mod settings;
+pub mod simplifier;
+pub mod tokenizer;
+
+pub use compiled::CompiledExpr;
+pub use error::Error;
+use interpreter::solver::{self, SolveOptions};
+use plot::PlotOptions;
+use settings::Settings;
+
+pub struct Calc;
+
+impl Calc {
+  pub fn calculate(formula: String) -> Result<String, Error> {
+    Self::calculate_with(formula, &Settings::default())
+  }
+
+  pub fn calculate_with(formula: String, settings: &Settings) -> Result<String, Error> {
+    interpreter::run_with(formula, settings)
+  }
+
+  /// Runs a script of statements separated by newlines or `;`, returning the
+  /// value of each expression statement on a line.
+  pub fn run_script(script: &str, settings: &Settings) -> Result<String, Error> {
+    interpreter::run_script(script, settings)
+  }
+
+  /// Summarizes the numbers of `input` in a table of count, sum, mean,
+  /// median, spread, extremes and quartiles.
+  pub fn stats(input: &str) -> Result<String, Error> {
+    interpreter::variadic::stats(input)
+  }
+
+  /// Parses and compiles `formula` once, for evaluating it many times with
+  /// different values of its variables.
+  pub fn compile(formula: &str) -> Result<CompiledExpr, Error> {
+    Self::compile_with(formula, &Settings::default())
+  }
+
+  pub fn compile_with(formula: &str, settings: &Settings) -> Result<CompiledExpr, Error> {
+    CompiledExpr::new(formula, settings)
+  }
+
+  pub fn differentiate(formula: String, variable: &str, settings: &Settings) -> Result<String, Error> {
+    derivative::run_with(formula, variable, settings)
+  }
+
+  pub fn differentiate_at(formula: String, variable: &str, at: f64, settings: &Settings) -> Result<String, Error> {
+    derivative::run_at(formula, variable, at, settings)
+  }
+
+  pub fn simplify(formula: String, settings: &Settings) -> Result<String, Error> {
+    simplifier::run_with(formula, settings)
+  }
+
+  pub fn plot(formulas: &[String], options: &PlotOptions, settings: &Settings) -> Result<String, Error> {
+    plot::run_with(formulas, options, settings)
+  }
+
+  pub fn solve(formula: String, variable: &str, options: &SolveOptions, settings: &Settings) -> Result<String, Error> {
+    solver::run_with(formula, variable, options, settings)
+  }
+}
+
+#[cfg(test)]
+mod lib_spec;
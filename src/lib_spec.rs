@@ -0,0 +1,310 @@
+use super::*;
+use settings::ImplicitMultiplication;
+
+fn s(t: &str) -> String {
+  t.to_string()
+}
+
+#[test]
+fn should_calculate_solo_positive_numbers() {
+  // integer
+  assert_eq!(Calc::calculate(s("0")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("1")), Ok(s("1")));
+  assert_eq!(Calc::calculate(s("2")), Ok(s("2")));
+  assert_eq!(Calc::calculate(s("3")), Ok(s("3")));
+  assert_eq!(Calc::calculate(s("4")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("5")), Ok(s("5")));
+  assert_eq!(Calc::calculate(s("6")), Ok(s("6")));
+  assert_eq!(Calc::calculate(s("7")), Ok(s("7")));
+  assert_eq!(Calc::calculate(s("8")), Ok(s("8")));
+  assert_eq!(Calc::calculate(s("9")), Ok(s("9")));
+
+  // float
+  assert_eq!(Calc::calculate(s("0.0")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("1.0")), Ok(s("1")));
+  assert_eq!(Calc::calculate(s("2.0")), Ok(s("2")));
+  assert_eq!(Calc::calculate(s("3.0")), Ok(s("3")));
+  assert_eq!(Calc::calculate(s("4.0")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("5.0")), Ok(s("5")));
+  assert_eq!(Calc::calculate(s("6.0")), Ok(s("6")));
+  assert_eq!(Calc::calculate(s("7.0")), Ok(s("7")));
+  assert_eq!(Calc::calculate(s("8.0")), Ok(s("8")));
+  assert_eq!(Calc::calculate(s("9.0")), Ok(s("9")));
+}
+
+#[test]
+fn should_calculate_solo_negative_number() {
+  // integer
+  assert_eq!(Calc::calculate(s("-0")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("-1")), Ok(s("-1")));
+  assert_eq!(Calc::calculate(s("-2")), Ok(s("-2")));
+  assert_eq!(Calc::calculate(s("-3")), Ok(s("-3")));
+  assert_eq!(Calc::calculate(s("-4")), Ok(s("-4")));
+  assert_eq!(Calc::calculate(s("-5")), Ok(s("-5")));
+  assert_eq!(Calc::calculate(s("-6")), Ok(s("-6")));
+  assert_eq!(Calc::calculate(s("-7")), Ok(s("-7")));
+  assert_eq!(Calc::calculate(s("-8")), Ok(s("-8")));
+  assert_eq!(Calc::calculate(s("-9")), Ok(s("-9")));
+
+  // float
+  assert_eq!(Calc::calculate(s("-0.0")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("-1.0")), Ok(s("-1")));
+  assert_eq!(Calc::calculate(s("-2.0")), Ok(s("-2")));
+  assert_eq!(Calc::calculate(s("-3.0")), Ok(s("-3")));
+  assert_eq!(Calc::calculate(s("-4.0")), Ok(s("-4")));
+  assert_eq!(Calc::calculate(s("-5.0")), Ok(s("-5")));
+  assert_eq!(Calc::calculate(s("-6.0")), Ok(s("-6")));
+  assert_eq!(Calc::calculate(s("-7.0")), Ok(s("-7")));
+  assert_eq!(Calc::calculate(s("-8.0")), Ok(s("-8")));
+  assert_eq!(Calc::calculate(s("-9.0")), Ok(s("-9")));
+}
+
+#[test]
+fn should_calculate_four_basic_operation() {
+  assert_eq!(Calc::calculate(s("1+2")), Ok(s("3")));
+  assert_eq!(Calc::calculate(s("3-4")), Ok(s("-1")));
+  assert_eq!(Calc::calculate(s("5*6")), Ok(s("30")));
+  assert_eq!(Calc::calculate(s("7/8")), Ok(s("0.875")));
+}
+
+#[test]
+fn should_calculate_three_types_of_brackets() {
+  assert_eq!(Calc::calculate(s("(0)")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("[2]")), Ok(s("2")));
+  assert_eq!(Calc::calculate(s("{4}")), Ok(s("4")));
+}
+
+#[test]
+fn should_calculate_respecting_precedence_order_of_basic_operators() {
+  // precedence order: * = / > + = -
+
+  assert_eq!(Calc::calculate(s("1+2+3")), Ok(s("6")));
+  assert_eq!(Calc::calculate(s("1+2-3")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("4+2/5")), Ok(s("4.4")));
+  assert_eq!(Calc::calculate(s("1+2*3")), Ok(s("7")));
+
+  assert_eq!(Calc::calculate(s("1-2+3")), Ok(s("2")));
+  assert_eq!(Calc::calculate(s("1-2-3")), Ok(s("-4")));
+  assert_eq!(Calc::calculate(s("4-2/5")), Ok(s("3.6")));
+  assert_eq!(Calc::calculate(s("1-2*3")), Ok(s("-5")));
+
+  assert_eq!(Calc::calculate(s("1/2+3")), Ok(s("3.5")));
+  assert_eq!(Calc::calculate(s("1/2-3")), Ok(s("-2.5")));
+  assert_eq!(Calc::calculate(s("1/2/5")), Ok(s("0.1")));
+  assert_eq!(Calc::calculate(s("2/2*3")), Ok(s("3")));
+
+  assert_eq!(Calc::calculate(s("3*2+3")), Ok(s("9")));
+  assert_eq!(Calc::calculate(s("3*2-3")), Ok(s("3")));
+  assert_eq!(Calc::calculate(s("3*2/5")), Ok(s("1.2")));
+  assert_eq!(Calc::calculate(s("3*2*3")), Ok(s("18")));
+}
+
+#[test]
+fn should_calculate_brackets_before_operators() {
+  assert_eq!(Calc::calculate(s("(1)")), Ok(s("1")));
+  assert_eq!(Calc::calculate(s("(+1)")), Ok(s("1")));
+  assert_eq!(Calc::calculate(s("(-1)")), Ok(s("-1")));
+
+  assert_eq!(Calc::calculate(s("(2+2)")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("(2)+(2)")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("(2)+2")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("2+(2)")), Ok(s("4")));
+
+  assert_eq!(Calc::calculate(s("(3+3)+(3+3)")), Ok(s("12")));
+  assert_eq!(Calc::calculate(s("(3+3)*(3+3)")), Ok(s("36")));
+  assert_eq!(Calc::calculate(s("(3+3)*(3/3)")), Ok(s("6")));
+}
+
+#[test]
+fn should_calculate_inner_brackets_before_outers() {
+  assert_eq!(Calc::calculate(s("{[(1)]}")), Ok(s("1")));
+  assert_eq!(Calc::calculate(s("[({1})]")), Ok(s("1")));
+  assert_eq!(Calc::calculate(s("({[1]})")), Ok(s("1")));
+
+  assert_eq!(Calc::calculate(s("((1+3)*4)")), Ok(s("16")));
+  assert_eq!(Calc::calculate(s("([1+3]*4)")), Ok(s("16")));
+  assert_eq!(Calc::calculate(s("({1+3}*4)")), Ok(s("16")));
+
+  assert_eq!(Calc::calculate(s("[(1+3)*4]")), Ok(s("16")));
+  assert_eq!(Calc::calculate(s("[[1+3]*4]")), Ok(s("16")));
+  assert_eq!(Calc::calculate(s("[{1+3}*4]")), Ok(s("16")));
+
+  assert_eq!(Calc::calculate(s("{(1+3)*4}")), Ok(s("16")));
+  assert_eq!(Calc::calculate(s("{[1+3]*4}")), Ok(s("16")));
+  assert_eq!(Calc::calculate(s("{{1+3}*4}")), Ok(s("16")));
+}
+
+#[test]
+fn should_calculate_mixed_precedence_from_left_to_right() {
+  assert_eq!(Calc::calculate(s("1-2*3-4")), Ok(s("-9")));
+  assert_eq!(Calc::calculate(s("1-2*3+4")), Ok(s("-1")));
+  assert_eq!(Calc::calculate(s("8/2/2")), Ok(s("2")));
+  assert_eq!(Calc::calculate(s("2-3-4")), Ok(s("-5")));
+}
+
+#[test]
+fn should_calculate_percentage_relative_to_the_left_operand_of_addition_and_subtraction() {
+  assert_eq!(Calc::calculate(s("200 + 15%")), Ok(s("230")));
+  assert_eq!(Calc::calculate(s("200 - 15%")), Ok(s("170")));
+  assert_eq!(Calc::calculate(s("200 + -15%")), Ok(s("170")));
+  assert_eq!(Calc::calculate(s("(100 + 100) + (10 + 5)%")), Ok(s("230")));
+}
+
+#[test]
+fn should_calculate_percentage_as_a_rate_elsewhere() {
+  assert_eq!(Calc::calculate(s("15%")), Ok(s("0.15")));
+  assert_eq!(Calc::calculate(s("-15%")), Ok(s("-0.15")));
+  assert_eq!(Calc::calculate(s("200 * 15%")), Ok(s("30")));
+  assert_eq!(Calc::calculate(s("200 / 50%")), Ok(s("400")));
+  assert_eq!(Calc::calculate(s("15% of 200")), Ok(s("30")));
+  assert_eq!(Calc::calculate(s("200 + 15% of 100")), Ok(s("215")));
+}
+
+#[test]
+fn should_not_calculate_of_without_a_percentage() {
+  assert_eq!(
+    Calc::calculate(s("15 of 200")),
+    Err(Error::InvalidExpression(s(
+      "Bad format: Operator of expects a percentage before it"
+    )))
+  );
+}
+
+#[test]
+fn should_calculate_exponentiation_from_right_to_left() {
+  assert_eq!(Calc::calculate(s("2^3")), Ok(s("8")));
+  assert_eq!(Calc::calculate(s("2^3^2")), Ok(s("512")));
+  assert_eq!(Calc::calculate(s("2*3^2")), Ok(s("18")));
+  assert_eq!(Calc::calculate(s("-2^2")), Ok(s("-4")));
+  assert_eq!(Calc::calculate(s("2^-1")), Ok(s("0.5")));
+}
+
+#[test]
+fn should_calculate_factorial_before_exponentiation() {
+  assert_eq!(Calc::calculate(s("0!")), Ok(s("1")));
+  assert_eq!(Calc::calculate(s("5!")), Ok(s("120")));
+  assert_eq!(Calc::calculate(s("3!!")), Ok(s("720")));
+  assert_eq!(Calc::calculate(s("2^3!")), Ok(s("64")));
+  assert_eq!(Calc::calculate(s("-3!")), Ok(s("-6")));
+  assert_eq!(Calc::calculate(s("(1+2)!")), Ok(s("6")));
+}
+
+#[test]
+fn should_calculate_big_factorial_exactly() {
+  assert_eq!(Calc::calculate(s("25!")), Ok(s("15511210043330985984000000")));
+  assert_eq!(Calc::calculate(s("25!+1")), Ok(s("15511210043330985984000001")));
+  assert_eq!(Calc::calculate(s("2^64")), Ok(s("18446744073709551616")));
+}
+
+#[test]
+fn should_calculate_every_integer_only_subtree_exactly() {
+  assert_eq!(Calc::calculate(s("10^309 - 10^309")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("171! - 171!")), Ok(s("0")));
+  assert_eq!(Calc::calculate(s("171!/170!")), Ok(s("171")));
+  assert_eq!(Calc::calculate(s("2^1024/2")), Calc::calculate(s("2^1023")));
+  assert_eq!(Calc::calculate(s("10^308*10")), Ok(format!("1{}", "0".repeat(309))));
+  assert_eq!(Calc::calculate(s("(171! - 171!) + 0.5")), Ok(s("0.5")));
+  assert_eq!(Calc::calculate(s("7/2")), Ok(s("3.5")));
+  assert!(
+    matches!(Calc::calculate(s("2^1024/3")), Err(Error::Overflow(_))),
+    "should round 2^1024 to a float where the division does not end exactly"
+  );
+}
+
+#[test]
+fn should_calculate_factorial_of_non_integers_with_gamma() {
+  assert_eq!(Calc::calculate(s("0.5!")), Ok(s("0.8862269254527586")));
+  assert_eq!(Calc::calculate(s("(-0.5)!")), Ok(s("1.7724538509055159")));
+}
+
+#[test]
+fn should_not_calculate_factorial_of_negative_integers() {
+  assert_eq!(Calc::calculate(s("(-3)!")), Err(Error::FactorialOfNegativeInteger));
+  assert_eq!(Calc::calculate(s("(2-3)!")), Err(Error::FactorialOfNegativeInteger));
+}
+
+#[test]
+fn should_calculate_implicit_multiplication() {
+  assert_eq!(Calc::calculate(s("2(3+4)")), Ok(s("14")));
+  assert_eq!(Calc::calculate(s("(1+2)(3+4)")), Ok(s("21")));
+  assert_eq!(Calc::calculate(s("2[3]{4}")), Ok(s("24")));
+  assert_eq!(Calc::calculate(s("3pi")), Ok(s("9.42477796076938")));
+  assert_eq!(Calc::calculate(s("2 pi e")), Ok(s("17.079468445347132")));
+  assert_eq!(Calc::calculate(s("1/2(4)")), Ok(s("2")));
+}
+
+#[test]
+fn should_calculate_implicit_multiplication_tighter_than_division() {
+  let settings = Settings {
+    implicit_multiplication: ImplicitMultiplication::Tighter,
+    ..Settings::default()
+  };
+
+  assert_eq!(Calc::calculate_with(s("1/2(4)"), &settings), Ok(s("0.125")));
+  assert_eq!(Calc::calculate_with(s("2^2(3)"), &settings), Ok(s("12")));
+  assert_eq!(Calc::calculate_with(s("6/2(1+2)"), &settings), Ok(s("1")));
+}
+
+#[test]
+fn should_not_calculate_implicit_multiplication_in_strict_mode() {
+  assert_eq!(
+    Calc::calculate_with(s("2(3+4)"), &Settings::strict()),
+    Err(Error::InvalidExpression(s(
+      "Bad format: There is no operator associated with this expression"
+    )))
+  );
+  assert_eq!(Calc::calculate_with(s("2*(3+4)"), &Settings::strict()), Ok(s("14")));
+}
+
+#[test]
+fn should_not_calculate_unknown_identifiers() {
+  assert_eq!(Calc::calculate(s("2x")), Err(Error::UndefinedIdentifier(s("x"))));
+}
+
+#[test]
+fn should_simplify() {
+  assert_eq!(Calc::simplify(s("x*1+0"), &Settings::default()), Ok(s("x")));
+  assert_eq!(Calc::simplify(s("(2*3)*x + x"), &Settings::default()), Ok(s("7*x")));
+  assert_eq!(
+    Calc::simplify(s("2(x)"), &Settings::strict()),
+    Err(Error::InvalidExpression(s(
+      "Bad format: There is no operator associated with this expression"
+    )))
+  );
+}
+
+#[test]
+fn should_solve() {
+  let options = SolveOptions::default();
+
+  assert_eq!(
+    Calc::solve(s("x^3 - 2*x = 5"), "x", &options, &Settings::default()),
+    Ok(s("x = 2.0945514815423265"))
+  );
+  assert_eq!(
+    Calc::solve(s("x^2 + 1 = 0"), "x", &options, &Settings::default()),
+    Ok(s("No real roots in [-100, 100]"))
+  );
+}
+
+#[test]
+fn should_compile() {
+  let expr = Calc::compile("a*x+b").unwrap();
+
+  assert_eq!(expr.eval(&[("a", 2.0), ("x", 3.0), ("b", 1.0)]), Ok(7.0));
+}
+
+#[test]
+fn should_calculate_unicode_symbols() {
+  assert_eq!(Calc::calculate(s("2 × 3 · 4")), Ok(s("24")));
+  assert_eq!(Calc::calculate(s("6 ÷ 4")), Ok(s("1.5")));
+  assert_eq!(Calc::calculate(s("5 − 7")), Ok(s("-2")));
+  assert_eq!(Calc::calculate(s("√16")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("√(7+2) + 1")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("2√4")), Ok(s("4")));
+  assert_eq!(Calc::calculate(s("3² + 2³")), Ok(s("17")));
+  assert_eq!(Calc::calculate(s("-3²")), Ok(s("-9")));
+  assert_eq!(Calc::calculate(s("2^3²")), Ok(s("512")));
+  assert_eq!(Calc::calculate(s("2π")), Ok(s("6.283185307179586")));
+  assert_eq!(Calc::calculate(s("1\u{a0}+\t2\n")), Ok(s("3")));
+}
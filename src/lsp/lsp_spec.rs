@@ -0,0 +1,215 @@
+use super::*;
+use crate::Error;
+use pretty_assertions::assert_eq;
+use std::io::Cursor;
+
+const DOCUMENT: &str = "rent = 1200  # monthly\nrent * 12; rent / 0\nsqrt(rent + y)\n(1 + 2 €";
+
+fn message(text: &str) -> Value {
+  json::parse(text).unwrap()
+}
+
+fn open(server: &mut LanguageServer) -> Vec<String> {
+  let text = json::string(DOCUMENT);
+  server.handle(&message(&format!(
+    r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"file:///budget.calc","text":{}}}}}}}"#,
+    text
+  )))
+}
+
+fn request(server: &mut LanguageServer, method: &str, params: &str) -> Value {
+  let reply = server.handle(&message(&format!(
+    r#"{{"jsonrpc":"2.0","id":1,"method":"{}","params":{{"textDocument":{{"uri":"file:///budget.calc"}},{}}}}}"#,
+    method, params
+  )));
+  assert_eq!(reply.len(), 1);
+  message(&reply[0]).get("result").unwrap().clone()
+}
+
+#[test]
+fn testing_analyze() {
+  let analysis = |line, span, assigns: Option<&str>, result| Analysis {
+    line,
+    span,
+    assigns: assigns.map(str::to_string),
+    result,
+  };
+  let symbol = Error::UndefinedSymbol("Error: Undefined symbol start €".to_string());
+  let undefined = Error::UndefinedIdentifier("y".to_string());
+
+  assert_eq!(
+    analyze(DOCUMENT, &Settings::default()),
+    vec![
+      analysis(0, (0, 11), Some("rent"), Ok("1200".to_string())),
+      analysis(1, (0, 9), None, Ok("14400".to_string())),
+      analysis(1, (11, 19), None, Err(Error::DivisionByZero.into())),
+      analysis(2, (0, 14), None, Err(Located::at(undefined, Some((12, 13))))),
+      analysis(3, (0, 10), None, Err(Located::at(symbol, Some((7, 10))))),
+    ]
+  );
+}
+
+#[test]
+fn testing_diagnostics() {
+  let mut server = LanguageServer::new(Settings::default());
+  let replies = open(&mut server);
+
+  assert_eq!(replies.len(), 1);
+  let notification = message(&replies[0]);
+  assert_eq!(
+    notification.get("method"),
+    Some(&Value::String("textDocument/publishDiagnostics".to_string()))
+  );
+  let ranges: Vec<String> = match notification.get("params").unwrap().get("diagnostics") {
+    Some(Value::Array(diagnostics)) => diagnostics
+      .iter()
+      .map(|diagnostic| {
+        format!(
+          "{} {}",
+          diagnostic.get("range").unwrap(),
+          diagnostic.get("code").unwrap()
+        )
+      })
+      .collect(),
+    _ => panic!("should list diagnostics"),
+  };
+  assert_eq!(
+    ranges,
+    vec![
+      r#"{"start":{"line":1,"character":11},"end":{"line":1,"character":19}} "division_by_zero""#,
+      r#"{"start":{"line":2,"character":12},"end":{"line":2,"character":13}} "undefined_identifier""#,
+      r#"{"start":{"line":3,"character":7},"end":{"line":3,"character":8}} "undefined_symbol""#,
+    ],
+    "should point at the name or symbol at fault when it is known"
+  );
+}
+
+#[test]
+fn testing_hover_and_inlay_hints() {
+  let mut server = LanguageServer::new(Settings::default());
+  open(&mut server);
+
+  let hover = request(
+    &mut server,
+    "textDocument/hover",
+    r#""position":{"line":1,"character":3}"#,
+  );
+  assert_eq!(
+    hover.to_string(),
+    r#"{"contents":{"kind":"plaintext","value":"= 14400"},"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":9}}}"#
+  );
+  let hover = request(
+    &mut server,
+    "textDocument/hover",
+    r#""position":{"line":1,"character":14}"#,
+  );
+  assert_eq!(hover, Value::Null, "should show nothing for a statement that failed");
+
+  let hints = request(
+    &mut server,
+    "textDocument/inlayHint",
+    r#""range":{"start":{"line":0,"character":0},"end":{"line":2,"character":0}}"#,
+  );
+  assert_eq!(
+    hints.to_string(),
+    concat!(
+      r#"[{"position":{"line":0,"character":11},"label":"= 1200","paddingLeft":true},"#,
+      r#"{"position":{"line":1,"character":9},"label":"= 14400","paddingLeft":true}]"#
+    )
+  );
+}
+
+#[test]
+fn testing_completion() {
+  let mut server = LanguageServer::new(Settings::default());
+  open(&mut server);
+
+  let items = match request(
+    &mut server,
+    "textDocument/completion",
+    r#""position":{"line":3,"character":5}"#,
+  ) {
+    Value::Array(items) => items,
+    _ => panic!("should list completions"),
+  };
+  let labels: Vec<String> = items
+    .iter()
+    .map(|item| item.get("label").unwrap().to_string())
+    .collect();
+  assert_eq!(labels[0], r#"")""#, "should close the open bracket first");
+  assert!(labels.contains(&r#""^""#.to_string()));
+  assert!(
+    labels.contains(&r#""rent""#.to_string()),
+    "should offer assigned variables"
+  );
+  let sqrt = items
+    .iter()
+    .find(|item| item.get("label") == Some(&Value::String("sqrt".to_string())));
+  assert_eq!(
+    sqrt.unwrap().get("insertText"),
+    Some(&Value::String("sqrt($1)".to_string()))
+  );
+}
+
+#[test]
+fn testing_highlights() {
+  let mut server = LanguageServer::new(Settings::default());
+  open(&mut server);
+
+  let highlights = request(
+    &mut server,
+    "textDocument/documentHighlight",
+    r#""position":{"line":2,"character":14}"#,
+  );
+  assert_eq!(
+    highlights.to_string(),
+    concat!(
+      r#"[{"range":{"start":{"line":2,"character":4},"end":{"line":2,"character":5}},"kind":1},"#,
+      r#"{"range":{"start":{"line":2,"character":13},"end":{"line":2,"character":14}},"kind":1}]"#
+    ),
+    "should match the bracket just before the cursor"
+  );
+  let highlights = request(
+    &mut server,
+    "textDocument/documentHighlight",
+    r#""position":{"line":3,"character":0}"#,
+  );
+  assert_eq!(highlights, Value::Null, "should ignore a bracket left open");
+}
+
+#[test]
+fn testing_unknown_requests() {
+  let mut server = LanguageServer::new(Settings::default());
+
+  assert_eq!(
+    server.handle(&message(r#"{"jsonrpc":"2.0","id":"a","method":"workspace/symbol"}"#)),
+    vec![r#"{"jsonrpc":"2.0","id":"a","error":{"code":-32601,"message":"Unknown method workspace/symbol"}}"#]
+  );
+  assert_eq!(
+    server.handle(&message(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#)),
+    Vec::<String>::new()
+  );
+}
+
+#[test]
+fn testing_run() {
+  let frame = |body: &str| format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+  let input = [
+    frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#),
+    frame(r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#),
+    frame(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+    frame(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#),
+  ]
+  .concat();
+  let mut output = Vec::new();
+
+  run(Cursor::new(input), &mut output, Settings::default()).unwrap();
+
+  let output = String::from_utf8(output).unwrap();
+  assert!(output.starts_with("Content-Length: "));
+  assert!(output.contains(r#""id":1,"result":{"capabilities":"#));
+  assert!(
+    output.ends_with(r#"{"jsonrpc":"2.0","id":2,"result":null}"#),
+    "should stop at exit"
+  );
+}
@@ -0,0 +1,490 @@
+use crate::error::Located;
+use crate::interpreter::{run_statement, Environment};
+use crate::json::{self, Value};
+use crate::parser::{parse_statement, split_statements, Statement};
+use crate::settings::{Floats, Settings};
+use crate::tokenizer::{tokenize_within, FUNCTIONS};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+/// Largest message body accepted from the client, in bytes.
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+/// What the server answers to `initialize`.
+const CAPABILITIES: &str = concat!(
+  r#"{"capabilities":{"textDocumentSync":1,"completionProvider":{"triggerCharacters":["(",")"]},"#,
+  r#""hoverProvider":true,"inlayHintProvider":true,"documentHighlightProvider":true},"#,
+  r#""serverInfo":{"name":"calc-lsp","version":""#,
+  env!("CARGO_PKG_VERSION"),
+  r#""}}"#
+);
+
+/// Operators offered as completions, with what they do.
+const OPERATORS: [(&str, &str); 9] = [
+  ("+", "addition"),
+  ("-", "subtraction"),
+  ("*", "multiplication"),
+  ("/", "division"),
+  ("^", "exponentiation"),
+  ("!", "factorial"),
+  ("%", "percentage"),
+  ("of", "percentage of"),
+  ("=", "assignment"),
+];
+
+// Kinds of completion items in the protocol
+const OPERATOR: u8 = 24;
+const FUNCTION: u8 = 3;
+const VARIABLE: u8 = 6;
+const CONSTANT: u8 = 21;
+
+/// A statement of a `.calc` document and what running it gave.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+  /// Line of the statement, from 0
+  pub line: usize,
+  /// Byte offsets of the statement in its line, surrounding spaces excluded
+  pub span: (usize, usize),
+  /// Variable the statement assigns, if any
+  pub assigns: Option<String>,
+  /// Value of the statement, or the error with its byte offsets in the
+  /// statement
+  pub result: Result<String, Located>,
+}
+
+/// Runs the statements of `text` as `Calc::run_script` does, but goes on
+/// after a statement fails so that every line gets its own result.
+pub fn analyze(text: &str, settings: &Settings) -> Vec<Analysis> {
+  let mut environment = Environment::new()
+    .with_floats(settings.floats)
+    .with_limits(&settings.limits);
+  let mut analyses = Vec::new();
+
+  for (line, content) in text.lines().enumerate() {
+    for (start, part) in split_statements(content) {
+      let offset = start + part.len() - part.trim_start().len();
+      let span = (offset, offset + part.trim().len());
+      if span.0 == span.1 {
+        continue;
+      }
+
+      let tokens = tokenize_within(
+        content[span.0..span.1].to_string(),
+        &settings.limits,
+        &environment.names(),
+        environment.budget(),
+      );
+      let statement = parse_statement(tokens.clone(), settings, environment.budget());
+      let assigns = match &statement {
+        Ok(Statement::Assignment(name, _)) => Some(name.clone()),
+        _ => None,
+      };
+      let result = statement.and_then(|statement| {
+        run_statement(statement, &mut environment, settings)
+          .map_err(|error| Located::named(error, tokens.as_deref().unwrap_or_default()))
+      });
+      analyses.push(Analysis {
+        line,
+        span,
+        assigns,
+        result,
+      });
+    }
+  }
+
+  analyses
+}
+
+/// Answers the requests of a client about the `.calc` documents it has open.
+pub struct LanguageServer {
+  settings: Settings,
+  documents: HashMap<String, String>,
+}
+impl LanguageServer {
+  pub fn new(settings: Settings) -> LanguageServer {
+    LanguageServer {
+      settings,
+      documents: HashMap::new(),
+    }
+  }
+
+  /// Handles a message of the client, returning the messages to send back:
+  /// the response to a request, or the diagnostics of a changed document.
+  pub fn handle(&mut self, message: &Value) -> Vec<String> {
+    let method = match message.get("method") {
+      Some(Value::String(method)) => method.as_str(),
+      _ => return vec![],
+    };
+    let params = message.get("params").unwrap_or(&Value::Null);
+    let id = message.get("id");
+
+    let result = match method {
+      "initialize" => Some(CAPABILITIES.to_string()),
+      "shutdown" => None,
+      "textDocument/didOpen" => {
+        let document = params.get("textDocument");
+        return match (uri(params), document.and_then(|document| document.get("text"))) {
+          (Some(uri), Some(Value::String(text))) => self.open(uri, text),
+          _ => vec![],
+        };
+      }
+      "textDocument/didChange" => {
+        let text = match params.get("contentChanges") {
+          Some(Value::Array(changes)) => changes.last().and_then(|change| change.get("text")),
+          _ => None,
+        };
+        return match (uri(params), text) {
+          (Some(uri), Some(Value::String(text))) => self.open(uri, text),
+          _ => vec![],
+        };
+      }
+      "textDocument/didClose" => {
+        return match uri(params) {
+          Some(uri) => {
+            self.documents.remove(uri);
+            vec![notification(
+              "textDocument/publishDiagnostics",
+              &diagnostics(uri, "", &[]),
+            )]
+          }
+          None => vec![],
+        };
+      }
+      "textDocument/hover" => self.hover(params),
+      "textDocument/inlayHint" => self.inlay_hints(params),
+      "textDocument/completion" => self.completion(params),
+      "textDocument/documentHighlight" => self.highlights(params),
+      _ if id.is_none() => return vec![],
+      _ => {
+        return vec![format!(
+          r#"{{"jsonrpc":"2.0","id":{},"error":{{"code":-32601,"message":{}}}}}"#,
+          id.unwrap_or(&Value::Null),
+          json::string(&format!("Unknown method {}", method))
+        )]
+      }
+    };
+
+    match id {
+      Some(id) => vec![format!(
+        r#"{{"jsonrpc":"2.0","id":{},"result":{}}}"#,
+        id,
+        result.unwrap_or_else(|| "null".to_string())
+      )],
+      None => vec![],
+    }
+  }
+
+  fn open(&mut self, uri: &str, text: &str) -> Vec<String> {
+    self.documents.insert(uri.to_string(), text.to_string());
+    let analyses = analyze(text, &self.settings);
+    vec![notification(
+      "textDocument/publishDiagnostics",
+      &diagnostics(uri, text, &analyses),
+    )]
+  }
+
+  /// The document and the line a request is about, with the byte offset of
+  /// its position in the line.
+  fn locate(&self, params: &Value) -> Option<(&str, usize, usize)> {
+    let text = self.documents.get(uri(params)?)?;
+    let position = params.get("position")?;
+    let line = number(position.get("line")?)?;
+    let content = text.lines().nth(line).unwrap_or_default();
+    Some((text, line, byte(content, number(position.get("character")?)?)))
+  }
+
+  /// The value of the statement under the cursor.
+  fn hover(&self, params: &Value) -> Option<String> {
+    let (text, line, at) = self.locate(params)?;
+    let content = text.lines().nth(line)?;
+    let analysis = analyze(text, &self.settings)
+      .into_iter()
+      .find(|analysis| analysis.line == line && analysis.span.0 <= at && at <= analysis.span.1)?;
+    let value = analysis.result.ok()?;
+
+    Some(format!(
+      r#"{{"contents":{{"kind":"plaintext","value":{}}},"range":{}}}"#,
+      json::string(&format!("= {}", value)),
+      range(content, line, analysis.span)
+    ))
+  }
+
+  /// The value of every statement in the requested lines, after it.
+  fn inlay_hints(&self, params: &Value) -> Option<String> {
+    let text = self.documents.get(uri(params)?)?;
+    let requested = params.get("range")?;
+    let first = number(requested.get("start")?.get("line")?)?;
+    let last = number(requested.get("end")?.get("line")?)?;
+    let lines: Vec<&str> = text.lines().collect();
+
+    let hints: Vec<String> = analyze(text, &self.settings)
+      .into_iter()
+      .filter(|analysis| first <= analysis.line && analysis.line <= last)
+      .filter_map(|analysis| {
+        let value = analysis.result.ok()?;
+        Some(format!(
+          r#"{{"position":{},"label":{},"paddingLeft":true}}"#,
+          position(lines[analysis.line], analysis.line, analysis.span.1),
+          json::string(&format!("= {}", value))
+        ))
+      })
+      .collect();
+    Some(format!("[{}]", hints.join(",")))
+  }
+
+  /// The bracket closing the innermost one left open before the cursor,
+  /// brackets, operators, functions and the variables assigned before.
+  fn completion(&self, params: &Value) -> Option<String> {
+    let (text, line, at) = self.locate(params)?;
+    let content = text.lines().nth(line).unwrap_or_default();
+    let statements = split_statements(&content[..at]);
+    let statement = statements.last().map_or("", |(_, statement)| statement);
+    let mut items: Vec<(String, u8, String, Option<String>)> = Vec::new();
+
+    if let Some(&(_, open)) = brackets(statement).open.last() {
+      let close = closing(open).to_string();
+      items.push((close, OPERATOR, format!("closes {}", open), None));
+    }
+    for (open, close) in [('(', ')'), ('[', ']'), ('{', '}')].iter() {
+      let label = format!("{}{}", open, close);
+      items.push((
+        label,
+        OPERATOR,
+        "brackets".to_string(),
+        Some(format!("{}$1{}", open, close)),
+      ));
+    }
+    for (operator, detail) in OPERATORS.iter() {
+      items.push((operator.to_string(), OPERATOR, detail.to_string(), None));
+    }
+    for function in FUNCTIONS.iter() {
+      let snippet = format!("{}($1)", function);
+      items.push((function.to_string(), FUNCTION, "function".to_string(), Some(snippet)));
+    }
+    let constants: &[&str] = match self.settings.floats {
+      Floats::Strict => &["pi", "e"],
+      Floats::Ieee => &["pi", "e", "inf", "nan"],
+    };
+    for constant in constants.iter() {
+      items.push((constant.to_string(), CONSTANT, "constant".to_string(), None));
+    }
+    let mut variables: Vec<String> = Vec::new();
+    for analysis in analyze(text, &self.settings) {
+      match analysis.assigns {
+        Some(name) if analysis.line < line && !variables.contains(&name) => variables.push(name),
+        _ => {}
+      }
+    }
+    for variable in variables {
+      items.push((variable, VARIABLE, "variable".to_string(), None));
+    }
+
+    let items: Vec<String> = items
+      .iter()
+      .enumerate()
+      .map(|(index, (label, kind, detail, snippet))| {
+        let insert = snippet.as_ref().map_or(String::new(), |snippet| {
+          format!(r#","insertText":{},"insertTextFormat":2"#, json::string(snippet))
+        });
+        format!(
+          r#"{{"label":{},"kind":{},"detail":{},"sortText":"{:03}"{}}}"#,
+          json::string(label),
+          kind,
+          json::string(detail),
+          index,
+          insert
+        )
+      })
+      .collect();
+    Some(format!("[{}]", items.join(",")))
+  }
+
+  /// The bracket at the cursor, or just before it, and the one it pairs with.
+  fn highlights(&self, params: &Value) -> Option<String> {
+    let (text, line, at) = self.locate(params)?;
+    let content = text.lines().nth(line)?;
+    let code = content.split('#').next().unwrap_or_default();
+    let pairs = brackets(code).pairs;
+
+    let pair = [Some(at), at.checked_sub(1)]
+      .iter()
+      .flatten()
+      .find_map(|&at| pairs.iter().find(|(open, close)| *open == at || *close == at))?;
+    let highlight = |at: usize| format!(r#"{{"range":{},"kind":1}}"#, range(content, line, (at, at + 1)));
+    Some(format!("[{},{}]", highlight(pair.0), highlight(pair.1)))
+  }
+}
+
+/// The brackets of a line, by byte offset.
+struct Brackets {
+  pairs: Vec<(usize, usize)>,
+  /// Brackets left open at the end, innermost last
+  open: Vec<(usize, char)>,
+}
+
+fn brackets(code: &str) -> Brackets {
+  let mut pairs = Vec::new();
+  let mut open: Vec<(usize, char)> = Vec::new();
+
+  for (at, character) in code.char_indices() {
+    match character {
+      '(' | '[' | '{' => open.push((at, character)),
+      ')' | ']' | '}' => {
+        if let Some(&(start, bracket)) = open.last() {
+          if closing(bracket) == character {
+            open.pop();
+            pairs.push((start, at));
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  Brackets { pairs, open }
+}
+
+fn closing(bracket: char) -> char {
+  match bracket {
+    '(' => ')',
+    '[' => ']',
+    _ => '}',
+  }
+}
+
+/// The errors of the statements of a document, where each one points at the
+/// symbol, number or name at fault when it is known, or the whole statement.
+fn diagnostics(uri: &str, text: &str, analyses: &[Analysis]) -> String {
+  let lines: Vec<&str> = text.lines().collect();
+  let diagnostics: Vec<String> = analyses
+    .iter()
+    .filter_map(|analysis| {
+      let located = analysis.result.as_ref().err()?;
+      let error = &located.error;
+      let content = lines[analysis.line];
+      let start = analysis.span.0;
+      let span = located
+        .span
+        .map_or(analysis.span, |(from, to)| (start + from, start + to));
+
+      Some(format!(
+        r#"{{"range":{},"severity":1,"source":"calc","code":{},"message":{}}}"#,
+        range(content, analysis.line, span),
+        json::string(error.kind()),
+        json::string(&error.to_string())
+      ))
+    })
+    .collect();
+
+  format!(
+    r#"{{"uri":{},"diagnostics":[{}]}}"#,
+    json::string(uri),
+    diagnostics.join(",")
+  )
+}
+
+fn notification(method: &str, params: &str) -> String {
+  format!(
+    r#"{{"jsonrpc":"2.0","method":{},"params":{}}}"#,
+    json::string(method),
+    params
+  )
+}
+
+fn uri(params: &Value) -> Option<&str> {
+  match params.get("textDocument")?.get("uri")? {
+    Value::String(uri) => Some(uri),
+    _ => None,
+  }
+}
+
+fn number(value: &Value) -> Option<usize> {
+  match value {
+    Value::Number(number) if *number >= 0.0 && number.fract() == 0.0 => Some(*number as usize),
+    _ => None,
+  }
+}
+
+/// Positions count UTF-16 code units from the start of their line.
+fn position(content: &str, line: usize, byte: usize) -> String {
+  let character = content[..byte].encode_utf16().count();
+  format!(r#"{{"line":{},"character":{}}}"#, line, character)
+}
+
+fn range(content: &str, line: usize, span: (usize, usize)) -> String {
+  format!(
+    r#"{{"start":{},"end":{}}}"#,
+    position(content, line, span.0),
+    position(content, line, span.1)
+  )
+}
+
+/// The byte offset of the UTF-16 `character` of a line, or its end.
+fn byte(content: &str, character: usize) -> usize {
+  let mut units = 0;
+  for (at, value) in content.char_indices() {
+    if units >= character {
+      return at;
+    }
+    units += value.len_utf16();
+  }
+  content.len()
+}
+
+/// Serves the language server protocol, reading the messages of the client
+/// from `input` and writing the replies to `output`, until the client exits
+/// or closes `input`.
+pub fn run(mut input: impl BufRead, mut output: impl Write, settings: Settings) -> io::Result<()> {
+  let mut server = LanguageServer::new(settings);
+
+  while let Some(body) = read_message(&mut input)? {
+    let replies = match json::parse(&body) {
+      Ok(message) if message.get("method") == Some(&Value::String("exit".to_string())) => break,
+      Ok(message) => server.handle(&message),
+      Err(message) => vec![format!(
+        r#"{{"jsonrpc":"2.0","id":null,"error":{{"code":-32700,"message":{}}}}}"#,
+        json::string(&message)
+      )],
+    };
+    for reply in replies {
+      write!(output, "Content-Length: {}\r\n\r\n{}", reply.len(), reply)?;
+    }
+    output.flush()?;
+  }
+
+  Ok(())
+}
+
+/// Reads the body of the next message, framed by a `Content-Length` header,
+/// or nothing once `input` is closed.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+  let mut length = None;
+
+  loop {
+    let mut header = String::new();
+    if input.read_line(&mut header)? == 0 {
+      return Ok(None);
+    }
+    match header.trim_end().split_once(':') {
+      None if header.trim_end().is_empty() && length.is_some() => break,
+      Some((name, value)) if name.trim().eq_ignore_ascii_case("content-length") => {
+        length = value.trim().parse::<usize>().ok();
+      }
+      _ => {}
+    }
+  }
+
+  let length = length.unwrap_or_default();
+  if length > MAX_MESSAGE {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("Message is larger than {} bytes", MAX_MESSAGE),
+    ));
+  }
+  let mut body = vec![0; length];
+  input.read_exact(&mut body)?;
+  Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+#[cfg(test)]
+mod lsp_spec;
@@ -1,30 +1,129 @@
-mod error;
-mod interpreter;
-mod parser;
-mod tokenizer;
+mod cli;
 
-use error::Error;
+use cli::{Arguments, Command, Output};
+use rust_calc::error::Located;
+use rust_calc::server::{self, ServerOptions};
+use rust_calc::{derivative, interpreter, json, simplifier, Calc};
 use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::net::TcpListener;
+use std::process;
 
 fn main() {
-  let formula = env::args().nth(1);
+  let args = env::args().skip(1).collect();
 
-  match formula {
-    Some(formula) => {
-      let result = Calc::calculate(formula).unwrap();
-      println!("{}", result);
+  match cli::parse_args(args) {
+    Ok(Arguments {
+      command: Command::Calculate(formula),
+      settings,
+      output,
+    }) => {
+      let result = interpreter::run_located(formula.clone(), &settings);
+      print(&formula, result, output);
     }
-    None => eprintln!("Error: Formula is required\nUse: calc '1+2-3*4/5'"),
+    Ok(Arguments {
+      command: Command::Diff { formula, variable, at },
+      settings,
+      output,
+    }) => {
+      let result = derivative::run_located(formula.clone(), &variable, at, &settings);
+      print(&formula, result, output);
+    }
+    Ok(Arguments {
+      command: Command::Simplify(formula),
+      settings,
+      output,
+    }) => {
+      let result = simplifier::run_located(formula.clone(), &settings);
+      print(&formula, result, output);
+    }
+    Ok(Arguments {
+      command: Command::Run(path),
+      settings,
+      ..
+    }) => match fs::read_to_string(&path) {
+      Ok(script) => match interpreter::run_script_located(&script, &settings) {
+        Ok(result) if result.is_empty() => (),
+        Ok(result) => println!("{}", result),
+        Err(failure) => fail(failure),
+      },
+      Err(error) => fail(format!("Cannot read {}: {}", path, error)),
+    },
+    Ok(Arguments {
+      command: Command::Stats,
+      ..
+    }) => {
+      let mut input = String::new();
+      match io::stdin().read_to_string(&mut input) {
+        Ok(_) => match Calc::stats(&input) {
+          Ok(result) => println!("{}", result),
+          Err(error) => fail(error),
+        },
+        Err(error) => fail(format!("Cannot read stdin: {}", error)),
+      }
+    }
+    Ok(Arguments {
+      command: Command::Serve(bind),
+      settings,
+      ..
+    }) => match TcpListener::bind(&bind) {
+      Ok(listener) => {
+        eprintln!("Listening on http://{}", bind);
+        let options = ServerOptions {
+          settings,
+          ..ServerOptions::default()
+        };
+        if let Err(error) = server::serve(listener, options) {
+          eprintln!("Error: {}", error);
+        }
+      }
+      Err(error) => eprintln!("Error: Cannot listen on {}: {}", bind, error),
+    },
+    Ok(Arguments {
+      command: Command::Solve {
+        formula,
+        variable,
+        options,
+      },
+      settings,
+      ..
+    }) => match Calc::solve(formula, &variable, &options, &settings) {
+      Ok(result) => println!("{}", result),
+      Err(error) => fail(error),
+    },
+    Ok(Arguments {
+      command: Command::Plot { formulas, options },
+      settings,
+      ..
+    }) => match Calc::plot(&formulas, &options, &settings) {
+      Ok(result) => println!("{}", result),
+      Err(error) => fail(error),
+    },
+    Err(message) => eprintln!("Error: {}\n{}", message, cli::USAGE),
   }
 }
 
-struct Calc;
+/// Prints `message` as an error and ends the process with a failure status.
+fn fail(message: impl fmt::Display) -> ! {
+  eprintln!("Error: {}", message);
+  process::exit(1);
+}
 
-impl Calc {
-  fn calculate(formula: String) -> Result<String, Error> {
-    interpreter::run(formula)
+/// Prints the result of `input`; an error ends the process with a failure
+/// status, after printing it to stdout too as JSON.
+fn print(input: &str, result: Result<String, Located>, output: Output) {
+  match output {
+    Output::Text => match result {
+      Ok(result) => println!("{}", result),
+      Err(error) => fail(error),
+    },
+    Output::Json => {
+      println!("{}", json::result(input, &result));
+      if result.is_err() {
+        process::exit(1);
+      }
+    }
   }
 }
-
-#[cfg(test)]
-mod main_spec;
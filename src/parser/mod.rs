@@ -1,12 +1,42 @@
-use crate::tokenizer::{Key, Kind, Token};
+use crate::error::Located;
+use crate::settings::{Angle, Budget, ImplicitMultiplication, Settings};
+use crate::tokenizer::{tokenize, tokenize_within, Key, Kind, Token};
 use crate::Error;
+use std::convert::TryFrom;
+use std::fmt;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Node {
   Ast(Box<Ast>),
+  Call(Token, Vec<Node>),
+  Factorial(Box<Node>),
+  /// Lower and upper bounds of an interval literal, as `[1.9 .. 2.1]`
+  Interval(Box<Node>, Box<Node>),
+  /// Rows of a matrix literal, as `[1, 2; 3, 4]`, all of the same length
+  Matrix(Vec<Vec<Node>>),
+  Percentage(Box<Node>),
   Token(Token),
 }
 
+/// Two expressions that should be equal, as in `x^3 - 2*x = 5`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Equation {
+  pub lhs: Ast,
+  pub rhs: Ast,
+}
+
+/// One statement of a script.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+  /// `name = expression`, binding the value for the statements after it
+  Assignment(String, Ast),
+  Expression(Ast),
+}
+
+/// The left operand of the innermost operation of a chain, then the
+/// operator and right operand of each operation from the innermost out.
+pub type LeftSpine = (Option<Node>, Vec<(Option<Token>, Option<Node>)>);
+
 #[derive(Debug, PartialEq)]
 pub struct Ast {
   pub operator: Option<Token>,
@@ -36,14 +66,44 @@ impl Ast {
     }
   }
 
-  fn node_ast(ast: Ast) -> Option<Node> {
-    Some(Node::Ast(Box::new(ast)))
+  /// This operation and the operations down its left operands, innermost
+  /// first, as `1+2+3` is `(1+2)+3`. A chain only nests to the left, so
+  /// walking its spine in a loop and recursing into the right operands alone
+  /// takes no more stack than the brackets of the formula.
+  pub fn left_spine(&self) -> Vec<&Ast> {
+    let mut ast = self;
+    let mut spine = vec![ast];
+    while let Some(Node::Ast(operand)) = &ast.operand_a {
+      ast = operand;
+      spine.push(ast);
+    }
+    spine.reverse();
+    spine
   }
-  fn node_token(token: Token) -> Option<Node> {
-    match token.kind {
-      Kind::Number => Some(Node::Token(token)),
-      _ => None,
+
+  /// The operands of the chain along `left_spine` from left to right, as
+  /// `1`, `2` and `3` for `1+2+3`.
+  pub fn spine_operands(&self) -> Vec<&Node> {
+    let spine = self.left_spine();
+    let operands_b = spine.iter().flat_map(|ast| ast.operand_b.iter());
+    spine[0].operand_a.iter().chain(operands_b).collect()
+  }
+
+  /// The operations of `left_spine` taken apart.
+  pub fn into_left_spine(mut self) -> LeftSpine {
+    let mut operations = vec![(self.operator.take(), self.operand_b.take())];
+    let mut operand_a = self.operand_a.take();
+    while let Some(Node::Ast(mut ast)) = operand_a {
+      operations.push((ast.operator.take(), ast.operand_b.take()));
+      operand_a = ast.operand_a.take();
     }
+    operations.reverse();
+    (operand_a, operations)
+  }
+
+  #[cfg(test)]
+  fn node_ast(ast: Ast) -> Option<Node> {
+    Some(Node::Ast(Box::new(ast)))
   }
   fn node_operator(token: Token) -> Option<Token> {
     match token.kind {
@@ -51,210 +111,890 @@ impl Ast {
       _ => None,
     }
   }
-  fn node_empty() -> Option<Node> {
-    Option::None
+}
+
+// cloned and dropped along the left spine, where the derived
+// implementations would recurse once per operation of a chain
+impl Clone for Ast {
+  fn clone(&self) -> Ast {
+    let spine = self.left_spine();
+    let innermost = Ast::new(
+      spine[0].operator.clone(),
+      spine[0].operand_a.clone(),
+      spine[0].operand_b.clone(),
+    );
+    spine[1..].iter().fold(innermost, |operand_a, ast| {
+      Ast::new(
+        ast.operator.clone(),
+        Some(Node::Ast(Box::new(operand_a))),
+        ast.operand_b.clone(),
+      )
+    })
+  }
+}
+impl Drop for Ast {
+  fn drop(&mut self) {
+    let mut operand_a = self.operand_a.take();
+    while let Some(Node::Ast(mut ast)) = operand_a {
+      operand_a = ast.operand_a.take();
+    }
   }
-  fn is_empty(&self) -> bool {
-    self.operator.is_none() && self.operand_a.is_none() && self.operand_b.is_none()
+}
+
+/// How a node binds when printed next to an operator.
+enum Shape {
+  Atom,
+  Signed,
+  Operation(Key),
+}
+
+fn shape_of(node: &Node) -> Shape {
+  match node {
+    Node::Ast(ast) => ast.shape(),
+    _ => Shape::Atom,
+  }
+}
+
+fn is_zero(node: &Option<Node>) -> bool {
+  match node {
+    Some(Node::Token(token)) => token.kind == Kind::Number && token.keys == [Key::Zero],
+    _ => false,
+  }
+}
+
+impl Ast {
+  /// The operand of a sign, as the `x` in `0 - x` written for `-x`, or the
+  /// operand that `parse` pads with `+ 0`.
+  fn signed_operand(&self) -> Option<&Node> {
+    let key = &self.operator.as_ref()?.keys[0];
+    if (*key == Key::Addition || *key == Key::Subtraction) && is_zero(&self.operand_a) {
+      return self.operand_b.as_ref();
+    }
+    if *key == Key::Addition && is_zero(&self.operand_b) {
+      return self.operand_a.as_ref();
+    }
+    None
+  }
+
+  fn shape(&self) -> Shape {
+    match (self.signed_operand(), &self.operator) {
+      (Some(operand), Some(operator)) if operator.keys[0] == Key::Addition => shape_of(operand),
+      (Some(_), _) => Shape::Signed,
+      (None, Some(operator)) => Shape::Operation(operator.keys[0].clone()),
+      (None, None) => Shape::Atom,
+    }
+  }
+}
+
+/// Whether `node` needs brackets as an operand of `operator`, on its right
+/// side when `is_right` is set.
+fn needs_brackets(node: &Node, operator: &Key, is_right: bool) -> bool {
+  match shape_of(node) {
+    Shape::Atom => false,
+    Shape::Signed => is_right || *operator == Key::Exponentiation,
+    Shape::Operation(key) => {
+      let same_precedence = !Key::precede(operator, &key) && !Key::precede(&key, operator);
+      Key::precede(operator, &key) || (same_precedence && is_right != operator.is_right_associative())
+    }
+  }
+}
+
+fn write_operand(f: &mut fmt::Formatter, node: &Node, operator: &Key, is_right: bool) -> fmt::Result {
+  if needs_brackets(node, operator, is_right) {
+    write!(f, "({})", node)
+  } else {
+    write!(f, "{}", node)
+  }
+}
+
+fn write_postfix(f: &mut fmt::Formatter, node: &Node, postfix: &str) -> fmt::Result {
+  match shape_of(node) {
+    Shape::Atom => write!(f, "{}{}", node, postfix),
+    _ => write!(f, "({}){}", node, postfix),
+  }
+}
+
+impl Ast {
+  /// Whether the operation is written from its left operand on, as all are
+  /// but `~` and those missing an operand.
+  fn writes_left(&self) -> bool {
+    let is_bit_not = matches!(&self.operator, Some(operator) if operator.keys[0] == Key::BitNot);
+    self.operator.is_some() && self.operand_a.is_some() && self.operand_b.is_some() && !is_bit_not
+  }
+
+  /// Writes the operation without its left operand, which `brackets` tells
+  /// to close, for the left operand of an operation that `writes_left`.
+  fn write_right(&self, f: &mut fmt::Formatter, brackets: bool) -> fmt::Result {
+    if brackets {
+      f.write_str(")")?;
+    }
+    if self.signed_operand().is_some() {
+      return Ok(());
+    }
+    let (operator, operand_b) = match (&self.operator, &self.operand_b) {
+      (Some(operator), Some(operand_b)) => (operator, operand_b),
+      _ => return Err(fmt::Error),
+    };
+    let key = &operator.keys[0];
+
+    match key {
+      Key::Addition
+      | Key::Subtraction
+      | Key::PlusMinus
+      | Key::Of
+      | Key::ShiftLeft
+      | Key::ShiftRight
+      | Key::BitAnd
+      | Key::Xor
+      | Key::BitOr => write!(f, " {} ", operator)?,
+      _ => write!(f, "{}", operator)?,
+    }
+    write_operand(f, operand_b, key, true)
+  }
+
+  /// Whether the left operand needs brackets, as it does in `(1+2)*3`.
+  fn brackets_left(&self) -> bool {
+    match (&self.operator, &self.operand_a) {
+      (Some(operator), Some(operand_a)) => {
+        self.signed_operand().is_none() && needs_brackets(operand_a, &operator.keys[0], false)
+      }
+      _ => false,
+    }
+  }
+}
+
+impl fmt::Display for Ast {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    // a chain is written from its innermost operation out, opening the
+    // brackets of its left operands up front
+    let spine = self.left_spine();
+    let start = spine.iter().rposition(|ast| !ast.writes_left()).unwrap_or(0);
+    let chain = &spine[start + 1..];
+    for _ in chain.iter().filter(|ast| ast.brackets_left()) {
+      f.write_str("(")?;
+    }
+
+    let innermost = spine[start];
+    let (operator, operand_a, operand_b) = match (&innermost.operator, &innermost.operand_a, &innermost.operand_b) {
+      (Some(operator), Some(operand_a), Some(operand_b)) => (operator, operand_a, operand_b),
+      _ => return Err(fmt::Error),
+    };
+    let key = &operator.keys[0];
+
+    if let Some(operand) = innermost.signed_operand() {
+      match (key, shape_of(operand)) {
+        (Key::Addition, _) => write!(f, "{}", operand)?,
+        (_, Shape::Atom) | (_, Shape::Operation(Key::Exponentiation)) => write!(f, "-{}", operand)?,
+        _ => write!(f, "-({})", operand)?,
+      }
+    } else if *key == Key::BitNot {
+      f.write_str("~")?;
+      write_postfix(f, operand_b, "")?;
+    } else {
+      write_operand(f, operand_a, key, false)?;
+      innermost.write_right(f, false)?;
+    }
+
+    for ast in chain {
+      ast.write_right(f, ast.brackets_left())?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for Equation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} = {}", self.lhs, self.rhs)
+  }
+}
+
+impl fmt::Display for Node {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Node::Ast(ast) => write!(f, "{}", ast),
+      Node::Call(function, arguments) => {
+        write!(f, "{}(", function)?;
+        for (i, argument) in arguments.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}", argument)?;
+        }
+        write!(f, ")")
+      }
+      Node::Factorial(node) => write_postfix(f, node, "!"),
+      Node::Interval(lower, upper) => write!(f, "[{} .. {}]", lower, upper),
+      Node::Matrix(rows) => {
+        let rows: Vec<String> = rows
+          .iter()
+          .map(|row| row.iter().map(Node::to_string).collect::<Vec<String>>().join(", "))
+          .collect();
+        write!(f, "[{}]", rows.join("; "))
+      }
+      Node::Percentage(node) => write_postfix(f, node, "%"),
+      Node::Token(token) => write!(f, "{}", token),
+    }
   }
 }
 
 pub fn parse(tokens: Result<Vec<Token>, Error>) -> Result<Ast, Error> {
+  parse_with(tokens, &Settings::default())
+}
+
+pub fn parse_with(tokens: Result<Vec<Token>, Error>, settings: &Settings) -> Result<Ast, Error> {
+  parse_located(tokens.map_err(Located::from), settings).map_err(Error::from)
+}
+
+/// Parses as `parse_with` does, failing with the tokens the error is about.
+pub fn parse_located(tokens: Result<Vec<Token>, Located>, settings: &Settings) -> Result<Ast, Located> {
+  parse_within(tokens, settings, &Budget::new(&settings.limits))
+}
+
+/// Parses as `parse_located` does, failing once the time of `budget` is up,
+/// which the evaluation goes on taking from.
+pub fn parse_within(tokens: Result<Vec<Token>, Located>, settings: &Settings, budget: &Budget) -> Result<Ast, Located> {
+  parse_tokens(&remove_space(tokens?), settings, budget)
+}
+
+fn parse_tokens(tokens: &[Token], settings: &Settings, budget: &Budget) -> Result<Ast, Located> {
+  if tokens.is_empty() {
+    return Err(Error::InvalidExpression("Input is empty".to_string()).into());
+  }
+  if let Some(equals) = tokens.iter().find(|token| is_equals(token)) {
+    return Err(Located::at(
+      Error::InvalidExpression("Bad format: Operator = is only accepted in equations".to_string()),
+      equals.span,
+    ));
+  }
+
+  to_ast(tokens, settings, budget)
+}
+
+/// Parses `lhs = rhs`, reading a formula without `=` as `formula = 0`.
+pub fn parse_equation(tokens: Result<Vec<Token>, Error>, settings: &Settings) -> Result<Equation, Error> {
   let tokens = remove_space(tokens?);
-  if tokens.len() == 0 {
-    return Err(Error::InvalidExpression("Input is empty".to_string()));
+  let mut sides = tokens.split(is_equals);
+
+  let zero = [Token::new_number(vec![Key::Zero])];
+  let lhs = sides.next().unwrap_or_default();
+  let rhs = sides.next().unwrap_or(&zero);
+  if sides.next().is_some() {
+    return Err(Error::InvalidExpression(
+      "Bad format: An equation has a single =".to_string(),
+    ));
+  }
+  if lhs.is_empty() || rhs.is_empty() {
+    return Err(Error::InvalidExpression(
+      "Bad format: Both sides of = need an expression".to_string(),
+    ));
   }
-  let ast = Ast::new(None, None, None);
 
-  to_ast(Ok(tokens), ast)
+  let budget = Budget::new(&settings.limits);
+  Ok(Equation {
+    lhs: to_ast(lhs, settings, &budget)?,
+    rhs: to_ast(rhs, settings, &budget)?,
+  })
 }
 
-fn to_ast(tokens: Result<Vec<Token>, Error>, ast: Ast) -> Result<Ast, Error> {
-  let mut tokens = tokens?;
-  let token_len = tokens.len();
+/// Parses the statements of a script, separated by newlines or `;`, ignoring
+/// blank statements and `#` comments.
+pub fn parse_script(script: &str, settings: &Settings) -> Result<Vec<Statement>, Error> {
+  script
+    .lines()
+    .flat_map(split_statements)
+    .filter(|(_, statement)| !statement.trim().is_empty())
+    .map(|(_, statement)| {
+      let budget = Budget::new(&settings.limits);
+      let tokens = tokenize_within(statement.to_string(), &settings.limits, &[], &budget);
+      parse_statement(tokens, settings, &budget).map_err(Error::from)
+    })
+    .collect()
+}
 
-  if token_len == 0 {
-    return Ok(ast);
+/// Splits a line of a script at the `;` outside brackets, which separate the
+/// rows of matrices inside them, after dropping its `#` comment. Each part
+/// comes with its byte offset in the line.
+pub fn split_statements(line: &str) -> Vec<(usize, &str)> {
+  let code = line.split('#').next().unwrap_or_default();
+  let mut statements = Vec::new();
+  let mut start = 0;
+  let mut depth = 0;
+
+  for (at, character) in code.char_indices() {
+    match character {
+      '(' | '[' | '{' => depth += 1,
+      ')' | ']' | '}' => depth -= 1,
+      ';' if depth <= 0 => {
+        statements.push((start, &code[start..at]));
+        start = at + 1;
+      }
+      _ => (),
+    }
   }
+  statements.push((start, &code[start..]));
 
-  if token_len == 1 && ast.is_empty() {
-    let token = tokens.pop().unwrap();
+  statements
+}
 
-    return match token.kind {
-      Kind::Number => Ok(Ast {
-        operator: Ast::new_operator(Key::Addition),
-        operand_a: Ast::new_number(vec![Key::Zero]),
-        operand_b: Ast::node_token(token),
-      }),
-      _ => Err(Error::InvalidExpression(
-        "Bad format: Unique value should be a number".to_string(),
-      )),
-    };
+/// Parses `name = expression` as an assignment and anything else as an
+/// expression, failing with the tokens the error is about, or once the time
+/// of `budget` is up.
+pub fn parse_statement(
+  tokens: Result<Vec<Token>, Located>,
+  settings: &Settings,
+  budget: &Budget,
+) -> Result<Statement, Located> {
+  let tokens = remove_space(tokens?);
+
+  match tokens.iter().position(is_equals) {
+    Some(1) if tokens[0].kind == Kind::Identifier => {
+      let expression = parse_tokens(&tokens[2..], settings, budget)?;
+      Ok(Statement::Assignment(tokens[0].to_string(), expression))
+    }
+    Some(equals) => Err(Located::at(
+      Error::InvalidExpression("Bad format: Only a variable name can be assigned with =".to_string()),
+      tokens[equals].span,
+    )),
+    None => Ok(Statement::Expression(parse_tokens(&tokens, settings, budget)?)),
   }
+}
 
-  if start_with_sign(&tokens) && ast.is_empty() {
-    let mut new_tokens = vec![Token::new_number(vec![Key::Zero])];
-    new_tokens.append(&mut tokens);
-    return to_ast(Ok(new_tokens), ast);
+/// Checks that `name` would be read as a single variable, as given to `diff`
+/// and `solve`.
+pub fn validate_variable(name: &str) -> Result<(), Error> {
+  match tokenize(name.to_string())?.as_slice() {
+    [token] if token.kind == Kind::Identifier => Ok(()),
+    _ => Err(Error::InvalidExpression(format!(
+      "Bad format: {} is not a variable name",
+      name
+    ))),
   }
+}
 
-  let token = tokens.first().unwrap();
+fn to_ast(tokens: &[Token], settings: &Settings, budget: &Budget) -> Result<Ast, Located> {
+  match to_node(tokens, 0, settings, budget)? {
+    Node::Ast(ast) => Ok(*ast),
+    // a percentage on the right of + would be taken relative to the 0
+    Node::Percentage(node) => Ok(Ast::new(
+      Ast::new_operator(Key::Addition),
+      Some(Node::Percentage(node)),
+      Ast::new_number(vec![Key::Zero]),
+    )),
+    node => Ok(Ast::new(
+      Ast::new_operator(Key::Addition),
+      Ast::new_number(vec![Key::Zero]),
+      Some(node),
+    )),
+  }
+}
 
-  match token.kind {
-    Kind::Bracket => resolve_brackets(tokens, ast),
-    Kind::Number => resolve_number(tokens, ast),
-    Kind::Operator => resolve_operator(tokens, ast),
-    _ => Err(Error::InvalidTokenSequence),
+fn to_node(tokens: &[Token], depth: usize, settings: &Settings, budget: &Budget) -> Result<Node, Located> {
+  let mut current = 0;
+  let node = resolve_expression(tokens, &mut current, None, depth, settings, budget)?;
+
+  match tokens.get(current) {
+    None => Ok(node),
+    Some(token) if starts_operand(token) => Err(Located::at(
+      Error::InvalidExpression("Bad format: There is no operator associated with this expression".to_string()),
+      token.span,
+    )),
+    Some(token) if token.kind == Kind::Separator => Err(misplaced_separator(token)),
+    Some(token) => Err(Located::at(Error::InvalidTokenSequence, token.span)),
   }
 }
 
+fn misplaced_separator(separator: &Token) -> Located {
+  let message = match separator.keys[0] {
+    Key::Semicolon => "Bad format: ; only separates the rows of a matrix",
+    Key::Range => "Bad format: .. only separates the bounds of an interval",
+    _ => "Bad format: , only separates the arguments of a function",
+  };
+  Located::at(Error::InvalidExpression(message.to_string()), separator.span)
+}
+
+/// Byte offsets from the first to the last of `tokens` in the formula.
+fn span_of(tokens: &[Token]) -> Option<(usize, usize)> {
+  Some((tokens.first()?.span?.0, tokens.last()?.span?.1))
+}
+
+fn is_equals(token: &Token) -> bool {
+  token.kind == Kind::Operator && token.keys[0] == Key::Equals
+}
+
 fn remove_space(tokens: Vec<Token>) -> Vec<Token> {
   tokens.into_iter().filter(|token| token.kind != Kind::Space).collect()
 }
 
-fn start_with_sign(tokens: &Vec<Token>) -> bool {
-  if tokens.len() == 0 {
-    return false;
+fn starts_operand(token: &Token) -> bool {
+  matches!(token.kind, Kind::Number | Kind::Date | Kind::Duration) || starts_implicit_operand(token, None)
+}
+
+/// Operands that may follow another operand without an operator, as the
+/// `pi` in `3pi` or the bracket in `2(3+4)`, and a number after a closing
+/// bracket, as the `3` in `(1+2)3`. Two numbers side by side, as `2 3`, are
+/// still rejected, as they more likely miss an operator than mean a product.
+fn starts_implicit_operand(token: &Token, previous: Option<&Token>) -> bool {
+  match token.kind {
+    Kind::Identifier | Kind::Function => true,
+    Kind::Bracket => bracket_close_of(token).is_some(),
+    Kind::Number => {
+      previous.is_some_and(|previous| previous.kind == Kind::Bracket && bracket_close_of(previous).is_none())
+    }
+    _ => false,
   }
-  let key = &tokens[0].keys[0];
+}
+
+fn is_sign(token: &Token) -> bool {
+  let key = &token.keys[0];
   *key == Key::Addition || *key == Key::Subtraction
 }
 
-fn resolve_brackets(tokens: Vec<Token>, mut ast: Ast) -> Result<Ast, Error> {
-  let bracket_expr = bracket_expression(tokens.clone())?;
-  let rest_expression = tokens[bracket_expr.len()..].to_vec();
-  let expression = remove_border_bracket(bracket_expr);
-  let ast_expression = to_ast(Ok(expression), Ast::new(None, None, None))?;
+/// Consumes operators while they bind tighter than `precedence`, so the loop
+/// only climbs as high as the operator that called it allows. `depth` counts
+/// the calls around this one, each of which resolves a deeper level.
+fn resolve_expression(
+  tokens: &[Token],
+  current: &mut usize,
+  precedence: Option<&Key>,
+  depth: usize,
+  settings: &Settings,
+  budget: &Budget,
+) -> Result<Node, Located> {
+  let mut node = resolve_operand(tokens, current, depth, settings, budget)?;
+  // whether the operations of the chain so far fit, which an operation on
+  // them does when its right operand does, as 1+2+3 is no deeper than 1+2
+  let mut fits = false;
+
+  while let Some(token) = tokens.get(*current) {
+    let (operator, key) = match next_operator(token, tokens[..*current].last(), settings) {
+      Some(operator) => operator,
+      None => break,
+    };
+    if let Some(precedence) = precedence {
+      let is_right_associative = key == *precedence && key.is_right_associative();
+      if !Key::precede(&key, precedence) && !is_right_associative {
+        break;
+      }
+    }
 
-  if rest_expression == [] && ast.is_empty() {
-    return Ok(ast_expression);
+    if token.kind == Kind::Operator {
+      *current += 1;
+    }
+    let operand_b = resolve_expression(tokens, current, Some(&key), depth + 1, settings, budget)?;
+    let span = operator.span;
+    let nested = (fits || nests_within(&node, settings)) && nests_within(&operand_b, settings);
+    node = resolve_operator(operator, node, operand_b)?;
+    if !nested {
+      return Err(Located::at(Error::TooDeep(settings.limits.depth), span));
+    }
+    fits = true;
   }
 
-  match ast {
-    Ast {
-      operand_a: None,
-      operand_b: None,
-      ..
-    } => ast.operand_a = Ast::node_ast(ast_expression),
-    Ast {
-      operand_a: Some(_),
-      operand_b: None,
-      ..
-    } => ast.operand_b = Ast::node_ast(ast_expression),
-    _ => {
-      return Err(Error::InvalidExpression(
-        "Bad format: There is no operator associated with this expression".to_string(),
+  Ok(node)
+}
+
+/// The operator after an operand along with the key that ranks it, which for
+/// an implicit multiplication, or for `^` in programmer mode, depends on the
+/// settings.
+fn next_operator(token: &Token, previous: Option<&Token>, settings: &Settings) -> Option<(Token, Key)> {
+  // ~ only ever comes before an operand
+  if token.kind == Kind::Operator && token.keys[0] == Key::BitNot {
+    return None;
+  }
+  if token.kind == Kind::Operator && token.keys[0] == Key::Exponentiation && settings.programmer.is_some() {
+    return Some((Token::new_operator(Key::Xor), Key::Xor));
+  }
+  if token.kind == Kind::Operator {
+    return Some((token.clone(), token.keys[0].clone()));
+  }
+  if !starts_implicit_operand(token, previous) {
+    return None;
+  }
+
+  let operator = Token::new_operator(Key::Multiplication);
+  match settings.implicit_multiplication {
+    ImplicitMultiplication::Disabled => None,
+    ImplicitMultiplication::SameAsExplicit => Some((operator, Key::Multiplication)),
+    ImplicitMultiplication::Tighter => Some((operator, Key::Juxtaposition)),
+  }
+}
+
+fn resolve_operand(
+  tokens: &[Token],
+  current: &mut usize,
+  depth: usize,
+  settings: &Settings,
+  budget: &Budget,
+) -> Result<Node, Located> {
+  // a missing operand is about the operator before it
+  let token = match tokens.get(*current) {
+    Some(token) => token,
+    None => {
+      return Err(Located::at(
+        Error::InvalidOperand,
+        tokens.last().and_then(|token| token.span),
       ))
     }
+  };
+  if depth > settings.limits.depth {
+    return Err(Located::at(Error::TooDeep(settings.limits.depth), token.span));
+  }
+  budget.read().map_err(|error| Located::at(error, token.span))?;
+
+  let node = match token.kind {
+    Kind::Number | Kind::Date | Kind::Duration | Kind::Identifier => {
+      *current += 1;
+      Node::Token(token.clone())
+    }
+    Kind::Bracket => resolve_brackets(tokens, current, depth, settings, budget)?,
+    Kind::Function => resolve_call(tokens, current, depth, settings, budget)?,
+    Kind::Operator if is_sign(token) => return resolve_sign(tokens, current, depth, settings, budget),
+    Kind::Operator if token.keys[0] == Key::BitNot => {
+      *current += 1;
+      let operand = resolve_operand(tokens, current, depth + 1, settings, budget)?;
+      let ast = Ast::new(
+        Ast::new_operator(Key::BitNot),
+        Ast::new_number(vec![Key::Zero]),
+        Some(operand),
+      );
+      return Ok(Node::Ast(Box::new(ast)));
+    }
+    Kind::Separator => return Err(misplaced_separator(token)),
+    _ => return Err(Located::at(Error::InvalidTokenSequence, token.span)),
+  };
+
+  resolve_postfix(tokens, current, node, settings)
+}
+
+fn resolve_sign(
+  tokens: &[Token],
+  current: &mut usize,
+  depth: usize,
+  settings: &Settings,
+  budget: &Budget,
+) -> Result<Node, Located> {
+  let sign = tokens[*current].clone();
+  *current += 1;
+  let operand = resolve_expression(tokens, current, Some(&Key::Multiplication), depth + 1, settings, budget)?;
+
+  let signed = |operand: Node| {
+    let ast = Ast::new(
+      Ast::node_operator(sign),
+      Ast::new_number(vec![Key::Zero]),
+      Some(operand),
+    );
+    Node::Ast(Box::new(ast))
+  };
+
+  match operand {
+    // -15% is read as (-15)%, keeping the sign inside the percentage
+    Node::Percentage(node) => Ok(Node::Percentage(Box::new(signed(*node)))),
+    node => Ok(signed(node)),
+  }
+}
+
+fn resolve_brackets(
+  tokens: &[Token],
+  current: &mut usize,
+  depth: usize,
+  settings: &Settings,
+  budget: &Budget,
+) -> Result<Node, Located> {
+  let bracket_expr = bracket_expression(&tokens[*current..])?;
+  *current += bracket_expr.len();
+  let is_matrix = bracket_expr[0].keys[0] == Key::BoxOpen;
+  let span = span_of(bracket_expr);
+  let expression = remove_border_bracket(bracket_expr);
+
+  if !is_matrix {
+    return to_node(expression, depth + 1, settings, budget);
+  }
+  let bounds = split_on(expression, Key::Range);
+  if bounds.len() > 1 {
+    return resolve_interval(bounds, span, depth, settings, budget);
+  }
+  let rows: Vec<Vec<&[Token]>> = split_on(expression, Key::Semicolon)
+    .into_iter()
+    .map(|row| split_on(row, Key::Comma))
+    .collect();
+  // [] around a single element groups as the other brackets do
+  if rows.len() == 1 && rows[0].len() == 1 {
+    return to_node(rows[0][0], depth + 1, settings, budget);
   }
 
-  if rest_expression == [] {
-    return Ok(ast);
+  if rows.iter().any(|row| row.len() != rows[0].len()) {
+    return Err(Located::at(
+      Error::InvalidExpression("Bad format: Rows of a matrix should have the same length".to_string()),
+      span,
+    ));
   }
+  let rows = rows
+    .into_iter()
+    .map(|row| {
+      row
+        .into_iter()
+        .map(|element| {
+          if element.is_empty() {
+            return Err(Located::at(
+              Error::InvalidExpression("Bad format: Matrix has an empty element".to_string()),
+              span,
+            ));
+          }
+          to_node(element, depth + 1, settings, budget)
+        })
+        .collect()
+    })
+    .collect::<Result<Vec<Vec<Node>>, Located>>()?;
+  Ok(Node::Matrix(rows))
+}
 
-  to_ast(Ok(rest_expression), ast)
+fn resolve_interval(
+  bounds: Vec<&[Token]>,
+  span: Option<(usize, usize)>,
+  depth: usize,
+  settings: &Settings,
+  budget: &Budget,
+) -> Result<Node, Located> {
+  match bounds.as_slice() {
+    [lower, upper] if !lower.is_empty() && !upper.is_empty() => Ok(Node::Interval(
+      Box::new(to_node(lower, depth + 1, settings, budget)?),
+      Box::new(to_node(upper, depth + 1, settings, budget)?),
+    )),
+    _ => Err(Located::at(
+      Error::InvalidExpression("Bad format: Interval should have a lower and an upper bound".to_string()),
+      span,
+    )),
+  }
 }
 
-fn resolve_number(mut tokens: Vec<Token>, mut ast: Ast) -> Result<Ast, Error> {
-  match ast {
-    Ast {
-      operand_a: None,
-      operand_b: None,
-      ..
-    } => {
-      let token = tokens.remove(0);
-      ast.operand_a = Ast::node_token(token);
-      to_ast(Ok(tokens), ast)
+fn resolve_call(
+  tokens: &[Token],
+  current: &mut usize,
+  depth: usize,
+  settings: &Settings,
+  budget: &Budget,
+) -> Result<Node, Located> {
+  let function = tokens[*current].clone();
+  *current += 1;
+
+  // √ takes the operand after it, brackets or not
+  if function.keys[0] == Key::SquareRoot {
+    let operand = resolve_operand(tokens, current, depth + 1, settings, budget)?;
+    return Ok(Node::Call(Token::new_function("sqrt"), vec![operand]));
+  }
+
+  match tokens.get(*current) {
+    Some(token) if token.kind == Kind::Bracket && bracket_close_of(token).is_some() => {
+      let bracket_expr = bracket_expression(&tokens[*current..])?;
+      *current += bracket_expr.len();
+      let span = span_of(bracket_expr)
+        .zip(function.span)
+        .map(|(brackets, name)| (name.0, brackets.1));
+
+      let arguments = split_on(remove_border_bracket(bracket_expr), Key::Comma)
+        .into_iter()
+        .map(|argument| {
+          if argument.is_empty() {
+            return Err(Located::at(
+              Error::InvalidExpression(format!("Bad format: Function {} has an empty argument", function)),
+              span,
+            ));
+          }
+          to_node(argument, depth + 1, settings, budget)
+        })
+        .collect::<Result<Vec<Node>, Located>>()?;
+      resolve_angles(function, arguments, settings)
     }
-    Ast {
-      operand_a: Some(_),
-      operand_b: None,
-      ..
-    } => {
-      let token = tokens.remove(0);
-      ast.operand_b = Ast::node_token(token);
-      to_ast(Ok(tokens), ast)
+    _ => Err(Located::at(
+      Error::InvalidExpression(format!(
+        "Bad format: Function {} expects its argument in brackets",
+        function
+      )),
+      function.span,
+    )),
+  }
+}
+
+/// Turns the angles going into the trigonometric functions from the angle
+/// mode into the radians they take, and their inverses' results back, and
+/// reads `deg` and `rad` as conversions out of the angle mode.
+fn resolve_angles(function: Token, arguments: Vec<Node>, settings: &Settings) -> Result<Node, Located> {
+  let mode = settings.angle;
+  let name = function.to_string();
+  let unit = match name.as_str() {
+    "sin" | "cos" | "tan" => {
+      let arguments = arguments
+        .into_iter()
+        .map(|argument| convert(argument, mode, Angle::Radians))
+        .collect();
+      return Ok(Node::Call(function, arguments));
     }
-    _ => Err(Error::InvalidTokenSequence),
+    "asin" | "acos" | "atan" => return Ok(convert(Node::Call(function, arguments), Angle::Radians, mode)),
+    "deg" => Angle::Degrees,
+    "rad" => Angle::Radians,
+    _ => return Ok(Node::Call(function, arguments)),
+  };
+
+  match <[Node; 1]>::try_from(arguments) {
+    Ok([angle]) => Ok(convert(angle, mode, unit)),
+    Err(_) => Err(Located::at(
+      Error::InvalidExpression(format!("Bad format: Function {} expects 1 argument", name)),
+      function.span,
+    )),
   }
 }
 
-fn resolve_operator(mut tokens: Vec<Token>, mut ast: Ast) -> Result<Ast, Error> {
-  let token = tokens.remove(0);
-  if token.kind != Kind::Operator {
-    return Err(Error::InvalidTokenSequence);
+/// `angle` given in the unit `from`, as `angle*pi/180` from degrees to
+/// radians.
+fn convert(angle: Node, from: Angle, to: Angle) -> Node {
+  if from == to {
+    return angle;
   }
+  let scaled = operation(Key::Multiplication, angle, half_turn(to));
+  operation(Key::Division, scaled, half_turn(from))
+}
 
-  if ast.operator == None {
-    ast.operator = Ast::node_operator(token);
-    return to_ast(Ok(tokens), ast);
+fn half_turn(unit: Angle) -> Node {
+  match unit {
+    Angle::Radians => Node::Token(Token::new_identifier("pi")),
+    Angle::Degrees => Node::Token(Token::new_number(vec![Key::One, Key::Eight, Key::Zero])),
+    Angle::Gradians => Node::Token(Token::new_number(vec![Key::Two, Key::Zero, Key::Zero])),
   }
+}
 
-  let Ast {
-    operator,
-    operand_a,
-    operand_b,
-  } = ast;
-  let operator = operator.unwrap();
-  let curr_operator = &token.keys[0];
-  let prev_operator = &operator.keys[0];
+fn operation(key: Key, operand_a: Node, operand_b: Node) -> Node {
+  let ast = Ast::new(Ast::new_operator(key), Some(operand_a), Some(operand_b));
+  Node::Ast(Box::new(ast))
+}
 
-  if Key::precede(curr_operator, prev_operator) {
-    let node_ast = Ast {
-      operator: Ast::node_operator(token),
-      operand_a: operand_b,
-      operand_b: Ast::node_empty(),
-    };
-    let operand_b = to_ast(Ok(tokens), node_ast)?;
+/// Splits the tokens between brackets at the `separator`s that are not
+/// nested in other brackets, as the arguments of a call or the rows of a
+/// matrix.
+fn split_on(tokens: &[Token], separator: Key) -> Vec<&[Token]> {
+  let mut arguments = vec![];
+  let mut start = 0;
+  let mut depth = 0;
+
+  for (at, token) in tokens.iter().enumerate() {
+    match token.kind {
+      Kind::Separator if depth == 0 && token.keys[0] == separator => {
+        arguments.push(&tokens[start..at]);
+        start = at + 1;
+      }
+      Kind::Bracket if bracket_close_of(token).is_some() => depth += 1,
+      Kind::Bracket => depth -= 1,
+      _ => (),
+    }
+  }
+  arguments.push(&tokens[start..]);
+
+  arguments
+}
 
-    let new_ast = Ast {
-      operator: Ast::node_operator(operator),
-      operand_a: operand_a,
-      operand_b: Ast::node_ast(operand_b),
+fn resolve_postfix(
+  tokens: &[Token],
+  current: &mut usize,
+  mut node: Node,
+  settings: &Settings,
+) -> Result<Node, Located> {
+  while let Some(token) = tokens.get(*current) {
+    if token.kind != Kind::Postfix {
+      break;
+    }
+    node = match token.keys[0] {
+      Key::Factorial => Node::Factorial(Box::new(node)),
+      Key::Percent => Node::Percentage(Box::new(node)),
+      Key::Squared => power(node, Key::Two),
+      Key::Cubed => power(node, Key::Three),
+      Key::Degree => convert(node, Angle::Degrees, settings.angle),
+      _ => break,
     };
+    node = within_depth(node, token.span, settings)?;
+    *current += 1;
+  }
 
-    Ok(new_ast)
+  Ok(node)
+}
+
+/// Fails on a `node` nested deeper than the limits allow, which the
+/// evaluators could not walk without exhausting the stack, at the `span` of
+/// the operator that built it.
+fn within_depth(node: Node, span: Option<(usize, usize)>, settings: &Settings) -> Result<Node, Located> {
+  if fits_depth(&node, settings.limits.depth) {
+    Ok(node)
   } else {
-    let operand_a = Ast {
-      operator: Ast::node_operator(operator),
-      operand_a,
-      operand_b,
-    };
-    let new_ast = Ast {
-      operator: Ast::node_operator(token),
-      operand_a: Ast::node_ast(operand_a),
-      operand_b: Ast::node_empty(),
-    };
+    Err(Located::at(Error::TooDeep(settings.limits.depth), span))
+  }
+}
+
+/// Whether `node` fits as an operand, a level below the limits.
+fn nests_within(node: &Node, settings: &Settings) -> bool {
+  settings.limits.depth > 0 && fits_depth(node, settings.limits.depth - 1)
+}
 
-    to_ast(Ok(tokens), new_ast)
+/// Whether `node` has at most `depth` levels, looking no deeper than that.
+fn fits_depth(node: &Node, depth: usize) -> bool {
+  let fits = |node: &Node| depth > 0 && fits_depth(node, depth - 1);
+  match node {
+    // the operands of a chain as 1+2+3 are all a level below it
+    Node::Ast(ast) => ast.spine_operands().into_iter().all(fits),
+    Node::Call(_, arguments) => arguments.iter().all(fits),
+    Node::Factorial(node) | Node::Percentage(node) => fits(node),
+    Node::Interval(lower, upper) => fits(lower) && fits(upper),
+    Node::Matrix(rows) => rows.iter().flatten().all(fits),
+    Node::Token(_) => true,
   }
 }
 
-fn bracket_expression(tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
-  if tokens[0].kind != Kind::Bracket {
-    return Err(Error::InvalidExpression(
-      "Bad format: Is expected a bracket at this point".to_string(),
+fn power(base: Node, exponent: Key) -> Node {
+  operation(
+    Key::Exponentiation,
+    base,
+    Node::Token(Token::new_number(vec![exponent])),
+  )
+}
+
+fn resolve_operator(operator: Token, operand_a: Node, operand_b: Node) -> Result<Node, Located> {
+  if operator.keys[0] == Key::Of && !matches!(operand_a, Node::Percentage(_)) {
+    return Err(Located::at(
+      Error::InvalidExpression("Bad format: Operator of expects a percentage before it".to_string()),
+      operator.span,
+    ));
+  }
+
+  let ast = Ast::new(Ast::node_operator(operator), Some(operand_a), Some(operand_b));
+  Ok(Node::Ast(Box::new(ast)))
+}
+
+/// The tokens from the bracket `tokens` start with to the one closing it,
+/// borrowed rather than copied, as a formula may have thousands of them.
+fn bracket_expression(tokens: &[Token]) -> Result<&[Token], Located> {
+  let open = &tokens[0];
+  if open.kind != Kind::Bracket {
+    return Err(Located::at(
+      Error::InvalidExpression("Bad format: Is expected a bracket at this point".to_string()),
+      open.span,
     ));
   }
   let mut count_opens = 0;
   let mut count_closes = 0;
-  let mut expression: Vec<Token> = vec![];
-  let open = tokens[0].clone();
-  let close = bracket_close_of(&open).unwrap();
+  let close = bracket_close_of(open).ok_or(Located::at(Error::InvalidTokenSequence, open.span))?;
 
-  for token in tokens {
-    match &token {
-      token if *token == open => count_opens += 1,
+  for (at, token) in tokens.iter().enumerate() {
+    match token {
+      token if token == open => count_opens += 1,
       token if *token == close => count_closes += 1,
       _ => (),
     }
-    expression.push(token);
     if count_opens > 0 && count_opens == count_closes {
-      break;
+      return Ok(&tokens[..=at]);
     }
   }
 
-  Ok(expression)
+  Err(Located::at(
+    Error::InvalidExpression(format!("Bad format: Bracket {} is not closed", open)),
+    open.span,
+  ))
 }
 
 fn bracket_close_of(bracket: &Token) -> Option<Token> {
@@ -266,10 +1006,8 @@ fn bracket_close_of(bracket: &Token) -> Option<Token> {
   }
 }
 
-fn remove_border_bracket(mut tokens: Vec<Token>) -> Vec<Token> {
-  tokens.remove(0);
-  tokens.remove(tokens.len() - 1);
-  tokens
+fn remove_border_bracket(tokens: &[Token]) -> &[Token] {
+  &tokens[1..tokens.len() - 1]
 }
 
 #[cfg(test)]
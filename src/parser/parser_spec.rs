@@ -1,4 +1,5 @@
 use super::*;
+use crate::settings::{Angle, ImplicitMultiplication, IntegerType, Overflow, Programmer, Settings};
 use pretty_assertions::assert_eq;
 use Key::*;
 
@@ -245,6 +246,305 @@ fn should_parse_expression_with_brackets() {
   assert_eq!(ast, Ok(expected_ast));
 }
 
+#[test]
+fn should_parse_percentage_as_postfix_of_the_operand() {
+  // formula = 200+15%
+
+  let tokens = vec![
+    Token::new_number(vec![Two, Zero, Zero]),
+    Token::new_operator(Addition),
+    Token::new_number(vec![One, Five]),
+    Token::new(Kind::Postfix, Percent),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Addition),
+    Ast::new_number(vec![Two, Zero, Zero]),
+    Some(Node::Percentage(Box::new(Ast::new_number(vec![One, Five]).unwrap()))),
+  );
+
+  let ast = parse(Ok(tokens));
+
+  assert_eq!(ast, Ok(expected_ast));
+}
+
+#[test]
+fn should_parse_signed_percentage_keeping_the_sign_inside() {
+  // formula = -15%
+
+  let tokens = vec![
+    Token::new_operator(Subtraction),
+    Token::new_number(vec![One, Five]),
+    Token::new(Kind::Postfix, Percent),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Addition),
+    Some(Node::Percentage(Box::new(
+      Ast::node_ast(Ast::new(
+        Ast::new_operator(Subtraction),
+        Ast::new_number(vec![Zero]),
+        Ast::new_number(vec![One, Five]),
+      ))
+      .unwrap(),
+    ))),
+    Ast::new_number(vec![Zero]),
+  );
+
+  let ast = parse(Ok(tokens));
+
+  assert_eq!(ast, Ok(expected_ast));
+}
+
+#[test]
+fn should_parse_of_only_after_a_percentage() {
+  let tokens = vec![
+    Token::new_number(vec![One, Five]),
+    Token::new(Kind::Postfix, Percent),
+    Token::new_operator(Of),
+    Token::new_number(vec![Two]),
+  ];
+  let invalid_tokens = vec![
+    Token::new_number(vec![One, Five]),
+    Token::new_operator(Of),
+    Token::new_number(vec![Two]),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Of),
+    Some(Node::Percentage(Box::new(Ast::new_number(vec![One, Five]).unwrap()))),
+    Ast::new_number(vec![Two]),
+  );
+
+  assert_eq!(parse(Ok(tokens)), Ok(expected_ast));
+  assert_eq!(
+    parse(Ok(invalid_tokens)),
+    Err(Error::InvalidExpression(
+      "Bad format: Operator of expects a percentage before it".to_string()
+    ))
+  );
+}
+
+#[test]
+fn should_parse_exponentiation_from_right_to_left() {
+  // formula = 2^3^2
+
+  let tokens = vec![
+    Token::new_number(vec![Two]),
+    Token::new_operator(Exponentiation),
+    Token::new_number(vec![Three]),
+    Token::new_operator(Exponentiation),
+    Token::new_number(vec![Two]),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Exponentiation),
+    Ast::new_number(vec![Two]),
+    Ast::node_ast(Ast::new(
+      Ast::new_operator(Exponentiation),
+      Ast::new_number(vec![Three]),
+      Ast::new_number(vec![Two]),
+    )),
+  );
+
+  let ast = parse(Ok(tokens));
+
+  assert_eq!(ast, Ok(expected_ast));
+}
+
+#[test]
+fn should_parse_factorial_tighter_than_exponentiation() {
+  // formula = 2^3!
+
+  let tokens = vec![
+    Token::new_number(vec![Two]),
+    Token::new_operator(Exponentiation),
+    Token::new_number(vec![Three]),
+    Token::new(Kind::Postfix, Factorial),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Exponentiation),
+    Ast::new_number(vec![Two]),
+    Some(Node::Factorial(Box::new(Ast::new_number(vec![Three]).unwrap()))),
+  );
+
+  let ast = parse(Ok(tokens));
+
+  assert_eq!(ast, Ok(expected_ast));
+}
+
+#[test]
+fn should_parse_implicit_multiplication_before_brackets_and_identifiers() {
+  // formula = 2(3)pi
+
+  let tokens = vec![
+    Token::new_number(vec![Two]),
+    Token::new_bracket(RoundOpen),
+    Token::new_number(vec![Three]),
+    Token::new_bracket(RoundClose),
+    Token::new_identifier("pi"),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Multiplication),
+    Ast::node_ast(Ast::new(
+      Ast::new_operator(Multiplication),
+      Ast::new_number(vec![Two]),
+      Ast::new_number(vec![Three]),
+    )),
+    Some(Node::Token(Token::new_identifier("pi"))),
+  );
+
+  let ast = parse(Ok(tokens));
+
+  assert_eq!(ast, Ok(expected_ast));
+}
+
+#[test]
+fn should_parse_implicit_multiplication_of_a_number_after_a_bracket() {
+  // formula = (1+2)3
+
+  let tokens = vec![
+    Token::new_bracket(RoundOpen),
+    Token::new_number(vec![One]),
+    Token::new_operator(Addition),
+    Token::new_number(vec![Two]),
+    Token::new_bracket(RoundClose),
+    Token::new_number(vec![Three]),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Multiplication),
+    Ast::node_ast(Ast::new(
+      Ast::new_operator(Addition),
+      Ast::new_number(vec![One]),
+      Ast::new_number(vec![Two]),
+    )),
+    Ast::new_number(vec![Three]),
+  );
+
+  let ast = parse(Ok(tokens));
+
+  assert_eq!(ast, Ok(expected_ast));
+}
+
+#[test]
+fn should_parse_implicit_multiplication_with_the_configured_precedence() {
+  // formula = 1/2x
+
+  let tokens = vec![
+    Token::new_number(vec![One]),
+    Token::new_operator(Division),
+    Token::new_number(vec![Two]),
+    Token::new_identifier("x"),
+  ];
+  let same = Settings::default();
+  let tighter = Settings {
+    implicit_multiplication: ImplicitMultiplication::Tighter,
+    ..Settings::default()
+  };
+
+  let expected_same_ast = Ast::new(
+    Ast::new_operator(Multiplication),
+    Ast::node_ast(Ast::new(
+      Ast::new_operator(Division),
+      Ast::new_number(vec![One]),
+      Ast::new_number(vec![Two]),
+    )),
+    Some(Node::Token(Token::new_identifier("x"))),
+  );
+  let expected_tighter_ast = Ast::new(
+    Ast::new_operator(Division),
+    Ast::new_number(vec![One]),
+    Ast::node_ast(Ast::new(
+      Ast::new_operator(Multiplication),
+      Ast::new_number(vec![Two]),
+      Some(Node::Token(Token::new_identifier("x"))),
+    )),
+  );
+
+  assert_eq!(parse_with(Ok(tokens.clone()), &same), Ok(expected_same_ast));
+  assert_eq!(parse_with(Ok(tokens), &tighter), Ok(expected_tighter_ast));
+}
+
+#[test]
+fn should_not_parse_implicit_multiplication_in_strict_mode() {
+  let tokens = vec![Token::new_number(vec![Three]), Token::new_identifier("pi")];
+
+  assert_eq!(
+    parse_with(Ok(tokens), &Settings::strict()),
+    Err(Error::InvalidExpression(
+      "Bad format: There is no operator associated with this expression".to_string()
+    ))
+  );
+}
+
+#[test]
+fn should_not_parse_adjacent_numbers() {
+  let tokens = vec![Token::new_number(vec![Two]), Token::new_number(vec![Three])];
+
+  assert_eq!(
+    parse(Ok(tokens)),
+    Err(Error::InvalidExpression(
+      "Bad format: There is no operator associated with this expression".to_string()
+    ))
+  );
+}
+
+#[test]
+fn should_parse_function_call() {
+  // formula = 2sin(x)
+
+  let tokens = vec![
+    Token::new_number(vec![Two]),
+    Token::new_function("sin"),
+    Token::new_bracket(RoundOpen),
+    Token::new_identifier("x"),
+    Token::new_bracket(RoundClose),
+  ];
+
+  let expected_ast = Ast::new(
+    Ast::new_operator(Multiplication),
+    Ast::new_number(vec![Two]),
+    Some(Node::Call(
+      Token::new_function("sin"),
+      vec![Node::Token(Token::new_identifier("x"))],
+    )),
+  );
+
+  assert_eq!(parse(Ok(tokens)), Ok(expected_ast));
+}
+
+#[test]
+fn should_not_parse_function_without_brackets() {
+  let tokens = vec![Token::new_function("sin"), Token::new_identifier("x")];
+
+  assert_eq!(
+    parse(Ok(tokens)),
+    Err(Error::InvalidExpression(
+      "Bad format: Function sin expects its argument in brackets".to_string()
+    ))
+  );
+}
+
+#[test]
+fn should_not_parse_unclosed_bracket() {
+  let tokens = vec![
+    Token::new_bracket(RoundOpen),
+    Token::new_number(vec![One]),
+    Token::new_operator(Addition),
+    Token::new_number(vec![Two]),
+  ];
+
+  assert_eq!(
+    parse(Ok(tokens)),
+    Err(Error::InvalidExpression(
+      "Bad format: Bracket ( is not closed".to_string()
+    ))
+  );
+}
+
 #[test]
 fn testing_remove_space() {
   let tokens = vec![
@@ -292,11 +592,11 @@ fn testing_bracket_expression() {
     Token::new_bracket(BoxClose),
     Token::new_bracket(CurlyClose),
   ];
-  let result = bracket_expression(tokens);
+  let result = bracket_expression(&tokens);
 
   assert_eq!(
     result,
-    Ok(expected_expression),
+    Ok(expected_expression.as_slice()),
     "should match the most outer pair of bracket"
   );
 }
@@ -304,12 +604,12 @@ fn testing_bracket_expression() {
 #[test]
 fn testing_error_bracket_expression() {
   let tokens = vec![Token::new_number(vec![Nine])];
-  let result = bracket_expression(tokens);
+  let result = bracket_expression(&tokens);
   let expected_result = Err(Error::InvalidExpression(
     "Bad format: Is expected a bracket at this point".to_string(),
   ));
 
-  assert_eq!(result, expected_result)
+  assert_eq!(result.map_err(Error::from), expected_result)
 }
 
 #[test]
@@ -337,7 +637,284 @@ fn testing_remove_border_bracket() {
     Token::new_bracket(CurlyClose),
   ];
   let expected_expression: Vec<Token> = vec![Token::new_number(vec![One])];
-  let expression = remove_border_bracket(tokens);
+  let expression = remove_border_bracket(&tokens);
 
   assert_eq!(expression, expected_expression);
 }
+
+#[test]
+fn testing_display() {
+  let print = |formula: &str| {
+    parse(crate::tokenizer::tokenize(formula.to_string()))
+      .unwrap()
+      .to_string()
+  };
+
+  assert_eq!(print("1"), "1");
+  assert_eq!(print("-1"), "-1");
+  assert_eq!(print("1+2*3"), "1 + 2*3");
+  assert_eq!(print("(1+2)*3"), "(1 + 2)*3");
+  assert_eq!(print("1-(2-3)"), "1 - (2 - 3)");
+  assert_eq!(print("(1-2)-3"), "1 - 2 - 3");
+  assert_eq!(print("2^3^2"), "2^3^2");
+  assert_eq!(print("(2^3)^2"), "(2^3)^2");
+  assert_eq!(print("-(1+2)"), "-(1 + 2)");
+  assert_eq!(print("-x^2"), "-x^2");
+  assert_eq!(print("(-x)^2"), "(-x)^2");
+  assert_eq!(print("2*-x"), "2*(-x)");
+  assert_eq!(print("(1+2)!"), "(1 + 2)!");
+  assert_eq!(print("15% of 200"), "15% of 200");
+  assert_eq!(print("2sin(x)"), "2*sin(x)");
+  assert_eq!(print("a|b&c<<1+2"), "a | b & c << 1 + 2");
+  assert_eq!(print("(a|b)&c"), "(a | b) & c");
+  assert_eq!(print("~x^2"), "~x^2");
+  assert_eq!(print("~(a&b)"), "~(a & b)");
+  assert_eq!(print("(5+/-0.1)*2"), "(5 ± 0.1)*2");
+  assert_eq!(print("((1+2)*3-4)/5"), "((1 + 2)*3 - 4)/5");
+  assert_eq!(print("(-x+1)*2-3"), "(-x + 1)*2 - 3");
+  assert_eq!(print("(~a|b)*2"), "(~a | b)*2");
+}
+
+#[test]
+fn testing_parse_bitwise_operators() {
+  let programmer = Settings {
+    programmer: Some(Programmer {
+      integer: IntegerType { bits: 8, signed: false },
+      overflow: Overflow::Wrap,
+    }),
+    ..Settings::default()
+  };
+  let operator = |formula: &str, settings: &Settings| {
+    parse_with(crate::tokenizer::tokenize(formula.to_string()), settings)
+      .unwrap()
+      .operator
+      .as_ref()
+      .unwrap()
+      .keys[0]
+      .clone()
+  };
+
+  assert_eq!(operator("a ^ b | c", &Settings::default()), BitOr);
+  assert_eq!(operator("a ^ b & c", &Settings::default()), BitAnd);
+  assert_eq!(operator("a ^ b & c", &programmer), Xor, "should read ^ as xor below &");
+  assert_eq!(operator("a << 1 ^ b", &programmer), Xor);
+  assert_eq!(
+    parse(crate::tokenizer::tokenize("2 ~ 3".to_string())),
+    Err(Error::InvalidTokenSequence),
+    "should only take ~ before an operand"
+  );
+}
+
+#[test]
+fn testing_parse_equation() {
+  let equation = |formula: &str| parse_equation(crate::tokenizer::tokenize(formula.to_string()), &Settings::default());
+
+  assert_eq!(
+    equation("x^3 - 2*x = 5").map(|equation| equation.to_string()),
+    Ok("x^3 - 2*x = 5".to_string())
+  );
+  assert_eq!(
+    equation("x^2 - 4").map(|equation| equation.to_string()),
+    Ok("x^2 - 4 = 0".to_string()),
+    "should read a formula as formula = 0"
+  );
+  assert_eq!(
+    equation("x = 1 = 2"),
+    Err(Error::InvalidExpression(
+      "Bad format: An equation has a single =".to_string()
+    ))
+  );
+  assert_eq!(
+    equation("x ="),
+    Err(Error::InvalidExpression(
+      "Bad format: Both sides of = need an expression".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_parse_rejects_equals() {
+  assert_eq!(
+    parse(crate::tokenizer::tokenize("1 = 1".to_string())),
+    Err(Error::InvalidExpression(
+      "Bad format: Operator = is only accepted in equations".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_validate_variable() {
+  assert_eq!(validate_variable("x"), Ok(()));
+  assert_eq!(
+    validate_variable("2x"),
+    Err(Error::InvalidExpression(
+      "Bad format: 2x is not a variable name".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_parse_call_arguments() {
+  let print = |formula: &str| parse(crate::tokenizer::tokenize(formula.to_string())).map(|ast| ast.to_string());
+
+  assert_eq!(print("sum(i^2,i,1,(2+3))"), Ok("sum(i^2, i, 1, 2 + 3)".to_string()));
+  assert_eq!(print("sum(sin(i), i, 1, 2)"), Ok("sum(sin(i), i, 1, 2)".to_string()));
+  assert_eq!(
+    print("sum(i,,1,2)"),
+    Err(Error::InvalidExpression(
+      "Bad format: Function sum has an empty argument".to_string()
+    ))
+  );
+  assert_eq!(
+    print("1,2"),
+    Err(Error::InvalidExpression(
+      "Bad format: , only separates the arguments of a function".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_parse_script() {
+  let script = "# budget\nrent = 1200; food = 400 # per month\n\nrent + food\n";
+  let statements = parse_script(script, &Settings::default()).unwrap();
+  let print: Vec<String> = statements
+    .iter()
+    .map(|statement| match statement {
+      Statement::Assignment(name, ast) => format!("{} := {}", name, ast),
+      Statement::Expression(ast) => ast.to_string(),
+    })
+    .collect();
+
+  assert_eq!(print, vec!["rent := 1200", "food := 400", "rent + food"]);
+  assert_eq!(
+    parse_script("2x = 4", &Settings::default()),
+    Err(Error::InvalidExpression(
+      "Bad format: Only a variable name can be assigned with =".to_string()
+    ))
+  );
+  assert_eq!(
+    parse_script("x =", &Settings::default()),
+    Err(Error::InvalidExpression("Input is empty".to_string()))
+  );
+}
+
+#[test]
+fn testing_parse_matrices() {
+  let print = |formula: &str| parse(crate::tokenizer::tokenize(formula.to_string())).map(|ast| ast.to_string());
+
+  assert_eq!(print("[1,2;3,4]*[x;2y]"), Ok("[1, 2; 3, 4]*[x; 2*y]".to_string()));
+  assert_eq!(
+    print("[1+2]*3"),
+    Ok("(1 + 2)*3".to_string()),
+    "should group a single element"
+  );
+  assert_eq!(
+    print("[1, 2; 3]"),
+    Err(Error::InvalidExpression(
+      "Bad format: Rows of a matrix should have the same length".to_string()
+    ))
+  );
+  assert_eq!(
+    print("[1, ; 3, 4]"),
+    Err(Error::InvalidExpression(
+      "Bad format: Matrix has an empty element".to_string()
+    ))
+  );
+  assert_eq!(
+    print("(1; 2)"),
+    Err(Error::InvalidExpression(
+      "Bad format: ; only separates the rows of a matrix".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_parse_intervals() {
+  let print = |formula: &str| parse(crate::tokenizer::tokenize(formula.to_string())).map(|ast| ast.to_string());
+
+  assert_eq!(print("[1.9..2.1]*x"), Ok("[1.9 .. 2.1]*x".to_string()));
+  assert_eq!(print("[-1 .. 2^3]"), Ok("[-1 .. 2^3]".to_string()));
+  assert_eq!(
+    print("[1 .. ]"),
+    Err(Error::InvalidExpression(
+      "Bad format: Interval should have a lower and an upper bound".to_string()
+    ))
+  );
+  assert_eq!(
+    print("[1 .. 2 .. 3]"),
+    Err(Error::InvalidExpression(
+      "Bad format: Interval should have a lower and an upper bound".to_string()
+    ))
+  );
+  assert_eq!(
+    print("(1 .. 2)"),
+    Err(Error::InvalidExpression(
+      "Bad format: .. only separates the bounds of an interval".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_parse_angles() {
+  let print = |formula: &str, angle: Angle| {
+    let settings = Settings {
+      angle,
+      ..Settings::default()
+    };
+    parse_with(crate::tokenizer::tokenize(formula.to_string()), &settings).map(|ast| ast.to_string())
+  };
+
+  assert_eq!(print("sin(x)", Angle::Radians), Ok("sin(x)".to_string()));
+  assert_eq!(print("sin(x)", Angle::Degrees), Ok("sin(x*pi/180)".to_string()));
+  assert_eq!(print("2asin(x)", Angle::Gradians), Ok("2*(asin(x)*200/pi)".to_string()));
+  assert_eq!(print("30° + 1", Angle::Radians), Ok("30*pi/180 + 1".to_string()));
+  assert_eq!(print("30°", Angle::Degrees), Ok("30".to_string()));
+  assert_eq!(print("deg(pi)", Angle::Radians), Ok("pi*180/pi".to_string()));
+  assert_eq!(print("rad(x + 1)", Angle::Gradians), Ok("(x + 1)*pi/200".to_string()));
+  assert_eq!(
+    print("deg(1, 2)", Angle::Radians),
+    Err(Error::InvalidExpression(
+      "Bad format: Function deg expects 1 argument".to_string()
+    ))
+  );
+}
+
+#[test]
+fn testing_parse_depth() {
+  let settings = Settings::default();
+  let nested = |open: &str, close: &str, levels: usize| format!("{}1{}", open.repeat(levels), close.repeat(levels));
+
+  for &levels in [128, 129, 1_000].iter() {
+    let formulas = vec![
+      nested("(", ")", levels),
+      nested("sin(", ")", levels),
+      nested("[", "]", levels),
+      nested("", "!", levels),
+      nested("2^", "", levels),
+      nested("-", "", levels),
+      nested("~", "", levels),
+    ];
+    for formula in formulas {
+      let result = parse_with(tokenize(formula.clone()), &settings);
+      assert_eq!(
+        matches!(result, Err(Error::TooDeep(128))),
+        levels > 128,
+        "should parse {}... {} levels deep only within the limits",
+        &formula[..8],
+        levels
+      );
+    }
+  }
+  assert!(
+    parse_with(tokenize(format!("1{}", "+1".repeat(5_000))), &settings).is_ok(),
+    "should parse a chain of operators of any length, as it nests no deeper"
+  );
+}
+
+#[test]
+fn testing_split_statements() {
+  assert_eq!(
+    split_statements("a = [1, 2; 3, 4]; det(a) # 2; 3"),
+    vec![(0, "a = [1, 2; 3, 4]"), (17, " det(a) ")]
+  );
+}
@@ -0,0 +1,291 @@
+use crate::interpreter::{evaluate, Environment};
+use crate::parser::{parse_with, Ast};
+use crate::settings::{Floats, Settings};
+use crate::tokenizer::tokenize_with;
+use crate::Error;
+
+/// Markers of the series in an ASCII chart, in order.
+const MARKERS: [char; 5] = ['*', '+', 'o', 'x', '#'];
+
+/// ANSI colours of the series in a braille chart, in order.
+const COLOURS: [u8; 5] = [31, 32, 34, 33, 35];
+
+/// Bits of the braille dots by row and column within a character.
+const BRAILLE_DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+  /// 2x4 dots per character, distinguishing series by colour
+  Braille,
+  /// One point per character, distinguishing series by marker
+  Ascii,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotOptions {
+  pub x: (f64, f64),
+  /// Range of the vertical axis, fitted to the values when `None`
+  pub y: Option<(f64, f64)>,
+  /// Size of the chart area in characters
+  pub width: usize,
+  pub height: usize,
+  pub style: Style,
+}
+impl Default for PlotOptions {
+  fn default() -> PlotOptions {
+    PlotOptions {
+      x: (-10.0, 10.0),
+      y: None,
+      width: 60,
+      height: 15,
+      style: Style::Braille,
+    }
+  }
+}
+
+/// Plots each formula as a function of `x`, leaving a gap wherever it is
+/// undefined.
+pub fn run_with(formulas: &[String], options: &PlotOptions, settings: &Settings) -> Result<String, Error> {
+  let series = formulas
+    .iter()
+    .map(|formula| parse_with(tokenize_with(formula.clone(), &settings.limits), settings))
+    .collect::<Result<Vec<Ast>, Error>>()?;
+
+  let mut canvas = Canvas::new(options);
+  // values out of range are gaps, as IEEE floats leave them
+  let environment = Environment::new()
+    .with_floats(Floats::Ieee)
+    .with_limits(&settings.limits);
+  let samples = series
+    .iter()
+    .map(|ast| sample(ast, &environment, options.x, canvas.columns()))
+    .collect::<Result<Vec<Vec<Option<f64>>>, Error>>()?;
+  let y = options.y.or_else(|| fit(&samples)).unwrap_or((-1.0, 1.0));
+
+  for (index, values) in samples.iter().enumerate() {
+    for (column, value) in values.iter().enumerate() {
+      if let Some(row) = value.and_then(|value| position(value, y, canvas.rows())) {
+        canvas.set(column, canvas.rows() - 1 - row, index);
+      }
+    }
+  }
+
+  Ok(render(&canvas, formulas, options, y))
+}
+
+/// The values of `ast` at `columns` points spread over the range of x, in an
+/// environment sharing its budget with that of `base`.
+fn sample(ast: &Ast, base: &Environment, (from, to): (f64, f64), columns: usize) -> Result<Vec<Option<f64>>, Error> {
+  let mut environment = base.clone();
+
+  (0..columns)
+    .map(|column| {
+      environment.bind("x", from + (to - from) * column as f64 / (columns - 1).max(1) as f64);
+      match evaluate(ast, &environment) {
+        Ok(value) if value.is_finite() => Ok(Some(value)),
+        Ok(_) => Ok(None),
+        Err(error) if error.is_domain_error() => Ok(None),
+        Err(error) => Err(error),
+      }
+    })
+    .collect()
+}
+
+/// The smallest range holding every value, widened when they are all equal.
+fn fit(samples: &[Vec<Option<f64>>]) -> Option<(f64, f64)> {
+  let values = samples.iter().flatten().flatten();
+  let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), value| {
+    (lo.min(*value), hi.max(*value))
+  });
+
+  match (lo.is_finite(), lo == hi) {
+    (false, _) => None,
+    (true, true) => Some((lo - 1.0, hi + 1.0)),
+    (true, false) => Some((lo, hi)),
+  }
+}
+
+/// Index of `value` among `count` positions spread over `range`, from the
+/// bottom, if it falls within the range.
+fn position(value: f64, (lo, hi): (f64, f64), count: usize) -> Option<usize> {
+  if value < lo || value > hi {
+    return None;
+  }
+  Some(((value - lo) / (hi - lo) * (count - 1) as f64).round() as usize)
+}
+
+#[derive(Clone, Copy, Default)]
+struct Cell {
+  dots: u8,
+  series: usize,
+}
+
+/// The chart area, addressed by the points it can show.
+struct Canvas {
+  style: Style,
+  cells: Vec<Vec<Cell>>,
+}
+impl Canvas {
+  fn new(options: &PlotOptions) -> Canvas {
+    Canvas {
+      style: options.style,
+      cells: vec![vec![Cell::default(); options.width.max(1)]; options.height.max(1)],
+    }
+  }
+
+  fn scale(&self) -> (usize, usize) {
+    match self.style {
+      Style::Braille => (2, 4),
+      Style::Ascii => (1, 1),
+    }
+  }
+
+  fn columns(&self) -> usize {
+    self.cells[0].len() * self.scale().0
+  }
+
+  fn rows(&self) -> usize {
+    self.cells.len() * self.scale().1
+  }
+
+  /// Marks the point at `column` and `row`, counted from the top left.
+  fn set(&mut self, column: usize, row: usize, series: usize) {
+    let (width, height) = self.scale();
+    let cell = &mut self.cells[row / height][column / width];
+
+    cell.dots |= match self.style {
+      Style::Braille => BRAILLE_DOTS[row % height][column % width],
+      Style::Ascii => 1,
+    };
+    cell.series = series;
+  }
+
+  /// The character at a cell, or `None` where nothing is drawn.
+  fn point(&self, row: usize, column: usize, coloured: bool) -> Option<String> {
+    let cell = self.cells[row][column];
+    if cell.dots == 0 {
+      return None;
+    }
+
+    match self.style {
+      Style::Ascii => Some(MARKERS[cell.series % MARKERS.len()].to_string()),
+      Style::Braille => {
+        let dots = char::from_u32(0x2800 + cell.dots as u32).unwrap();
+        if coloured {
+          Some(colour(&dots.to_string(), cell.series))
+        } else {
+          Some(dots.to_string())
+        }
+      }
+    }
+  }
+}
+
+fn colour(text: &str, series: usize) -> String {
+  format!("\x1b[{}m{}\x1b[0m", COLOURS[series % COLOURS.len()], text)
+}
+
+/// Box drawing pieces: vertical, horizontal, crossing, tick and corner.
+fn frame(style: Style) -> [char; 5] {
+  match style {
+    Style::Braille => ['│', '─', '┼', '┤', '└'],
+    Style::Ascii => ['|', '-', '+', '+', '+'],
+  }
+}
+
+fn render(canvas: &Canvas, formulas: &[String], options: &PlotOptions, y: (f64, f64)) -> String {
+  let [vertical, horizontal, crossing, tick, corner] = frame(options.style);
+  let (width, height) = (canvas.cells[0].len(), canvas.cells.len());
+  let coloured = options.style == Style::Braille && formulas.len() > 1;
+
+  // zero lines, in cells from the top left
+  let (scale_x, scale_y) = canvas.scale();
+  let zero_row = position(0.0, y, canvas.rows()).map(|row| (canvas.rows() - 1 - row) / scale_y);
+  let zero_column = position(0.0, options.x, canvas.columns())
+    .map(|column| column / scale_x)
+    .filter(|column| *column > 0);
+
+  let mut labels = vec![None; height];
+  labels[0] = Some(label(y.1));
+  labels[height - 1] = Some(label(y.0));
+  if let Some(row) = zero_row.filter(|row| labels[*row].is_none()) {
+    labels[row] = Some(label(0.0));
+  }
+  let margin = labels
+    .iter()
+    .flatten()
+    .map(|label| label.chars().count())
+    .max()
+    .unwrap_or(0);
+
+  let mut lines: Vec<String> = Vec::new();
+  for (row, label) in labels.iter().enumerate() {
+    let mut line = match label {
+      Some(label) => format!("{:>margin$} {}", label, tick, margin = margin),
+      None => format!("{:>margin$} {}", "", vertical, margin = margin),
+    };
+    for column in 0..width {
+      let point = canvas.point(row, column, coloured);
+      let axis = match (Some(row) == zero_row, Some(column) == zero_column) {
+        (true, true) => crossing,
+        (true, false) => horizontal,
+        (false, true) => vertical,
+        (false, false) => ' ',
+      };
+      line.push_str(&point.unwrap_or_else(|| axis.to_string()));
+    }
+    lines.push(line.trim_end().to_string());
+  }
+
+  let axis: String = std::iter::repeat_n(horizontal, width).collect();
+  lines.push(format!("{:>margin$} {}{}", "", corner, axis, margin = margin));
+  lines.push(format!(
+    "{:>margin$}  {}",
+    "",
+    x_labels(options.x, width),
+    margin = margin
+  ));
+
+  if formulas.len() > 1 {
+    for (series, formula) in formulas.iter().enumerate() {
+      let marker = match options.style {
+        Style::Ascii => MARKERS[series % MARKERS.len()].to_string(),
+        Style::Braille => colour("⣿", series),
+      };
+      lines.push(format!("{} {}", marker, formula));
+    }
+  }
+
+  lines.join("\n")
+}
+
+/// The bounds of the horizontal axis under its ends, with the middle value
+/// centred between them when there is room.
+fn x_labels((from, to): (f64, f64), width: usize) -> String {
+  let (left, middle, right) = (label(from), label((from + to) / 2.0), label(to));
+  let mut line: Vec<char> = vec![' '; width.max(left.len() + right.len() + 1)];
+
+  let middle_start = (width / 2).saturating_sub(middle.len() / 2);
+  if middle_start > left.len() && middle_start + middle.len() < line.len() - right.len() {
+    line.splice(middle_start..middle_start + middle.len(), middle.chars());
+  }
+  line.splice(0..left.len(), left.chars());
+  let right_start = line.len() - right.len();
+  line.splice(right_start.., right.chars());
+
+  line.into_iter().collect()
+}
+
+/// A value with at most two decimals, as written on an axis.
+fn label(value: f64) -> String {
+  let label = format!("{:.2}", value);
+  let label = label.trim_end_matches('0').trim_end_matches('.');
+
+  match label {
+    "-0" => "0".to_string(),
+    label => label.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod plot_spec;
@@ -0,0 +1,125 @@
+use super::*;
+use crate::settings::Limits;
+use pretty_assertions::assert_eq;
+
+fn plot(formulas: &[&str], options: &PlotOptions) -> Result<String, Error> {
+  let formulas: Vec<String> = formulas.iter().map(|formula| formula.to_string()).collect();
+  run_with(&formulas, options, &Settings::default())
+}
+
+fn ascii(x: (f64, f64), width: usize, height: usize) -> PlotOptions {
+  PlotOptions {
+    x,
+    width,
+    height,
+    style: Style::Ascii,
+    ..PlotOptions::default()
+  }
+}
+
+#[test]
+fn testing_plot_ascii() {
+  let expected = [
+    " 2 +     |  **",
+    "   |     |**",
+    " 0 +----**----",
+    "   |  ** |",
+    "-2 +**   |",
+    "   +----------",
+    "    -2   0   2",
+  ];
+
+  assert_eq!(plot(&["x"], &ascii((-2.0, 2.0), 10, 5)), Ok(expected.join("\n")));
+}
+
+#[test]
+fn testing_plot_gaps() {
+  let expected = [
+    " 2.5 +     |*",
+    "     |     | ***",
+    "   0 +**---+----*",
+    "     |  ** |",
+    "-2.5 +    *|",
+    "     +-----------",
+    "      -2   0    2",
+  ];
+
+  assert_eq!(
+    plot(&["1/x"], &ascii((-2.0, 2.0), 11, 5)),
+    Ok(expected.join("\n")),
+    "should leave x = 0 to the axis"
+  );
+}
+
+#[test]
+fn testing_plot_y_range() {
+  let options = PlotOptions {
+    y: Some((0.4, 0.6)),
+    ..ascii((-2.0, 2.0), 10, 3)
+  };
+  let expected = [
+    "0.6 +     |",
+    "    |     |   *",
+    "0.4 +     |",
+    "    +----------",
+    "     -2   0   2",
+  ];
+
+  assert_eq!(
+    plot(&["1/x"], &options),
+    Ok(expected.join("\n")),
+    "should clip the values outside the range"
+  );
+}
+
+#[test]
+fn testing_plot_series() {
+  let chart = plot(&["1", "-1"], &ascii((0.0, 1.0), 10, 3)).unwrap();
+  let lines: Vec<&str> = chart.lines().collect();
+
+  assert_eq!(lines[0], " 1 +**********");
+  assert_eq!(lines[2], "-1 +++++++++++");
+  assert_eq!(lines[5..].to_vec(), vec!["* 1", "+ -1"]);
+}
+
+#[test]
+fn testing_plot_braille() {
+  let options = PlotOptions {
+    x: (0.0, 1.0),
+    width: 10,
+    height: 2,
+    ..PlotOptions::default()
+  };
+  let expected = ["1 ┤     ⣀⡠⠔⠒⠉", "0 ┤⣀⠤⠔⠊⠉─────", "  └──────────", "   0   0.5  1"];
+
+  assert_eq!(plot(&["x"], &options), Ok(expected.join("\n")));
+}
+
+#[test]
+fn testing_plot_errors() {
+  assert_eq!(
+    plot(&["x + y"], &PlotOptions::default()),
+    Err(Error::UndefinedIdentifier("y".to_string()))
+  );
+
+  let limited = Settings {
+    limits: Limits {
+      steps: 100,
+      ..Limits::default()
+    },
+    ..Settings::default()
+  };
+  assert_eq!(
+    run_with(&["x^2".to_string()], &PlotOptions::default(), &limited),
+    Err(Error::TooManySteps(100)),
+    "should count the steps of every column against the limits"
+  );
+}
+
+#[test]
+fn testing_label() {
+  assert_eq!(label(10.0), "10");
+  assert_eq!(label(-0.2199), "-0.22");
+  assert_eq!(label(-0.001), "0");
+  assert_eq!(label(2.5), "2.5");
+}
@@ -0,0 +1,367 @@
+use crate::error::Located;
+use crate::interpreter::{run_ast_with, Environment};
+use crate::json::{self, Value};
+use crate::parser::{parse_located, parse_within};
+use crate::settings::{Angle, Floats, ImplicitMultiplication, Settings};
+use crate::tokenizer::{tokenize_with_variables, tokenize_within};
+use crate::Error;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Largest request line and headers accepted, in bytes.
+const MAX_HEAD: usize = 8 * 1024;
+
+/// Largest number of decimals a request may ask for.
+const MAX_DECIMALS: f64 = 17.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerOptions {
+  /// Largest request body accepted, in bytes
+  pub max_body: usize,
+  /// Longest a client may take to send a request, and a calculation to run
+  pub timeout: Duration,
+  /// Requests handled at once, calculations included, beyond which clients
+  /// are turned away
+  pub max_connections: usize,
+  /// Settings of the requests that do not choose their own
+  pub settings: Settings,
+}
+impl Default for ServerOptions {
+  fn default() -> ServerOptions {
+    ServerOptions {
+      max_body: 64 * 1024,
+      timeout: Duration::from_secs(5),
+      max_connections: 64,
+      settings: Settings::default(),
+    }
+  }
+}
+
+/// Serves `POST /evaluate` and `POST /parse` on `listener`, handling each
+/// connection on its own thread until the listener fails.
+pub fn serve(listener: TcpListener, options: ServerOptions) -> io::Result<()> {
+  let options = Arc::new(options);
+  let connections = Arc::new(AtomicUsize::new(0));
+
+  for stream in listener.incoming() {
+    let mut stream = stream?;
+    // the whole request has to arrive within the timeout, however slowly
+    // the client sends it
+    let deadline = Instant::now() + options.timeout;
+    if connections.fetch_add(1, Ordering::SeqCst) >= options.max_connections {
+      connections.fetch_sub(1, Ordering::SeqCst);
+      let _ = turn_away(&mut stream);
+      continue;
+    }
+
+    let options = Arc::clone(&options);
+    let connections = Arc::clone(&connections);
+    thread::spawn(move || {
+      let _ = handle(stream, &options, deadline);
+      connections.fetch_sub(1, Ordering::SeqCst);
+    });
+  }
+
+  Ok(())
+}
+
+/// Answers without waiting for the request, only dropping what has already
+/// arrived so that closing does not reset the connection.
+fn turn_away(stream: &mut TcpStream) -> io::Result<()> {
+  stream.set_nonblocking(true)?;
+  let mut chunk = [0; 4096];
+  while matches!(stream.read(&mut chunk), Ok(count) if count > 0) {}
+  stream.set_nonblocking(false)?;
+
+  write_response(stream, &error(503, "busy", "Too many requests at once"))
+}
+
+struct Request {
+  method: String,
+  path: String,
+  body: String,
+}
+
+struct Response {
+  status: u16,
+  body: String,
+}
+
+fn handle(mut stream: TcpStream, options: &ServerOptions, deadline: Instant) -> io::Result<()> {
+  stream.set_write_timeout(Some(options.timeout))?;
+
+  let response = match read_request(&mut stream, options.max_body, deadline) {
+    Ok(request) => route(request, options),
+    Err(response) => response,
+  };
+  write_response(&mut stream, &response)
+}
+
+fn read_request(stream: &mut TcpStream, max_body: usize, deadline: Instant) -> Result<Request, Response> {
+  let mut buffer: Vec<u8> = Vec::new();
+  let mut chunk = [0; 4096];
+
+  let head_end = loop {
+    if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+      break end;
+    }
+    if buffer.len() > MAX_HEAD {
+      return Err(error(431, "bad_request", "Request headers are too large"));
+    }
+    let count = read(stream, &mut chunk, deadline)?;
+    buffer.extend_from_slice(&chunk[..count]);
+  };
+
+  let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+  let mut lines = head.split("\r\n");
+  let mut request_line = lines.next().unwrap_or_default().split(' ');
+  let (method, path) = match (request_line.next(), request_line.next()) {
+    (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+    _ => return Err(error(400, "bad_request", "Malformed request line")),
+  };
+
+  let content_length = lines
+    .filter_map(|line| line.split_once(':'))
+    .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+    .map(|(_, value)| value.trim().parse::<usize>());
+  let length = match content_length {
+    Some(Ok(length)) => length,
+    Some(Err(_)) => return Err(error(400, "bad_request", "Malformed Content-Length")),
+    None if method == "POST" => return Err(error(411, "bad_request", "Content-Length is required")),
+    None => 0,
+  };
+  if length > max_body {
+    return Err(error(
+      413,
+      "payload_too_large",
+      &format!("Request body is larger than {} bytes", max_body),
+    ));
+  }
+
+  let mut body = buffer.split_off(head_end + 4);
+  while body.len() < length {
+    let count = read(stream, &mut chunk, deadline)?;
+    body.extend_from_slice(&chunk[..count]);
+  }
+  body.truncate(length);
+
+  let body = String::from_utf8(body).map_err(|_| error(400, "bad_request", "Request body is not UTF-8"))?;
+  Ok(Request { method, path, body })
+}
+
+/// Reads what the client sent so far, failing when it stops early or the
+/// `deadline` of the whole request passes, as a read timeout alone would
+/// wait that long for every byte.
+fn read(stream: &mut TcpStream, chunk: &mut [u8], deadline: Instant) -> Result<usize, Response> {
+  let timed_out = || error(408, "timeout", "Request took too long to send");
+  let left = deadline.saturating_duration_since(Instant::now());
+  if left.is_zero() {
+    return Err(timed_out());
+  }
+  if stream.set_read_timeout(Some(left)).is_err() {
+    return Err(error(400, "bad_request", "Request could not be read"));
+  }
+
+  match stream.read(chunk) {
+    Ok(0) => Err(error(400, "bad_request", "Request ended early")),
+    Ok(count) => Ok(count),
+    Err(error_kind) if matches!(error_kind.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+      Err(timed_out())
+    }
+    Err(_) => Err(error(400, "bad_request", "Request could not be read")),
+  }
+}
+
+fn route(request: Request, options: &ServerOptions) -> Response {
+  let handler: fn(&Value, &Settings) -> Result<String, Response> = match request.path.as_str() {
+    "/evaluate" => evaluate,
+    "/parse" => parse,
+    path => return error(404, "not_found", &format!("No such endpoint {}", path)),
+  };
+  if request.method != "POST" {
+    return error(405, "method_not_allowed", "Only POST is accepted");
+  }
+
+  let body = match json::parse(&request.body) {
+    Ok(body @ Value::Object(_)) => body,
+    Ok(_) => return error(400, "bad_request", "Request body should be a JSON object"),
+    Err(message) => return error(400, "bad_request", &message),
+  };
+  let mut settings = match settings(&body, &options.settings) {
+    Ok(settings) => settings,
+    Err(message) => return error(400, "bad_request", &message),
+  };
+  // the calculation stops by itself at the timeout, failing with
+  // Error::Timeout, and runs on the thread of the connection so that it
+  // counts as one of max_connections until it does
+  let time = settings
+    .limits
+    .time
+    .map_or(options.timeout, |time| time.min(options.timeout));
+  settings.limits.time = Some(time);
+
+  match handler(&body, &settings) {
+    Ok(body) => Response { status: 200, body },
+    Err(response) => response,
+  }
+}
+
+/// `{"formula": string, "variables"?: {name: number}, "decimals"?: number}`,
+/// answered as the JSON output of the command line.
+fn evaluate(body: &Value, settings: &Settings) -> Result<String, Response> {
+  let formula = formula(body)?;
+  let mut environment = Environment::new()
+    .with_floats(settings.floats)
+    .with_limits(&settings.limits);
+  match body.get("variables") {
+    None | Some(Value::Null) => {}
+    Some(Value::Object(variables)) => {
+      for (name, value) in variables {
+        match value {
+          Value::Number(value) => environment.bind(name, *value),
+          _ => {
+            return Err(error(
+              400,
+              "bad_request",
+              &format!("Variable {} should be a number", name),
+            ))
+          }
+        }
+      }
+    }
+    Some(_) => return Err(error(400, "bad_request", "variables should be an object")),
+  }
+  let decimals = match body.get("decimals") {
+    None | Some(Value::Null) => None,
+    Some(Value::Number(decimals)) if decimals.fract() == 0.0 && (0.0..=MAX_DECIMALS).contains(decimals) => {
+      Some(*decimals as usize)
+    }
+    Some(_) => return Err(error(400, "bad_request", "decimals should be an integer from 0 to 17")),
+  };
+
+  // a unit of duration named as one of the variables is that variable
+  let tokens = tokenize_within(
+    formula.clone(),
+    &settings.limits,
+    &environment.names(),
+    environment.budget(),
+  )
+  .map_err(|located| failure(&formula, &located))?;
+  let result = parse_within(Ok(tokens.clone()), settings, environment.budget())
+    .and_then(|ast| run_ast_with(ast, &environment, settings).map_err(|error| Located::named(error, &tokens)))
+    .map_err(|located| failure(&formula, &located))?;
+  let formatted = match (decimals, result.parse::<f64>()) {
+    (Some(decimals), Ok(value)) => format!("{:.*}", decimals, value),
+    _ => result,
+  };
+  Ok(json::success(&formula, &formatted))
+}
+
+/// `{"formula": string}`, answered as `{"input", "ast", "formatted"}`.
+fn parse(body: &Value, settings: &Settings) -> Result<String, Response> {
+  let formula = formula(body)?;
+  let ast = parse_located(
+    tokenize_with_variables(formula.clone(), &settings.limits, &[]),
+    settings,
+  )
+  .map_err(|located| failure(&formula, &located))?;
+
+  Ok(format!(
+    "{{\"input\":{},\"ast\":{},\"formatted\":{}}}",
+    json::string(&formula),
+    json::ast(&ast),
+    json::string(&ast.to_string())
+  ))
+}
+
+fn formula(body: &Value) -> Result<String, Response> {
+  match body.get("formula") {
+    Some(Value::String(formula)) => Ok(formula.clone()),
+    _ => Err(error(400, "bad_request", "formula should be a string")),
+  }
+}
+
+/// A formula that could not be calculated, as opposed to a malformed request,
+/// or one that took longer than the server waits for.
+fn failure(formula: &str, calculation_error: &Located) -> Response {
+  let status = match calculation_error.error {
+    Error::Timeout(_) => 503,
+    _ => 422,
+  };
+  Response {
+    status,
+    body: json::failure(formula, calculation_error),
+  }
+}
+
+fn settings(body: &Value, defaults: &Settings) -> Result<Settings, String> {
+  let mut settings = defaults.clone();
+  if let Some(mode) = option(body, "implicit_multiplication") {
+    settings.implicit_multiplication = match mode {
+      "same" => ImplicitMultiplication::SameAsExplicit,
+      "tighter" => ImplicitMultiplication::Tighter,
+      "off" => ImplicitMultiplication::Disabled,
+      _ => return Err("implicit_multiplication should be same, tighter or off".to_string()),
+    };
+  }
+  if let Some(unit) = option(body, "angle") {
+    settings.angle = Angle::parse(unit).ok_or("angle should be rad, deg or grad")?;
+  }
+  if let Some(mode) = option(body, "floats") {
+    settings.floats = match mode {
+      "strict" => Floats::Strict,
+      "ieee" => Floats::Ieee,
+      _ => return Err("floats should be strict or ieee".to_string()),
+    };
+  }
+  Ok(settings)
+}
+
+/// The text of the optional setting `name`, empty when it is not a string.
+fn option<'a>(body: &'a Value, name: &str) -> Option<&'a str> {
+  match body.get(name) {
+    None | Some(Value::Null) => None,
+    Some(Value::String(value)) => Some(value.as_str()),
+    Some(_) => Some(""),
+  }
+}
+
+fn error(status: u16, kind: &str, message: &str) -> Response {
+  Response {
+    status,
+    body: format!(
+      "{{\"error\":{{\"kind\":{},\"message\":{},\"span\":null}}}}",
+      json::string(kind),
+      json::string(message)
+    ),
+  }
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+  let reason = match response.status {
+    200 => "OK",
+    400 => "Bad Request",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    408 => "Request Timeout",
+    411 => "Length Required",
+    413 => "Payload Too Large",
+    422 => "Unprocessable Entity",
+    431 => "Request Header Fields Too Large",
+    _ => "Service Unavailable",
+  };
+
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    response.status,
+    reason,
+    response.body.len(),
+    response.body
+  )?;
+  stream.flush()
+}
@@ -0,0 +1,237 @@
+use crate::Error;
+use std::cell::Cell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How two adjacent operands, as in `2(3+4)` or `3pi`, are multiplied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImplicitMultiplication {
+  /// Adjacent operands are an error, every product needs a `*`
+  Disabled,
+  /// Same precedence as `*`, so `1/2x` is `(1/2)*x`
+  SameAsExplicit,
+  /// Binds tighter than `*` and `/`, so `1/2x` is `1/(2*x)`
+  Tighter,
+}
+
+/// The unit of the angles that trigonometric functions take and give back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Angle {
+  /// 2π to the turn
+  Radians,
+  /// 360 to the turn
+  Degrees,
+  /// 400 to the turn
+  Gradians,
+}
+impl Angle {
+  /// The unit named `rad`, `deg` or `grad`.
+  pub fn parse(name: &str) -> Option<Angle> {
+    match name {
+      "rad" => Some(Angle::Radians),
+      "deg" => Some(Angle::Degrees),
+      "grad" => Some(Angle::Gradians),
+      _ => None,
+    }
+  }
+}
+
+/// Fixed-width integer type of programmer mode, as `u8` or `i32`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IntegerType {
+  /// 8, 16, 32 or 64
+  pub bits: u32,
+  /// Two's complement when set
+  pub signed: bool,
+}
+impl IntegerType {
+  /// The type named as in Rust, from `u8` to `i64`.
+  pub fn parse(name: &str) -> Option<IntegerType> {
+    let signed = match name.get(..1) {
+      Some("u") => false,
+      Some("i") => true,
+      _ => return None,
+    };
+    match name[1..].parse() {
+      Ok(bits @ (8 | 16 | 32 | 64)) => Some(IntegerType { bits, signed }),
+      _ => None,
+    }
+  }
+
+  pub fn min(&self) -> i128 {
+    if self.signed {
+      -(1 << (self.bits - 1))
+    } else {
+      0
+    }
+  }
+
+  pub fn max(&self) -> i128 {
+    if self.signed {
+      (1 << (self.bits - 1)) - 1
+    } else {
+      (1 << self.bits) - 1
+    }
+  }
+}
+impl fmt::Display for IntegerType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", if self.signed { "i" } else { "u" }, self.bits)
+  }
+}
+
+/// What a result outside the range of the integer type becomes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+  /// Keeps the low bits, as the hardware does
+  Wrap,
+  /// Fails with `Error::Overflow`
+  Error,
+}
+
+/// What float arithmetic does with a result an f64 cannot hold.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Floats {
+  /// Fails with `Error::Overflow`, `Error::Underflow` or `Error::NotANumber`
+  #[default]
+  Strict,
+  /// Goes on with inf, 0 and nan as IEEE 754 has it, which are then
+  /// constants of formulas too
+  Ieee,
+}
+
+/// Integer arithmetic with bitwise operators, where `^` is xor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Programmer {
+  pub integer: IntegerType,
+  pub overflow: Overflow,
+}
+
+/// Bounds on the work a formula may cause, so that one from an untrusted
+/// source fails with an error rather than exhausting the stack or the CPU.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+  /// Characters of the formula, or of each statement of a script
+  pub length: usize,
+  /// Tokens of the formula, not counting spaces
+  pub tokens: usize,
+  /// Levels of brackets, calls and operators nested in one another, where
+  /// the operations of a chain as `1+2+3` are a single level
+  pub depth: usize,
+  /// Nodes and instructions evaluated, counting each term of sum, prod and
+  /// integrate
+  pub steps: u64,
+  /// Time an evaluation may run for, unbounded when not set
+  pub time: Option<Duration>,
+}
+impl Default for Limits {
+  fn default() -> Limits {
+    Limits {
+      length: 64 * 1024,
+      tokens: 16 * 1024,
+      depth: 128,
+      steps: 100_000_000,
+      time: None,
+    }
+  }
+}
+
+/// Steps between two readings of the clock, which costs more than a step.
+const STEPS_PER_CLOCK: u64 = 1024;
+
+/// The steps an evaluation has taken, checked against those and against the
+/// time its limits allow, from tokenizing the formula on.
+#[derive(Debug, PartialEq)]
+pub struct Budget {
+  steps: u64,
+  /// When the evaluation started and for how long it may run
+  deadline: Option<(Instant, Duration)>,
+  spent: Cell<u64>,
+  /// Tokens and operands read before the evaluation, which are no steps
+  read: Cell<u64>,
+}
+impl Budget {
+  pub fn new(limits: &Limits) -> Budget {
+    Budget {
+      steps: limits.steps,
+      deadline: limits.time.map(|time| (Instant::now(), time)),
+      spent: Cell::new(0),
+      read: Cell::new(0),
+    }
+  }
+
+  /// Takes `steps` more, failing once there are more of them than allowed
+  /// or the time is up.
+  pub fn spend(&self, steps: u64) -> Result<(), Error> {
+    let before = self.spent.get();
+    let spent = before.saturating_add(steps);
+    self.spent.set(spent);
+
+    if spent > self.steps {
+      return Err(Error::TooManySteps(self.steps));
+    }
+    if spent / STEPS_PER_CLOCK != before / STEPS_PER_CLOCK {
+      return self.within_time();
+    }
+    Ok(())
+  }
+
+  /// Counts a token or an operand read, failing once the time is up as
+  /// `spend` does, but leaving the steps to the evaluation.
+  pub fn read(&self) -> Result<(), Error> {
+    let read = self.read.get() + 1;
+    self.read.set(read);
+
+    if read.is_multiple_of(STEPS_PER_CLOCK) {
+      return self.within_time();
+    }
+    Ok(())
+  }
+
+  fn within_time(&self) -> Result<(), Error> {
+    match self.deadline {
+      Some((start, time)) if start.elapsed() > time => Err(Error::Timeout(time)),
+      _ => Ok(()),
+    }
+  }
+}
+impl Default for Budget {
+  fn default() -> Budget {
+    Budget {
+      steps: u64::MAX,
+      deadline: None,
+      spent: Cell::new(0),
+      read: Cell::new(0),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+  pub implicit_multiplication: ImplicitMultiplication,
+  /// Evaluates over fixed-width integers instead of floats when set
+  pub programmer: Option<Programmer>,
+  pub angle: Angle,
+  pub floats: Floats,
+  pub limits: Limits,
+}
+impl Settings {
+  /// Settings that only accept the explicit notation.
+  pub fn strict() -> Settings {
+    Settings {
+      implicit_multiplication: ImplicitMultiplication::Disabled,
+      ..Settings::default()
+    }
+  }
+}
+impl Default for Settings {
+  fn default() -> Settings {
+    Settings {
+      implicit_multiplication: ImplicitMultiplication::SameAsExplicit,
+      programmer: None,
+      angle: Angle::Radians,
+      floats: Floats::Strict,
+      limits: Limits::default(),
+    }
+  }
+}
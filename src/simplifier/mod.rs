@@ -0,0 +1,678 @@
+use crate::error::Located;
+use crate::interpreter::{calendar, evaluate_node, Environment};
+use crate::parser::{parse_within, Ast, Node};
+use crate::settings::{Floats, Settings};
+use crate::tokenizer::{tokenize_within, Key, Kind, Token};
+use crate::Error;
+use std::cmp::Ordering;
+
+/// Integers past 2^53 are left to the exact evaluation of the interpreter.
+const EXACT_LIMIT: f64 = 9_007_199_254_740_992.0;
+
+/// Simplifies `formula`, printing the result.
+pub fn run_with(formula: String, settings: &Settings) -> Result<String, Error> {
+  run_located(formula, settings).map_err(Error::from)
+}
+
+/// Simplifies `formula` as `run_with` does, failing with the part of the
+/// formula the error is about.
+pub fn run_located(formula: String, settings: &Settings) -> Result<String, Located> {
+  let environment = Environment::new()
+    .with_floats(settings.floats)
+    .with_limits(&settings.limits);
+  let tokens = tokenize_within(formula, &settings.limits, &[], environment.budget());
+  let ast = parse_within(tokens, settings, environment.budget())?;
+  Ok(simplify_in(&ast, &environment).to_string())
+}
+
+/// Folds constant subtrees, drops neutral elements and combines like terms.
+///
+/// The result evaluates to the same value as `ast` wherever both are defined,
+/// so it can be simplified once and evaluated many times. Cancelling a
+/// variable widens the domain: `x/x` becomes 1, and `0/x` and `x*0` become 0,
+/// even where `x` is 0 or undefined. Constant subtrees that fail to evaluate,
+/// as in `0*(1/0)`, are kept so the error still reaches the interpreter.
+/// Operations on matrices, dates and durations are kept as written, as the
+/// products of matrices do not commute and the terms of dates do not either.
+pub fn simplify(ast: &Ast) -> Ast {
+  simplify_in(ast, &Environment::new())
+}
+
+/// Simplifies `ast` as `simplify` does, folding constants with the floats and
+/// the limits of `environment`. A value that may not be finite is never
+/// cancelled, as `inf*0` is nan: neither `inf` nor a number too large to fold
+/// is, and under IEEE floats neither is a variable, which may be bound to
+/// inf or nan, so `x*0` is kept there.
+pub fn simplify_in(ast: &Ast, environment: &Environment) -> Ast {
+  match expand_ast(ast, environment).into_node() {
+    Node::Ast(ast) => *ast,
+    node => Ast::new(
+      Ast::new_operator(Key::Addition),
+      Ast::new_number(vec![Key::Zero]),
+      Some(node),
+    ),
+  }
+}
+
+fn expand_ast(ast: &Ast, environment: &Environment) -> Sum {
+  // along the left operands of a chain in a loop, as 1+2+3 is (1+2)+3
+  let spine = ast.left_spine();
+  let a = spine[0].operand_a.as_ref().map(|a| expand(a, environment));
+  let innermost = expand_operation(spine[0], a, environment);
+  spine[1..]
+    .iter()
+    .fold(innermost, |a, ast| expand_operation(ast, Some(a), environment))
+}
+
+/// The operation of `ast` on `a`, the expansion of its left operand.
+fn expand_operation(ast: &Ast, a: Option<Sum>, environment: &Environment) -> Sum {
+  let (operator, a, b) = match (&ast.operator, a, &ast.operand_b) {
+    (Some(operator), Some(a), Some(b)) => (&operator.keys[0], a, b),
+    _ => return Sum::symbol(Node::Ast(Box::new(ast.clone()))),
+  };
+  // a product of matrices depends on the order of its factors, and a date
+  // on the order and the sign of the terms around it
+  if a.is_opaque() || is_opaque(b) {
+    return Sum::symbol(binary(operator.clone(), a.into_node(), expand_operand(b, environment)));
+  }
+
+  let b_sum = || expand(b, environment);
+  match (operator, b) {
+    // a ± b% is a ± a*b/100
+    (Key::Addition, Node::Percentage(_)) => a.clone().add(a.mul(b_sum(), environment), environment),
+    (Key::Subtraction, Node::Percentage(_)) => a.clone().add(a.mul(b_sum(), environment).neg(), environment),
+    (Key::Addition, _) => a.add(b_sum(), environment),
+    (Key::Subtraction, _) => a.add(b_sum().neg(), environment),
+    (Key::Multiplication, _) | (Key::Of, _) => a.mul(b_sum(), environment),
+    (Key::Division, _) => a.div(b_sum(), environment),
+    (Key::Exponentiation, _) => a.pow(b_sum(), environment),
+    _ => Sum::symbol(Node::Ast(Box::new(ast.clone()))),
+  }
+}
+
+/// `node` simplified on its own, where a percentage stays one.
+fn expand_operand(node: &Node, environment: &Environment) -> Node {
+  match node {
+    Node::Percentage(operand) => Node::Percentage(Box::new(expand(operand, environment).into_node())),
+    _ => expand(node, environment).into_node(),
+  }
+}
+
+/// Whether `node` holds a value that is not a number, as a matrix, a date
+/// or a duration, whose operations are kept as written.
+fn is_opaque(node: &Node) -> bool {
+  match node {
+    Node::Ast(ast) => ast.spine_operands().into_iter().any(is_opaque),
+    Node::Call(function, arguments) => {
+      calendar::FUNCTIONS.contains(&function.to_string().as_str()) || arguments.iter().any(is_opaque)
+    }
+    Node::Factorial(operand) | Node::Percentage(operand) => is_opaque(operand),
+    Node::Interval(lower, upper) => is_opaque(lower) || is_opaque(upper),
+    Node::Matrix(_) => true,
+    Node::Token(token) => matches!(token.kind, Kind::Date | Kind::Duration),
+  }
+}
+
+fn expand(node: &Node, environment: &Environment) -> Sum {
+  let expand_node = |node: &Node| expand(node, environment).into_node();
+  match node {
+    Node::Ast(ast) => expand_ast(ast, environment),
+    Node::Call(function, arguments) => {
+      let arguments: Vec<Sum> = arguments.iter().map(|argument| expand(argument, environment)).collect();
+      if let Some(exponent) = log_of_e(function, &arguments, environment) {
+        return Sum::constant(exponent);
+      }
+      let constant = arguments.iter().all(|argument| argument.as_constant().is_some());
+      let call = Node::Call(function.clone(), arguments.into_iter().map(Sum::into_node).collect());
+      if constant {
+        fold(call, environment)
+      } else {
+        unknown(call, environment)
+      }
+    }
+    Node::Factorial(operand) => {
+      let operand = expand(operand, environment);
+      let constant = operand.as_constant().is_some();
+      let factorial = Node::Factorial(Box::new(operand.into_node()));
+      if constant {
+        fold(factorial, environment)
+      } else {
+        unknown(factorial, environment)
+      }
+    }
+    Node::Percentage(operand) => expand(operand, environment).div(Sum::constant(Ratio::integer(100.0)), environment),
+    Node::Interval(lower, upper) => unknown(
+      Node::Interval(Box::new(expand_node(lower)), Box::new(expand_node(upper))),
+      environment,
+    ),
+    Node::Matrix(rows) => {
+      let rows = rows.iter().map(|row| row.iter().map(expand_node).collect()).collect();
+      Sum::symbol(Node::Matrix(rows))
+    }
+    // by the interpreter, which rejects 1e-400 as strict floats do
+    Node::Token(token) if token.kind == Kind::Number => fold(node.clone(), environment),
+    // a constant as pi stays named, where a variable is left unbound
+    Node::Token(token) if token.kind == Kind::Identifier => match evaluate_node(Some(node), environment) {
+      Ok(value) if value.is_finite() => Sum::symbol(node.clone()),
+      Err(Error::UndefinedIdentifier(_)) => unknown(node.clone(), environment),
+      _ => Sum::fragile(node.clone()),
+    },
+    Node::Token(_) => Sum::symbol(node.clone()),
+  }
+}
+
+/// `r` for `ln(e^r)`, as in `ln(e)` or `ln(e^2)`, exact where folding the
+/// value of the call would round it, unless `e` is bound to another value.
+fn log_of_e(function: &Token, arguments: &[Sum], environment: &Environment) -> Option<Ratio> {
+  let argument = match arguments {
+    [argument] if function.to_string() == "ln" && environment.get("e").is_none() => argument,
+    _ => return None,
+  };
+  match argument.terms.as_slice() {
+    [term] if term.coefficient.is_one() => match term.factors.as_slice() {
+      [factor] if factor.key == "e" => Some(factor.exponent),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+/// Replaces a node whose operands are constants by its value, unless the
+/// interpreter rejects it or the value is not finite or would lose precision.
+fn fold(node: Node, environment: &Environment) -> Sum {
+  match evaluate_node(Some(&node), environment) {
+    Ok(value) => match Ratio::from(value) {
+      Some(value) => Sum::constant(value),
+      // kept as written, so products of it are not known to be finite
+      None => Sum::fragile(node),
+    },
+    Err(_) => Sum::fragile(node),
+  }
+}
+
+/// `node`, whose value depends on variables, which IEEE floats may bind to
+/// inf or nan.
+fn unknown(node: Node, environment: &Environment) -> Sum {
+  match environment.floats() {
+    Floats::Strict => Sum::symbol(node),
+    Floats::Ieee => Sum::fragile(node),
+  }
+}
+
+/// `a` and `b` under `key` as written, where the operation failed to
+/// simplify, folding it when both are constants.
+fn keep(key: Key, a: Sum, b: Sum, environment: &Environment) -> Sum {
+  let constant = a.as_constant().is_some() && b.as_constant().is_some();
+  let fragile = a.is_fragile() || b.is_fragile();
+  let node = binary(key, a.into_node(), b.into_node());
+  match (constant, fragile) {
+    (true, _) => fold(node, environment),
+    (false, true) => Sum::fragile(node),
+    (false, false) => Sum::symbol(node),
+  }
+}
+
+/// A number kept as an exact fraction of integers while it fits, falling back
+/// to its floating point value (with denominator 1) otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Ratio {
+  numerator: f64,
+  denominator: f64,
+}
+impl Ratio {
+  fn new(numerator: f64, denominator: f64) -> Option<Ratio> {
+    let is_exact = |value: f64| value.fract() == 0.0 && value.abs() < EXACT_LIMIT;
+
+    if denominator == 0.0 {
+      return None;
+    }
+    if is_exact(numerator) && is_exact(denominator) {
+      let divisor = gcd(numerator.abs(), denominator.abs()) * denominator.signum();
+      return Some(Ratio {
+        numerator: numerator / divisor,
+        denominator: denominator / divisor,
+      });
+    }
+
+    let value = numerator / denominator;
+    if !value.is_finite() || (value.fract() == 0.0 && value.abs() >= EXACT_LIMIT) {
+      return None;
+    }
+    Some(Ratio {
+      numerator: value,
+      denominator: 1.0,
+    })
+  }
+
+  fn from(value: f64) -> Option<Ratio> {
+    Ratio::new(value, 1.0)
+  }
+
+  fn integer(value: f64) -> Ratio {
+    Ratio {
+      numerator: value,
+      denominator: 1.0,
+    }
+  }
+
+  fn value(&self) -> f64 {
+    self.numerator / self.denominator
+  }
+
+  fn is_zero(&self) -> bool {
+    self.numerator == 0.0
+  }
+
+  fn is_one(&self) -> bool {
+    self.numerator == 1.0 && self.denominator == 1.0
+  }
+
+  fn is_integer(&self) -> bool {
+    self.denominator == 1.0 && self.numerator.fract() == 0.0
+  }
+
+  fn neg(self) -> Ratio {
+    Ratio {
+      numerator: -self.numerator,
+      denominator: self.denominator,
+    }
+  }
+
+  fn add(self, other: Ratio) -> Option<Ratio> {
+    Ratio::new(
+      self.numerator * other.denominator + other.numerator * self.denominator,
+      self.denominator * other.denominator,
+    )
+  }
+
+  fn mul(self, other: Ratio) -> Option<Ratio> {
+    Ratio::new(self.numerator * other.numerator, self.denominator * other.denominator)
+  }
+
+  fn recip(self) -> Option<Ratio> {
+    Ratio::new(self.denominator, self.numerator)
+  }
+
+  fn pow(self, exponent: Ratio) -> Option<Ratio> {
+    if !exponent.is_integer() {
+      return Ratio::from(self.value().powf(exponent.value()));
+    }
+    if exponent.numerator.abs() > 1024.0 {
+      return None;
+    }
+
+    let power = exponent.numerator.abs() as i32;
+    let ratio = Ratio::new(self.numerator.powi(power), self.denominator.powi(power))?;
+    if exponent.numerator < 0.0 {
+      ratio.recip()
+    } else {
+      Some(ratio)
+    }
+  }
+}
+
+fn gcd(a: f64, b: f64) -> f64 {
+  if b == 0.0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
+
+/// `base^exponent` inside a product, where a fragile base is a constant the
+/// interpreter rejects or a value that may not be finite, and so must never
+/// be cancelled, and an opaque base is one that `is_opaque`.
+#[derive(Clone, Debug)]
+struct Factor {
+  base: Node,
+  exponent: Ratio,
+  key: String,
+  fragile: bool,
+  opaque: bool,
+}
+impl Factor {
+  fn new(base: Node, exponent: Ratio) -> Factor {
+    let key = base.to_string();
+    let opaque = is_opaque(&base);
+    Factor {
+      base,
+      exponent,
+      key,
+      fragile: false,
+      opaque,
+    }
+  }
+
+  // names come before bracketed factors, as in 2*x*(x + 1)
+  fn compare(&self, other: &Factor) -> Ordering {
+    let is_composite = |factor: &Factor| !matches!(factor.base, Node::Token(_));
+    is_composite(self)
+      .cmp(&is_composite(other))
+      .then_with(|| self.key.cmp(&other.key))
+  }
+}
+
+/// `coefficient * factors`, with factors sorted and of distinct bases.
+#[derive(Clone, Debug)]
+struct Term {
+  coefficient: Ratio,
+  factors: Vec<Factor>,
+}
+impl Term {
+  fn key(&self) -> String {
+    let factors: Vec<String> = self
+      .factors
+      .iter()
+      .map(|factor| format!("{}^{}", factor.key, factor.exponent.value()))
+      .collect();
+    factors.join("*")
+  }
+
+  fn is_fragile(&self) -> bool {
+    self.factors.iter().any(|factor| factor.fragile)
+  }
+
+  fn degree(&self) -> f64 {
+    self.factors.iter().map(|factor| factor.exponent.value()).sum()
+  }
+
+  // higher degrees first and constants last, as in x^2 + 2*x + 1
+  fn compare(&self, other: &Term) -> Ordering {
+    self
+      .factors
+      .is_empty()
+      .cmp(&other.factors.is_empty())
+      .then_with(|| other.degree().partial_cmp(&self.degree()).unwrap_or(Ordering::Equal))
+      .then_with(|| self.key().cmp(&other.key()))
+  }
+
+  fn mul(self, other: Term) -> Option<Term> {
+    let coefficient = self.coefficient.mul(other.coefficient)?;
+    let mut factors: Vec<Factor> = Vec::new();
+
+    for factor in self.factors.into_iter().chain(other.factors) {
+      let like = factors.iter().position(|other| other.key == factor.key);
+      let exponent = like.and_then(|index| Some((index, factors[index].exponent.add(factor.exponent)?)));
+      match exponent {
+        // x/x is nan where x is inf, so a fragile base is not divided out
+        Some((_, exponent)) if exponent.is_zero() && factor.fragile => factors.push(factor),
+        Some((index, exponent)) => factors[index].exponent = exponent,
+        None if like.is_some() => return None,
+        None => factors.push(factor),
+      }
+    }
+    factors.retain(|factor| factor.fragile || !factor.exponent.is_zero());
+    factors.sort_by(Factor::compare);
+
+    Some(Term { coefficient, factors })
+  }
+
+  fn pow(self, exponent: Ratio) -> Option<Term> {
+    let coefficient = self.coefficient.pow(exponent)?;
+    let factors = self
+      .factors
+      .into_iter()
+      .map(|factor| {
+        Some(Factor {
+          exponent: factor.exponent.mul(exponent)?,
+          ..factor
+        })
+      })
+      .collect::<Option<Vec<Factor>>>()?;
+
+    Some(Term { coefficient, factors })
+  }
+
+  /// Whether the term is negative, and the node of its absolute value.
+  fn into_node(self) -> (bool, Node) {
+    let negative = self.coefficient.numerator < 0.0;
+    let magnitude = self.coefficient.numerator.abs();
+    let (numerator, denominator): (Vec<Factor>, Vec<Factor>) = self
+      .factors
+      .into_iter()
+      .partition(|factor| factor.exponent.value() >= 0.0);
+
+    let mut over: Vec<Node> = numerator.into_iter().map(factor_node).collect();
+    if magnitude != 1.0 || over.is_empty() {
+      over.insert(0, number(magnitude));
+    }
+    let mut under: Vec<Node> = denominator
+      .into_iter()
+      .map(|factor| {
+        factor_node(Factor {
+          exponent: factor.exponent.neg(),
+          ..factor
+        })
+      })
+      .collect();
+    if self.coefficient.denominator != 1.0 {
+      under.insert(0, number(self.coefficient.denominator));
+    }
+
+    let node = if under.is_empty() {
+      product(over)
+    } else {
+      binary(Key::Division, product(over), product(under))
+    };
+    (negative, node)
+  }
+}
+
+/// A sum of terms, with like terms combined and sorted.
+#[derive(Clone, Debug)]
+struct Sum {
+  terms: Vec<Term>,
+}
+impl Sum {
+  fn new(terms: Vec<Term>) -> Sum {
+    let mut combined: Vec<Term> = Vec::new();
+
+    for term in terms {
+      let key = term.key();
+      let like = combined.iter().position(|other| other.key() == key);
+      match like.and_then(|index| Some((index, combined[index].coefficient.add(term.coefficient)?))) {
+        // x - x is nan where x is inf, so a fragile term is not cancelled
+        Some((_, coefficient)) if coefficient.is_zero() && term.is_fragile() => combined.push(term),
+        Some((index, coefficient)) => combined[index].coefficient = coefficient,
+        None => combined.push(term),
+      }
+    }
+    combined.retain(|term| term.is_fragile() || !term.coefficient.is_zero());
+    combined.sort_by(Term::compare);
+
+    Sum { terms: combined }
+  }
+
+  fn constant(value: Ratio) -> Sum {
+    Sum::new(vec![Term {
+      coefficient: value,
+      factors: vec![],
+    }])
+  }
+
+  fn symbol(node: Node) -> Sum {
+    Sum::new(vec![Term {
+      coefficient: Ratio::integer(1.0),
+      factors: vec![Factor::new(node, Ratio::integer(1.0))],
+    }])
+  }
+
+  fn fragile(node: Node) -> Sum {
+    let factor = Factor {
+      fragile: true,
+      ..Factor::new(node, Ratio::integer(1.0))
+    };
+    Sum::new(vec![Term {
+      coefficient: Ratio::integer(1.0),
+      factors: vec![factor],
+    }])
+  }
+
+  fn is_fragile(&self) -> bool {
+    self.terms.iter().any(Term::is_fragile)
+  }
+
+  fn is_opaque(&self) -> bool {
+    self
+      .terms
+      .iter()
+      .any(|term| term.factors.iter().any(|factor| factor.opaque))
+  }
+
+  fn as_constant(&self) -> Option<Ratio> {
+    match self.terms.as_slice() {
+      [] => Some(Ratio::integer(0.0)),
+      [term] if term.factors.is_empty() => Some(term.coefficient),
+      _ => None,
+    }
+  }
+
+  /// The sum as a single term, bracketing it when it has several.
+  fn into_term(self) -> Term {
+    match self.terms.len() {
+      1 => self.terms.into_iter().next().unwrap(),
+      _ => Term {
+        coefficient: Ratio::integer(1.0),
+        factors: vec![self.into_factor(Ratio::integer(1.0))],
+      },
+    }
+  }
+
+  /// The sum bracketed as the base of a factor, as fragile as its terms.
+  fn into_factor(self, exponent: Ratio) -> Factor {
+    Factor {
+      fragile: self.is_fragile(),
+      ..Factor::new(self.into_node(), exponent)
+    }
+  }
+
+  fn add(self, other: Sum, environment: &Environment) -> Sum {
+    // constants too large to add exactly are added by the interpreter
+    if let (Some(a), Some(b)) = (self.as_constant(), other.as_constant()) {
+      if a.add(b).is_none() {
+        return keep(Key::Addition, self, other, environment);
+      }
+    }
+    Sum::new(self.terms.into_iter().chain(other.terms).collect())
+  }
+
+  fn neg(self) -> Sum {
+    let terms = self
+      .terms
+      .into_iter()
+      .map(|term| Term {
+        coefficient: term.coefficient.neg(),
+        factors: term.factors,
+      })
+      .collect();
+    Sum::new(terms)
+  }
+
+  fn scale(&self, value: Ratio) -> Option<Sum> {
+    let terms = self
+      .terms
+      .iter()
+      .map(|term| {
+        Some(Term {
+          coefficient: term.coefficient.mul(value)?,
+          factors: term.factors.clone(),
+        })
+      })
+      .collect::<Option<Vec<Term>>>()?;
+    Some(Sum::new(terms))
+  }
+
+  fn mul(self, other: Sum, environment: &Environment) -> Sum {
+    let fragile = self.is_fragile() || other.is_fragile();
+    let product = match (self.as_constant(), other.as_constant()) {
+      // 0 times a value that may not be finite is not known to be 0
+      (Some(value), _) | (_, Some(value)) if value.is_zero() && fragile => None,
+      (Some(value), _) => other.scale(value),
+      (_, Some(value)) => self.scale(value),
+      _ => {
+        let term = self.clone().into_term().mul(other.clone().into_term());
+        term.map(|term| Sum::new(vec![term]))
+      }
+    };
+    product.unwrap_or_else(|| keep(Key::Multiplication, self, other, environment))
+  }
+
+  fn div(self, other: Sum, environment: &Environment) -> Sum {
+    let quotient = match other.as_constant() {
+      Some(value) if value.is_zero() => {
+        return Sum::fragile(binary(Key::Division, self.into_node(), other.into_node()))
+      }
+      Some(value) => value.recip().and_then(|value| self.scale(value)),
+      // 0/x is nan where x is inf or nan
+      None if other.is_fragile() && self.as_constant().is_some_and(|value| value.is_zero()) => None,
+      None => {
+        let inverse = other.clone().into_term().pow(Ratio::integer(-1.0));
+        inverse.map(|inverse| self.clone().mul(Sum::new(vec![inverse]), environment))
+      }
+    };
+    quotient.unwrap_or_else(|| keep(Key::Division, self, other, environment))
+  }
+
+  fn pow(self, exponent: Sum, environment: &Environment) -> Sum {
+    let power = match (self.as_constant(), exponent.as_constant()) {
+      (Some(base), Some(value)) => base.pow(value).map(Sum::constant),
+      (None, Some(exponent)) if exponent.is_zero() && !self.is_fragile() => Some(Sum::constant(Ratio::integer(1.0))),
+      (None, Some(exponent)) if exponent.is_integer() && self.terms.len() == 1 => {
+        let term = self.clone().into_term().pow(exponent);
+        term.map(|term| Sum::new(vec![term]))
+      }
+      (None, Some(exponent)) => Some(Sum::new(vec![Term {
+        coefficient: Ratio::integer(1.0),
+        factors: vec![self.clone().into_factor(exponent)],
+      }])),
+      _ => None,
+    };
+    power.unwrap_or_else(|| keep(Key::Exponentiation, self, exponent, environment))
+  }
+
+  fn into_node(self) -> Node {
+    let mut terms = self.terms.into_iter().map(Term::into_node);
+
+    let mut sum = match terms.next() {
+      Some((true, node)) => binary(Key::Subtraction, number(0.0), node),
+      Some((false, node)) => node,
+      None => return number(0.0),
+    };
+    for (negative, node) in terms {
+      let key = if negative { Key::Subtraction } else { Key::Addition };
+      sum = binary(key, sum, node);
+    }
+    sum
+  }
+}
+
+fn factor_node(factor: Factor) -> Node {
+  let exponent = factor.exponent;
+
+  if exponent.is_one() {
+    return factor.base;
+  }
+  let exponent = if exponent.denominator == 1.0 {
+    number(exponent.numerator)
+  } else {
+    binary(Key::Division, number(exponent.numerator), number(exponent.denominator))
+  };
+  binary(Key::Exponentiation, factor.base, exponent)
+}
+
+fn product(nodes: Vec<Node>) -> Node {
+  let mut nodes = nodes.into_iter();
+  let first = nodes.next().unwrap_or_else(|| number(1.0));
+  nodes.fold(first, |product, node| binary(Key::Multiplication, product, node))
+}
+
+fn number(value: f64) -> Node {
+  Node::Token(Token::new_float(value))
+}
+
+fn binary(key: Key, a: Node, b: Node) -> Node {
+  Node::Ast(Box::new(Ast::new(Ast::new_operator(key), Some(a), Some(b))))
+}
+
+#[cfg(test)]
+mod simplifier_spec;
@@ -0,0 +1,204 @@
+use super::*;
+use crate::interpreter::evaluate;
+use crate::parser::parse_with;
+use crate::tokenizer::tokenize;
+use crate::Calc;
+use pretty_assertions::assert_eq;
+
+fn s(formula: &str) -> String {
+  run_with(formula.to_string(), &Settings::default()).unwrap()
+}
+
+fn parse(formula: &str) -> Ast {
+  parse_with(tokenize(formula.to_string()), &Settings::default()).unwrap()
+}
+
+#[test]
+fn testing_simplify_identities() {
+  assert_eq!(s("x*1+0"), "x");
+  assert_eq!(s("0+x"), "x");
+  assert_eq!(s("x*0"), "0");
+  assert_eq!(s("x-x"), "0");
+  assert_eq!(s("x/x"), "1");
+  assert_eq!(s("x^1"), "x");
+  assert_eq!(s("x^0"), "1");
+  assert_eq!(s("--x"), "x");
+}
+
+#[test]
+fn testing_simplify_constant_folding() {
+  assert_eq!(s("(2*3)*x"), "6*x");
+  assert_eq!(s("1/2+1/3"), "5/6", "should keep fractions exact");
+  assert_eq!(s("2^10"), "1024");
+  assert_eq!(s("sqrt(16)*x"), "4*x");
+  assert_eq!(s("3!"), "6");
+  assert_eq!(s("200 + 15%"), "230");
+  assert_eq!(s("1/0"), "1/0", "should leave the error to the interpreter");
+  assert_eq!(s("25!"), "25!", "should leave big integers to the exact evaluation");
+  assert_eq!(s("ln(e)*x"), "x");
+  assert_eq!(
+    s("ln(e^2) + ln(e^(1/2))"),
+    "5/2",
+    "should keep the logarithms of powers of e exact"
+  );
+  assert_eq!(s("ln(2*e)"), "ln(2*e)");
+}
+
+#[test]
+fn testing_simplify_keeps_domain_errors() {
+  assert_eq!(s("0*(1/0)"), "0*(1/0)");
+  assert_eq!(s("x*0 + 1/0"), "1/0");
+  assert_eq!(s("sqrt(-1)*0"), "sqrt(-1)*0");
+  assert_eq!(s("1/0 - 1/0"), "1/0 - 1/0");
+  assert_eq!(s("(1/0)^0"), "(1/0)^0");
+  assert_eq!(s("1/(x-x)"), "1/0");
+
+  for formula in ["0*(1/0)", "x*0 + 1/0", "ln(0)*0", "(1/0)/(1/0)"].iter() {
+    let simplified = simplify(&parse(formula));
+    assert!(
+      evaluate(&simplified, &Environment::new().with("x", 1.0)).is_err(),
+      "{} should still fail once simplified to {}",
+      formula,
+      simplified
+    );
+  }
+}
+
+#[test]
+fn testing_simplify_keeps_values_that_may_not_be_finite() {
+  let ieee = Settings {
+    floats: Floats::Ieee,
+    ..Settings::default()
+  };
+  let s_ieee = |formula: &str| run_with(formula.to_string(), &ieee).unwrap();
+
+  assert_eq!(s("inf*0"), "inf*0");
+  assert_eq!(s("x*0 + inf*0"), "inf*0");
+  assert_eq!(s("1e308*10*0"), "10*1e308*0");
+  assert_eq!(s_ieee("nan*0"), "nan*0");
+  assert_eq!(s_ieee("x*0"), "x*0");
+  assert_eq!(s_ieee("x - x"), "x - x");
+  assert_eq!(s_ieee("x/x"), "x/x");
+  assert_eq!(s_ieee("0/x"), "0/x");
+  assert_eq!(s_ieee("x*1 + 2*x"), "3*x");
+
+  for formula in ["inf*0", "x*0 + inf", "nan - nan", "(1e308 + 1e308)*0", "1e-400*0"].iter() {
+    let simplified = simplify_in(&parse(formula), &Environment::new());
+    assert!(
+      evaluate(&simplified, &Environment::new().with("x", 1.0)).is_err(),
+      "{} should still fail under strict floats once simplified to {}",
+      formula,
+      simplified
+    );
+  }
+  let environment = Environment::new().with_floats(Floats::Ieee);
+  for (formula, x) in [("x*0", f64::INFINITY), ("x - x", f64::NAN), ("x/x", f64::INFINITY)].iter() {
+    let simplified = simplify_in(&parse(formula), &environment);
+    let value = evaluate(&simplified, &environment.clone().with("x", *x)).unwrap();
+    assert!(
+      value.is_nan(),
+      "{} should still be nan once simplified to {}",
+      formula,
+      simplified
+    );
+  }
+}
+
+#[test]
+fn testing_simplify_like_terms() {
+  assert_eq!(s("2x+3x"), "5*x");
+  assert_eq!(s("x*x*x"), "x^3");
+  assert_eq!(s("y*x+x*y"), "2*x*y");
+  assert_eq!(s("2(x+1) - 2x"), "2");
+  assert_eq!(s("sin(x)*2 + sin(x)"), "3*sin(x)");
+  assert_eq!(s("(x+1)*(x+1)"), "(x + 1)^2");
+  assert_eq!(s("x/(2y)"), "x/(2*y)");
+}
+
+#[test]
+fn testing_simplify_long_chains() {
+  assert_eq!(s(&vec!["x"; 5000].join(" + ")), "5000*x");
+  assert_eq!(s(&format!("{} - x", vec!["x*2"; 2000].join(" + "))), "3999*x");
+}
+
+#[test]
+fn testing_simplify_ordering() {
+  assert_eq!(s("x*2"), "2*x");
+  assert_eq!(s("1+x+x^2+2x+3"), "x^2 + 3*x + 4");
+  assert_eq!(s("2-x"), "-x + 2");
+  assert_eq!(s("(x+1)*x*2"), "2*x*(x + 1)");
+}
+
+#[test]
+fn testing_simplify_keeps_values() {
+  let formulas = [
+    "x*1+0",
+    "(2*3)*x - x/4",
+    "(x+1)^2*(x+1)/(2x)",
+    "x + 10%",
+    "2^x*2^x + sin(x)^2",
+    "x^0.5*x^0.5 + 1/(x*x)",
+    "3x! - (x+1)! / (x+1)",
+  ];
+
+  for formula in formulas.iter() {
+    let ast = parse(formula);
+    let simplified = simplify(&ast);
+    for x in [0.5, 1.0, 2.5, 7.0].iter() {
+      let environment = Environment::new().with("x", *x);
+      let expected = evaluate(&ast, &environment).unwrap();
+      let actual = evaluate(&simplified, &environment).unwrap();
+      assert!(
+        (expected - actual).abs() <= 1e-9 * expected.abs().max(1.0),
+        "{} at x = {}: {} != {}",
+        formula,
+        x,
+        expected,
+        actual
+      );
+    }
+  }
+}
+
+#[test]
+fn testing_simplify_keeps_matrix_products() {
+  assert_eq!(s("[1,2;3,4]*[0,1;1,0]"), "[1, 2; 3, 4]*[0, 1; 1, 0]");
+  assert_eq!(s("[0,1;1,0]*(2*3)*[1,2;3,4]"), "[0, 1; 1, 0]*6*[1, 2; 3, 4]");
+
+  for formula in ["[1,2;3,4]*[0,1;1,0]", "[0,1;1,0]*2*[1,2;3,4] - [1,1;1,1]*0"].iter() {
+    let simplified = s(formula);
+    assert_eq!(
+      Calc::calculate(simplified.clone()),
+      Calc::calculate(formula.to_string()),
+      "{} simplified to {}",
+      formula,
+      simplified
+    );
+  }
+}
+
+#[test]
+fn testing_simplify_keeps_dates() {
+  assert_eq!(s("2026-01-02 - 2026-01-01 + x"), "2026-01-02 - 2026-01-01 + x");
+  assert_eq!(s("3d*2 - 1h"), "3d*2 - 1h");
+  assert_eq!(s("weekday(2026-01-01) + 0*x"), "weekday(2026-01-01)");
+
+  for formula in ["2026-01-02 - 2026-01-01 + 2d", "2026-01-01 + (2*3)*1h - 1d"].iter() {
+    let simplified = s(formula);
+    assert_eq!(
+      Calc::calculate(simplified.clone()),
+      Calc::calculate(formula.to_string()),
+      "{} simplified to {}",
+      formula,
+      simplified
+    );
+  }
+}
+
+#[test]
+fn testing_simplify_reparses() {
+  let simplified = simplify(&parse("x*(2*3) - x/4 + y^2*y"));
+
+  assert_eq!(simplified.to_string(), "y^3 + 23*x/4");
+  assert_eq!(parse(&simplified.to_string()), simplified);
+}
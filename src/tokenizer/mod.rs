@@ -1,12 +1,24 @@
+use crate::error::Located;
+use crate::settings::{Budget, Limits};
 use crate::Error;
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Kind {
   Bracket,
+  Separator,
   Operator,
   Number,
+  /// A date in UTC as `2026-10-18` or `2026-10-18T09:30:15`
+  Date,
+  /// A duration as `90min` or `3d 4h`
+  Duration,
   Digit,
   Dot,
+  Postfix,
+  Letter,
+  Identifier,
+  Function,
   Space,
 }
 impl Kind {
@@ -14,10 +26,49 @@ impl Kind {
     use Key::*;
     match *self {
       Kind::Bracket => vec![RoundOpen, RoundClose, BoxOpen, BoxClose, CurlyOpen, CurlyClose],
-      Kind::Operator => vec![Addition, Subtraction, Multiplication, Division],
+      Kind::Separator => vec![Comma, Semicolon, Range],
+      Kind::Operator => vec![
+        Addition,
+        Subtraction,
+        PlusMinus,
+        Multiplication,
+        Division,
+        Of,
+        Exponentiation,
+        Equals,
+        BitNot,
+        BitAnd,
+        BitOr,
+        Xor,
+        ShiftLeft,
+        ShiftRight,
+      ],
       Kind::Number => vec![Dot, Zero, One, Two, Three, Four, Five, Six, Seven, Eight, Nine],
+      Kind::Date => vec![
+        Zero,
+        One,
+        Two,
+        Three,
+        Four,
+        Five,
+        Six,
+        Seven,
+        Eight,
+        Nine,
+        Subtraction,
+        Letter('T'),
+        Colon,
+      ],
+      Kind::Duration => ('a'..='z')
+        .map(Letter)
+        .chain(vec![
+          Zero, One, Two, Three, Four, Five, Six, Seven, Eight, Nine, Dot, Space,
+        ])
+        .collect(),
       Kind::Digit => vec![Zero, One, Two, Three, Four, Five, Six, Seven, Eight, Nine],
       Kind::Dot => vec![Dot],
+      Kind::Postfix => vec![Factorial, Percent, Squared, Cubed, Degree],
+      Kind::Letter | Kind::Identifier | Kind::Function => ('a'..='z').chain('A'..='Z').map(Letter).collect(),
       Kind::Space => vec![Space],
     }
   }
@@ -33,11 +84,34 @@ pub enum Key {
   CurlyOpen,  // '{',
   CurlyClose, // '}',
 
+  // Kind::Separator
+  Comma,     // ',',
+  Semicolon, // ';', between the rows of a matrix
+  Range,     // '..', between the bounds of an interval
+
+  // Kind::Postfix
+  Factorial, // '!',
+  Percent,   // '%',
+  Squared,   // '²', read as ^2
+  Cubed,     // '³', read as ^3
+  Degree,    // '°', an angle in degrees
+
   // Kind::Operator
+  BitNot,         // '~', only before an operand
+  Exponentiation, // '^',
+  Juxtaposition,  // implicit multiplication, never tokenized
   Multiplication, // '*',
   Division,       // '/',
+  Of,             // 'of',
   Addition,       // '+',
   Subtraction,    // '-',
+  PlusMinus,      // '±' or '+/-', between a value and its uncertainty
+  ShiftLeft,      // '<<',
+  ShiftRight,     // '>>',
+  BitAnd,         // '&',
+  Xor,            // '^' in programmer mode, never tokenized
+  BitOr,          // '|',
+  Equals,         // '=', only between the sides of an equation
 
   // Kind::Digit
   Zero,  // '0',
@@ -54,8 +128,17 @@ pub enum Key {
   // Kind::Dot
   Dot, // '.',
 
+  // Kind::Date
+  Colon, // ':', in the time of a date
+
+  // Kind::Letter
+  Letter(char), // 'a'..'z', 'A'..'Z', and '_' in the names of functions
+
+  // Kind::Function
+  SquareRoot, // '√', read as sqrt
+
   // Kind::Space
-  Space, // ' ',
+  Space, // ' ', tab, newline or non-breaking space
 }
 impl Key {
   fn into_key(char_value: char) -> Key {
@@ -67,10 +150,24 @@ impl Key {
       ']' => BoxClose,
       '{' => CurlyOpen,
       '}' => CurlyClose,
-      '*' => Multiplication,
-      '/' => Division,
+      ',' => Comma,
+      ';' => Semicolon,
+      '*' | '×' | '·' => Multiplication,
+      '/' | '÷' => Division,
       '+' => Addition,
-      '-' => Subtraction,
+      '-' | '−' => Subtraction,
+      '±' => PlusMinus,
+      '=' => Equals,
+      '^' => Exponentiation,
+      '~' => BitNot,
+      '&' => BitAnd,
+      '|' => BitOr,
+      '!' => Factorial,
+      '%' => Percent,
+      '²' => Squared,
+      '³' => Cubed,
+      '°' => Degree,
+      '√' => SquareRoot,
       '0' => Zero,
       '1' => One,
       '2' => Two,
@@ -82,7 +179,9 @@ impl Key {
       '8' => Eight,
       '9' => Nine,
       '.' => Dot,
-      ' ' => Space,
+      ':' => Colon,
+      ' ' | '\t' | '\n' | '\r' | '\u{a0}' => Space,
+      letter if letter.is_ascii_alphabetic() || letter == '_' => Letter(letter),
       _ => panic!("Undefined char: {}", char_value),
     }
   }
@@ -91,67 +190,111 @@ impl Key {
     use Key::*;
     match *self {
       BoxOpen | BoxClose | CurlyOpen | CurlyClose | RoundOpen | RoundClose => Kind::Bracket,
-      Addition | Subtraction | Multiplication | Division => Kind::Operator,
+      Comma | Semicolon | Range => Kind::Separator,
+      Addition | Subtraction | PlusMinus | Multiplication | Division | Of | Juxtaposition | Exponentiation | Equals
+      | BitNot | BitAnd | BitOr | Xor | ShiftLeft | ShiftRight => Kind::Operator,
       Zero | One | Two | Three | Four | Five | Six | Seven | Eight | Nine => Kind::Digit,
       Dot => Kind::Dot,
+      Colon => Kind::Date,
+      Factorial | Percent | Squared | Cubed | Degree => Kind::Postfix,
+      Letter(_) => Kind::Letter,
+      SquareRoot => Kind::Function,
       Space => Kind::Space,
     }
   }
 
   pub fn precede(key_a: &Key, key_b: &Key) -> bool {
+    use Key::*;
     match (key_a, key_b) {
-      (Key::Multiplication, Key::Division) => false,
-      (Key::Division, Key::Multiplication) => false,
-      (Key::Addition, Key::Subtraction) => false,
-      (Key::Subtraction, Key::Addition) => false,
+      (Multiplication | Division | Of, Multiplication | Division | Of) => false,
+      (Addition | Subtraction | PlusMinus, Addition | Subtraction | PlusMinus) => false,
+      (ShiftLeft | ShiftRight, ShiftLeft | ShiftRight) => false,
       _ => key_a < key_b,
     }
   }
 
-  fn to_char(&self) -> char {
+  pub fn is_right_associative(&self) -> bool {
+    *self == Key::Exponentiation
+  }
+}
+impl fmt::Display for Key {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     use Key::*;
-    match self {
-      RoundOpen => '(',
-      RoundClose => ')',
-      BoxOpen => '[',
-      BoxClose => ']',
-      CurlyOpen => '{',
-      CurlyClose => '}',
-      Multiplication => '*',
-      Division => '/',
-      Addition => '+',
-      Subtraction => '-',
-      Zero => '0',
-      One => '1',
-      Two => '2',
-      Three => '3',
-      Four => '4',
-      Five => '5',
-      Six => '6',
-      Seven => '7',
-      Eight => '8',
-      Nine => '9',
-      Dot => '.',
-      Space => ' ',
-    }
-  }
-
-  pub fn to_string(&self) -> String {
-    self.to_char().to_string()
+    let symbol = match self {
+      RoundOpen => "(",
+      RoundClose => ")",
+      BoxOpen => "[",
+      BoxClose => "]",
+      CurlyOpen => "{",
+      CurlyClose => "}",
+      Comma => ",",
+      Semicolon => ";",
+      Range => "..",
+      Factorial => "!",
+      Percent => "%",
+      Squared => "²",
+      Cubed => "³",
+      Degree => "°",
+      Exponentiation => "^",
+      Multiplication => "*",
+      Division => "/",
+      Of => "of",
+      Juxtaposition => "",
+      Addition => "+",
+      Subtraction => "-",
+      PlusMinus => "±",
+      Equals => "=",
+      BitNot => "~",
+      BitAnd => "&",
+      BitOr => "|",
+      Xor => "^",
+      ShiftLeft => "<<",
+      ShiftRight => ">>",
+      Zero => "0",
+      One => "1",
+      Two => "2",
+      Three => "3",
+      Four => "4",
+      Five => "5",
+      Six => "6",
+      Seven => "7",
+      Eight => "8",
+      Nine => "9",
+      Dot => ".",
+      Colon => ":",
+      Letter(letter) => return write!(f, "{}", letter),
+      SquareRoot => "√",
+      Space => " ",
+    };
+    f.write_str(symbol)
   }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Token {
   pub kind: Kind,
   pub keys: Vec<Key>,
+  /// Value of a number, as read by the lexer
+  pub value: Option<f64>,
+  /// Byte offsets of the token in the formula, if it was read from one
+  pub span: Option<(usize, usize)>,
 }
 impl Token {
   pub fn new(kind: Kind, key: Key) -> Token {
     if key.kind() != kind {
       panic!("Invalid token arguments");
     }
-    Token { kind, keys: vec![key] }
+    Self::unplaced(kind, vec![key])
+  }
+
+  /// Token made up rather than read from a formula.
+  fn unplaced(kind: Kind, keys: Vec<Key>) -> Token {
+    Token {
+      kind,
+      keys,
+      value: None,
+      span: None,
+    }
   }
 
   pub fn new_bracket(key: Key) -> Token {
@@ -162,17 +305,15 @@ impl Token {
   }
 
   pub fn new_number(keys: Vec<Key>) -> Token {
-    if keys.len() == 0 {
+    if keys.is_empty() {
       panic!("Invalid token arguments")
     }
 
     {
       let valid_keys = Kind::Number.keys();
-      let is_valid_keys = keys
-        .iter()
-        .fold(true, |acc, key| acc == true && valid_keys.contains(&key));
+      let is_valid_keys = keys.iter().all(|key| valid_keys.contains(key));
 
-      if is_valid_keys == false {
+      if !is_valid_keys {
         panic!("Invalid token arguments")
       }
     }
@@ -188,258 +329,449 @@ impl Token {
         is_valid_sequence = false;
       }
 
-      if is_valid_sequence == false {
+      if !is_valid_sequence {
         panic!("Invalid token arguments")
       }
     }
 
+    let value = keys.iter().map(ToString::to_string).collect::<String>().parse().ok();
     Token {
-      kind: Kind::Number,
-      keys,
+      value,
+      ..Self::unplaced(Kind::Number, keys)
+    }
+  }
+
+  pub fn new_identifier(name: &str) -> Token {
+    if name.is_empty() || !name.chars().all(|letter| letter.is_ascii_alphabetic()) {
+      panic!("Invalid token arguments")
     }
+
+    Self::unplaced(Kind::Identifier, name.chars().map(Key::Letter).collect())
+  }
+
+  pub fn new_function(name: &str) -> Token {
+    if !FUNCTIONS.contains(&name) {
+      panic!("Invalid token arguments")
+    }
+
+    Self::unplaced(Kind::Function, name.chars().map(Key::Letter).collect())
+  }
+
+  /// Number token for a non-negative finite value, as written by Display.
+  pub fn new_float(value: f64) -> Token {
+    if !value.is_finite() || value.is_sign_negative() {
+      panic!("Invalid token arguments")
+    }
+
+    Token {
+      value: Some(value),
+      ..Self::new_number(value.to_string().chars().map(Key::into_key).collect())
+    }
+  }
+
+  /// Number token for the digits of a non-negative integer, as long as
+  /// they go.
+  pub fn new_integer(digits: &str) -> Token {
+    if !digits.chars().all(|digit| digit.is_ascii_digit()) {
+      panic!("Invalid token arguments")
+    }
+
+    Self::new_number(digits.chars().map(Key::into_key).collect())
   }
 
   pub fn new_operator(key: Key) -> Token {
     Self::new(Kind::Operator, key)
   }
 
-  #[allow(dead_code)]
   pub fn new_space(keys: Vec<Key>) -> Token {
-    if keys.len() == 0 {
+    if keys.is_empty() {
       panic!("Invalid token arguments")
     }
 
     {
       let is_valid_keys = keys.iter().filter(|key| key.kind() != Kind::Space).count() == 0;
 
-      if is_valid_keys == false {
+      if !is_valid_keys {
         panic!("Invalid token arguments")
       }
     }
 
-    Token {
-      kind: Kind::Space,
-      keys,
-    }
+    Self::unplaced(Kind::Space, keys)
   }
-
-  pub fn to_string(&self) -> String {
-    self.keys.iter().fold("".to_string(), |mut acc, key| {
-      acc.push_str(&key.to_string());
-      acc
-    })
+}
+/// Tokens are equal when written alike, wherever they are in the formula.
+impl PartialEq for Token {
+  fn eq(&self, other: &Token) -> bool {
+    self.kind == other.kind && self.keys == other.keys
+  }
+}
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.keys.iter().try_for_each(|key| write!(f, "{}", key))
   }
 }
 
 pub fn tokenize(formula: String) -> Result<Vec<Token>, Error> {
-  let tokens = Vec::new();
-  let current: usize = 0;
-  run_tokenize(formula, tokens, current)
-    .map(digits_into_number)
-    .map(join_spaces)
+  Lexer::new(&formula).map(|lexeme| lexeme.map(Token::from)).collect()
 }
 
-fn run_tokenize(formula: String, mut tokens: Vec<Token>, mut current: usize) -> Result<Vec<Token>, Error> {
-  if current >= formula.len() {
-    return Ok(tokens);
-  }
+/// Tokenizes `formula` as `tokenize` does, failing on a formula longer or of
+/// more tokens than the limits allow before reading any further.
+pub fn tokenize_with(formula: String, limits: &Limits) -> Result<Vec<Token>, Error> {
+  tokenize_with_variables(formula, limits, &[]).map_err(Error::from)
+}
 
-  let mut new_tokens: Vec<Token> = Vec::new();
+/// Tokenizes `formula` as `tokenize_with` does, reading a unit of duration
+/// that names one of `variables` as that variable, so `3h` is `3*h` once `h`
+/// is bound. An error comes with the text it is about: the symbol or number
+/// the lexer rejected, or the first token past the limit.
+pub fn tokenize_with_variables(formula: String, limits: &Limits, variables: &[String]) -> Result<Vec<Token>, Located> {
+  tokenize_within(formula, limits, variables, &Budget::new(limits))
+}
 
-  for tokenizer in &TOKENIZERS {
-    let token = tokenizer(&formula, current);
-    if let Some(token) = token {
-      current += token.to_string().len();
-      new_tokens.push(token);
-    }
+/// Tokenizes `formula` as `tokenize_with_variables` does, failing once the
+/// time of `budget` is up, which the parser and the evaluation go on taking
+/// from.
+pub fn tokenize_within(
+  formula: String,
+  limits: &Limits,
+  variables: &[String],
+  budget: &Budget,
+) -> Result<Vec<Token>, Located> {
+  if formula.chars().nth(limits.length).is_some() {
+    return Err(Error::InputTooLong(limits.length).into());
   }
 
-  if new_tokens.len() == 0 {
-    let err_message = format!("Error: Undefined symbol start {}", formula.split_at(current).1);
-    return Err(Error::UndefinedSymbol(err_message));
+  let mut tokens = Vec::new();
+  let mut count = 0;
+  let mut lexer = Lexer::new(&formula).with_variables(variables);
+  while let Some(lexeme) = lexer.next() {
+    let token = Token::from(lexeme.map_err(|error| Located::at(error, lexer.rejected()))?);
+    if token.kind != Kind::Space {
+      count += 1;
+    }
+    if count > limits.tokens {
+      return Err(Located::at(Error::TooManyTokens(limits.tokens), token.span));
+    }
+    budget.read().map_err(|error| Located::at(error, token.span))?;
+    tokens.push(token);
   }
-
-  tokens.append(&mut new_tokens);
-
-  run_tokenize(formula, tokens, current)
+  Ok(tokens)
 }
 
-fn join_spaces(tokens: Vec<Token>) -> Vec<Token> {
-  let mut token_list: Vec<Token> = Vec::new();
-  let mut token_space: Option<Token> = None;
-
-  for token in tokens {
-    match token.kind {
-      Kind::Space => {
-        token_space = Some(Token {
-          kind: Kind::Space,
-          keys: match token_space {
-            None => token.keys,
-            Some(prev_token) => vec![prev_token.keys, token.keys].concat(),
-          },
-        });
-      }
-
-      _ => {
-        if let Some(prev_token) = token_space {
-          token_list.push(prev_token);
-          token_space = None;
+/// A token as written in the formula, borrowing its text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lexeme<'a> {
+  pub kind: Kind,
+  pub text: &'a str,
+  /// Byte offsets of the start and the end of the text in the formula
+  pub span: (usize, usize),
+  /// Value of a number, parsed once while reading it
+  pub value: Option<f64>,
+}
+impl From<Lexeme<'_>> for Token {
+  fn from(lexeme: Lexeme) -> Token {
+    let keys = match (&lexeme.kind, lexeme.text) {
+      (Kind::Operator, "of") => vec![Key::Of],
+      (Kind::Operator, "<<") => vec![Key::ShiftLeft],
+      (Kind::Operator, ">>") => vec![Key::ShiftRight],
+      (Kind::Operator, "+/-") => vec![Key::PlusMinus],
+      (Kind::Separator, "..") => vec![Key::Range],
+      // 0x1f and 0b101 are kept as the digits of their decimal value
+      (Kind::Number, text) => match radix_of(text) {
+        Some(radix) => {
+          let value = u64::from_str_radix(&text[2..], radix).unwrap_or_default();
+          value.to_string().chars().map(Key::into_key).collect()
         }
-        token_list.push(token);
-      }
+        None => text.chars().map(Key::into_key).collect(),
+      },
+      (Kind::Identifier, "π") => vec![Key::Letter('p'), Key::Letter('i')],
+      (_, text) => text.chars().map(Key::into_key).collect(),
+    };
+    Token {
+      kind: lexeme.kind,
+      keys,
+      value: lexeme.value,
+      span: Some(lexeme.span),
     }
   }
+}
 
-  if let Some(prev_token) = token_space {
-    token_list.push(prev_token);
+/// Reads the lexemes of a formula in a single pass, stopping after the first
+/// error.
+pub struct Lexer<'a> {
+  formula: &'a str,
+  position: usize,
+  /// Names read as variables rather than as units of durations
+  variables: &'a [String],
+  /// Byte offsets of the text the lexer stopped at with an error
+  rejected: Option<(usize, usize)>,
+}
+impl<'a> Lexer<'a> {
+  pub fn new(formula: &'a str) -> Lexer<'a> {
+    Lexer {
+      formula,
+      position: 0,
+      variables: &[],
+      rejected: None,
+    }
   }
 
-  token_list
-}
+  /// Byte offsets of the symbol or number the lexer stopped at, once it
+  /// failed.
+  pub fn rejected(&self) -> Option<(usize, usize)> {
+    self.rejected
+  }
 
-fn digits_into_number(tokens: Vec<Token>) -> Vec<Token> {
-  let mut token_list: Vec<Token> = Vec::new();
-  let mut token_number: Option<Token> = None;
-
-  for token in tokens {
-    match token.kind {
-      Kind::Digit => {
-        token_number = Some(Token {
-          kind: Kind::Number,
-          keys: match token_number {
-            None => token.keys,
-            Some(prev_token) => vec![prev_token.keys, token.keys].concat(),
-          },
-        })
-      }
+  pub fn with_variables(mut self, variables: &'a [String]) -> Lexer<'a> {
+    self.variables = variables;
+    self
+  }
 
-      Kind::Dot => {
-        token_number = match token_number {
-          None => panic!("Number should not start with ."),
-          Some(prev_token) if prev_token.keys.contains(&Key::Dot) => {
-            panic!("Number should not contains more than one .")
-          }
-          Some(prev_token) => Some(Token {
-            kind: Kind::Number,
-            keys: vec![prev_token.keys, token.keys].concat(),
-          }),
-        }
+  fn lexeme(&mut self, kind: Kind, length: usize, value: Option<f64>) -> Lexeme<'a> {
+    let span = (self.position, self.position + length);
+    self.position = span.1;
+    Lexeme {
+      kind,
+      text: &self.formula[span.0..span.1],
+      span,
+      value,
+    }
+  }
+
+  fn number(&mut self, rest: &str) -> Result<Lexeme<'a>, Error> {
+    if let Some(radix) = radix_of(rest) {
+      return self.integer(rest, radix);
+    }
+    if let Some(length) = date_length(rest) {
+      return Ok(self.lexeme(Kind::Date, length, None));
+    }
+    let bytes = rest.as_bytes();
+    let mut length = digits(bytes, 0);
+
+    // the .. after a number is the range of an interval, as in [1..2]
+    if bytes.get(length) == Some(&b'.') && bytes.get(length + 1) != Some(&b'.') {
+      let fraction = digits(bytes, length + 1);
+      if bytes.get(fraction) == Some(&b'.') && bytes.get(fraction + 1) != Some(&b'.') {
+        let length = digits(bytes, fraction + 1);
+        return Err(self.fail(length, "Bad format: Number should not contain more than one ."));
+      }
+      if fraction == length + 1 {
+        return Err(self.fail(fraction, "Bad format: Number should not end with ."));
       }
+      length = fraction;
+    }
 
-      _ => {
-        if let Some(prev_token) = token_number {
-          token_list.push(prev_token);
-          token_number = None;
-        }
-        token_list.push(token);
+    // 1e308 and 2.5e-3 in scientific notation, where the e is not the
+    // constant of 2e as long as digits follow it
+    if bytes.get(length) == Some(&b'e') {
+      let sign = matches!(bytes.get(length + 1), Some(b'+') | Some(b'-')) as usize;
+      let exponent = digits(bytes, length + 1 + sign);
+      if exponent > length + 1 + sign {
+        let value = rest[..exponent].parse().ok();
+        return Ok(self.lexeme(Kind::Number, exponent, value));
       }
     }
-  }
 
-  if let Some(prev_token) = token_number {
-    token_list.push(prev_token);
+    if let Some(length) = duration_length(rest, length, self.variables) {
+      return Ok(self.lexeme(Kind::Duration, length, None));
+    }
+    let value = rest[..length].parse().ok();
+    Ok(self.lexeme(Kind::Number, length, value))
   }
 
-  for token in &token_list {
-    if token.kind == Kind::Number && token.keys.ends_with(&[Key::Dot]) {
-      panic!("Number should not end with .")
+  /// Reads an integer written in hexadecimal as `0x1f` or in binary as
+  /// `0b101`, up to 64 bits.
+  fn integer(&mut self, rest: &str, radix: u32) -> Result<Lexeme<'a>, Error> {
+    let length = rest[2..]
+      .find(|digit: char| !digit.is_digit(radix))
+      .map_or(rest.len(), |end| end + 2);
+
+    match u64::from_str_radix(&rest[2..length], radix) {
+      Ok(value) => Ok(self.lexeme(Kind::Number, length, Some(value as f64))),
+      Err(_) => Err(self.fail(
+        length,
+        &format!("Bad format: Number {} does not fit in 64 bits", &rest[..length]),
+      )),
     }
   }
 
-  token_list
-}
-
-static TOKENIZERS: [fn(formula: &String, current: usize) -> Option<Token>; 13] = [
-  tokenize_addition,
-  tokenize_box_bracket_close,
-  tokenize_box_bracket_open,
-  tokenize_curly_bracket_close,
-  tokenize_curly_bracket_open,
-  tokenize_decimal_digit,
-  tokenize_division,
-  tokenize_dot,
-  tokenize_multiplication,
-  tokenize_round_bracket_close,
-  tokenize_round_bracket_open,
-  tokenize_space,
-  tokenize_subtration,
-];
-
-fn tokenize_addition(formula: &String, current: usize) -> Option<Token> {
-  tokenize_char(Kind::Operator, '+', &formula, current)
-}
-
-fn tokenize_division(formula: &String, current: usize) -> Option<Token> {
-  tokenize_char(Kind::Operator, '/', formula, current)
-}
-
-fn tokenize_multiplication(formula: &String, current: usize) -> Option<Token> {
-  tokenize_char(Kind::Operator, '*', formula, current)
-}
-
-fn tokenize_subtration(formula: &String, current: usize) -> Option<Token> {
-  tokenize_char(Kind::Operator, '-', formula, current)
-}
-
-fn tokenize_box_bracket_open(formula: &String, current: usize) -> Option<Token> {
-  tokenize_bracket(formula, current, Key::BoxOpen, '[')
-}
-
-fn tokenize_box_bracket_close(formula: &String, current: usize) -> Option<Token> {
-  tokenize_bracket(formula, current, Key::BoxClose, ']')
-}
+  /// Stops the lexer with an error about the next `length` bytes.
+  fn fail(&mut self, length: usize, message: &str) -> Error {
+    self.stop(length, Error::InvalidExpression(message.to_string()))
+  }
 
-fn tokenize_curly_bracket_open(formula: &String, current: usize) -> Option<Token> {
-  tokenize_bracket(formula, current, Key::CurlyOpen, '{')
-}
+  fn stop(&mut self, length: usize, error: Error) -> Error {
+    self.rejected = Some((self.position, self.position + length));
+    self.position = self.formula.len();
+    error
+  }
 
-fn tokenize_curly_bracket_close(formula: &String, current: usize) -> Option<Token> {
-  tokenize_bracket(formula, current, Key::CurlyClose, '}')
+  fn word(&mut self, rest: &str) -> Lexeme<'a> {
+    let end_of = |is_part: fn(char) -> bool| rest.find(|letter: char| !is_part(letter)).unwrap_or(rest.len());
+    let mut length = end_of(|letter| letter.is_ascii_alphabetic());
+    // only the names of functions go on after an _
+    let name = end_of(|letter| letter.is_ascii_alphabetic() || letter == '_');
+    if FUNCTIONS.contains(&&rest[..name]) {
+      length = name;
+    }
+    let kind = match &rest[..length] {
+      "of" => Kind::Operator,
+      name if FUNCTIONS.contains(&name) => Kind::Function,
+      _ => Kind::Identifier,
+    };
+    self.lexeme(kind, length, None)
+  }
 }
-
-fn tokenize_round_bracket_open(formula: &String, current: usize) -> Option<Token> {
-  tokenize_bracket(formula, current, Key::RoundOpen, '(')
+impl<'a> Iterator for Lexer<'a> {
+  type Item = Result<Lexeme<'a>, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let rest = &self.formula[self.position..];
+    let first = rest.chars().next()?;
+    let kind = match first {
+      '0'..='9' => return Some(self.number(rest)),
+      '.' if rest.starts_with("..") => return Some(Ok(self.lexeme(Kind::Separator, 2, None))),
+      '.' => {
+        let length = digits(rest.as_bytes(), 1);
+        return Some(Err(self.fail(length, "Bad format: Number should not start with .")));
+      }
+      letter if letter.is_ascii_alphabetic() => return Some(Ok(self.word(rest))),
+      space if is_space(space) => {
+        let length = rest.find(|space: char| !is_space(space)).unwrap_or(rest.len());
+        return Some(Ok(self.lexeme(Kind::Space, length, None)));
+      }
+      '(' | ')' | '[' | ']' | '{' | '}' => Kind::Bracket,
+      ',' | ';' => Kind::Separator,
+      '+' if rest.starts_with("+/-") => return Some(Ok(self.lexeme(Kind::Operator, 3, None))),
+      '+' | '-' | '*' | '/' | '^' | '=' | '×' | '·' | '÷' | '−' | '~' | '&' | '|' | '±' => Kind::Operator,
+      '<' | '>' if rest[1..].starts_with(first) => return Some(Ok(self.lexeme(Kind::Operator, 2, None))),
+      '!' | '%' | '²' | '³' | '°' => Kind::Postfix,
+      'π' => Kind::Identifier,
+      '√' => Kind::Function,
+      _ => {
+        let err_message = format!("Error: Undefined symbol start {}", rest);
+        return Some(Err(self.stop(first.len_utf8(), Error::UndefinedSymbol(err_message))));
+      }
+    };
+    Some(Ok(self.lexeme(kind, first.len_utf8(), None)))
+  }
 }
 
-fn tokenize_round_bracket_close(formula: &String, current: usize) -> Option<Token> {
-  tokenize_bracket(formula, current, Key::RoundClose, ')')
+fn is_space(value: char) -> bool {
+  matches!(value, ' ' | '\t' | '\n' | '\r' | '\u{a0}')
 }
 
-fn tokenize_space(formula: &String, current: usize) -> Option<Token> {
-  tokenize_char(Kind::Space, ' ', formula, current)
+/// Radix of a number starting with `0x` or `0b` and a digit of that radix.
+fn radix_of(number: &str) -> Option<u32> {
+  let radix = match number.get(..2) {
+    Some("0x") | Some("0X") => 16,
+    Some("0b") | Some("0B") => 2,
+    _ => return None,
+  };
+  match number[2..].chars().next() {
+    Some(digit) if digit.is_digit(radix) => Some(radix),
+    _ => None,
+  }
 }
 
-fn tokenize_dot(formula: &String, current: usize) -> Option<Token> {
-  tokenize_char(Kind::Dot, '.', formula, current)
+/// Length of the date at the start of `text`, with the time of the day when
+/// it has one. The calendar checks that the date exists.
+fn date_length(text: &str) -> Option<usize> {
+  let matches = |pattern: &str| {
+    text.len() >= pattern.len()
+      && pattern
+        .bytes()
+        .zip(text.bytes())
+        .all(|(expected, byte)| match expected {
+          b'9' => byte.is_ascii_digit(),
+          _ => byte == expected,
+        })
+  };
+  ["9999-99-99T99:99:99", "9999-99-99T99:99", "9999-99-99"]
+    .iter()
+    .find(|pattern| matches(pattern))
+    .map(|pattern| pattern.len())
 }
 
-fn tokenize_char(kind: Kind, char_value: char, formula: &String, current: usize) -> Option<Token> {
-  match formula.chars().nth(current) {
-    Some(value) if value == char_value => Some(Token {
-      kind,
-      keys: vec![Key::into_key(char_value)],
-    }),
-    Some(_) => None,
-    None => None,
+/// Units of durations, the longest first.
+pub const DURATION_UNITS: [&str; 5] = ["min", "w", "d", "h", "s"];
+
+/// Length of the duration at the start of `text` whose first number is
+/// `number` bytes long, taking in the parts after it separated by spaces, as
+/// in `3d 4h`.
+fn duration_length(text: &str, number: usize, variables: &[String]) -> Option<usize> {
+  let mut end = number + unit_length(&text[number..], variables)?;
+  loop {
+    let start = text.len() - text[end..].trim_start_matches(' ').len();
+    let bytes = &text.as_bytes()[start..];
+    let mut number = digits(bytes, 0);
+    if number > 0 && bytes.get(number) == Some(&b'.') && digits(bytes, number + 1) > number + 1 {
+      number = digits(bytes, number + 1);
+    }
+    match unit_length(&text[start + number..], variables) {
+      Some(unit) if number > 0 => end = start + number + unit,
+      _ => return Some(end),
+    }
   }
 }
 
-fn tokenize_bracket(formula: &String, current: usize, bracket: Key, char_value: char) -> Option<Token> {
-  match bracket.kind() {
-    Kind::Bracket => tokenize_char(Kind::Bracket, char_value, formula, current),
-    _ => panic!("Invalid kind"),
+/// Length of the unit at the start of `text`, unless it goes on as a name,
+/// as the `s` of `2sin(x)`, or is one of `variables`.
+fn unit_length(text: &str, variables: &[String]) -> Option<usize> {
+  let unit = DURATION_UNITS.iter().find(|unit| text.starts_with(*unit))?;
+  if variables.iter().any(|variable| variable == unit) {
+    return None;
+  }
+  match text[unit.len()..].chars().next() {
+    Some(next) if next.is_ascii_alphabetic() || next == '_' || next == '(' => None,
+    _ => Some(unit.len()),
   }
 }
 
-fn tokenize_decimal_digit(formula: &String, current: usize) -> Option<Token> {
-  let digits = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
-  let value = formula.chars().nth(current);
-
-  digits.iter().find(|&&digit| Some(digit) == value).map(|&digit| Token {
-    kind: Kind::Digit,
-    keys: vec![Key::into_key(digit)],
-  })
+/// End of the run of ASCII digits starting at `start`.
+fn digits(bytes: &[u8], start: usize) -> usize {
+  start + bytes[start..].iter().take_while(|byte| byte.is_ascii_digit()).count()
 }
 
+pub const FUNCTIONS: [&str; 36] = [
+  "abs",
+  "add_business_days",
+  "acos",
+  "asin",
+  "atan",
+  "cos",
+  "cosh",
+  "cross",
+  "deg",
+  "det",
+  "digamma",
+  "dot",
+  "exp",
+  "integrate",
+  "inv",
+  "ln",
+  "log",
+  "max",
+  "mean",
+  "median",
+  "min",
+  "percentile",
+  "prod",
+  "product",
+  "rad",
+  "series",
+  "sin",
+  "sinh",
+  "sqrt",
+  "stdev",
+  "sum",
+  "tan",
+  "tanh",
+  "transpose",
+  "variance",
+  "weekday",
+];
+
 #[cfg(test)]
 mod tokenizer_spec;
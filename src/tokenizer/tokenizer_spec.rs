@@ -26,31 +26,34 @@ fn testing_key_precedence() {
     "CurlyClose should precede Multiplication"
   );
 
-  assert_eq!(
-    Key::precede(&Multiplication, &Division),
-    false,
+  assert!(
+    !Key::precede(&Multiplication, &Division),
     "Multiplication should not precede Division"
   );
 
-  assert_eq!(
-    Key::precede(&Multiplication, &Division),
-    false,
+  assert!(
+    !Key::precede(&Multiplication, &Division),
     "Division should not precede Multiplication"
   );
 
   assert!(Key::precede(&Division, &Addition), "Division should precede Addition");
 
-  assert_eq!(
-    Key::precede(&Addition, &Subtraction),
-    false,
+  assert!(
+    Key::precede(&Exponentiation, &Multiplication),
+    "Exponentiation should precede Multiplication"
+  );
+
+  assert!(
+    !Key::precede(&Addition, &Subtraction),
     "Addition should not precede Subtraction"
   );
 
-  assert_eq!(
-    Key::precede(&Addition, &Subtraction),
-    false,
+  assert!(
+    !Key::precede(&Addition, &Subtraction),
     "Subtraction should not precede Addition"
   );
+
+  assert!(Key::precede(&Subtraction, &Equals), "Subtraction should precede Equals");
 }
 
 /// describe Tokenizer
@@ -65,50 +68,74 @@ fn testing_tokenize() {
     Token {
       kind: Kind::Operator,
       keys: vec![Subtraction],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Operator,
       keys: vec![Multiplication],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Operator,
       keys: vec![Division],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Operator,
       keys: vec![Addition],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Bracket,
       keys: vec![RoundOpen],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Bracket,
       keys: vec![RoundClose],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Bracket,
       keys: vec![BoxOpen],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Bracket,
       keys: vec![BoxClose],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Bracket,
       keys: vec![CurlyOpen],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Bracket,
       keys: vec![CurlyClose],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Space,
       keys: vec![Space, Space],
+      value: None,
+      span: None,
     },
     Token {
       kind: Kind::Number,
       keys: vec![One, Dot, Two],
+      value: Some(1.2),
+      span: None,
     },
   ];
 
@@ -116,356 +143,505 @@ fn testing_tokenize() {
 }
 
 #[test]
-fn testing_join_spaces() {
+fn testing_tokenize_joins_spaces() {
   let formula = String::from("   ");
-  let tokens = run_tokenize(formula.clone(), Vec::new(), 0).unwrap();
-  let tokens_with_joined_space = join_spaces(run_tokenize(formula, Vec::new(), 0).unwrap());
-
-  let expected_tokens = vec![
-    Token {
-      kind: Kind::Space,
-      keys: vec![Space],
-    },
-    Token {
-      kind: Kind::Space,
-      keys: vec![Space],
-    },
-    Token {
-      kind: Kind::Space,
-      keys: vec![Space],
-    },
-  ];
+  let tokens = tokenize(formula).unwrap();
 
-  let expected_tokens_with_joined_space = vec![Token {
+  let expected_tokens = vec![Token {
     kind: Kind::Space,
     keys: vec![Space, Space, Space],
+    value: None,
+    span: None,
   }];
 
   assert_eq!(tokens, expected_tokens);
-  assert_eq!(tokens_with_joined_space, expected_tokens_with_joined_space);
 }
 
 #[test]
-fn testing_digits_into_number() {
+fn testing_tokenize_number() {
   let formula = String::from("123.45");
-  let tokens = run_tokenize(formula.clone(), Vec::new(), 0).unwrap();
-  let tokens_number = digits_into_number(run_tokenize(formula, Vec::new(), 0).unwrap());
-
-  let expected_tokens = vec![
-    Token {
-      kind: Kind::Digit,
-      keys: vec![One],
-    },
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Two],
-    },
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Three],
-    },
-    Token {
-      kind: Kind::Dot,
-      keys: vec![Dot],
-    },
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Four],
-    },
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Five],
-    },
-  ];
+  let tokens = tokenize(formula).unwrap();
 
-  let expected_tokens_number = vec![Token {
+  let expected_tokens = vec![Token {
     kind: Kind::Number,
     keys: vec![One, Two, Three, Dot, Four, Five],
+    value: Some(123.45),
+    span: None,
   }];
 
   assert_eq!(tokens, expected_tokens);
-  assert_eq!(tokens_number, expected_tokens_number);
+  assert_eq!(
+    tokenize(String::from("0123456789")).unwrap(),
+    vec![Token::new_number(vec![
+      Zero, One, Two, Three, Four, Five, Six, Seven, Eight, Nine
+    ])]
+  );
 }
 
 #[test]
-#[should_panic = "Number should not contains more than one ."]
-fn testing_digits_into_number_should_not_accept_more_than_one_dot() {
-  let formula = String::from("12.3.45");
-  digits_into_number(tokenize(formula).unwrap());
+fn testing_lexer_scientific_notation() {
+  fn lexeme(formula: &str) -> Lexeme<'_> {
+    Lexer::new(formula).next().unwrap().unwrap()
+  }
+
+  assert_eq!(lexeme("1e308").text, "1e308");
+  assert_eq!(lexeme("1e308").value, Some(1e308));
+  assert_eq!(lexeme("2.5e-3*2").value, Some(2.5e-3));
+  assert_eq!(lexeme("4e+2").value, Some(400.0));
+  assert_eq!(
+    tokenize(String::from("2e")).unwrap(),
+    vec![Token::new_number(vec![Two]), Token::new_identifier("e")],
+    "should read an e without digits after it as the constant"
+  );
+  assert_eq!(
+    tokenize(String::from("2e+x")).unwrap().len(),
+    4,
+    "should read an e without digits after its sign as the constant"
+  );
 }
 
 #[test]
-#[should_panic = "Number should not start with ."]
-fn testing_digits_into_number_should_not_start_with_dot() {
-  let formula = String::from(".45");
-  digits_into_number(tokenize(formula).unwrap());
+fn testing_tokenize_number_should_not_accept_more_than_one_dot() {
+  assert_eq!(
+    tokenize(String::from("12.3.45")),
+    Err(Error::InvalidExpression(
+      "Bad format: Number should not contain more than one .".to_string()
+    ))
+  );
 }
 
 #[test]
-#[should_panic = "Number should not end with ."]
-fn testing_digits_into_number_should_not_end_with_dot() {
-  let formula = String::from("007.");
-  digits_into_number(tokenize(formula).unwrap());
+fn testing_tokenize_number_should_not_start_with_dot() {
+  assert_eq!(
+    tokenize(String::from(".45")),
+    Err(Error::InvalidExpression(
+      "Bad format: Number should not start with .".to_string()
+    ))
+  );
 }
 
 #[test]
-fn testing_tokenize_char() {
-  let formula = String::from("1+2");
-  let token = tokenize_char(Kind::Operator, '+', &formula, 1).unwrap();
-
-  let expected_token = Token {
-    kind: Kind::Operator,
-    keys: vec![Addition],
-  };
-
-  assert_eq!(token, expected_token);
+fn testing_tokenize_number_should_not_end_with_dot() {
+  assert_eq!(
+    tokenize(String::from("007.")),
+    Err(Error::InvalidExpression(
+      "Bad format: Number should not end with .".to_string()
+    ))
+  );
 }
 
 #[test]
-fn testing_tokenize_addition() {
-  let formula = String::from("1+2");
-  let token = tokenize_addition(&formula, 1).unwrap();
+fn testing_tokenize_symbols() {
+  let symbols = [
+    ('+', Kind::Operator, Addition),
+    ('-', Kind::Operator, Subtraction),
+    ('*', Kind::Operator, Multiplication),
+    ('/', Kind::Operator, Division),
+    ('^', Kind::Operator, Exponentiation),
+    ('=', Kind::Operator, Equals),
+    ('!', Kind::Postfix, Factorial),
+    ('%', Kind::Postfix, Percent),
+    (',', Kind::Separator, Comma),
+    ('(', Kind::Bracket, RoundOpen),
+    (')', Kind::Bracket, RoundClose),
+    ('[', Kind::Bracket, BoxOpen),
+    (']', Kind::Bracket, BoxClose),
+    ('{', Kind::Bracket, CurlyOpen),
+    ('}', Kind::Bracket, CurlyClose),
+  ];
 
-  let expected_token = Token {
-    kind: Kind::Operator,
-    keys: vec![Addition],
-  };
+  for (symbol, kind, key) in symbols.iter() {
+    let tokens = tokenize(format!("1{}2", symbol)).unwrap();
+
+    assert_eq!(
+      tokens[1],
+      Token {
+        kind: kind.clone(),
+        keys: vec![key.clone()],
+        value: None,
+        span: None,
+      },
+      "should read {} as {:?}",
+      symbol,
+      key
+    );
+  }
+}
 
-  assert_eq!(token, expected_token);
+#[test]
+fn testing_tokenize_undefined_symbol() {
+  assert_eq!(
+    tokenize(String::from("1 + 2 $ 3")),
+    Err(Error::UndefinedSymbol("Error: Undefined symbol start $ 3".to_string()))
+  );
 }
 
 #[test]
-fn testing_tokenize_division() {
-  let formula = String::from("1/2");
-  let token = tokenize_division(&formula, 1).unwrap();
+fn testing_tokenize_identifier() {
+  let formula = String::from("2pi");
+  let tokens = tokenize(formula).unwrap();
 
-  let expected_token = Token {
-    kind: Kind::Operator,
-    keys: vec![Division],
-  };
+  let expected_tokens = vec![
+    Token {
+      kind: Kind::Number,
+      keys: vec![Two],
+      value: Some(2.0),
+      span: None,
+    },
+    Token {
+      kind: Kind::Identifier,
+      keys: vec![Letter('p'), Letter('i')],
+      value: None,
+      span: None,
+    },
+  ];
 
-  assert_eq!(token, expected_token);
+  assert_eq!(tokens, expected_tokens);
+  assert_eq!(Token::new_identifier("pi"), expected_tokens[1]);
 }
 
 #[test]
-fn testing_tokenize_multiplication() {
-  let formula = String::from("1*2");
-  let token = tokenize_multiplication(&formula, 1).unwrap();
-
-  let expected_token = Token {
-    kind: Kind::Operator,
-    keys: vec![Multiplication],
-  };
+fn testing_letters_into_identifier_keywords() {
+  let formula = String::from("15% of offset");
+  let tokens = tokenize(formula).unwrap();
 
-  assert_eq!(token, expected_token);
+  assert_eq!(
+    tokens[3],
+    Token::new_operator(Of),
+    "should read the word of as operator"
+  );
+  assert_eq!(
+    tokens[5],
+    Token::new_identifier("offset"),
+    "should not split of out of a longer word"
+  );
 }
 
 #[test]
-fn testing_tokenize_subtration() {
-  let formula = String::from("1-2");
-  let token = tokenize_subtration(&formula, 1).unwrap();
+fn testing_tokenize_function() {
+  let tokens = tokenize(String::from("sqrt(4)")).unwrap();
 
-  let expected_token = Token {
-    kind: Kind::Operator,
-    keys: vec![Subtraction],
-  };
-
-  assert_eq!(token, expected_token);
+  assert_eq!(tokens[0], Token::new_function("sqrt"));
 }
 
+/// describe Lexer
+
 #[test]
-fn testing_tokenize_box_bracket_open() {
-  let formula = String::from("[1]");
-  let token = tokenize_box_bracket_open(&formula, 0).unwrap();
+fn testing_lexer() {
+  let formula = "12.5*rate of  x";
+  let lexemes = Lexer::new(formula).collect::<Result<Vec<Lexeme>, Error>>().unwrap();
 
-  let expected_token = Token {
-    kind: Kind::Bracket,
-    keys: vec![BoxOpen],
-  };
+  let expected_lexemes = vec![
+    Lexeme {
+      kind: Kind::Number,
+      text: "12.5",
+      span: (0, 4),
+      value: Some(12.5),
+    },
+    Lexeme {
+      kind: Kind::Operator,
+      text: "*",
+      span: (4, 5),
+      value: None,
+    },
+    Lexeme {
+      kind: Kind::Identifier,
+      text: "rate",
+      span: (5, 9),
+      value: None,
+    },
+    Lexeme {
+      kind: Kind::Space,
+      text: " ",
+      span: (9, 10),
+      value: None,
+    },
+    Lexeme {
+      kind: Kind::Operator,
+      text: "of",
+      span: (10, 12),
+      value: None,
+    },
+    Lexeme {
+      kind: Kind::Space,
+      text: "  ",
+      span: (12, 14),
+      value: None,
+    },
+    Lexeme {
+      kind: Kind::Identifier,
+      text: "x",
+      span: (14, 15),
+      value: None,
+    },
+  ];
 
-  assert_eq!(token, expected_token);
+  assert_eq!(lexemes, expected_lexemes);
 }
 
 #[test]
-fn testing_tokenize_box_bracket_close() {
-  let formula = String::from("[1]");
-  let token = tokenize_box_bracket_close(&formula, 2).unwrap();
-
-  let expected_token = Token {
-    kind: Kind::Bracket,
-    keys: vec![BoxClose],
-  };
+fn testing_lexer_stops_after_an_error() {
+  let mut lexer = Lexer::new("1#2");
 
-  assert_eq!(token, expected_token);
+  assert!(lexer.next().unwrap().is_ok());
+  assert!(lexer.next().unwrap().is_err());
+  assert_eq!(lexer.next(), None);
 }
 
 #[test]
-fn testing_tokenize_curly_bracket_open() {
-  let formula = String::from("{2}");
-  let token = tokenize_curly_bracket_open(&formula, 0).unwrap();
+fn testing_lexer_long_input() {
+  let formula = "1+".repeat(100_000) + "1";
+  let count = Lexer::new(&formula).count();
 
-  let expected_token = Token {
-    kind: Kind::Bracket,
-    keys: vec![CurlyOpen],
-  };
-
-  assert_eq!(token, expected_token);
+  assert_eq!(count, 200_001, "should read long formulas without recursing");
 }
 
 #[test]
-fn testing_tokenize_curly_bracket_close() {
-  let formula = String::from("{2}");
-  let token = tokenize_curly_bracket_close(&formula, 2).unwrap();
-
-  let expected_token = Token {
-    kind: Kind::Bracket,
-    keys: vec![CurlyClose],
+fn testing_tokenize_with_limits() {
+  let limits = Limits {
+    length: 10,
+    tokens: 3,
+    ..Limits::default()
   };
 
-  assert_eq!(token, expected_token);
+  assert_eq!(
+    tokenize_with("1 + 2".to_string(), &limits).map(|tokens| tokens.len()),
+    Ok(5),
+    "should not count spaces as tokens"
+  );
+  assert_eq!(
+    tokenize_with("1 + 2 + 3".to_string(), &limits),
+    Err(Error::TooManyTokens(3))
+  );
+  assert_eq!(
+    tokenize_with("√√9".to_string(), &limits).map(|tokens| tokens.len()),
+    Ok(3),
+    "should count characters rather than bytes"
+  );
+  assert_eq!(
+    tokenize_with("12345678.90".to_string(), &limits),
+    Err(Error::InputTooLong(10))
+  );
+  assert_eq!(
+    tokenize_with("√√√9".to_string(), &limits).map(|tokens| tokens.len()),
+    Err(Error::TooManyTokens(3))
+  );
 }
 
 #[test]
-fn testing_tokenize_round_bracket_open() {
-  let formula = String::from("(3)");
-  let token = tokenize_round_bracket_open(&formula, 0).unwrap();
+fn testing_tokenize_unicode_aliases() {
+  assert_eq!(
+    tokenize(String::from("2×3·4÷5−1")),
+    tokenize(String::from("2*3*4/5-1")),
+    "should read × and · as *, ÷ as / and − as -"
+  );
+  assert_eq!(tokenize(String::from("2π")).unwrap()[1], Token::new_identifier("pi"));
+  assert_eq!(
+    tokenize(String::from("x²³")).unwrap()[1..].to_vec(),
+    vec![Token::new(Kind::Postfix, Squared), Token::new(Kind::Postfix, Cubed)]
+  );
+  assert_eq!(
+    tokenize(String::from("30°")).unwrap()[1],
+    Token::new(Kind::Postfix, Degree)
+  );
+  assert_eq!(
+    tokenize(String::from("√4")).unwrap()[0],
+    Token::new(Kind::Function, SquareRoot)
+  );
+}
 
-  let expected_token = Token {
-    kind: Kind::Bracket,
-    keys: vec![RoundOpen],
-  };
+#[test]
+fn testing_tokenize_unicode_spaces() {
+  let tokens = tokenize(String::from("1\t+\u{a0}\n2")).unwrap();
 
-  assert_eq!(token, expected_token);
+  assert_eq!(tokens.len(), 5);
+  assert_eq!(tokens[1], Token::new_space(vec![Space]));
+  assert_eq!(tokens[3], Token::new_space(vec![Space, Space]));
 }
 
 #[test]
-fn testing_tokenize_round_bracket_close() {
-  let formula = String::from("(3)");
-  let token = tokenize_round_bracket_close(&formula, 2).unwrap();
+fn testing_lexer_unicode_spans() {
+  let spans: Vec<(usize, usize)> = Lexer::new("π×2²").map(|lexeme| lexeme.unwrap().span).collect();
 
-  let expected_token = Token {
-    kind: Kind::Bracket,
-    keys: vec![RoundClose],
-  };
-
-  assert_eq!(token, expected_token);
+  assert_eq!(spans, vec![(0, 2), (2, 4), (4, 5), (5, 7)]);
+  assert_eq!(
+    tokenize(String::from("1 € 2")),
+    Err(Error::UndefinedSymbol("Error: Undefined symbol start € 2".to_string()))
+  );
 }
 
 #[test]
-#[should_panic = "Invalid kind"]
-fn testing_tokenize_bracket() {
-  let formula = String::from("(3)");
-  tokenize_bracket(&formula, 2, Multiplication, '(').unwrap();
+fn testing_tokens_keep_the_value_and_span_of_lexemes() {
+  let tokens = tokenize(String::from("π×0x1f")).unwrap();
+  let read = |token: &Token| (token.value, token.span);
+
+  assert_eq!(read(&tokens[0]), (None, Some((0, 2))));
+  assert_eq!(read(&tokens[2]), (Some(31.0), Some((4, 8))));
+  assert_eq!(read(&Token::new_integer("12")), (Some(12.0), None));
+  assert_eq!(Token::new_float(0.1).value, Some(0.1));
 }
 
 #[test]
-fn testing_tokenize_space() {
-  let formula = String::from("1  +  2");
-  let token = tokenize_space(&formula, 1).unwrap();
-
-  let expected_token = Token {
-    kind: Kind::Space,
-    keys: vec![Space],
-  };
+fn testing_tokenize_bitwise_operators() {
+  let operators: Vec<Token> = tokenize(String::from("~1&2|3^4<<5>>6"))
+    .unwrap()
+    .into_iter()
+    .filter(|token| token.kind == Kind::Operator)
+    .collect();
 
-  assert_eq!(token, expected_token);
+  assert_eq!(
+    operators,
+    vec![BitNot, BitAnd, BitOr, Exponentiation, ShiftLeft, ShiftRight]
+      .into_iter()
+      .map(Token::new_operator)
+      .collect::<Vec<Token>>(),
+    "should leave ^ to the parser, which reads it as xor in programmer mode"
+  );
+  assert!(Key::precede(&Subtraction, &ShiftLeft) && !Key::precede(&ShiftLeft, &ShiftRight));
+  assert!(Key::precede(&ShiftRight, &BitAnd) && Key::precede(&BitAnd, &Xor) && Key::precede(&Xor, &BitOr));
+  assert_eq!(
+    tokenize(String::from("1 < 2")),
+    Err(Error::UndefinedSymbol("Error: Undefined symbol start < 2".to_string()))
+  );
 }
 
 #[test]
-fn testing_tokenize_dot() {
-  let formula = String::from("10.5");
-  let token = tokenize_dot(&formula, 2).unwrap();
+fn testing_lexer_hexadecimal_and_binary_numbers() {
+  let lexemes: Vec<Lexeme> = Lexer::new("0xFf+0b101").map(Result::unwrap).collect();
 
-  let expected_token = Token {
-    kind: Kind::Dot,
-    keys: vec![Dot],
-  };
-
-  assert_eq!(token, expected_token);
+  assert_eq!(lexemes[0].text, "0xFf");
+  assert_eq!(lexemes[0].value, Some(255.0));
+  assert_eq!(lexemes[2].value, Some(5.0));
+  assert_eq!(
+    tokenize(String::from("0xFf")).unwrap(),
+    vec![Token::new_number(vec![Two, Five, Five])],
+    "should keep the decimal digits of the value"
+  );
+  assert_eq!(
+    tokenize(String::from("0b2")).unwrap()[0],
+    Token::new_number(vec![Zero]),
+    "should only read a prefix followed by a digit of its radix"
+  );
+  assert_eq!(
+    tokenize(String::from("0x1ffffffffffffffff")),
+    Err(Error::InvalidExpression(
+      "Bad format: Number 0x1ffffffffffffffff does not fit in 64 bits".to_string()
+    ))
+  );
 }
 
 #[test]
-fn testing_tokenize_decimal_digit() {
-  let formula = String::from("0123456789");
-  let token_zero = tokenize_decimal_digit(&formula, 0).unwrap();
-  let token_one = tokenize_decimal_digit(&formula, 1).unwrap();
-  let token_two = tokenize_decimal_digit(&formula, 2).unwrap();
-  let token_three = tokenize_decimal_digit(&formula, 3).unwrap();
-  let token_four = tokenize_decimal_digit(&formula, 4).unwrap();
-  let token_five = tokenize_decimal_digit(&formula, 5).unwrap();
-  let token_six = tokenize_decimal_digit(&formula, 6).unwrap();
-  let token_seven = tokenize_decimal_digit(&formula, 7).unwrap();
-  let token_eight = tokenize_decimal_digit(&formula, 8).unwrap();
-  let token_nine = tokenize_decimal_digit(&formula, 9).unwrap();
-
-  assert_eq!(
-    token_zero,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Zero],
-    }
-  );
+fn testing_lexer_dates_and_durations() {
+  let lexemes = |formula| {
+    Lexer::new(formula)
+      .map(|lexeme| {
+        let lexeme = lexeme.unwrap();
+        (lexeme.kind, lexeme.text)
+      })
+      .collect::<Vec<(Kind, &str)>>()
+  };
+
   assert_eq!(
-    token_one,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![One],
-    }
+    lexemes("2026-10-18T09:30+3d 4h"),
+    vec![
+      (Kind::Date, "2026-10-18T09:30"),
+      (Kind::Operator, "+"),
+      (Kind::Duration, "3d 4h")
+    ]
   );
   assert_eq!(
-    token_two,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Two],
-    }
+    lexemes("1.5h -2"),
+    vec![
+      (Kind::Duration, "1.5h"),
+      (Kind::Space, " "),
+      (Kind::Operator, "-"),
+      (Kind::Number, "2")
+    ]
   );
   assert_eq!(
-    token_three,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Three],
-    }
+    lexemes("2sin(x)")[0..2],
+    [(Kind::Number, "2"), (Kind::Function, "sin")],
+    "should not read a unit that goes on as a name"
   );
+  assert_eq!(lexemes("2min(1)")[1], (Kind::Function, "min"));
+  assert_eq!(lexemes("add_business_days")[0], (Kind::Function, "add_business_days"));
   assert_eq!(
-    token_four,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Four],
-    }
+    tokenize(String::from("a_b")),
+    Err(Error::UndefinedSymbol("Error: Undefined symbol start _b".to_string())),
+    "should only read _ in the names of functions"
   );
   assert_eq!(
-    token_five,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Five],
-    }
+    tokenize(String::from("2026-10-18")).unwrap()[0].to_string(),
+    "2026-10-18"
   );
+}
+
+#[test]
+fn testing_lexer_units_named_as_variables() {
+  let kinds = |formula: &str, variable: &str| {
+    let variables = [variable.to_string()];
+    Lexer::new(formula)
+      .with_variables(&variables)
+      .map(|lexeme| lexeme.unwrap().kind)
+      .collect::<Vec<Kind>>()
+  };
+
+  for unit in ["d", "h", "s", "w"].iter() {
+    assert_eq!(
+      kinds(&format!("3{}^2", unit), unit),
+      vec![Kind::Number, Kind::Identifier, Kind::Operator, Kind::Number],
+      "should read {} as a variable",
+      unit
+    );
+    assert_eq!(kinds(&format!("3{}", unit), "x"), vec![Kind::Duration]);
+  }
   assert_eq!(
-    token_six,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Six],
-    }
+    kinds("3d 4h", "h"),
+    vec![Kind::Duration, Kind::Space, Kind::Number, Kind::Identifier],
+    "should end the duration before the variable"
   );
+  assert_eq!(kinds("90min", "m"), vec![Kind::Duration]);
+}
+
+#[test]
+fn testing_lexer_interval_ranges() {
+  let lexemes = |formula| {
+    Lexer::new(formula)
+      .map(|lexeme| {
+        let lexeme = lexeme.unwrap();
+        (lexeme.kind, lexeme.text)
+      })
+      .collect::<Vec<(Kind, &str)>>()
+  };
+
   assert_eq!(
-    token_seven,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Seven],
-    }
+    lexemes("[1.9..2]"),
+    vec![
+      (Kind::Bracket, "["),
+      (Kind::Number, "1.9"),
+      (Kind::Separator, ".."),
+      (Kind::Number, "2"),
+      (Kind::Bracket, "]")
+    ]
   );
+  assert_eq!(lexemes("1 .. 2")[2], (Kind::Separator, ".."));
+  assert_eq!(tokenize(String::from("1..2")).unwrap()[1].keys, vec![Range]);
   assert_eq!(
-    token_eight,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Eight],
-    }
+    tokenize(String::from("1...2")),
+    Err(Error::InvalidExpression(
+      "Bad format: Number should not start with .".to_string()
+    ))
   );
-  assert_eq!(
-    token_nine,
-    Token {
-      kind: Kind::Digit,
-      keys: vec![Nine],
-    }
+}
+
+#[test]
+fn testing_tokenize_plus_minus() {
+  for formula in ["5±0.1", "5+/-0.1"].iter() {
+    let tokens = tokenize(formula.to_string()).unwrap();
+    assert_eq!(tokens[1], Token::new_operator(PlusMinus), "should read {}", formula);
+  }
+  assert!(
+    !Key::precede(&PlusMinus, &Addition) && !Key::precede(&Addition, &PlusMinus),
+    "± should bind as + does"
   );
 }
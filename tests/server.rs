@@ -0,0 +1,226 @@
+use rust_calc::server::{serve, ServerOptions};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+fn start(options: ServerOptions) -> SocketAddr {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let address = listener.local_addr().unwrap();
+  thread::spawn(move || serve(listener, options));
+  address
+}
+
+/// Sends `request` as is and returns the status and body of the response.
+fn send(address: SocketAddr, request: &str) -> (u16, String) {
+  let mut stream = TcpStream::connect(address).unwrap();
+  // a server turning the client away may answer before reading everything
+  let _ = stream.write_all(request.as_bytes());
+
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  let (head, body) = response.split_once("\r\n\r\n").unwrap();
+  let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+  (status, body.to_string())
+}
+
+fn post(address: SocketAddr, path: &str, body: &str) -> (u16, String) {
+  let request = format!(
+    "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+    path,
+    body.len(),
+    body
+  );
+  send(address, &request)
+}
+
+#[test]
+fn should_evaluate() {
+  let address = start(ServerOptions::default());
+
+  assert_eq!(
+    post(address, "/evaluate", r#"{"formula": "1+2*3"}"#),
+    (200, r#"{"input":"1+2*3","value":7,"formatted":"7"}"#.to_string())
+  );
+  assert_eq!(
+    post(
+      address,
+      "/evaluate",
+      r#"{"formula": "a*x+b", "variables": {"a": 2, "x": 3, "b": 0.5}}"#
+    ),
+    (200, r#"{"input":"a*x+b","value":6.5,"formatted":"6.5"}"#.to_string())
+  );
+  assert_eq!(
+    post(address, "/evaluate", r#"{"formula": "pi", "decimals": 3}"#),
+    (200, r#"{"input":"pi","value":3.142,"formatted":"3.142"}"#.to_string())
+  );
+  assert_eq!(
+    post(
+      address,
+      "/evaluate",
+      r#"{"formula": "2(3)", "implicit_multiplication": "off"}"#
+    )
+    .0,
+    422
+  );
+}
+
+#[test]
+fn should_read_variables_named_as_units_of_duration() {
+  let address = start(ServerOptions::default());
+
+  assert_eq!(
+    post(address, "/evaluate", r#"{"formula": "3h", "variables": {"h": 2}}"#),
+    (200, r#"{"input":"3h","value":6,"formatted":"6"}"#.to_string())
+  );
+  assert_eq!(
+    post(address, "/evaluate", r#"{"formula": "2s", "variables": {"s": 5}}"#),
+    (200, r#"{"input":"2s","value":10,"formatted":"10"}"#.to_string())
+  );
+  assert_eq!(
+    post(address, "/evaluate", r#"{"formula": "3h"}"#).1,
+    r#"{"input":"3h","value":null,"formatted":"3h"}"#.to_string(),
+    "should read a unit of duration that names no variable as one"
+  );
+}
+
+#[test]
+fn should_report_calculation_errors() {
+  let address = start(ServerOptions::default());
+
+  assert_eq!(
+    post(address, "/evaluate", r#"{"formula": "2*y"}"#),
+    (
+      422,
+      r#"{"error":{"kind":"undefined_identifier","message":"Undefined identifier y","span":[2,3]}}"#.to_string()
+    )
+  );
+}
+
+#[test]
+fn should_parse() {
+  let address = start(ServerOptions::default());
+
+  assert_eq!(
+    post(address, "/parse", r#"{"formula": "2x^2"}"#),
+    (
+      200,
+      concat!(
+        r#"{"input":"2x^2","ast":{"operator":"*","operands":[{"number":2},"#,
+        r#"{"operator":"^","operands":[{"identifier":"x"},{"number":2}]}]},"formatted":"2*x^2"}"#
+      )
+      .to_string()
+    )
+  );
+}
+
+#[test]
+fn should_reject_malformed_requests() {
+  let address = start(ServerOptions::default());
+
+  assert_eq!(post(address, "/evaluate", "{\"formula\": ").0, 400);
+  assert_eq!(post(address, "/evaluate", r#"{"formula": 12}"#).0, 400);
+  assert_eq!(
+    post(address, "/evaluate", r#"{"formula": "x", "variables": {"x": "1"}}"#).0,
+    400
+  );
+  assert_eq!(post(address, "/unknown", "{}").0, 404);
+  assert_eq!(send(address, "GET /evaluate HTTP/1.1\r\n\r\n").0, 405);
+  assert_eq!(send(address, "POST /evaluate HTTP/1.1\r\n\r\n").0, 411);
+}
+
+#[test]
+fn should_limit_request_size() {
+  let address = start(ServerOptions {
+    max_body: 32,
+    ..ServerOptions::default()
+  });
+  let formula = format!(r#"{{"formula": "{}"}}"#, "1+".repeat(20) + "1");
+
+  let (status, body) = post(address, "/evaluate", &formula);
+  assert_eq!(status, 413);
+  assert!(body.contains("payload_too_large"));
+}
+
+#[test]
+fn should_limit_time() {
+  let address = start(ServerOptions {
+    timeout: Duration::from_millis(200),
+    ..ServerOptions::default()
+  });
+
+  let (status, body) = send(address, "POST /evaluate HTTP/1.1\r\nContent-Length: 100\r\n\r\n{");
+  assert_eq!(status, 408, "should stop waiting for a slow client");
+  assert!(body.contains("timeout"));
+
+  let slow = r#"{"formula": "series(series(i*j, j, 1, 100000), i, 1, 100000)"}"#;
+  let (status, body) = post(address, "/evaluate", slow);
+  assert_eq!(status, 503, "should abandon a slow calculation");
+  assert!(body.contains("timeout"));
+}
+
+#[test]
+fn should_limit_the_time_of_the_whole_request() {
+  let address = start(ServerOptions {
+    timeout: Duration::from_millis(500),
+    ..ServerOptions::default()
+  });
+  let body = r#"{"formula": "1+2"}"#;
+  let request = format!(
+    "POST /evaluate HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+    body.len(),
+    body
+  );
+
+  // each byte comes well within the timeout, but all of them do not, and
+  // none comes after the answer, which would reset the connection
+  let mut stream = TcpStream::connect(address).unwrap();
+  stream.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+  for byte in request.bytes() {
+    stream.write_all(&[byte]).unwrap();
+    if stream.peek(&mut [0]).is_ok() {
+      break;
+    }
+  }
+  stream.set_read_timeout(None).unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  assert!(response.starts_with("HTTP/1.1 408"), "{}", response);
+  assert!(response.contains("timeout"));
+}
+
+#[test]
+fn should_handle_requests_concurrently() {
+  let address = start(ServerOptions::default());
+
+  let clients: Vec<_> = (0..16)
+    .map(|i| {
+      thread::spawn(move || {
+        let body = format!(r#"{{"formula": "x^2", "variables": {{"x": {}}}}}"#, i);
+        post(address, "/evaluate", &body)
+      })
+    })
+    .collect();
+
+  for (i, client) in clients.into_iter().enumerate() {
+    let (status, body) = client.join().unwrap();
+    assert_eq!(status, 200);
+    assert!(body.contains(&format!(r#""value":{}"#, i * i)), "{}", body);
+  }
+}
+
+#[test]
+fn should_turn_clients_away_when_busy() {
+  let address = start(ServerOptions {
+    max_connections: 1,
+    timeout: Duration::from_secs(1),
+    ..ServerOptions::default()
+  });
+
+  let _idle = TcpStream::connect(address).unwrap();
+  thread::sleep(Duration::from_millis(100));
+
+  let (status, body) = post(address, "/evaluate", r#"{"formula": "1"}"#);
+  assert_eq!(status, 503);
+  assert!(body.contains("busy"));
+}